@@ -0,0 +1,100 @@
+//! The focused window on X11, read from `_NET_ACTIVE_WINDOW`. Only the
+//! window class and the owning process name are read, never the title.
+//! Wayland compositors don't tell ordinary clients which window is focused,
+//! so under Wayland only XWayland windows are seen.
+
+use std::fs;
+
+use x11rb::connection::Connection as _;
+use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt as _, Window};
+use x11rb::rust_connection::RustConnection;
+
+use super::{Foreground, ForegroundSource};
+
+struct X11 {
+    conn: RustConnection,
+    root: Window,
+    active_window: Atom,
+    wm_pid: Atom,
+}
+
+impl X11 {
+    fn connect() -> Option<Self> {
+        let (conn, screen) = x11rb::connect(None).ok()?;
+        let root = conn.setup().roots[screen].root;
+        let intern = |name: &[u8]| Some(conn.intern_atom(true, name).ok()?.reply().ok()?.atom);
+        let active_window = intern(b"_NET_ACTIVE_WINDOW")?;
+        let wm_pid = intern(b"_NET_WM_PID")?;
+        Some(Self {
+            conn,
+            root,
+            active_window,
+            wm_pid,
+        })
+    }
+
+    fn property(&self, window: Window, property: Atom, kind: impl Into<Atom>) -> Option<Vec<u8>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, kind, 0, 256)
+            .ok()?
+            .reply()
+            .ok()?;
+        Some(reply.value)
+    }
+
+    /// The active window, or `Err` if the X server can't be reached.
+    fn active(&self) -> Result<Option<Window>, ()> {
+        let active = self
+            .property(self.root, self.active_window, AtomEnum::WINDOW)
+            .ok_or(())?;
+        let window = active
+            .get(..4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u32::from_ne_bytes);
+        Ok(window.filter(|window| *window != 0))
+    }
+
+    fn describe(&self, window: Window) -> Option<Foreground> {
+        // WM_CLASS is "instance\0class\0".
+        let class = self.property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING)?;
+        let class = class
+            .split(|byte| *byte == 0)
+            .rfind(|part| !part.is_empty())
+            .map(|part| String::from_utf8_lossy(part).into_owned())?;
+
+        let app = self
+            .property(window, self.wm_pid, AtomEnum::CARDINAL)
+            .and_then(|pid| Some(u32::from_ne_bytes(pid.get(..4)?.try_into().ok()?)))
+            .and_then(|pid| fs::read_to_string(format!("/proc/{pid}/comm")).ok())
+            .map(|comm| comm.trim().to_owned())
+            .unwrap_or_else(|| class.to_lowercase());
+
+        Some(Foreground {
+            app,
+            window_class: class,
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct SystemForeground {
+    x11: Option<X11>,
+}
+
+impl ForegroundSource for SystemForeground {
+    fn foreground(&mut self) -> Option<Foreground> {
+        if self.x11.is_none() {
+            self.x11 = X11::connect();
+        }
+        let x11 = self.x11.as_ref()?;
+        match x11.active() {
+            Ok(window) => x11.describe(window?),
+            Err(()) => {
+                // The X server went away; reconnect on the next sample.
+                self.x11 = None;
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,350 @@
+//! Opt-in sampling of the foreground application, to learn how focus time
+//! is actually spent. Every few seconds the focused window's application and
+//! window class are written to the local store; window titles are never
+//! read. Samples stay on this machine: only per-block summaries and
+//! aggregated `user_patterns` rows are ever shown or uploaded, and turning
+//! the sampler off deletes them.
+
+#[cfg(target_os = "linux")]
+mod linux;
+mod patterns;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rusqlite::{params, Row};
+use serde_json::json;
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::error::{Error, Result};
+use crate::queue::{Action, Connectivity, NewOperation, Table};
+use crate::store::Store;
+use crate::vault::TokenVault;
+
+pub use patterns::{BlockActivity, Category, Pattern, Share};
+
+pub const SAMPLE_INTERVAL: StdDuration = StdDuration::from_secs(5);
+
+const ENABLED_SETTING: &str = "activity.enabled";
+/// Samples older than this are deleted.
+const RETENTION: Duration = Duration::days(30);
+const PRUNE_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Foreground {
+    pub app: String,
+    pub window_class: String,
+}
+
+/// The currently focused application, or `None` if nothing is focused or
+/// it can't be told.
+pub trait ForegroundSource: Send {
+    fn foreground(&mut self) -> Option<Foreground>;
+}
+
+/// Used where the focused window can't be read.
+pub struct NoForegroundSource;
+
+impl ForegroundSource for NoForegroundSource {
+    fn foreground(&mut self) -> Option<Foreground> {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sample {
+    pub sampled_at: DateTime<Utc>,
+    pub app: String,
+    pub window_class: String,
+}
+
+impl Sample {
+    const COLUMNS: &'static str = "sampled_at, app, window_class";
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            sampled_at: row.get(0)?,
+            app: row.get(1)?,
+            window_class: row.get(2)?,
+        })
+    }
+}
+
+impl Store {
+    pub fn record_sample(&self, at: DateTime<Utc>, foreground: &Foreground) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO activity_samples (sampled_at, app, window_class) VALUES (?1, ?2, ?3)",
+            params![at, foreground.app, foreground.window_class],
+        )?;
+        Ok(())
+    }
+
+    pub fn samples_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Sample>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM activity_samples
+             WHERE sampled_at >= ?1 AND sampled_at < ?2
+             ORDER BY sampled_at",
+            Sample::COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![start, end], Sample::from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn prune_samples(&self, before: DateTime<Utc>) -> Result<()> {
+        self.conn().execute(
+            "DELETE FROM activity_samples WHERE sampled_at < ?1",
+            [before],
+        )?;
+        Ok(())
+    }
+
+    pub fn clear_samples(&self) -> Result<()> {
+        self.conn().execute("DELETE FROM activity_samples", [])?;
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct Activity {
+    enabled: AtomicBool,
+}
+
+#[cfg(target_os = "linux")]
+fn system_source() -> linux::SystemForeground {
+    linux::SystemForeground::default()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn system_source() -> NoForegroundSource {
+    NoForegroundSource
+}
+
+/// Start the sampler. It does nothing until enabled, and skips samples
+/// while the user is away.
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    let enabled = app
+        .state::<Store>()
+        .setting(ENABLED_SETTING)?
+        .unwrap_or(false);
+    app.manage(Activity {
+        enabled: AtomicBool::new(enabled),
+    });
+
+    let app = app.handle().clone();
+    std::thread::spawn(move || {
+        let mut source = system_source();
+        let mut last_prune: Option<std::time::Instant> = None;
+        loop {
+            std::thread::sleep(SAMPLE_INTERVAL);
+            if !app.state::<Activity>().enabled.load(Ordering::Relaxed)
+                || app.state::<crate::presence::Presence>().is_idle()
+            {
+                continue;
+            }
+            let store = app.state::<Store>();
+            if let Some(foreground) = source.foreground() {
+                if let Err(err) = store.record_sample(Utc::now(), &foreground) {
+                    log::error!("failed to record activity sample: {err}");
+                }
+            }
+            if last_prune.map_or(true, |at| at.elapsed() >= PRUNE_INTERVAL) {
+                if let Err(err) = store.prune_samples(Utc::now() - RETENTION) {
+                    log::error!("failed to prune activity samples: {err}");
+                }
+                last_prune = Some(std::time::Instant::now());
+            }
+        }
+    });
+    Ok(())
+}
+
+pub fn block_summaries(store: &Store, date: NaiveDate) -> Result<Vec<BlockActivity>> {
+    let blocks = store.blocks_for_date(date)?;
+    let (Some(start), Some(end)) = (
+        blocks.iter().map(|block| block.start_time).min(),
+        blocks.iter().map(|block| block.end_time).max(),
+    ) else {
+        return Ok(Vec::new());
+    };
+    let samples = store.samples_between(start, end)?;
+    Ok(blocks
+        .iter()
+        .filter_map(|block| patterns::summarize(block, &samples))
+        .collect())
+}
+
+pub fn export_patterns(
+    store: &Store,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<Pattern>> {
+    let blocks = store.list_time_blocks(start, end)?;
+    let samples = store.samples_between(start, end)?;
+    Ok(patterns::export(
+        &blocks,
+        &samples,
+        start,
+        end,
+        store.zone()?,
+    ))
+}
+
+#[tauri::command]
+pub fn activity_get_enabled(activity: State<'_, Activity>) -> bool {
+    activity.enabled.load(Ordering::Relaxed)
+}
+
+/// Opt in or out. Opting out deletes every sample taken so far.
+#[tauri::command]
+pub fn activity_set_enabled(
+    enabled: bool,
+    activity: State<'_, Activity>,
+    store: State<'_, Store>,
+) -> Result<()> {
+    store.set_setting(ENABLED_SETTING, &enabled)?;
+    activity.enabled.store(enabled, Ordering::Relaxed);
+    if !enabled {
+        store.clear_samples()?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn activity_block_summaries(
+    date: NaiveDate,
+    store: State<'_, Store>,
+) -> Result<Vec<BlockActivity>> {
+    block_summaries(&store, date)
+}
+
+#[tauri::command]
+pub fn activity_export_patterns(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    store: State<'_, Store>,
+) -> Result<Vec<Pattern>> {
+    export_patterns(&store, start, end)
+}
+
+/// Queue the aggregated patterns for upload to `user_patterns`.
+#[tauri::command]
+pub fn activity_publish_patterns<R: Runtime>(
+    app: AppHandle<R>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    store: State<'_, Store>,
+) -> Result<usize> {
+    let user_id = app
+        .state::<TokenVault>()
+        .user_id()?
+        .ok_or_else(|| Error::Auth("not signed in".into()))?;
+    let patterns = export_patterns(&store, start, end)?;
+    for pattern in &patterns {
+        store.enqueue(NewOperation {
+            idempotency_key: Some(format!(
+                "patterns:{}:{}:{}",
+                pattern.pattern_type,
+                start.timestamp(),
+                end.timestamp()
+            )),
+            table: Table::UserPatterns,
+            action: Action::Insert,
+            row_id: None,
+            payload: json!({
+                "user_id": user_id,
+                "pattern_type": pattern.pattern_type,
+                "pattern_data": pattern.pattern_data,
+                "confidence": pattern.confidence,
+                "last_observed": pattern.last_observed,
+            }),
+        })?;
+    }
+    app.state::<Connectivity>().wake();
+    Ok(patterns.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::store::UserPreferences;
+    use crate::testing::block;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, hour, minute, 0).unwrap()
+    }
+
+    fn foreground(app: &str) -> Foreground {
+        Foreground {
+            app: app.into(),
+            window_class: app.into(),
+        }
+    }
+
+    #[test]
+    fn samples_keep_no_window_titles() {
+        let store = Store::open_in_memory().unwrap();
+        let columns: Vec<String> = store
+            .conn()
+            .prepare("SELECT name FROM pragma_table_info('activity_samples')")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert!(!columns.iter().any(|column| column.contains("title")));
+    }
+
+    #[test]
+    fn samples_are_read_by_range_and_pruned() {
+        let store = Store::open_in_memory().unwrap();
+        store.record_sample(at(9, 5), &foreground("kitty")).unwrap();
+        store.record_sample(at(9, 0), &foreground("code")).unwrap();
+        store
+            .record_sample(at(10, 0), &foreground("firefox"))
+            .unwrap();
+
+        let samples = store.samples_between(at(9, 0), at(10, 0)).unwrap();
+        let apps: Vec<&str> = samples.iter().map(|s| s.app.as_str()).collect();
+        assert_eq!(apps, ["code", "kitty"]);
+        assert_eq!(samples[0].sampled_at, at(9, 0));
+
+        store.prune_samples(at(9, 5)).unwrap();
+        let samples = store.samples_between(at(0, 0), at(23, 0)).unwrap();
+        assert_eq!(samples.len(), 2);
+        store.clear_samples().unwrap();
+        assert!(store
+            .samples_between(at(0, 0), at(23, 0))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn block_summaries_cover_the_days_blocks() {
+        let store = Store::open_in_memory().unwrap();
+        let mut preferences = UserPreferences::defaults("u1");
+        preferences.timezone = "UTC".into();
+        store.save_preferences(&preferences).unwrap();
+        store
+            .save_time_block(&block("api", at(9, 0), at(10, 0)))
+            .unwrap();
+        store
+            .save_time_block(&block("docs", at(14, 0), at(15, 0)))
+            .unwrap();
+        store.record_sample(at(9, 30), &foreground("code")).unwrap();
+        store
+            .record_sample(at(12, 0), &foreground("firefox"))
+            .unwrap();
+
+        let summaries = block_summaries(&store, at(0, 0).date_naive()).unwrap();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].block_id, "api");
+        assert_eq!(summaries[0].summary, "100% editor during Focus: api");
+
+        let next_day = NaiveDate::from_ymd_opt(2026, 3, 3).unwrap();
+        assert!(block_summaries(&store, next_day).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,653 @@
+//! Turning raw samples into per-block summaries and `user_patterns` rows.
+//! Only categories and application names leave this module; nothing here
+//! sees a window title.
+
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Timelike, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::{Sample, SAMPLE_INTERVAL};
+use crate::schedule::time::Zone;
+use crate::store::{BlockType, TimeBlock};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Category {
+    Editor,
+    Terminal,
+    Docs,
+    Design,
+    Browser,
+    Chat,
+    Email,
+    Meeting,
+    Other,
+}
+
+impl Category {
+    /// Categories that count as deep work.
+    fn is_focused(self) -> bool {
+        matches!(
+            self,
+            Category::Editor | Category::Terminal | Category::Docs | Category::Design
+        )
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Category::Editor => "editor",
+            Category::Terminal => "terminal",
+            Category::Docs => "docs",
+            Category::Design => "design",
+            Category::Browser => "browser",
+            Category::Chat => "chat",
+            Category::Email => "email",
+            Category::Meeting => "meeting",
+            Category::Other => "other",
+        }
+    }
+}
+
+/// Words of a window class or process name that mark its category.
+const CATEGORIES: &[(Category, &[&str])] = &[
+    (
+        Category::Meeting,
+        &["zoom", "teams", "skype", "skypeforlinux", "webex", "jitsi"],
+    ),
+    (
+        Category::Chat,
+        &[
+            "slack",
+            "discord",
+            "telegram",
+            "telegramdesktop",
+            "signal",
+            "element",
+            "whatsapp",
+        ],
+    ),
+    (
+        Category::Email,
+        &[
+            "thunderbird",
+            "betterbird",
+            "evolution",
+            "geary",
+            "mailspring",
+            "mail",
+        ],
+    ),
+    (
+        Category::Editor,
+        &[
+            "code",
+            "codium",
+            "vscodium",
+            "jetbrains",
+            "idea",
+            "pycharm",
+            "webstorm",
+            "goland",
+            "rustrover",
+            "clion",
+            "zed",
+            "sublime",
+            "vim",
+            "gvim",
+            "nvim",
+            "neovide",
+            "emacs",
+            "gedit",
+            "texteditor",
+            "kate",
+            "helix",
+        ],
+    ),
+    (
+        Category::Terminal,
+        &[
+            "terminal",
+            "konsole",
+            "kgx",
+            "alacritty",
+            "kitty",
+            "wezterm",
+            "foot",
+            "tilix",
+            "xterm",
+            "ghostty",
+        ],
+    ),
+    (
+        Category::Browser,
+        &[
+            "firefox",
+            "chrome",
+            "chromium",
+            "brave",
+            "vivaldi",
+            "epiphany",
+            "opera",
+            "edge",
+            "msedge",
+            "librewolf",
+        ],
+    ),
+    (
+        Category::Docs,
+        &[
+            "libreoffice",
+            "soffice",
+            "obsidian",
+            "notion",
+            "logseq",
+            "evince",
+            "okular",
+            "zotero",
+        ],
+    ),
+    (
+        Category::Design,
+        &["figma", "gimp", "inkscape", "krita", "blender", "darktable"],
+    ),
+];
+
+/// Match whole words of the window class and process name, so `code`
+/// finds `Code` and `code-oss` but not `barcode`, and `mail` isn't `gmail`.
+pub fn categorize(sample: &Sample) -> Category {
+    let words: Vec<String> = [&sample.window_class, &sample.app]
+        .into_iter()
+        .flat_map(|name| name.split(|c: char| !c.is_ascii_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect();
+    CATEGORIES
+        .iter()
+        .find(|(_, needles)| {
+            needles
+                .iter()
+                .any(|needle| words.iter().any(|w| w == needle))
+        })
+        .map_or(Category::Other, |(category, _)| *category)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Share<T> {
+    pub name: T,
+    pub share: f64,
+}
+
+/// How a block's time was spent, e.g. "80% editor, 15% browser during
+/// Focus: API refactor".
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockActivity {
+    pub block_id: String,
+    pub block_type: BlockType,
+    pub title: String,
+    pub active_minutes: i64,
+    pub categories: Vec<Share<Category>>,
+    pub apps: Vec<Share<String>>,
+    pub summary: String,
+}
+
+fn minutes(samples: usize) -> i64 {
+    (samples as u64 * SAMPLE_INTERVAL.as_secs() / 60) as i64
+}
+
+/// Shares of `counts`, largest first.
+fn shares<T: Clone + Ord>(counts: &HashMap<T, usize>, total: usize) -> Vec<Share<T>> {
+    let mut shares: Vec<Share<T>> = counts
+        .iter()
+        .map(|(name, count)| Share {
+            name: name.clone(),
+            share: *count as f64 / total as f64,
+        })
+        .collect();
+    shares.sort_by(|a, b| {
+        b.share
+            .total_cmp(&a.share)
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    shares
+}
+
+fn block_label(block_type: BlockType) -> &'static str {
+    match block_type {
+        BlockType::Focus => "Focus",
+        BlockType::Meeting => "Meeting",
+        BlockType::Email => "Email",
+        BlockType::QuickDecisions => "Quick decisions",
+        BlockType::Break => "Break",
+        BlockType::Blocked => "Blocked",
+    }
+}
+
+fn in_block<'a>(samples: &'a [Sample], block: &TimeBlock) -> impl Iterator<Item = &'a Sample> {
+    let (start, end) = (block.start_time, block.end_time);
+    samples
+        .iter()
+        .filter(move |sample| start <= sample.sampled_at && sample.sampled_at < end)
+}
+
+pub fn summarize(block: &TimeBlock, samples: &[Sample]) -> Option<BlockActivity> {
+    let mut categories = HashMap::new();
+    let mut apps = HashMap::new();
+    let mut total = 0;
+    for sample in in_block(samples, block) {
+        *categories.entry(categorize(sample)).or_insert(0) += 1;
+        *apps.entry(sample.app.clone()).or_insert(0) += 1;
+        total += 1;
+    }
+    if total == 0 {
+        return None;
+    }
+
+    let categories = shares(&categories, total);
+    let mut apps = shares(&apps, total);
+    apps.truncate(5);
+    let mix = categories
+        .iter()
+        .filter(|share| share.share >= 0.05)
+        .take(3)
+        .map(|share| format!("{:.0}% {}", share.share * 100.0, share.name.label()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    Some(BlockActivity {
+        block_id: block.id.clone(),
+        block_type: block.block_type,
+        title: block.title.clone(),
+        active_minutes: minutes(total),
+        summary: format!(
+            "{mix} during {}: {}",
+            block_label(block.block_type),
+            block.title
+        ),
+        categories,
+        apps,
+    })
+}
+
+/// A `user_patterns` row without its ids.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pattern {
+    pub pattern_type: &'static str,
+    pub pattern_data: Value,
+    pub confidence: f64,
+    pub last_observed: DateTime<Utc>,
+}
+
+/// Samples needed for full confidence: about ten hours.
+const CONFIDENT_SAMPLES: f64 = 7200.0;
+
+fn confidence(samples: usize) -> f64 {
+    (samples as f64 / CONFIDENT_SAMPLES).min(1.0)
+}
+
+/// Aggregate samples over `[start, end)` into `focus_time` (how focused
+/// each hour of the day is during focus blocks) and `task_timing` (where
+/// time goes in each kind of block) patterns. Hours are on `zone`'s clock.
+pub fn export(
+    blocks: &[TimeBlock],
+    samples: &[Sample],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    zone: Zone,
+) -> Vec<Pattern> {
+    let Some(last_observed) = samples.iter().map(|sample| sample.sampled_at).max() else {
+        return Vec::new();
+    };
+
+    // Hour of day -> (focused samples, samples) during focus blocks.
+    let mut hours: BTreeMap<u32, (usize, usize)> = BTreeMap::new();
+    let mut focus_samples = 0;
+    // Block type -> category counts.
+    let mut by_type: BTreeMap<&'static str, HashMap<Category, usize>> = BTreeMap::new();
+    let mut typed_samples = 0;
+
+    for block in blocks {
+        for sample in in_block(samples, block) {
+            let category = categorize(sample);
+            *by_type
+                .entry(block.block_type.as_str())
+                .or_default()
+                .entry(category)
+                .or_insert(0) += 1;
+            typed_samples += 1;
+            if block.block_type == BlockType::Focus {
+                let hour = zone.local(sample.sampled_at).hour();
+                let entry = hours.entry(hour).or_default();
+                entry.0 += usize::from(category.is_focused());
+                entry.1 += 1;
+                focus_samples += 1;
+            }
+        }
+    }
+
+    let window = json!({ "start": start, "end": end });
+    let mut patterns = Vec::new();
+    if focus_samples > 0 {
+        let hours: Vec<Value> = hours
+            .into_iter()
+            .map(|(hour, (focused, total))| {
+                json!({
+                    "hour": hour,
+                    "focusedShare": focused as f64 / total as f64,
+                    "minutes": minutes(total),
+                })
+            })
+            .collect();
+        patterns.push(Pattern {
+            pattern_type: "focus_time",
+            pattern_data: json!({ "source": "activity", "window": window, "hours": hours }),
+            confidence: confidence(focus_samples),
+            last_observed,
+        });
+    }
+    if typed_samples > 0 {
+        let block_types: serde_json::Map<String, Value> = by_type
+            .into_iter()
+            .map(|(block_type, counts)| {
+                let total = counts.values().sum();
+                let categories: serde_json::Map<String, Value> = shares(&counts, total)
+                    .into_iter()
+                    .map(|share| (share.name.label().to_owned(), share.share.into()))
+                    .collect();
+                let data = json!({ "minutes": minutes(total), "categories": categories });
+                (block_type.to_owned(), data)
+            })
+            .collect();
+        patterns.push(Pattern {
+            pattern_type: "task_timing",
+            pattern_data: json!({ "source": "activity", "window": window, "blockTypes": block_types }),
+            confidence: confidence(typed_samples),
+            last_observed,
+        });
+    }
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::testing::block;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, hour, minute, 0).unwrap()
+    }
+
+    fn sample(sampled_at: DateTime<Utc>, window_class: &str, app: &str) -> Sample {
+        Sample {
+            sampled_at,
+            app: app.into(),
+            window_class: window_class.into(),
+        }
+    }
+
+    /// `count` samples of `app`, one per interval from `from`.
+    fn run(from: DateTime<Utc>, app: &str, count: usize) -> Vec<Sample> {
+        let step = Duration::from_std(SAMPLE_INTERVAL).unwrap();
+        (0..count)
+            .map(|n| sample(from + step * n as i32, app, &app.to_lowercase()))
+            .collect()
+    }
+
+    #[test]
+    fn categorize_matches_whole_words() {
+        let cases = [
+            ("Code", "code", Category::Editor),
+            ("code-oss", "code-oss", Category::Editor),
+            ("VSCodium", "codium", Category::Editor),
+            ("jetbrains-idea-ce", "java", Category::Editor),
+            ("sublime_text", "sublime_text", Category::Editor),
+            ("dev.zed.Zed", "zed-editor", Category::Editor),
+            ("Gnome-terminal", "gnome-terminal-", Category::Terminal),
+            ("org.wezfurlong.wezterm", "wezterm-gui", Category::Terminal),
+            ("firefox", "firefox-bin", Category::Browser),
+            ("Google-chrome", "chrome", Category::Browser),
+            ("Chromium", "chromium", Category::Browser),
+            ("Microsoft-edge", "msedge", Category::Browser),
+            ("thunderbird", "thunderbird", Category::Email),
+            ("TelegramDesktop", "telegram-deskto", Category::Chat),
+            ("Slack", "slack", Category::Chat),
+            ("zoom", "zoom", Category::Meeting),
+            ("teams-for-linux", "teams-for-linux", Category::Meeting),
+            ("libreoffice-writer", "soffice.bin", Category::Docs),
+            ("Gimp-2.10", "gimp-2.10", Category::Design),
+            // Matches inside other words don't count.
+            ("Barcode-scanner", "barcode", Category::Other),
+            ("gmail-notifier", "gmail-notifier", Category::Other),
+            ("steam", "steamwebhelper", Category::Other),
+            ("Revolution", "revolution", Category::Other),
+            ("football-manager", "fm", Category::Other),
+            ("Knowledge-base", "kb", Category::Other),
+            ("Organizer", "organizer", Category::Other),
+            ("Operator", "operator", Category::Other),
+            ("Ideapad-settings", "ideapad", Category::Other),
+            ("", "", Category::Other),
+        ];
+        for (class, app, expected) in cases {
+            assert_eq!(
+                categorize(&sample(at(9, 0), class, app)),
+                expected,
+                "{class:?} / {app:?}"
+            );
+        }
+        // Either name is enough.
+        assert_eq!(
+            categorize(&sample(at(9, 0), "Navigator", "firefox")),
+            Category::Browser
+        );
+    }
+
+    #[test]
+    fn summaries_round_and_order_the_mix() {
+        let mut focus = block("api", at(9, 0), at(10, 0));
+        focus.title = "API refactor".into();
+        let mut samples = run(at(9, 0), "Code", 16);
+        samples.extend(run(at(9, 10), "firefox", 3));
+        samples.extend(run(at(9, 20), "Slack", 1));
+
+        let activity = summarize(&focus, &samples).unwrap();
+        assert_eq!(
+            activity.summary,
+            "80% editor, 15% browser, 5% chat during Focus: API refactor"
+        );
+        assert_eq!(activity.block_id, "api");
+        assert_eq!(activity.title, "API refactor");
+        assert_eq!(activity.active_minutes, 1);
+        let apps: Vec<(&str, f64)> = activity
+            .apps
+            .iter()
+            .map(|share| (share.name.as_str(), share.share))
+            .collect();
+        assert_eq!(apps, [("code", 0.8), ("firefox", 0.15), ("slack", 0.05)]);
+
+        // Thirds round to whole percents; ties go in category order, and
+        // slivers under 5% are left out.
+        let mut samples = run(at(9, 0), "firefox", 10);
+        samples.extend(run(at(9, 10), "Code", 10));
+        samples.extend(run(at(9, 20), "kitty", 10));
+        samples.extend(run(at(9, 30), "Slack", 1));
+        let summary = summarize(&focus, &samples).unwrap().summary;
+        assert_eq!(
+            summary,
+            "32% editor, 32% terminal, 32% browser during Focus: API refactor"
+        );
+        let samples = [run(at(9, 0), "Code", 2), run(at(9, 10), "firefox", 1)].concat();
+        let summary = summarize(&focus, &samples).unwrap().summary;
+        assert!(summary.starts_with("67% editor, 33% browser during"));
+    }
+
+    #[test]
+    fn summaries_only_count_samples_inside_the_block() {
+        let meeting = TimeBlock {
+            block_type: BlockType::Meeting,
+            ..block("standup", at(9, 0), at(9, 30))
+        };
+        let samples = [
+            sample(at(8, 59) + Duration::seconds(55), "Code", "code"),
+            sample(at(9, 0), "zoom", "zoom"),
+            sample(at(9, 29) + Duration::seconds(55), "zoom", "zoom"),
+            sample(at(9, 30), "Code", "code"),
+        ];
+        let activity = summarize(&meeting, &samples).unwrap();
+        assert_eq!(activity.summary, "100% meeting during Meeting: standup");
+        assert_eq!(activity.apps.len(), 1);
+
+        assert!(summarize(&meeting, &[]).is_none());
+        assert!(summarize(&meeting, &samples[..1]).is_none());
+        assert!(summarize(&meeting, &samples[3..]).is_none());
+    }
+
+    #[test]
+    fn summaries_list_the_top_five_apps() {
+        let focus = block("api", at(9, 0), at(10, 0));
+        let samples: Vec<Sample> = ["a", "b", "c", "d", "e", "f", "g"]
+            .iter()
+            .enumerate()
+            .flat_map(|(n, app)| run(at(9, n as u32), app, 7 - n))
+            .collect();
+        let activity = summarize(&focus, &samples).unwrap();
+        let apps: Vec<&str> = activity
+            .apps
+            .iter()
+            .map(|share| share.name.as_str())
+            .collect();
+        assert_eq!(apps, ["a", "b", "c", "d", "e"]);
+        assert_eq!(activity.summary, "100% other during Focus: api");
+    }
+
+    /// A focus block across two Berlin hours, a meeting, and time outside
+    /// any block.
+    fn day() -> (Vec<TimeBlock>, Vec<Sample>) {
+        let mut focus = block("api", at(9, 30), at(10, 30));
+        focus.title = "Quarterly numbers for Acme".into();
+        let meeting = TimeBlock {
+            block_type: BlockType::Meeting,
+            ..block("standup", at(10, 30), at(11, 0))
+        };
+        let samples = [
+            run(at(9, 40), "Code", 12),
+            run(at(9, 50), "firefox", 12),
+            run(at(10, 10), "kitty", 12),
+            run(at(10, 30), "zoom", 36),
+            run(at(10, 45), "Slack", 12),
+            run(at(12, 0), "Code", 12),
+        ]
+        .concat();
+        (vec![focus, meeting], samples)
+    }
+
+    #[test]
+    fn export_aggregates_focus_hours_and_block_types() {
+        let (blocks, samples) = day();
+        let (start, end) = (at(0, 0), at(0, 0) + Duration::days(1));
+        let berlin = Zone::named("Europe/Berlin").unwrap();
+        let patterns = export(&blocks, &samples, start, end, berlin);
+        let window = json!({ "start": "2026-03-02T00:00:00Z", "end": "2026-03-03T00:00:00Z" });
+
+        assert_eq!(patterns.len(), 2);
+        let focus = &patterns[0];
+        assert_eq!(focus.pattern_type, "focus_time");
+        assert_eq!(
+            focus.pattern_data,
+            json!({
+                "source": "activity",
+                "window": window,
+                "hours": [
+                    { "hour": 10, "focusedShare": 0.5, "minutes": 2 },
+                    { "hour": 11, "focusedShare": 1.0, "minutes": 1 },
+                ],
+            })
+        );
+        assert_eq!(focus.confidence, 36.0 / CONFIDENT_SAMPLES);
+        assert_eq!(focus.last_observed, at(12, 0) + Duration::seconds(55));
+
+        let timing = &patterns[1];
+        assert_eq!(timing.pattern_type, "task_timing");
+        assert_eq!(
+            timing.pattern_data,
+            json!({
+                "source": "activity",
+                "window": window,
+                "blockTypes": {
+                    "focus": {
+                        "minutes": 3,
+                        "categories": {
+                            "editor": 1.0 / 3.0,
+                            "terminal": 1.0 / 3.0,
+                            "browser": 1.0 / 3.0,
+                        },
+                    },
+                    "meeting": {
+                        "minutes": 4,
+                        "categories": { "meeting": 0.75, "chat": 0.25 },
+                    },
+                },
+            })
+        );
+        assert_eq!(timing.confidence, 84.0 / CONFIDENT_SAMPLES);
+
+        // Hours follow the zone asked for.
+        let patterns = export(&blocks, &samples, start, end, Zone::named("UTC").unwrap());
+        let hours: Vec<&Value> = patterns[0].pattern_data["hours"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|hour| &hour["hour"])
+            .collect();
+        assert_eq!(hours, [&json!(9), &json!(10)]);
+    }
+
+    #[test]
+    fn export_is_empty_without_samples_in_blocks() {
+        let (blocks, samples) = day();
+        let zone = Zone::named("UTC").unwrap();
+        assert!(export(&blocks, &[], at(0, 0), at(23, 0), zone).is_empty());
+        let outside = &samples[samples.len() - 12..];
+        assert!(export(&blocks, outside, at(0, 0), at(23, 0), zone).is_empty());
+        let breaks = [TimeBlock {
+            block_type: BlockType::Break,
+            ..block("lunch", at(12, 0), at(13, 0))
+        }];
+        let patterns = export(&breaks, outside, at(0, 0), at(23, 0), zone);
+        let types: Vec<&str> = patterns.iter().map(|p| p.pattern_type).collect();
+        assert_eq!(types, ["task_timing"]);
+    }
+
+    #[test]
+    fn export_carries_no_names_or_titles() {
+        let (blocks, mut samples) = day();
+        samples.push(sample(
+            at(9, 45),
+            "Budget 2027 - confidential.ods",
+            "soffice.bin",
+        ));
+        let patterns = export(
+            &blocks,
+            &samples,
+            at(0, 0),
+            at(23, 0),
+            Zone::named("UTC").unwrap(),
+        );
+        let exported = serde_json::to_string(&patterns).unwrap();
+        for sample in &samples {
+            assert!(!exported.contains(&sample.app), "{}", sample.app);
+            assert!(
+                !exported.contains(&sample.window_class),
+                "{}",
+                sample.window_class
+            );
+        }
+        for block in &blocks {
+            assert!(!exported.contains(&block.title), "{}", block.title);
+        }
+    }
+}
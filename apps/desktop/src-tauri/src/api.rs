@@ -0,0 +1,614 @@
+//! An opt-in local API for scripting the running app from shells, editors
+//! and git hooks. It listens on a Unix socket in the app data dir, or on
+//! 127.0.0.1, and every request needs the per-install bearer token.
+//!
+//! Where to connect and the token are written to `api.json` next to the
+//! socket, readable only by the user, so local tools can find the server.
+//! Endpoints call the same functions as the matching IPC commands:
+//!
+//! - `GET /today`: the day's schedule, like `store_today`
+//! - `GET /blocks/next`: the running block and the next one
+//! - `POST /tasks` with `{"text": "..."}`: quick-capture syntax, like `capture_submit`
+//! - `POST /blocks/{id}/complete`, with `id` percent-encoded: like `store_complete_time_block`
+//! - `GET /inbox` with optional `?decision=now|tomorrow|never`: emails, like `store_list_emails`
+//! - `GET /focus`: like `focus_status`
+//! - `POST /focus/start` with optional `{"blockId", "pomodoro"}`: like `focus_start`
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::Utc;
+use percent_encoding::percent_decode_str;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, Runtime, State};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::error::{Error, Result};
+use crate::focus::Pomodoro;
+use crate::store::{Email, Store, TimeBlock};
+
+const SETTINGS_KEY: &str = "api.settings";
+const TOKEN_KEY: &str = "api.token";
+pub(crate) const DISCOVERY_FILE: &str = "api.json";
+const SOCKET_FILE: &str = "dayli.sock";
+const DEFAULT_PORT: u16 = 47_821;
+const MAX_BODY: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    Unix,
+    Tcp,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub transport: Transport,
+    pub port: u16,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            transport: if cfg!(unix) {
+                Transport::Unix
+            } else {
+                Transport::Tcp
+            },
+            port: DEFAULT_PORT,
+        }
+    }
+}
+
+/// What `api.json` holds: everything a client needs to connect.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Discovery {
+    pub transport: Transport,
+    /// The socket path, or `127.0.0.1:<port>`.
+    pub address: String,
+    pub token: String,
+}
+
+struct Running {
+    server: Arc<Server>,
+    thread: JoinHandle<()>,
+    discovery: Discovery,
+    discovery_path: PathBuf,
+}
+
+#[derive(Default)]
+pub struct Api {
+    running: Mutex<Option<Running>>,
+}
+
+fn data_dir<R: Runtime>(app: &AppHandle<R>) -> Result<PathBuf> {
+    Ok(app.path().app_local_data_dir()?)
+}
+
+fn new_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// The install's token, created the first time it's needed.
+fn token(store: &Store) -> Result<String> {
+    if let Some(token) = store.setting(TOKEN_KEY)? {
+        return Ok(token);
+    }
+    let token = new_token();
+    store.set_setting(TOKEN_KEY, &token)?;
+    Ok(token)
+}
+
+fn settings(store: &Store) -> ApiSettings {
+    store
+        .setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Write a file only the user can read.
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(contents)?;
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn bind(dir: &Path, settings: &ApiSettings) -> Result<(Server, Transport, String)> {
+    let bind_error = |err: Box<dyn std::error::Error + Send + Sync>| Error::Api(err.to_string());
+    #[cfg(unix)]
+    if settings.transport == Transport::Unix {
+        use std::os::unix::fs::PermissionsExt;
+        let path = dir.join(SOCKET_FILE);
+        // A socket left by a crash refuses new binds.
+        let _ = fs::remove_file(&path);
+        let server = Server::http_unix(&path).map_err(bind_error)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        return Ok((server, Transport::Unix, path.to_string_lossy().into_owned()));
+    }
+    let address = format!("127.0.0.1:{}", settings.port);
+    let server = Server::http(&address).map_err(bind_error)?;
+    Ok((server, Transport::Tcp, address))
+}
+
+fn json_response(status: u16, body: &Value) -> Response<std::io::Cursor<Vec<u8>>> {
+    let header = Header::from_bytes("Content-Type", "application/json").expect("valid header");
+    Response::from_data(serde_json::to_vec(body).unwrap_or_default())
+        .with_status_code(status)
+        .with_header(header)
+}
+
+fn error_status(err: &Error) -> u16 {
+    match err {
+        Error::NotFound(_) => 404,
+        Error::Json(_) | Error::Capture(_) | Error::Focus(_) => 400,
+        Error::Auth(_) => 401,
+        _ => 500,
+    }
+}
+
+/// Constant-time comparison, so the token can't be guessed byte by byte.
+fn token_matches(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    request.headers().iter().any(|header| {
+        header.field.equiv("Authorization")
+            && header
+                .value
+                .as_str()
+                .strip_prefix("Bearer ")
+                .is_some_and(|given| token_matches(given.trim(), token))
+    })
+}
+
+#[derive(Deserialize)]
+struct NewTask {
+    text: String,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FocusStart {
+    block_id: Option<String>,
+    pomodoro: Option<Pomodoro>,
+}
+
+/// The running block and the next one to start.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NextBlocks {
+    pub current: Option<TimeBlock>,
+    pub next: Option<TimeBlock>,
+}
+
+pub fn next_blocks(store: &Store) -> Result<NextBlocks> {
+    let now = Utc::now();
+    let current = store.blocks_at(now)?.into_iter().next();
+    let next = store
+        .list_time_blocks(now, now + chrono::Duration::days(7))?
+        .into_iter()
+        .find(|block| block.start_time > now);
+    Ok(NextBlocks { current, next })
+}
+
+/// Emails, newest first, with the given triage decision if there is one.
+pub fn inbox(store: &Store, decision: Option<&str>) -> Result<Vec<Email>> {
+    Ok(store
+        .list_emails(None)?
+        .into_iter()
+        .filter(|email| decision.is_none() || email.decision.as_deref() == decision)
+        .collect())
+}
+
+fn parse<T: serde::de::DeserializeOwned + Default>(body: &str) -> Result<T> {
+    if body.trim().is_empty() {
+        return Ok(T::default());
+    }
+    Ok(serde_json::from_str(body)?)
+}
+
+fn route<R: Runtime>(
+    app: &AppHandle<R>,
+    method: &Method,
+    path: &str,
+    body: &str,
+) -> Result<(u16, Value)> {
+    let store = app.state::<Store>();
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let query: HashMap<_, _> = url::form_urlencoded::parse(query.as_bytes()).collect();
+    let segments: Vec<Cow<'_, str>> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| percent_decode_str(segment).decode_utf8_lossy())
+        .collect();
+    let segments: Vec<&str> = segments.iter().map(|segment| segment.as_ref()).collect();
+
+    let ok = |value: Value| Ok((200, value));
+    match (method, segments.as_slice()) {
+        (Method::Get, ["today"]) => ok(json!(store.day_schedule(store.today()?)?)),
+        (Method::Get, ["blocks", "next"]) => ok(json!(next_blocks(&store)?)),
+        (Method::Post, ["tasks"]) => {
+            let new_task: NewTask = serde_json::from_str(body)?;
+            Ok((201, json!(crate::capture::capture(app, &new_task.text)?)))
+        }
+        (Method::Post, ["blocks", id, "complete"]) => {
+            ok(json!(crate::store::commands::complete_block(app, id)?))
+        }
+        (Method::Get, ["inbox"]) => ok(json!(inbox(
+            &store,
+            query.get("decision").map(|decision| decision.as_ref())
+        )?)),
+        (Method::Get, ["focus"]) => ok(json!(crate::focus::status(app))),
+        (Method::Post, ["focus", "start"]) => {
+            let start: FocusStart = parse(body)?;
+            ok(json!(crate::focus::start(
+                app,
+                start.block_id.as_deref(),
+                start.pomodoro
+            )?))
+        }
+        _ => Err(Error::NotFound(format!("{method} {path}"))),
+    }
+}
+
+fn respond<R: Runtime>(app: &AppHandle<R>, mut request: Request, token: &str) {
+    let response = if !authorized(&request, token) {
+        json_response(401, &json!({ "error": "missing or wrong bearer token" }))
+    } else {
+        let mut body = String::new();
+        let read = request.as_reader().take(MAX_BODY).read_to_string(&mut body);
+        let method = request.method().clone();
+        let path = request.url().to_owned();
+        match read
+            .map_err(Error::from)
+            .and_then(|_| route(app, &method, &path, &body))
+        {
+            Ok((status, value)) => json_response(status, &value),
+            Err(err) => json_response(error_status(&err), &json!({ "error": err.to_string() })),
+        }
+    };
+    if let Err(err) = request.respond(response) {
+        log::error!("failed to answer api request: {err}");
+    }
+}
+
+/// Stop the server and remove its socket and discovery file.
+fn stop<R: Runtime>(app: &AppHandle<R>) {
+    let Some(running) = app.state::<Api>().running.lock().unwrap().take() else {
+        return;
+    };
+    running.server.unblock();
+    let _ = running.thread.join();
+    let _ = fs::remove_file(&running.discovery_path);
+    if running.discovery.transport == Transport::Unix {
+        let _ = fs::remove_file(&running.discovery.address);
+    }
+}
+
+/// Start the server if it's enabled, replacing one already running.
+fn restart<R: Runtime>(app: &AppHandle<R>) -> Result<()> {
+    stop(app);
+    let store = app.state::<Store>();
+    let settings = settings(&store);
+    if !settings.enabled {
+        return Ok(());
+    }
+
+    let dir = data_dir(app)?;
+    fs::create_dir_all(&dir)?;
+    let (server, transport, address) = bind(&dir, &settings)?;
+    let discovery = Discovery {
+        transport,
+        address,
+        token: token(&store)?,
+    };
+    let discovery_path = dir.join(DISCOVERY_FILE);
+    write_private(&discovery_path, &serde_json::to_vec_pretty(&discovery)?)?;
+
+    let server = Arc::new(server);
+    let thread = {
+        let server = server.clone();
+        let app = app.clone();
+        let token = discovery.token.clone();
+        std::thread::spawn(move || {
+            for request in server.incoming_requests() {
+                respond(&app, request, &token);
+            }
+        })
+    };
+    *app.state::<Api>().running.lock().unwrap() = Some(Running {
+        server,
+        thread,
+        discovery,
+        discovery_path,
+    });
+    Ok(())
+}
+
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    app.manage(Api::default());
+    if let Err(err) = restart(app.handle()) {
+        log::error!("failed to start local api: {err}");
+    }
+    Ok(())
+}
+
+/// Shut the server down on exit so the socket isn't left behind.
+pub fn shutdown<R: Runtime>(app: &AppHandle<R>) {
+    if app.try_state::<Api>().is_some() {
+        stop(app);
+    }
+}
+
+/// Where clients find the running server. The token stays in the discovery
+/// file; the webview never sees it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Listening {
+    pub transport: Transport,
+    pub discovery_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiStatus {
+    pub settings: ApiSettings,
+    pub running: Option<Listening>,
+}
+
+#[tauri::command]
+pub fn api_status(api: State<'_, Api>, store: State<'_, Store>) -> ApiStatus {
+    ApiStatus {
+        settings: settings(&store),
+        running: api
+            .running
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|running| Listening {
+                transport: running.discovery.transport,
+                discovery_path: running.discovery_path.clone(),
+            }),
+    }
+}
+
+#[tauri::command]
+pub fn api_set_settings<R: Runtime>(
+    app: AppHandle<R>,
+    settings: ApiSettings,
+    store: State<'_, Store>,
+) -> Result<()> {
+    store.set_setting(SETTINGS_KEY, &settings)?;
+    restart(&app)
+}
+
+/// Replace the token, cutting off every client holding the old one.
+#[tauri::command]
+pub fn api_rotate_token<R: Runtime>(app: AppHandle<R>, store: State<'_, Store>) -> Result<()> {
+    store.set_setting(TOKEN_KEY, &new_token())?;
+    restart(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::TcpStream;
+
+    use chrono::Duration;
+
+    use super::*;
+    use crate::testing::{block, TestApp};
+
+    const TOKEN: &str = "s3cret-token";
+
+    fn call(app: &TestApp, method: Method, path: &str, body: &str) -> (u16, Value) {
+        route(app.handle(), &method, path, body)
+            .unwrap_or_else(|err| (error_status(&err), json!({ "error": err.to_string() })))
+    }
+
+    fn seed(app: &TestApp) {
+        let now = Utc::now();
+        let store = app.store();
+        store
+            .save_time_block(&block(
+                "now 1/a",
+                now - Duration::minutes(10),
+                now + Duration::minutes(50),
+            ))
+            .unwrap();
+        store
+            .save_time_block(&block(
+                "later",
+                now + Duration::hours(2),
+                now + Duration::hours(3),
+            ))
+            .unwrap();
+    }
+
+    #[test]
+    fn next_blocks_are_the_running_and_the_upcoming_one() {
+        let app = TestApp::new();
+        seed(&app);
+        let (status, body) = call(&app, Method::Get, "/blocks/next", "");
+        assert_eq!(status, 200);
+        assert_eq!(body["current"]["id"], "now 1/a");
+        assert_eq!(body["next"]["id"], "later");
+    }
+
+    #[test]
+    fn tasks_are_captured_with_the_quick_syntax() {
+        let app = TestApp::new();
+        let (status, body) = call(
+            &app,
+            Method::Post,
+            "/tasks",
+            r#"{"text": "Write the report ~30m !high"}"#,
+        );
+        assert_eq!(status, 201);
+        assert_eq!(body["title"], "Write the report");
+        assert_eq!(body["estimated_minutes"], 30);
+        assert_eq!(app.store().list_tasks(None).unwrap().len(), 1);
+
+        let (status, _) = call(&app, Method::Post, "/tasks", r#"{"text": "  "}"#);
+        assert_eq!(status, 400);
+        let (status, _) = call(&app, Method::Post, "/tasks", "not json");
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn focus_starts_on_the_running_block_and_completing_it_ends_the_session() {
+        let app = TestApp::new();
+        seed(&app);
+        assert_eq!(call(&app, Method::Get, "/focus", ""), (200, Value::Null));
+
+        let (status, body) = call(&app, Method::Post, "/focus/start", "");
+        assert_eq!(status, 200);
+        assert_eq!(body["blockId"], "now 1/a");
+        let (status, _) = call(&app, Method::Post, "/focus/start", "{}");
+        assert_eq!(status, 400, "a session is already running");
+        assert_eq!(
+            call(&app, Method::Get, "/focus", "").1["blockId"],
+            "now 1/a"
+        );
+
+        // Ids are percent-decoded, so any id can be addressed.
+        let (status, body) = call(&app, Method::Post, "/blocks/now%201%2Fa/complete", "");
+        assert_eq!(status, 200);
+        assert_eq!(body["id"], "now 1/a");
+        assert!(body["metadata"]["completedAt"].is_string());
+        assert_eq!(call(&app, Method::Get, "/focus", ""), (200, Value::Null));
+    }
+
+    #[test]
+    fn unknown_blocks_and_routes_are_not_found() {
+        let app = TestApp::new();
+        let (status, _) = call(&app, Method::Post, "/blocks/nope/complete", "");
+        assert_eq!(status, 404);
+        let (status, _) = call(&app, Method::Get, "/tasks", "");
+        assert_eq!(status, 404);
+        let (status, _) = call(&app, Method::Delete, "/focus", "");
+        assert_eq!(status, 404);
+        let (status, body) = call(&app, Method::Get, "/today?x=1", "");
+        assert_eq!(status, 200);
+        assert!(body["timeBlocks"].is_array());
+        let (status, body) = call(&app, Method::Get, "/inbox?decision=now", "");
+        assert_eq!((status, body), (200, json!([])));
+    }
+
+    #[test]
+    fn token_comparison() {
+        assert!(token_matches(TOKEN, TOKEN));
+        assert!(!token_matches("s3cret-tokem", TOKEN));
+        assert!(!token_matches("s3cret", TOKEN));
+        assert!(!token_matches("", TOKEN));
+    }
+
+    fn raw(address: &str, authorization: Option<&str>) -> u16 {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let header = authorization
+            .map(|value| format!("Authorization: {value}\r\n"))
+            .unwrap_or_default();
+        write!(
+            stream,
+            "GET /focus HTTP/1.1\r\nHost: localhost\r\n{header}Connection: close\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.split(' ').nth(1).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn every_request_needs_the_bearer_token() {
+        let app = TestApp::new();
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap().to_string();
+        let cases = [
+            (None, 401),
+            (Some("Bearer wrong".to_owned()), 401),
+            (Some(TOKEN.to_owned()), 401),
+            (Some(format!("Basic {TOKEN}")), 401),
+            (Some(format!("Bearer {TOKEN}x")), 401),
+            (Some(format!("Bearer {TOKEN}")), 200),
+        ];
+        let count = cases.len();
+        let handle = app.handle().clone();
+        let serving = std::thread::spawn(move || {
+            for request in server.incoming_requests().take(count) {
+                respond(&handle, request, TOKEN);
+            }
+        });
+        for (authorization, expected) in cases {
+            assert_eq!(
+                raw(&address, authorization.as_deref()),
+                expected,
+                "{authorization:?}"
+            );
+        }
+        serving.join().unwrap();
+    }
+
+    #[test]
+    fn status_doesnt_reveal_the_token() {
+        let app = TestApp::new();
+        app.app.manage(Api::default());
+        let server = Arc::new(Server::http("127.0.0.1:0").unwrap());
+        *app.app.state::<Api>().running.lock().unwrap() = Some(Running {
+            server: server.clone(),
+            thread: std::thread::spawn(|| {}),
+            discovery: Discovery {
+                transport: Transport::Tcp,
+                address: "127.0.0.1:47821".into(),
+                token: TOKEN.into(),
+            },
+            discovery_path: PathBuf::from("/data/api.json"),
+        });
+
+        let status = api_status(app.app.state(), app.app.state());
+        let json = serde_json::to_string(&status).unwrap();
+        assert!(!json.contains(TOKEN), "{json}");
+        let running = &serde_json::to_value(&status).unwrap()["running"];
+        assert_eq!(
+            running,
+            &json!({ "transport": "tcp", "discoveryPath": "/data/api.json" })
+        );
+    }
+}
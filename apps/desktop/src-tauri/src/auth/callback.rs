@@ -0,0 +1,126 @@
+use url::Url;
+
+use crate::error::{Error, Result};
+
+pub const CALLBACK_URL: &str = "dayli://auth/callback";
+
+/// What the auth server sent back to `dayli://auth/callback`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Callback {
+    Code {
+        code: String,
+        state: String,
+    },
+    Denied {
+        error: String,
+        description: Option<String>,
+    },
+}
+
+/// Returns true if `url` is addressed to the OAuth callback handler.
+pub fn is_callback(url: &Url) -> bool {
+    url.scheme() == "dayli" && url.host_str() == Some("auth") && url.path() == "/callback"
+}
+
+/// Parse a `dayli://auth/callback?...` URL. Supabase reports errors in either
+/// the query or the fragment, so both are checked.
+pub fn parse(url: &Url) -> Result<Callback> {
+    if !is_callback(url) {
+        return Err(Error::InvalidCallback(format!("unexpected url {url}")));
+    }
+
+    let mut params: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+    if let Some(fragment) = url.fragment() {
+        params.extend(url::form_urlencoded::parse(fragment.as_bytes()).into_owned());
+    }
+    let get = |key: &str| {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    };
+
+    if let Some(error) = get("error") {
+        return Ok(Callback::Denied {
+            error,
+            description: get("error_description"),
+        });
+    }
+
+    let code = get("code").filter(|c| !c.is_empty());
+    let state = get("state").filter(|s| !s.is_empty());
+    match (code, state) {
+        (Some(code), Some(state)) => Ok(Callback::Code { code, state }),
+        (None, _) => Err(Error::InvalidCallback("missing code".into())),
+        (_, None) => Err(Error::InvalidCallback("missing state".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(url: &str) -> Result<Callback> {
+        parse(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn recognises_only_the_callback_url() {
+        assert!(is_callback(
+            &Url::parse("dayli://auth/callback?code=a").unwrap()
+        ));
+        assert!(!is_callback(&Url::parse("dayli://auth/other").unwrap()));
+        assert!(!is_callback(&Url::parse("https://auth/callback").unwrap()));
+        assert!(matches!(
+            parse_str("dayli://open/today"),
+            Err(Error::InvalidCallback(_))
+        ));
+    }
+
+    #[test]
+    fn parses_code_and_state() {
+        assert_eq!(
+            parse_str("dayli://auth/callback?state=s1&code=c%2B1").unwrap(),
+            Callback::Code {
+                code: "c+1".into(),
+                state: "s1".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn reads_errors_from_the_query_or_fragment() {
+        assert_eq!(
+            parse_str("dayli://auth/callback?error=access_denied").unwrap(),
+            Callback::Denied {
+                error: "access_denied".into(),
+                description: None,
+            }
+        );
+        assert_eq!(
+            parse_str(
+                "dayli://auth/callback?state=s1#error=server_error&error_description=Try+again"
+            )
+            .unwrap(),
+            Callback::Denied {
+                error: "server_error".into(),
+                description: Some("Try again".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_a_missing_or_empty_code_or_state() {
+        for url in [
+            "dayli://auth/callback?state=s1",
+            "dayli://auth/callback?code=&state=s1",
+            "dayli://auth/callback?code=c1",
+            "dayli://auth/callback?code=c1&state=",
+        ] {
+            assert!(
+                matches!(parse_str(url), Err(Error::InvalidCallback(_))),
+                "{url}"
+            );
+        }
+    }
+}
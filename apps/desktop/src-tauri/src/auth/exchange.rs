@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::config::SupabaseConfig;
+use crate::error::{Error, Result};
+
+/// The session returned by the Supabase token endpoint. Refresh tokens are
+/// kept in the vault and never serialized to the webview.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthSession {
+    pub access_token: String,
+    #[serde(skip_serializing)]
+    pub refresh_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+    #[serde(default)]
+    pub provider_token: Option<String>,
+    #[serde(default, skip_serializing)]
+    pub provider_refresh_token: Option<String>,
+    #[serde(default)]
+    pub user: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(alias = "error_description", alias = "msg")]
+    message: Option<String>,
+}
+
+/// Exchange an authorization code and PKCE verifier for a session.
+pub async fn exchange_code(
+    client: &reqwest::Client,
+    config: &SupabaseConfig,
+    code: &str,
+    verifier: &str,
+) -> Result<AuthSession> {
+    let response = client
+        .post(format!("{}/auth/v1/token", config.url))
+        .query(&[("grant_type", "pkce")])
+        .header("apikey", &config.anon_key)
+        .json(&json!({ "auth_code": code, "code_verifier": verifier }))
+        .send()
+        .await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let message = response
+            .json::<ErrorBody>()
+            .await
+            .ok()
+            .and_then(|body| body.message)
+            .unwrap_or_else(|| status.to_string());
+        return Err(Error::Auth(message));
+    }
+
+    Ok(response.json().await?)
+}
+
+#[cfg(all(test, desktop))]
+mod tests {
+    use std::thread::JoinHandle;
+
+    use serde_json::Value;
+
+    use super::*;
+
+    /// A token endpoint answering one request with `status` and `body`,
+    /// returning what it was sent.
+    fn token_endpoint(status: u16, body: &str) -> (SupabaseConfig, JoinHandle<(String, Value)>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let config = SupabaseConfig {
+            url: format!("http://{}", server.server_addr()),
+            anon_key: "anon".into(),
+        };
+        let body = body.to_owned();
+        let handle = std::thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut sent = String::new();
+            request.as_reader().read_to_string(&mut sent).unwrap();
+            let url = request.url().to_owned();
+            let apikey = request
+                .headers()
+                .iter()
+                .any(|header| header.field.equiv("apikey") && header.value == "anon");
+            assert!(apikey, "the anon key is sent");
+            let response = tiny_http::Response::from_string(body).with_status_code(status);
+            request.respond(response).unwrap();
+            (url, serde_json::from_str(&sent).unwrap())
+        });
+        (config, handle)
+    }
+
+    fn exchange(config: &SupabaseConfig) -> Result<AuthSession> {
+        tauri::async_runtime::block_on(exchange_code(
+            &reqwest::Client::new(),
+            config,
+            "code-1",
+            "verifier-1",
+        ))
+    }
+
+    #[test]
+    fn exchanges_the_code_and_verifier_for_a_session() {
+        let (config, server) = token_endpoint(
+            200,
+            r#"{"access_token":"at","refresh_token":"rt","token_type":"bearer",
+                "expires_in":3600,"provider_token":"gt","user":{"id":"u1"}}"#,
+        );
+        let session = exchange(&config).unwrap();
+        let (url, sent) = server.join().unwrap();
+
+        assert_eq!(url, "/auth/v1/token?grant_type=pkce");
+        assert_eq!(
+            sent,
+            serde_json::json!({ "auth_code": "code-1", "code_verifier": "verifier-1" })
+        );
+        assert_eq!(session.access_token, "at");
+        assert_eq!(session.refresh_token, "rt");
+        assert_eq!(session.expires_at, None);
+        assert_eq!(session.provider_token.as_deref(), Some("gt"));
+        assert_eq!(session.user["id"], "u1");
+        // The refresh tokens never reach the webview.
+        let shown = serde_json::to_value(&session).unwrap();
+        assert!(shown.get("refresh_token").is_none());
+        assert!(shown.get("provider_refresh_token").is_none());
+    }
+
+    #[test]
+    fn reports_the_error_body() {
+        for body in [
+            r#"{"error":"invalid_grant","error_description":"Code expired"}"#,
+            r#"{"code":400,"msg":"Code expired"}"#,
+        ] {
+            let (config, server) = token_endpoint(400, body);
+            let err = exchange(&config).unwrap_err();
+            server.join().unwrap();
+            assert!(
+                matches!(&err, Error::Auth(message) if message == "Code expired"),
+                "{err}"
+            );
+        }
+    }
+
+    #[test]
+    fn falls_back_to_the_status_without_a_message() {
+        let (config, server) = token_endpoint(502, "Bad Gateway");
+        let err = exchange(&config).unwrap_err();
+        server.join().unwrap();
+        assert!(
+            matches!(&err, Error::Auth(message) if message.starts_with("502")),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,246 @@
+//! Native OAuth sign-in. The webview asks for an authorize URL, the system
+//! browser completes the provider flow, and the auth server redirects to
+//! `dayli://auth/callback`, which the deep-link plugin hands to
+//! [`handle_callback`].
+
+mod callback;
+mod exchange;
+mod pkce;
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use url::Url;
+
+use crate::config::AppConfig;
+use crate::error::{Error, Result};
+use crate::vault::{unix_now, Credential, Provider, TokenVault};
+
+pub use callback::{is_callback, parse, Callback, CALLBACK_URL};
+pub use exchange::{exchange_code, AuthSession};
+pub use pkce::{random_token, Pkce};
+
+pub const SESSION_EVENT: &str = "auth://session";
+pub const ERROR_EVENT: &str = "auth://error";
+
+/// How long a started login waits for its callback.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+/// Google doesn't report the provider token's lifetime through Supabase; its
+/// access tokens last an hour.
+const GOOGLE_TOKEN_LIFETIME_SECS: i64 = 60 * 60;
+
+struct PendingLogin {
+    state: String,
+    verifier: String,
+    started: Instant,
+}
+
+#[derive(Default)]
+pub struct AuthState {
+    pending: Mutex<Option<PendingLogin>>,
+    client: reqwest::Client,
+}
+
+impl AuthState {
+    /// Remember a new login attempt and return the URL the browser should open.
+    fn begin(&self, config: &AppConfig, provider: &str) -> Result<Url> {
+        let pkce = Pkce::generate();
+        let state = random_token(16);
+
+        let mut redirect = Url::parse(CALLBACK_URL)?;
+        redirect.query_pairs_mut().append_pair("state", &state);
+
+        let mut url = Url::parse(&format!("{}/auth/v1/authorize", config.supabase.url))?;
+        url.query_pairs_mut()
+            .append_pair("provider", provider)
+            .append_pair("redirect_to", redirect.as_str())
+            .append_pair("code_challenge", &pkce.challenge)
+            .append_pair("code_challenge_method", "s256");
+
+        *self.pending.lock().unwrap() = Some(PendingLogin {
+            state,
+            verifier: pkce.verifier,
+            started: Instant::now(),
+        });
+        Ok(url)
+    }
+
+    /// Consume the pending login if `state` matches it. A login can only be
+    /// completed once.
+    fn take_verifier(&self, state: &str) -> Result<String> {
+        let pending = self
+            .pending
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or_else(|| Error::InvalidCallback("no login in progress".into()))?;
+
+        if pending.started.elapsed() > LOGIN_TIMEOUT {
+            return Err(Error::InvalidCallback("login expired".into()));
+        }
+        if pending.state != state {
+            return Err(Error::InvalidCallback("state mismatch".into()));
+        }
+        Ok(pending.verifier)
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct AuthError {
+    message: String,
+}
+
+/// Start an OAuth login and return the authorize URL to open in the browser.
+#[tauri::command]
+pub fn auth_begin(
+    provider: String,
+    auth: State<'_, AuthState>,
+    config: State<'_, AppConfig>,
+) -> Result<String> {
+    Ok(auth.begin(&config, &provider)?.into())
+}
+
+/// Handle a `dayli://auth/callback` URL: validate it against the pending
+/// login, exchange the code, store the tokens in the vault and emit the
+/// session to the webview.
+pub fn handle_callback<R: Runtime>(app: &AppHandle<R>, url: &Url) {
+    let app = app.clone();
+    let url = url.clone();
+    tauri::async_runtime::spawn(async move {
+        let event = match complete(&app, &url).await {
+            Ok(session) => app.emit(SESSION_EVENT, session),
+            Err(err) => app.emit(
+                ERROR_EVENT,
+                AuthError {
+                    message: err.to_string(),
+                },
+            ),
+        };
+        if let Err(err) = event {
+            log::warn!("failed to emit auth event: {err}");
+        }
+    });
+}
+
+async fn complete<R: Runtime>(app: &AppHandle<R>, url: &Url) -> Result<AuthSession> {
+    let (code, state) = match parse(url)? {
+        Callback::Code { code, state } => (code, state),
+        Callback::Denied { error, description } => {
+            return Err(Error::Auth(description.unwrap_or(error)));
+        }
+    };
+
+    let auth = app.state::<AuthState>();
+    let config = app.state::<AppConfig>();
+    let verifier = auth.take_verifier(&state)?;
+    let session = exchange_code(&auth.client, &config.supabase, &code, &verifier).await?;
+
+    let vault = app.state::<TokenVault>();
+    vault.set(
+        Provider::Supabase,
+        Credential {
+            access_token: session.access_token.clone(),
+            refresh_token: Some(session.refresh_token.clone()),
+            expires_at: session.expires_at.or(Some(unix_now() + session.expires_in)),
+        },
+    )?;
+    if let Some(token) = &session.provider_token {
+        vault.set(
+            Provider::Google,
+            Credential {
+                access_token: token.clone(),
+                refresh_token: session.provider_refresh_token.clone(),
+                expires_at: Some(unix_now() + GOOGLE_TOKEN_LIFETIME_SECS),
+            },
+        )?;
+    }
+
+    Ok(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GoogleConfig, SupabaseConfig};
+
+    fn config() -> AppConfig {
+        AppConfig {
+            open_devtools: false,
+            start_minimized: false,
+            tray: false,
+            supabase: SupabaseConfig {
+                url: "https://project.supabase.co".into(),
+                anon_key: "anon".into(),
+            },
+            google: GoogleConfig::default(),
+            hosts_path: Default::default(),
+        }
+    }
+
+    fn query(url: &Url, key: &str) -> Option<String> {
+        url.query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.into_owned())
+    }
+
+    /// Start a login and return its state, read back from the redirect URL.
+    fn begin(auth: &AuthState) -> String {
+        let url = auth.begin(&config(), "google").unwrap();
+        assert_eq!(url.path(), "/auth/v1/authorize");
+        assert_eq!(query(&url, "provider").as_deref(), Some("google"));
+        assert_eq!(
+            query(&url, "code_challenge_method").as_deref(),
+            Some("s256")
+        );
+        let redirect = Url::parse(&query(&url, "redirect_to").unwrap()).unwrap();
+        assert!(is_callback(&redirect));
+        query(&redirect, "state").unwrap()
+    }
+
+    #[test]
+    fn hands_out_the_verifier_once_for_the_matching_state() {
+        let auth = AuthState::default();
+        let state = begin(&auth);
+        let verifier = auth.take_verifier(&state).unwrap();
+        assert_eq!(verifier.len(), 43);
+        assert!(matches!(
+            auth.take_verifier(&state),
+            Err(Error::InvalidCallback(message)) if message == "no login in progress"
+        ));
+    }
+
+    #[test]
+    fn a_state_mismatch_ends_the_login() {
+        let auth = AuthState::default();
+        let state = begin(&auth);
+        assert!(matches!(
+            auth.take_verifier("forged"),
+            Err(Error::InvalidCallback(message)) if message == "state mismatch"
+        ));
+        // The attempt is spent, so the real callback can't complete it later.
+        assert!(auth.take_verifier(&state).is_err());
+    }
+
+    #[test]
+    fn a_login_expires() {
+        let auth = AuthState::default();
+        let state = begin(&auth);
+        auth.pending.lock().unwrap().as_mut().unwrap().started =
+            Instant::now() - LOGIN_TIMEOUT - Duration::from_secs(1);
+        assert!(matches!(
+            auth.take_verifier(&state),
+            Err(Error::InvalidCallback(message)) if message == "login expired"
+        ));
+    }
+
+    #[test]
+    fn a_new_login_replaces_the_pending_one() {
+        let auth = AuthState::default();
+        let first = begin(&auth);
+        let second = begin(&auth);
+        assert_ne!(first, second);
+        assert!(auth.take_verifier(&second).is_ok());
+    }
+}
@@ -0,0 +1,237 @@
+//! Launch at login. On Linux this is an XDG autostart entry,
+//! `$XDG_CONFIG_HOME/autostart/dayli.desktop`, whose `Exec` line carries
+//! [`MINIMIZED_FLAG`] when the app should start in the tray. The entry itself
+//! is the source of truth, so the settings always reflect what the desktop
+//! session will actually do.
+
+use serde::Serialize;
+use tauri::{Manager, Runtime};
+
+use crate::config::AppConfig;
+use crate::error::Result;
+
+/// Passed on the command line to start hidden in the tray.
+pub const MINIMIZED_FLAG: &str = "--minimized";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutostartSettings {
+    pub enabled: bool,
+    pub minimized: bool,
+}
+
+#[cfg(target_os = "linux")]
+mod xdg {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{AutostartSettings, MINIMIZED_FLAG};
+    use crate::error::{Error, Result};
+
+    const FILE_NAME: &str = "dayli.desktop";
+
+    fn entry_path() -> Result<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .ok_or_else(|| Error::Autostart("no config directory".into()))?;
+        Ok(config_home.join("autostart").join(FILE_NAME))
+    }
+
+    /// The binary to launch. An AppImage runs from a temporary mount, so the
+    /// image itself is what has to be started.
+    fn executable() -> Result<PathBuf> {
+        match std::env::var_os("APPIMAGE") {
+            Some(image) => Ok(PathBuf::from(image)),
+            None => Ok(std::env::current_exe()?),
+        }
+    }
+
+    /// Quote an `Exec` argument as the desktop entry spec requires.
+    fn quote(arg: &str) -> String {
+        if !arg.contains(|c: char| c.is_whitespace() || "\"'\\><~|&;$*?#()`".contains(c)) {
+            return arg.to_owned();
+        }
+        let mut quoted = String::with_capacity(arg.len() + 2);
+        quoted.push('"');
+        for c in arg.chars() {
+            if matches!(c, '"' | '`' | '$' | '\\') {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        // `Exec` values are themselves string-escaped, so backslashes double.
+        quoted.replace('\\', "\\\\")
+    }
+
+    pub fn read() -> Result<AutostartSettings> {
+        let contents = match fs::read_to_string(entry_path()?) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(AutostartSettings::default())
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let value = |key: &str| {
+            contents.lines().find_map(|line| {
+                let (k, v) = line.split_once('=')?;
+                (k.trim() == key).then(|| v.trim().to_owned())
+            })
+        };
+        let hidden = value("Hidden").is_some_and(|v| v == "true")
+            || value("X-GNOME-Autostart-enabled").is_some_and(|v| v == "false");
+        let minimized = value("Exec")
+            .is_some_and(|exec| exec.split_whitespace().any(|arg| arg == MINIMIZED_FLAG));
+        Ok(AutostartSettings {
+            enabled: !hidden,
+            minimized,
+        })
+    }
+
+    pub fn write(settings: AutostartSettings) -> Result<()> {
+        let path = entry_path()?;
+        if !settings.enabled {
+            return match fs::remove_file(&path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+                _ => Ok(()),
+            };
+        }
+
+        let mut exec = quote(&executable()?.to_string_lossy());
+        if settings.minimized {
+            exec.push(' ');
+            exec.push_str(MINIMIZED_FLAG);
+        }
+        let entry = format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=Dayli\n\
+             Comment=Open today's plan at login\n\
+             Exec={exec}\n\
+             Terminal=false\n\
+             X-GNOME-Autostart-enabled=true\n"
+        );
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, entry)?;
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn quotes_exec_arguments() {
+            assert_eq!(quote("/usr/bin/dayli"), "/usr/bin/dayli");
+            assert_eq!(
+                quote("/home/sam/My Apps/dayli"),
+                r#""/home/sam/My Apps/dayli""#
+            );
+            // Quotes, `$` and backticks are escaped inside the quotes, and
+            // every backslash is doubled again for the string value.
+            assert_eq!(
+                quote(r#"/opt/say "hi"/dayli"#),
+                r#""/opt/say \\"hi\\"/dayli""#
+            );
+            assert_eq!(quote("/opt/$HOME/a`b`"), r#""/opt/\\$HOME/a\\`b\\`""#);
+            assert_eq!(quote(r"C:\dayli"), r#""C:\\\\dayli""#);
+        }
+
+        /// The environment is shared by every test thread, so all of the
+        /// round trips run in this one test.
+        #[test]
+        fn entries_round_trip_under_xdg_config_home() {
+            let dir = tempfile::tempdir().unwrap();
+            std::env::set_var("XDG_CONFIG_HOME", dir.path());
+            std::env::set_var("APPIMAGE", "/home/sam/My Apps/Dayli.AppImage");
+            let path = dir.path().join("autostart").join(FILE_NAME);
+
+            assert_eq!(read().unwrap(), AutostartSettings::default());
+
+            let minimized = AutostartSettings {
+                enabled: true,
+                minimized: true,
+            };
+            write(minimized).unwrap();
+            assert_eq!(read().unwrap(), minimized);
+            let entry = fs::read_to_string(&path).unwrap();
+            assert!(entry.contains("Exec=\"/home/sam/My Apps/Dayli.AppImage\" --minimized\n"));
+
+            let shown = AutostartSettings {
+                enabled: true,
+                minimized: false,
+            };
+            write(shown).unwrap();
+            assert_eq!(read().unwrap(), shown);
+
+            // Turned off in the desktop's own settings.
+            let entry = fs::read_to_string(&path).unwrap();
+            fs::write(
+                &path,
+                entry.replace(
+                    "X-GNOME-Autostart-enabled=true",
+                    "X-GNOME-Autostart-enabled=false",
+                ),
+            )
+            .unwrap();
+            assert!(!read().unwrap().enabled);
+
+            write(AutostartSettings::default()).unwrap();
+            assert!(!path.exists());
+            assert_eq!(read().unwrap(), AutostartSettings::default());
+            // Disabling twice is fine.
+            write(AutostartSettings::default()).unwrap();
+
+            std::env::remove_var("APPIMAGE");
+            std::env::remove_var("XDG_CONFIG_HOME");
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod xdg {
+    use super::AutostartSettings;
+    use crate::error::{Error, Result};
+
+    pub fn read() -> Result<AutostartSettings> {
+        Ok(AutostartSettings::default())
+    }
+
+    pub fn write(_settings: AutostartSettings) -> Result<()> {
+        Err(Error::Autostart(
+            "launch at login is only supported on Linux".into(),
+        ))
+    }
+}
+
+/// Finish startup for a launch with [`MINIMIZED_FLAG`]: the main window
+/// stays hidden behind the tray, and today's schedule is fetched straight
+/// away so the tray and reminders are ready by the time the user looks.
+pub fn init<R: Runtime>(app: &tauri::App<R>) {
+    if !app.state::<AppConfig>().start_minimized {
+        return;
+    }
+    let app = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(err) = crate::sync::sync_now(&app, &reqwest::Client::new()).await {
+            log::error!("morning schedule fetch failed: {err}");
+        }
+        crate::reminders::reschedule(&app);
+        crate::tray::refresh(&app);
+    });
+}
+
+#[tauri::command]
+pub fn get_autostart() -> Result<AutostartSettings> {
+    xdg::read()
+}
+
+#[tauri::command]
+pub fn set_autostart(enabled: bool, minimized: bool) -> Result<AutostartSettings> {
+    xdg::write(AutostartSettings { enabled, minimized })?;
+    xdg::read()
+}
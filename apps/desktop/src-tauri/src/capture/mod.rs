@@ -0,0 +1,214 @@
+//! Global-hotkey quick capture. A small frameless window, created hidden at
+//! startup so it appears instantly, turns one line of inline syntax (see
+//! [`parse`]) into a backlog task in the local store.
+
+mod parse;
+
+use std::borrow::Cow;
+use std::sync::Mutex;
+
+use chrono::Utc;
+use tauri::http::Response;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+use crate::error::{Error, Result};
+use crate::store::{Store, Task};
+use crate::vault::TokenVault;
+
+pub(crate) use parse::parse_duration;
+pub use parse::{parse, Captured};
+
+pub const CREATED_EVENT: &str = "capture://created";
+
+const WINDOW_LABEL: &str = "capture";
+const SHORTCUT_SETTING: &str = "capture.shortcut";
+const DEFAULT_SHORTCUT: &str = "CommandOrControl+Shift+Space";
+const PROTOCOL: &str = "capture";
+const PAGE: &str = include_str!("../../../src/capture.html");
+
+/// The shortcut currently registered for capture.
+pub struct CaptureShortcut(Mutex<String>);
+
+fn page_url() -> WebviewUrl {
+    // Windows and Android serve custom protocols over http.
+    let url = if cfg!(any(windows, target_os = "android")) {
+        format!("http://{PROTOCOL}.localhost/")
+    } else {
+        format!("{PROTOCOL}://localhost/")
+    };
+    WebviewUrl::CustomProtocol(url.parse().expect("valid capture url"))
+}
+
+fn create_window<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<()> {
+    WebviewWindowBuilder::new(app, WINDOW_LABEL, page_url())
+        .title("Quick capture")
+        .inner_size(560.0, 64.0)
+        .resizable(false)
+        .decorations(false)
+        .always_on_top(true)
+        .skip_taskbar(true)
+        .center()
+        .visible(false)
+        .build()?;
+    Ok(())
+}
+
+/// Show the capture window, or hide it if it's already up.
+pub fn toggle<R: Runtime>(app: &AppHandle<R>) {
+    let Some(window) = app.get_webview_window(WINDOW_LABEL) else {
+        if let Err(err) = create_window(app) {
+            log::error!("failed to create capture window: {err}");
+        }
+        return toggle(app);
+    };
+    if window.is_visible().unwrap_or(false) {
+        let _ = window.hide();
+    } else {
+        let _ = window.center();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+fn hide<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window(WINDOW_LABEL) {
+        let _ = window.hide();
+    }
+}
+
+/// Turn a capture line into a backlog task owned by `user_id`.
+pub fn to_task(captured: Captured, user_id: String) -> Task {
+    let now = Utc::now();
+    let mut tags = captured.tags;
+    if let Some(due) = captured.due {
+        tags.push(format!("due:{due}"));
+    }
+    Task {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id,
+        title: captured.title,
+        description: None,
+        completed: false,
+        source: Some("manual".into()),
+        source_id: None,
+        email_id: None,
+        status: Some("backlog".into()),
+        priority: captured.priority,
+        estimated_minutes: captured.estimated_minutes,
+        score: Some(0),
+        urgency: Some(50),
+        days_in_backlog: Some(0),
+        tags,
+        created_at: now,
+        updated_at: now,
+    }
+}
+
+/// Add a task from a capture line to the local store; sync pushes it.
+pub fn capture<R: Runtime>(app: &AppHandle<R>, text: &str) -> Result<Task> {
+    let store = app.state::<Store>();
+    let captured = parse(text, store.today()?);
+    if captured.title.is_empty() {
+        return Err(Error::Capture("a task needs a title".into()));
+    }
+
+    let user_id = match app.state::<TokenVault>().user_id()? {
+        Some(id) => id,
+        None => store
+            .get_preferences()?
+            .map(|preferences| preferences.user_id)
+            .ok_or_else(|| Error::Capture("sign in before adding tasks".into()))?,
+    };
+
+    let task = to_task(captured, user_id);
+    store.save_task(&task)?;
+    if let Err(err) = app.emit(CREATED_EVENT, &task) {
+        log::warn!("failed to emit capture event: {err}");
+    }
+    Ok(task)
+}
+
+fn register<R: Runtime>(app: &AppHandle<R>, shortcut: &str) -> Result<()> {
+    let shortcut: Shortcut = shortcut
+        .parse()
+        .map_err(|err| Error::Capture(format!("invalid shortcut {shortcut:?}: {err}")))?;
+    app.global_shortcut()
+        .register(shortcut)
+        .map_err(|err| Error::Capture(err.to_string()))
+}
+
+/// Serve the capture page from the binary.
+pub fn register_protocol<R: Runtime>(builder: tauri::Builder<R>) -> tauri::Builder<R> {
+    builder.register_uri_scheme_protocol(PROTOCOL, |_ctx, _request| {
+        Response::builder()
+            .header("Content-Type", "text/html; charset=utf-8")
+            .body(Cow::Borrowed(PAGE.as_bytes()))
+            .expect("valid capture response")
+    })
+}
+
+/// Install the global-shortcut plugin, register the saved shortcut and
+/// create the hidden capture window.
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    app.handle().plugin(
+        tauri_plugin_global_shortcut::Builder::new()
+            .with_handler(|app, _shortcut, event| {
+                if event.state() == ShortcutState::Pressed {
+                    toggle(app);
+                }
+            })
+            .build(),
+    )?;
+
+    let shortcut = app
+        .state::<Store>()
+        .setting(SHORTCUT_SETTING)?
+        .unwrap_or_else(|| DEFAULT_SHORTCUT.to_owned());
+    if let Err(err) = register(app.handle(), &shortcut) {
+        log::error!("failed to register capture shortcut: {err}");
+    }
+    app.manage(CaptureShortcut(Mutex::new(shortcut)));
+
+    create_window(app.handle())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn capture_submit<R: Runtime>(app: AppHandle<R>, text: String) -> Result<Task> {
+    let task = capture(&app, &text)?;
+    hide(&app);
+    Ok(task)
+}
+
+#[tauri::command]
+pub fn capture_dismiss<R: Runtime>(app: AppHandle<R>) {
+    hide(&app);
+}
+
+#[tauri::command]
+pub fn capture_get_shortcut(shortcut: State<'_, CaptureShortcut>) -> String {
+    shortcut.0.lock().unwrap().clone()
+}
+
+/// Change and persist the capture shortcut. The old one stays registered if
+/// the new one can't be.
+#[tauri::command]
+pub fn capture_set_shortcut<R: Runtime>(
+    app: AppHandle<R>,
+    shortcut: String,
+    current: State<'_, CaptureShortcut>,
+    store: State<'_, Store>,
+) -> Result<()> {
+    let mut current = current.0.lock().unwrap();
+    if *current == shortcut {
+        return Ok(());
+    }
+    register(&app, &shortcut)?;
+    if let Ok(old) = current.parse::<Shortcut>() {
+        let _ = app.global_shortcut().unregister(old);
+    }
+    store.set_setting(SHORTCUT_SETTING, &shortcut)?;
+    *current = shortcut;
+    Ok(())
+}
@@ -0,0 +1,226 @@
+//! Inline syntax for quick capture:
+//!
+//! - `~45m`, `~1h`, `~1h30m`, `~90` — estimate (bare numbers are minutes)
+//! - `!high`, `!medium`, `!low` (or `!h`, `!m`, `!l`) — priority
+//! - `today`, `tomorrow`, a weekday name — when to do it, as a `due:` tag
+//! - `#tag` — a tag
+//!
+//! Everything else is the title, e.g. `Review PR ~45m !high tomorrow`.
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Captured {
+    pub title: String,
+    pub estimated_minutes: Option<i64>,
+    pub priority: Option<String>,
+    pub due: Option<NaiveDate>,
+    pub tags: Vec<String>,
+}
+
+/// Minutes in `45m`, `1h`, `1h30m`, `2hr` or `90`, or `None` if it isn't one
+/// or doesn't fit.
+pub(crate) fn parse_duration(s: &str) -> Option<i64> {
+    if let Ok(minutes) = s.parse::<i64>() {
+        return (minutes > 0).then_some(minutes);
+    }
+
+    let mut total = 0;
+    let mut digits = String::new();
+    let mut rest = s;
+    while let Some(c) = rest.chars().next() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            rest = &rest[1..];
+            continue;
+        }
+        let value: i64 = digits.parse().ok()?;
+        digits.clear();
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (unit, tail) = rest.split_at(unit_len);
+        let minutes = match unit {
+            "h" | "hr" | "hrs" | "hour" | "hours" => value.checked_mul(60)?,
+            "m" | "min" | "mins" | "minutes" => value,
+            _ => return None,
+        };
+        total = minutes.checked_add(total)?;
+        rest = tail;
+    }
+    if !digits.is_empty() {
+        return None;
+    }
+    (total > 0).then_some(total)
+}
+
+fn parse_priority(s: &str) -> Option<&'static str> {
+    match s.to_ascii_lowercase().as_str() {
+        "high" | "h" | "!" => Some("high"),
+        "medium" | "med" | "m" => Some("medium"),
+        "low" | "l" => Some("low"),
+        _ => None,
+    }
+}
+
+fn parse_day(s: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let weekday = match s.to_ascii_lowercase().as_str() {
+        "today" => return Some(today),
+        "tomorrow" | "tmrw" => return today.succ_opt(),
+        "monday" | "mon" => Weekday::Mon,
+        "tuesday" | "tue" => Weekday::Tue,
+        "wednesday" | "wed" => Weekday::Wed,
+        "thursday" | "thu" => Weekday::Thu,
+        "friday" | "fri" => Weekday::Fri,
+        "saturday" | "sat" => Weekday::Sat,
+        "sunday" | "sun" => Weekday::Sun,
+        _ => return None,
+    };
+    // The next such day, never today.
+    let ahead = (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+    Some(today + Duration::days(if ahead == 0 { 7 } else { ahead as i64 }))
+}
+
+/// Parse a capture line. Markers that don't parse stay in the title.
+pub fn parse(input: &str, today: NaiveDate) -> Captured {
+    let mut captured = Captured::default();
+    let mut title = Vec::new();
+
+    for word in input.split_whitespace() {
+        if let Some(minutes) = word.strip_prefix('~').and_then(parse_duration) {
+            captured.estimated_minutes = Some(minutes);
+        } else if let Some(priority) = word.strip_prefix('!').and_then(parse_priority) {
+            captured.priority = Some(priority.to_owned());
+        } else if let Some(tag) = word.strip_prefix('#').filter(|tag| !tag.is_empty()) {
+            captured.tags.push(tag.to_owned());
+        } else if let Some(due) = captured
+            .due
+            .is_none()
+            .then(|| parse_day(word, today))
+            .flatten()
+        {
+            captured.due = Some(due);
+        } else {
+            title.push(word);
+        }
+    }
+
+    captured.title = title.join(" ");
+    captured
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wednesday 4 March 2026.
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, 4).unwrap()
+    }
+
+    fn day(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, d).unwrap()
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30m"), Some(30));
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("1h"), Some(60));
+        assert_eq!(parse_duration("1h30m"), Some(90));
+        assert_eq!(parse_duration("2hr"), Some(120));
+        assert_eq!(parse_duration("1hour15mins"), Some(75));
+        for bad in [
+            "",
+            "0",
+            "-5",
+            "0m",
+            "m",
+            "1h30",
+            "30s",
+            "1.5h",
+            "h1",
+            // Overflowing i64 minutes.
+            "153722867280912931h",
+            "9223372036854775807m1m",
+            "99999999999999999999",
+            "99999999999999999999m",
+        ] {
+            assert_eq!(parse_duration(bad), None, "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn estimates() {
+        assert_eq!(parse("Call ~30m", today()).estimated_minutes, Some(30));
+        assert_eq!(parse("Call ~1h30m", today()).estimated_minutes, Some(90));
+        assert_eq!(parse("Call ~45", today()).estimated_minutes, Some(45));
+        // Markers that don't parse stay in the title.
+        let captured = parse("Call ~soon", today());
+        assert_eq!(captured.estimated_minutes, None);
+        assert_eq!(captured.title, "Call ~soon");
+    }
+
+    #[test]
+    fn date_words() {
+        let due = |input: &str| parse(input, today()).due;
+        assert_eq!(due("Pay rent today"), Some(today()));
+        assert_eq!(due("Pay rent tomorrow"), Some(day(5)));
+        assert_eq!(due("Pay rent TMRW"), Some(day(5)));
+        assert_eq!(due("Pay rent friday"), Some(day(6)));
+        assert_eq!(due("Pay rent Mon"), Some(day(9)));
+        // The same weekday as today means next week.
+        assert_eq!(due("Pay rent wednesday"), Some(day(11)));
+        assert_eq!(due("Pay rent"), None);
+
+        // Only the first date word counts; later ones are title.
+        let captured = parse("Friday retro prep tomorrow", today());
+        assert_eq!(captured.due, Some(day(6)));
+        assert_eq!(captured.title, "retro prep tomorrow");
+    }
+
+    #[test]
+    fn priority_markers() {
+        let priority = |input: &str| parse(input, today()).priority;
+        assert_eq!(priority("Ship it !high").as_deref(), Some("high"));
+        assert_eq!(priority("Ship it !H").as_deref(), Some("high"));
+        assert_eq!(priority("Ship it !!").as_deref(), Some("high"));
+        assert_eq!(priority("Ship it !med").as_deref(), Some("medium"));
+        assert_eq!(priority("Ship it !m").as_deref(), Some("medium"));
+        assert_eq!(priority("Ship it !low").as_deref(), Some("low"));
+        // The last marker wins.
+        assert_eq!(priority("Ship it !low !high").as_deref(), Some("high"));
+
+        let captured = parse("Ship it !urgent", today());
+        assert_eq!(captured.priority, None);
+        assert_eq!(captured.title, "Ship it !urgent");
+    }
+
+    #[test]
+    fn everything_together() {
+        let captured = parse("  Review   PR ~45m !high tomorrow #work #code  ", today());
+        assert_eq!(
+            captured,
+            Captured {
+                title: "Review PR".into(),
+                estimated_minutes: Some(45),
+                priority: Some("high".into()),
+                due: Some(day(5)),
+                tags: vec!["work".into(), "code".into()],
+            }
+        );
+    }
+
+    #[test]
+    fn the_title_can_end_up_empty() {
+        assert_eq!(parse("", today()), Captured::default());
+        assert_eq!(parse("   ", today()).title, "");
+        let captured = parse("~30m !high today #errand", today());
+        assert_eq!(captured.title, "");
+        assert_eq!(captured.estimated_minutes, Some(30));
+        // A lone `#` isn't a tag.
+        assert_eq!(parse("#", today()).title, "#");
+    }
+}
@@ -0,0 +1,88 @@
+//! A minimal HTTP/1.1 client for the running app's local API (see
+//! [`crate::api`]), over its Unix socket or loopback port.
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::api::{Discovery, Transport};
+use crate::error::{Error, Result};
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+pub struct Client {
+    discovery: Discovery,
+}
+
+impl Client {
+    /// The API the app advertised in `dir`, if it's running with one.
+    pub fn discover(dir: &Path) -> Option<Self> {
+        let data = std::fs::read(dir.join(crate::api::DISCOVERY_FILE)).ok()?;
+        let discovery = serde_json::from_slice(&data).ok()?;
+        Some(Self { discovery })
+    }
+
+    fn connect(&self) -> std::io::Result<Box<dyn Stream>> {
+        match self.discovery.transport {
+            #[cfg(unix)]
+            Transport::Unix => Ok(Box::new(std::os::unix::net::UnixStream::connect(
+                &self.discovery.address,
+            )?)),
+            #[cfg(not(unix))]
+            Transport::Unix => Err(std::io::ErrorKind::Unsupported.into()),
+            Transport::Tcp => Ok(Box::new(TcpStream::connect(&self.discovery.address)?)),
+        }
+    }
+
+    /// Send a request and return the JSON body. Failing to connect comes
+    /// back as [`Error::Io`], so callers can fall back to the store.
+    pub fn request(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value> {
+        let body = body.map(Value::to_string).unwrap_or_default();
+        let mut stream = self.connect()?;
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\n\
+             Host: localhost\r\n\
+             Authorization: Bearer {}\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\r\n{body}",
+            self.discovery.token,
+            body.len(),
+        )?;
+        stream.flush()?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        let response = String::from_utf8_lossy(&response);
+        let (head, body) = response
+            .split_once("\r\n\r\n")
+            .ok_or_else(|| Error::Api("malformed response".into()))?;
+        let status: u16 = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| Error::Api("malformed status line".into()))?;
+        let value: Value = if body.trim().is_empty() {
+            Value::Null
+        } else {
+            serde_json::from_str(body)?
+        };
+
+        if status >= 400 {
+            let message = value
+                .get("error")
+                .and_then(Value::as_str)
+                .unwrap_or("request failed")
+                .to_owned();
+            return Err(match status {
+                404 => Error::NotFound(message),
+                _ => Error::Api(message),
+            });
+        }
+        Ok(value)
+    }
+}
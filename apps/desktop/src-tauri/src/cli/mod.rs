@@ -0,0 +1,619 @@
+//! Command-line companion. When the `dayli` binary is run with a subcommand
+//! it answers in the terminal instead of opening a window. Commands go
+//! through the running app's local API when it's enabled, and otherwise read
+//! and write the local store directly; starting focus needs the app.
+//!
+//! Every command takes `--json` for machine-readable output. `dayli block
+//! next` prints a single line, for tmux and shell prompts.
+
+mod client;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+use crate::schedule::time::Zone;
+use crate::store::Store;
+
+use client::Client;
+
+/// The bundle identifier from `tauri.conf.json`, which names the app's data
+/// directory.
+const IDENTIFIER: &str = "com.mitchforest.dayli";
+
+const COMMANDS: &[&str] = &["today", "task", "block", "focus", "inbox", "mcp", "help"];
+/// Flags that take a value.
+const VALUE_FLAGS: &[&str] = &["est", "decision", "pomodoro"];
+
+const USAGE: &str = "\
+usage: dayli <command> [--json]
+
+commands:
+  today                         today's schedule
+  task add <text> [--est 30m]   add a backlog task (quick-capture syntax)
+  block next                    the current and next block, on one line
+  focus start [block-id] [--pomodoro 25/5]
+                                start a focus session (needs the app running)
+  focus status                  the running focus session
+  inbox [--decision now|tomorrow|never]
+                                emails, optionally by triage decision
+  mcp                           serve MCP tools on stdin/stdout, for AI assistants";
+
+struct Args {
+    positional: Vec<String>,
+    flags: HashMap<String, Option<String>>,
+}
+
+impl Args {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut flags = HashMap::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                positional.push(arg.clone());
+                continue;
+            };
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None if VALUE_FLAGS.contains(&flag) => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| usage(format!("--{flag} needs a value")))?;
+                    (flag, Some(value.clone()))
+                }
+                None => (flag, None),
+            };
+            flags.insert(name.to_owned(), value);
+        }
+        Ok(Self { positional, flags })
+    }
+
+    fn has(&self, flag: &str) -> bool {
+        self.flags.contains_key(flag)
+    }
+
+    fn value(&self, flag: &str) -> Option<&str> {
+        self.flags.get(flag)?.as_deref()
+    }
+
+    fn word(&self, index: usize) -> Option<&str> {
+        self.positional.get(index).map(String::as_str)
+    }
+}
+
+/// Bad command-line input.
+fn usage(message: impl Into<String>) -> Error {
+    Error::Api(format!("{}; see `dayli help`", message.into()))
+}
+
+fn data_dir() -> Result<PathBuf> {
+    let base = dirs::data_local_dir().ok_or_else(|| Error::Api("no data directory".into()))?;
+    Ok(base.join(IDENTIFIER))
+}
+
+struct Context {
+    dir: PathBuf,
+    client: Option<Client>,
+}
+
+impl Context {
+    fn store(&self) -> Result<Store> {
+        let path = self.dir.join("dayli.db");
+        if !path.exists() {
+            return Err(Error::NotFound(
+                "no local data yet; open Dayli and sign in first".into(),
+            ));
+        }
+        Store::open(&path)
+    }
+
+    /// The user's zone, from the local store; the system's if there's none.
+    fn zone(&self) -> Zone {
+        self.store()
+            .and_then(|store| store.zone())
+            .unwrap_or_else(|_| Zone::system())
+    }
+
+    /// Ask the running app, or fall back to `local` if it isn't reachable.
+    fn api_or(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<Value>,
+        local: impl FnOnce(&Store) -> Result<Value>,
+    ) -> Result<Value> {
+        if let Some(client) = &self.client {
+            match client.request(method, path, body.as_ref()) {
+                Err(Error::Io(_)) => {}
+                result => return result,
+            }
+        }
+        local(&self.store()?)
+    }
+}
+
+fn local_time(value: &Value, zone: Zone) -> String {
+    value
+        .as_str()
+        .and_then(|at| at.parse::<DateTime<Utc>>().ok())
+        .map(|at| zone.local(at).format("%H:%M").to_string())
+        .unwrap_or_else(|| "--:--".into())
+}
+
+fn minutes_until(value: &Value) -> Option<i64> {
+    let at = value.as_str()?.parse::<DateTime<Utc>>().ok()?;
+    Some((at - Utc::now()).num_minutes().max(0))
+}
+
+fn str_field<'a>(value: &'a Value, field: &str) -> &'a str {
+    value.get(field).and_then(Value::as_str).unwrap_or_default()
+}
+
+fn today(ctx: &Context) -> Result<(Value, String)> {
+    let day = ctx.api_or("GET", "/today", None, |store| {
+        Ok(json!(store.day_schedule(store.today()?)?))
+    })?;
+    let zone = ctx.zone();
+    let blocks = day["timeBlocks"].as_array().cloned().unwrap_or_default();
+    let date = day["date"]
+        .as_str()
+        .and_then(|date| date.parse::<NaiveDate>().ok())
+        .unwrap_or_else(|| zone.today());
+    let mut text = date.format("%A %-d %B").to_string();
+    if blocks.is_empty() {
+        text.push_str("\n  nothing scheduled");
+    }
+    for block in &blocks {
+        text.push_str(&format!(
+            "\n  {}–{}  {:<16} {}",
+            local_time(&block["start_time"], zone),
+            local_time(&block["end_time"], zone),
+            str_field(block, "type"),
+            str_field(block, "title"),
+        ));
+    }
+    Ok((day, text))
+}
+
+fn task_add(ctx: &Context, args: &Args) -> Result<(Value, String)> {
+    let mut text = args.positional[2..].join(" ");
+    if text.trim().is_empty() {
+        return Err(usage("task add needs the task text"));
+    }
+    if let Some(est) = args.value("est") {
+        let minutes = crate::capture::parse_duration(est)
+            .ok_or_else(|| usage(format!("can't read --est {est:?}; try 30m or 1h30m")))?;
+        text.push_str(&format!(" ~{minutes}m"));
+    }
+
+    let task = ctx.api_or("POST", "/tasks", Some(json!({ "text": text })), |store| {
+        let captured = crate::capture::parse(&text, store.today()?);
+        if captured.title.is_empty() {
+            return Err(Error::Capture("a task needs a title".into()));
+        }
+        let user_id = store
+            .get_preferences()?
+            .map(|preferences| preferences.user_id)
+            .ok_or_else(|| Error::Capture("sign in to Dayli before adding tasks".into()))?;
+        let task = crate::capture::to_task(captured, user_id);
+        store.save_task(&task)?;
+        Ok(json!(task))
+    })?;
+    let mut line = format!("added: {}", str_field(&task, "title"));
+    if let Some(minutes) = task["estimated_minutes"].as_i64() {
+        line.push_str(&format!(" ({minutes}m)"));
+    }
+    Ok((task, line))
+}
+
+fn block_next(ctx: &Context) -> Result<(Value, String)> {
+    let next = ctx.api_or("GET", "/blocks/next", None, |store| {
+        Ok(json!(crate::api::next_blocks(store)?))
+    })?;
+    let line = match (&next["current"], &next["next"]) {
+        (current, _) if current.is_object() => format!(
+            "{} · {}m left",
+            str_field(current, "title"),
+            minutes_until(&current["end_time"]).unwrap_or(0)
+        ),
+        (_, next) if next.is_object() => format!(
+            "Next: {} at {}",
+            str_field(next, "title"),
+            local_time(&next["start_time"], ctx.zone())
+        ),
+        _ => "Nothing scheduled".to_owned(),
+    };
+    Ok((next, line))
+}
+
+fn focus_line(status: &Value) -> String {
+    if status.is_null() {
+        return "No focus session".to_owned();
+    }
+    let worked = status["workedSeconds"].as_u64().unwrap_or(0) / 60;
+    let left = status["remainingSeconds"].as_u64().unwrap_or(0) / 60;
+    let state = if status["paused"].is_null() {
+        "focusing"
+    } else {
+        "paused"
+    };
+    format!("{state}: {worked}m worked, {left}m left")
+}
+
+fn focus(ctx: &Context, args: &Args) -> Result<(Value, String)> {
+    let not_running = || Error::Api("focus needs Dayli running with the local API enabled".into());
+    let client = ctx.client.as_ref().ok_or_else(not_running)?;
+    let request = |method: &str, path: &str, body: Option<&Value>| {
+        client.request(method, path, body).map_err(|err| match err {
+            Error::Io(_) => not_running(),
+            other => other,
+        })
+    };
+    match args.word(1) {
+        Some("start") => {
+            let pomodoro = args
+                .value("pomodoro")
+                .map(|value| {
+                    let (work, rest) = value.split_once('/').unwrap_or((value, "5"));
+                    match (work.parse::<u32>(), rest.parse::<u32>()) {
+                        (Ok(work), Ok(rest)) => {
+                            Ok(json!({ "workMinutes": work, "breakMinutes": rest }))
+                        }
+                        _ => Err(usage(format!("can't read --pomodoro {value:?}; try 25/5"))),
+                    }
+                })
+                .transpose()?;
+            let body = json!({ "blockId": args.word(2), "pomodoro": pomodoro });
+            let status = request("POST", "/focus/start", Some(&body))?;
+            let line = focus_line(&status);
+            Ok((status, line))
+        }
+        Some("status") | None => {
+            let status = request("GET", "/focus", None)?;
+            let line = focus_line(&status);
+            Ok((status, line))
+        }
+        Some(other) => Err(usage(format!("unknown focus command {other:?}"))),
+    }
+}
+
+fn inbox(ctx: &Context, args: &Args) -> Result<(Value, String)> {
+    let decision = args.value("decision");
+    if let Some(decision) = decision {
+        if !["now", "tomorrow", "never"].contains(&decision) {
+            return Err(usage(format!(
+                "--decision is now, tomorrow or never, not {decision:?}"
+            )));
+        }
+    }
+    let path = match decision {
+        Some(decision) => format!("/inbox?decision={decision}"),
+        None => "/inbox".to_owned(),
+    };
+    let emails = ctx.api_or("GET", &path, None, |store| {
+        Ok(json!(crate::api::inbox(store, decision)?))
+    })?;
+    let emails = emails.as_array().cloned().unwrap_or_default();
+    let mut text = format!("{} emails", emails.len());
+    for email in &emails {
+        let from = email["from_name"]
+            .as_str()
+            .unwrap_or_else(|| str_field(email, "from_email"));
+        text.push_str(&format!("\n  {from:<24} {}", str_field(email, "subject")));
+    }
+    Ok((json!(emails), text))
+}
+
+fn dispatch(ctx: &Context, args: &Args) -> Result<(Value, String)> {
+    match (args.word(0), args.word(1)) {
+        (Some("today"), _) => today(ctx),
+        (Some("task"), Some("add")) => task_add(ctx, args),
+        (Some("block"), Some("next")) => block_next(ctx),
+        (Some("focus"), _) => focus(ctx, args),
+        (Some("inbox"), _) => inbox(ctx, args),
+        (Some(command), sub) => Err(usage(match sub {
+            Some(sub) => format!("unknown command {command} {sub}"),
+            None => format!("{command} needs a subcommand"),
+        })),
+        (None, _) => Err(usage("missing command")),
+    }
+}
+
+/// Run a CLI command if `args` (without the program name) is one, returning
+/// the exit code; `None` means start the app as usual. Deep links and
+/// `--minimized` aren't commands, so they still reach the app.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.first()?;
+    if !COMMANDS.contains(&command.as_str()) {
+        return None;
+    }
+    crate::logging::init();
+    let parsed = match Args::parse(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("dayli: {err}");
+            return Some(2);
+        }
+    };
+    if command == "help" || parsed.has("help") {
+        println!("{USAGE}");
+        return Some(0);
+    }
+
+    if command == "mcp" {
+        // stdout belongs to the protocol, so only errors are printed.
+        let result = data_dir().and_then(|dir| {
+            let ctx = Context { client: None, dir };
+            crate::mcp::serve_stdio(&ctx.store()?)
+        });
+        return Some(match result {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("dayli: {err}");
+                1
+            }
+        });
+    }
+
+    let result = data_dir().and_then(|dir| {
+        let ctx = Context {
+            client: Client::discover(&dir),
+            dir,
+        };
+        dispatch(&ctx, &parsed)
+    });
+    let (code, output) = render(result, parsed.has("json"));
+    match output {
+        Ok(out) => println!("{out}"),
+        Err(err) => eprintln!("{err}"),
+    }
+    Some(code)
+}
+
+/// The exit code and what to print: `Ok` for stdout, `Err` for stderr. With
+/// `--json` even errors go to stdout, as JSON.
+fn render(
+    result: Result<(Value, String)>,
+    json: bool,
+) -> (i32, std::result::Result<String, String>) {
+    match result {
+        Ok((value, _)) if json => (0, Ok(value.to_string())),
+        Ok((_, text)) => (0, Ok(text)),
+        Err(err) if json => (1, Ok(json!({ "error": err.to_string() }).to_string())),
+        Err(err) => (1, Err(format!("dayli: {err}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::api::{Discovery, Transport};
+    use crate::store::{Email, UserPreferences};
+
+    const TOKEN: &str = "cli-token";
+
+    fn args(line: &str) -> Args {
+        let words: Vec<String> = line.split_whitespace().map(str::to_owned).collect();
+        Args::parse(&words).unwrap()
+    }
+
+    /// A data dir with a store and, if given, a discovery file for `address`.
+    fn context(address: Option<String>) -> (Context, TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let store = Store::open(&dir.path().join("dayli.db")).unwrap();
+        store
+            .save_preferences(&UserPreferences::defaults("u1"))
+            .unwrap();
+        for (id, decision) in [("e1", "now"), ("e2", "never")] {
+            let email: Email = serde_json::from_value(json!({
+                "id": id,
+                "user_id": "u1",
+                "gmail_id": null,
+                "from_email": format!("{id}@example.com"),
+                "from_name": null,
+                "subject": format!("About {id}"),
+                "body_preview": null,
+                "full_body": null,
+                "decision": decision,
+                "action_type": null,
+                "status": "unread",
+                "urgency": null,
+                "importance": null,
+                "days_in_backlog": 0,
+                "received_at": "2026-03-02T09:00:00Z",
+                "processed_at": null,
+                "metadata": {},
+                "created_at": "2026-03-02T09:00:00Z",
+                "updated_at": "2026-03-02T09:00:00Z",
+            }))
+            .unwrap();
+            store.save_email(&email).unwrap();
+        }
+        if let Some(address) = address {
+            let discovery = Discovery {
+                transport: Transport::Tcp,
+                address,
+                token: TOKEN.into(),
+            };
+            std::fs::write(
+                dir.path().join(crate::api::DISCOVERY_FILE),
+                serde_json::to_vec(&discovery).unwrap(),
+            )
+            .unwrap();
+        }
+        let ctx = Context {
+            client: Client::discover(dir.path()),
+            dir: dir.path().to_owned(),
+        };
+        (ctx, dir)
+    }
+
+    /// A port nothing listens on.
+    fn dead_address() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    struct Seen {
+        method: String,
+        url: String,
+        authorization: Option<String>,
+        body: String,
+    }
+
+    /// Answer one request with `reply`, reporting what was asked.
+    fn stub_api(reply: Value) -> (String, mpsc::Receiver<Seen>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap().to_string();
+        let (seen, requests) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let authorization = request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv("Authorization"))
+                .map(|header| header.value.to_string());
+            seen.send(Seen {
+                method: request.method().to_string(),
+                url: request.url().to_owned(),
+                authorization,
+                body,
+            })
+            .unwrap();
+            request
+                .respond(tiny_http::Response::from_string(reply.to_string()))
+                .unwrap();
+        });
+        (address, requests)
+    }
+
+    #[test]
+    fn value_flags_take_the_next_word_or_an_equals_value() {
+        let parsed = args("task add Write it --est 30m --json");
+        assert_eq!(parsed.positional, ["task", "add", "Write", "it"]);
+        assert_eq!(parsed.value("est"), Some("30m"));
+        assert!(parsed.has("json"));
+        assert_eq!(parsed.value("json"), None);
+
+        let parsed = args("task add --est=1h30m Write it");
+        assert_eq!(parsed.value("est"), Some("1h30m"));
+        assert_eq!(parsed.positional, ["task", "add", "Write", "it"]);
+
+        let parsed = args("focus start b1 --pomodoro 25");
+        assert_eq!(parsed.value("pomodoro"), Some("25"));
+        assert_eq!(parsed.word(2), Some("b1"));
+
+        // A value flag at the end has nothing to take.
+        let words = vec!["task".to_owned(), "add".to_owned(), "--est".to_owned()];
+        assert!(Args::parse(&words).is_err());
+    }
+
+    #[test]
+    fn json_output_goes_to_stdout_even_for_errors() {
+        let value = json!({ "title": "Write" });
+        let ok = || Ok((value.clone(), "added: Write".to_owned()));
+        assert_eq!(render(ok(), true), (0, Ok(value.to_string())));
+        assert_eq!(render(ok(), false), (0, Ok("added: Write".to_owned())));
+
+        let failed = || Err(Error::NotFound("no block b9".into()));
+        let (code, out) = render(failed(), true);
+        assert_eq!(code, 1);
+        let out: Value = serde_json::from_str(&out.unwrap()).unwrap();
+        assert!(out["error"].as_str().unwrap().contains("no block b9"));
+        let (code, out) = render(failed(), false);
+        assert_eq!(code, 1);
+        assert!(out.unwrap_err().starts_with("dayli: "));
+    }
+
+    #[test]
+    fn task_add_falls_back_to_the_store_when_the_api_is_down() {
+        for address in [None, Some(dead_address())] {
+            let (ctx, _dir) = context(address);
+            let (task, line) =
+                task_add(&ctx, &args("task add Write the report --est 30m")).unwrap();
+            assert_eq!(task["title"], "Write the report");
+            assert_eq!(task["estimated_minutes"], 30);
+            assert_eq!(line, "added: Write the report (30m)");
+
+            let (task, _) = task_add(&ctx, &args("task add Plan --est=1h30m")).unwrap();
+            assert_eq!(task["estimated_minutes"], 90);
+            assert_eq!(ctx.store().unwrap().list_tasks(None).unwrap().len(), 2);
+
+            assert!(task_add(&ctx, &args("task add Plan --est soon")).is_err());
+            assert!(task_add(&ctx, &args("task add --est 30m")).is_err());
+        }
+    }
+
+    #[test]
+    fn today_and_inbox_read_the_store_when_the_api_is_down() {
+        let (ctx, _dir) = context(Some(dead_address()));
+        let (day, text) = today(&ctx).unwrap();
+        assert_eq!(day["timeBlocks"], json!([]));
+        assert!(text.ends_with("nothing scheduled"));
+
+        let (emails, text) = inbox(&ctx, &args("inbox --decision now")).unwrap();
+        assert_eq!(emails.as_array().unwrap().len(), 1);
+        assert_eq!(emails[0]["id"], "e1");
+        assert!(text.starts_with("1 emails"));
+        assert!(inbox(&ctx, &args("inbox --decision later")).is_err());
+    }
+
+    #[test]
+    fn inbox_goes_through_the_api_when_it_answers() {
+        let (address, requests) = stub_api(json!([
+            { "from_name": "Ann", "from_email": "ann@example.com", "subject": "Lunch?" }
+        ]));
+        let (ctx, _dir) = context(Some(address));
+        let (emails, text) = inbox(&ctx, &args("inbox --decision=tomorrow")).unwrap();
+        assert_eq!(emails[0]["subject"], "Lunch?");
+        assert!(text.contains("Ann"));
+
+        let seen = requests.recv().unwrap();
+        assert_eq!(seen.method, "GET");
+        assert_eq!(seen.url, "/inbox?decision=tomorrow");
+        assert_eq!(seen.authorization.as_deref(), Some("Bearer cli-token"));
+    }
+
+    #[test]
+    fn focus_start_sends_the_pomodoro() {
+        let (address, requests) =
+            stub_api(json!({ "workedSeconds": 0, "remainingSeconds": 3600, "paused": null }));
+        let (ctx, _dir) = context(Some(address));
+        let (_, line) = focus(&ctx, &args("focus start b1 --pomodoro 25")).unwrap();
+        assert_eq!(line, "focusing: 0m worked, 60m left");
+
+        let seen = requests.recv().unwrap();
+        assert_eq!(
+            (seen.method.as_str(), seen.url.as_str()),
+            ("POST", "/focus/start")
+        );
+        let body: Value = serde_json::from_str(&seen.body).unwrap();
+        assert_eq!(
+            body,
+            json!({ "blockId": "b1", "pomodoro": { "workMinutes": 25, "breakMinutes": 5 } })
+        );
+    }
+
+    #[test]
+    fn focus_needs_the_app() {
+        let (ctx, _dir) = context(Some(dead_address()));
+        let err = focus(&ctx, &args("focus status")).unwrap_err();
+        assert!(err.to_string().contains("needs Dayli running"));
+        let (ctx, _dir) = context(None);
+        assert!(focus(&ctx, &args("focus status")).is_err());
+        let (ctx, _dir) = context(Some(dead_address()));
+        let err = focus(&ctx, &args("focus start --pomodoro 25/x")).unwrap_err();
+        assert!(err.to_string().contains("try 25/5"));
+    }
+}
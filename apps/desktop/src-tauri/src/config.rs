@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+/// Options for building the Dayli app. Both the desktop binary and the
+/// mobile entry point go through [`crate::builder`], so anything registered
+/// there is available on every platform.
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    /// Open the webview devtools for the main window on startup (debug builds only).
+    pub open_devtools: bool,
+    /// Start hidden in the tray, as when launched at login with
+    /// `--minimized`.
+    pub start_minimized: bool,
+    /// Show the tray icon. Off for headless runs such as integration tests,
+    /// where there's no desktop to put it on; closing the main window then
+    /// quits instead of hiding it.
+    pub tray: bool,
+    pub supabase: SupabaseConfig,
+    pub google: GoogleConfig,
+    /// The hosts file the focus shield edits. It has to be writable by the
+    /// user, e.g. through a group or ACL set up once by an administrator.
+    /// Deliberately not a setting: the webview doesn't get to pick which
+    /// file is rewritten.
+    pub hosts_path: PathBuf,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            open_devtools: cfg!(debug_assertions),
+            start_minimized: std::env::args().any(|arg| arg == "--minimized"),
+            tray: true,
+            supabase: SupabaseConfig::from_env(),
+            google: GoogleConfig::from_env(),
+            hosts_path: PathBuf::from(crate::shield::HOSTS_PATH),
+        }
+    }
+}
+
+/// Where the Supabase project lives. Read from `SUPABASE_URL` and
+/// `SUPABASE_ANON_KEY` at runtime, falling back to the values the app was
+/// built with.
+#[derive(Debug, Clone)]
+pub struct SupabaseConfig {
+    pub url: String,
+    pub anon_key: String,
+}
+
+impl SupabaseConfig {
+    pub fn from_env() -> Self {
+        Self {
+            url: env_var("SUPABASE_URL", option_env!("SUPABASE_URL"))
+                .unwrap_or_default()
+                .trim_end_matches('/')
+                .to_owned(),
+            anon_key: env_var("SUPABASE_ANON_KEY", option_env!("SUPABASE_ANON_KEY"))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The Google OAuth client used to refresh calendar and Gmail tokens.
+/// Read from `GOOGLE_CLIENT_ID` and `GOOGLE_CLIENT_SECRET`.
+#[derive(Debug, Clone, Default)]
+pub struct GoogleConfig {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+impl GoogleConfig {
+    pub fn from_env() -> Self {
+        Self {
+            client_id: env_var("GOOGLE_CLIENT_ID", option_env!("GOOGLE_CLIENT_ID")),
+            client_secret: env_var("GOOGLE_CLIENT_SECRET", option_env!("GOOGLE_CLIENT_SECRET")),
+        }
+    }
+}
+
+/// A runtime environment variable, or the value it had at build time.
+fn env_var(name: &str, built: Option<&str>) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .or_else(|| built.map(str::to_owned))
+        .filter(|value| !value.is_empty())
+}
@@ -0,0 +1,38 @@
+//! Routing for `dayli://` URLs delivered by the deep-link plugin.
+
+use tauri::{App, AppHandle, Runtime};
+use tauri_plugin_deep_link::DeepLinkExt;
+use url::Url;
+
+use crate::auth;
+
+pub fn init<R: Runtime>(app: &App<R>) {
+    // Linux and Windows dev builds need the scheme registered at runtime.
+    #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+    if let Err(err) = app.deep_link().register_all() {
+        log::error!("failed to register deep link schemes: {err}");
+    }
+
+    let handle = app.handle().clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            dispatch(&handle, &url);
+        }
+    });
+
+    // The URL the app was launched with, if any.
+    if let Ok(Some(urls)) = app.deep_link().get_current() {
+        for url in urls {
+            dispatch(app.handle(), &url);
+        }
+    }
+}
+
+/// Hand a `dayli://` URL to the subsystem that owns it.
+pub fn dispatch<R: Runtime>(app: &AppHandle<R>, url: &Url) {
+    if auth::is_callback(url) {
+        auth::handle_callback(app, url);
+    } else {
+        log::warn!("ignoring unknown deep link {url}");
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Serialize, Serializer};
+
+/// Errors returned from the Rust side of the app. Commands return these
+/// directly; they serialize to their display string for the webview.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Tauri(#[from] tauri::Error),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
+    Url(#[from] url::ParseError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Keyring(#[from] keyring::Error),
+    #[error(transparent)]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("invalid callback: {0}")]
+    InvalidCallback(String),
+    #[error("authentication failed: {0}")]
+    Auth(String),
+    #[error("vault: {0}")]
+    Vault(String),
+    #[error("queue: {0}")]
+    Queue(String),
+    #[error("sync: {0}")]
+    Sync(String),
+    #[error("capture: {0}")]
+    Capture(String),
+    #[error("autostart: {0}")]
+    Autostart(String),
+    #[error("focus: {0}")]
+    Focus(String),
+    #[error("focus shield: {0}")]
+    Shield(String),
+    #[error("local api: {0}")]
+    Api(String),
+    #[error("mcp: {0}")]
+    Mcp(String),
+    #[error("calendar file: {0}")]
+    Ics(String),
+    #[error("recurrence: {0}")]
+    Recurrence(String),
+    #[error("schedule: {0}")]
+    Schedule(String),
+    #[error("timezone: {0}")]
+    Timezone(String),
+}
+
+impl Serialize for Error {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
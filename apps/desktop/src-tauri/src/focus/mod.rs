@@ -0,0 +1,493 @@
+//! Focus sessions owned by the backend, so focus time is time actually
+//! worked rather than the length of the block. A session is tied to one
+//! time block; it can be paused, resumed and extended, and optionally split
+//! into pomodoro work and break phases. The running session is checkpointed
+//! to the store so it survives a restart, and on completion the minutes
+//! worked go into the block's `metadata.focus` and are added to the day's
+//! `stats.focusMinutes`.
+//!
+//! On desktop the session pauses itself while the presence monitor reports
+//! the user away, and gives back the idle stretch before that was noticed.
+
+mod session;
+
+use std::sync::Mutex;
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+
+use crate::error::{Error, Result};
+use crate::queue::{Action, Connectivity, NewOperation, Table};
+use crate::store::{BlockType, DailySchedule, Store, TimeBlock};
+
+pub use session::{FocusSession, PauseReason, Phase, Pomodoro};
+
+pub const TICK_EVENT: &str = "focus://tick";
+pub const STATE_EVENT: &str = "focus://state";
+pub const PHASE_EVENT: &str = "focus://phase";
+pub const COMPLETED_EVENT: &str = "focus://completed";
+
+const SESSION_SETTING: &str = "focus.session";
+const TICK_INTERVAL: StdDuration = StdDuration::from_secs(1);
+/// How often a running session is written to the store.
+const CHECKPOINT_INTERVAL: StdDuration = StdDuration::from_secs(15);
+
+#[derive(Default)]
+pub struct Focus {
+    session: Mutex<Option<FocusSession>>,
+}
+
+/// A session as the webview sees it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusStatus {
+    pub block_id: String,
+    pub started_at: DateTime<Utc>,
+    pub paused: Option<PauseReason>,
+    pub phase: Phase,
+    pub pomodoro: Option<Pomodoro>,
+    pub planned_minutes: i64,
+    pub worked_seconds: u64,
+    pub remaining_seconds: u64,
+    pub phase_remaining_seconds: Option<u64>,
+    pub interruptions: u32,
+}
+
+impl FocusStatus {
+    fn of(session: &FocusSession, now: Instant) -> Self {
+        Self {
+            block_id: session.block_id.clone(),
+            started_at: session.started_at,
+            paused: session.paused,
+            phase: session.phase,
+            pomodoro: session.pomodoro,
+            planned_minutes: session.planned_minutes,
+            worked_seconds: session.worked(now).as_secs(),
+            remaining_seconds: session.remaining(now).as_secs(),
+            phase_remaining_seconds: session.phase_remaining(now).map(|left| left.as_secs()),
+            interruptions: session.interruptions,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusSummary {
+    pub block_id: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub planned_minutes: i64,
+    pub worked_minutes: i64,
+    pub interruptions: u32,
+}
+
+fn emit<R: Runtime, S: Serialize + Clone>(app: &AppHandle<R>, event: &str, payload: S) {
+    if let Err(err) = app.emit(event, payload) {
+        log::warn!("failed to emit {event}: {err}");
+    }
+}
+
+fn persist(store: &Store, session: Option<&FocusSession>) -> Result<()> {
+    store.set_setting(SESSION_SETTING, &session)
+}
+
+/// Redraw what shows the session outside the webview. Called with the
+/// session unlocked, since the tray reads it back.
+fn changed<R: Runtime>(app: &AppHandle<R>) {
+    #[cfg(desktop)]
+    crate::tray::refresh(app);
+    #[cfg(not(desktop))]
+    let _ = app;
+}
+
+/// Apply `change` to the current session, save it and report it. `change`
+/// returns whether anything changed.
+fn update<R: Runtime>(
+    app: &AppHandle<R>,
+    change: impl FnOnce(&mut FocusSession, Instant) -> bool,
+) -> Result<FocusStatus> {
+    let focus = app.state::<Focus>();
+    let mut guard = focus.session.lock().unwrap();
+    let session = guard
+        .as_mut()
+        .ok_or_else(|| Error::Focus("no focus session".into()))?;
+    let now = Instant::now();
+    let updated = change(session, now);
+    session.checkpoint(now);
+    let status = FocusStatus::of(session, now);
+    if updated {
+        persist(&app.state::<Store>(), Some(session))?;
+        emit(app, STATE_EVENT, status.clone());
+        drop(guard);
+        changed(app);
+    }
+    Ok(status)
+}
+
+pub fn status<R: Runtime>(app: &AppHandle<R>) -> Option<FocusStatus> {
+    let focus = app.state::<Focus>();
+    let session = focus.session.lock().unwrap();
+    session
+        .as_ref()
+        .map(|session| FocusStatus::of(session, Instant::now()))
+}
+
+/// The block to focus on when none is named: the one running now,
+/// preferring a focus block.
+fn current_block(store: &Store) -> Result<TimeBlock> {
+    let blocks = store.blocks_at(Utc::now())?;
+    blocks
+        .iter()
+        .find(|block| block.block_type == BlockType::Focus)
+        .or(blocks.first())
+        .cloned()
+        .ok_or_else(|| Error::Focus("no block is running now".into()))
+}
+
+/// Start a session for `block_id`, or the block running now, planned for
+/// the length of the block.
+pub fn start<R: Runtime>(
+    app: &AppHandle<R>,
+    block_id: Option<&str>,
+    pomodoro: Option<Pomodoro>,
+) -> Result<FocusStatus> {
+    let store = app.state::<Store>();
+    let block = match block_id {
+        Some(id) => store
+            .get_time_block(id)?
+            .ok_or_else(|| Error::Focus(format!("no block {id}")))?,
+        None => current_block(&store)?,
+    };
+
+    let focus = app.state::<Focus>();
+    let mut guard = focus.session.lock().unwrap();
+    if let Some(current) = guard.as_ref() {
+        return Err(Error::Focus(format!(
+            "a session is already running for block {}",
+            current.block_id
+        )));
+    }
+    let now = Instant::now();
+    let planned = (block.end_time - block.start_time).num_minutes();
+    let session = FocusSession::start(block.id, planned, pomodoro, Utc::now(), now);
+    persist(&store, Some(&session))?;
+    let status = FocusStatus::of(&session, now);
+    *guard = Some(session);
+    drop(guard);
+    emit(app, STATE_EVENT, status.clone());
+    changed(app);
+    Ok(status)
+}
+
+pub fn pause<R: Runtime>(app: &AppHandle<R>) -> Result<FocusStatus> {
+    update(app, |session, now| session.pause(PauseReason::User, now))
+}
+
+pub fn resume<R: Runtime>(app: &AppHandle<R>) -> Result<FocusStatus> {
+    update(app, |session, now| session.resume(now))
+}
+
+pub fn extend<R: Runtime>(app: &AppHandle<R>, minutes: i64) -> Result<FocusStatus> {
+    update(app, |session, _| {
+        session.extend(minutes);
+        true
+    })
+}
+
+/// End the session and record the time worked.
+pub fn complete<R: Runtime>(app: &AppHandle<R>) -> Result<FocusSummary> {
+    let focus = app.state::<Focus>();
+    let session = focus
+        .session
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| Error::Focus("no focus session".into()))?;
+
+    let summary = FocusSummary {
+        block_id: session.block_id.clone(),
+        started_at: session.started_at,
+        ended_at: Utc::now(),
+        planned_minutes: session.planned_minutes,
+        worked_minutes: session.worked_minutes(Instant::now()),
+        interruptions: session.interruptions,
+    };
+    let store = app.state::<Store>();
+    persist(&store, None)?;
+    record(&store, &summary)?;
+    app.state::<Connectivity>().wake();
+    emit(app, COMPLETED_EVENT, summary.clone());
+    changed(app);
+    Ok(summary)
+}
+
+fn object(value: &mut Value) -> &mut serde_json::Map<String, Value> {
+    if !value.is_object() {
+        *value = json!({});
+    }
+    value.as_object_mut().unwrap()
+}
+
+/// Write the minutes worked into the block's metadata and add them to the
+/// day's focus total, creating the day's schedule if there isn't one yet.
+/// Blocks sync on their own; the schedule gets an increment through the
+/// queue, so minutes logged on another device aren't overwritten.
+fn record(store: &Store, summary: &FocusSummary) -> Result<()> {
+    let Some(mut block) = store.get_time_block(&summary.block_id)? else {
+        return Ok(());
+    };
+    object(&mut block.metadata).insert(
+        "focus".into(),
+        json!({
+            "actualMinutes": summary.worked_minutes,
+            "plannedMinutes": summary.planned_minutes,
+            "startedAt": summary.started_at,
+            "endedAt": summary.ended_at,
+            "interruptions": summary.interruptions,
+        }),
+    );
+    block.updated_at = summary.ended_at;
+    store.save_time_block(&block)?;
+
+    let zone = store.zone()?;
+    let date = zone.local(block.start_time).date();
+    let mut schedule = match store.get_schedule(date)? {
+        Some(schedule) => schedule,
+        None => DailySchedule {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: block.user_id.clone(),
+            schedule_date: date,
+            stats: json!({ "emailsProcessed": 0, "tasksCompleted": 0, "focusMinutes": 0 }),
+            created_at: summary.ended_at,
+            updated_at: summary.ended_at,
+        },
+    };
+    let stats = object(&mut schedule.stats);
+    let total = stats
+        .get("focusMinutes")
+        .and_then(Value::as_i64)
+        .unwrap_or(0);
+    stats.insert(
+        "focusMinutes".into(),
+        (total + summary.worked_minutes).into(),
+    );
+    schedule.updated_at = summary.ended_at;
+    store.save_schedule(&schedule)?;
+    store.enqueue(NewOperation {
+        idempotency_key: Some(format!(
+            "focus:{}:{}",
+            summary.block_id,
+            summary.started_at.timestamp()
+        )),
+        table: Table::DailySchedules,
+        action: Action::Increment,
+        row_id: Some(schedule.id),
+        payload: json!({
+            "schedule_date": date,
+            "deltas": { "focusMinutes": summary.worked_minutes },
+        }),
+    })?;
+    Ok(())
+}
+
+/// Pause while the user is away, giving back the time since their last
+/// input, and pick up again when they return.
+#[cfg(desktop)]
+fn follow_presence<R: Runtime>(app: AppHandle<R>) {
+    let mut away = app.state::<crate::presence::Presence>().subscribe();
+    tauri::async_runtime::spawn(async move {
+        while away.changed().await.is_ok() {
+            let since = *away.borrow_and_update();
+            let result = match since {
+                Some(since) => update(&app, |session, now| {
+                    let paused = session.pause(PauseReason::Idle, now);
+                    if paused {
+                        session.deduct((Utc::now() - since).to_std().unwrap_or_default());
+                    }
+                    paused
+                }),
+                None => update(&app, |session, now| {
+                    session.paused == Some(PauseReason::Idle) && session.resume(now)
+                }),
+            };
+            // Having no session to pause is the usual case.
+            match result {
+                Ok(_) | Err(Error::Focus(_)) => {}
+                Err(err) => log::error!("failed to update focus session: {err}"),
+            }
+        }
+    });
+}
+
+/// Restore a session left running at the last exit and start ticking.
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    let restored = app
+        .state::<Store>()
+        .setting::<Option<FocusSession>>(SESSION_SETTING)?
+        .flatten()
+        .map(|session| session.restored(Instant::now()));
+    app.manage(Focus {
+        session: Mutex::new(restored),
+    });
+    #[cfg(desktop)]
+    follow_presence(app.handle().clone());
+
+    let app = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let mut last_checkpoint = Instant::now();
+        loop {
+            tokio::time::sleep(TICK_INTERVAL).await;
+            let focus = app.state::<Focus>();
+            let mut guard = focus.session.lock().unwrap();
+            let Some(session) = guard.as_mut().filter(|session| session.is_running()) else {
+                continue;
+            };
+            let now = Instant::now();
+            let entered = session.checkpoint(now);
+            let status = FocusStatus::of(session, now);
+            let due = entered.is_some() || last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL;
+            let snapshot = due.then(|| session.clone());
+            drop(guard);
+
+            emit(&app, TICK_EVENT, status.clone());
+            if entered.is_some() {
+                emit(&app, PHASE_EVENT, status);
+            }
+            if let Some(snapshot) = snapshot {
+                if let Err(err) = persist(&app.state::<Store>(), Some(&snapshot)) {
+                    log::error!("failed to checkpoint focus session: {err}");
+                }
+                last_checkpoint = now;
+            }
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn focus_status<R: Runtime>(app: AppHandle<R>) -> Option<FocusStatus> {
+    status(&app)
+}
+
+#[tauri::command]
+pub fn focus_start<R: Runtime>(
+    app: AppHandle<R>,
+    block_id: Option<String>,
+    pomodoro: Option<Pomodoro>,
+) -> Result<FocusStatus> {
+    start(&app, block_id.as_deref(), pomodoro)
+}
+
+#[tauri::command]
+pub fn focus_pause<R: Runtime>(app: AppHandle<R>) -> Result<FocusStatus> {
+    pause(&app)
+}
+
+#[tauri::command]
+pub fn focus_resume<R: Runtime>(app: AppHandle<R>) -> Result<FocusStatus> {
+    resume(&app)
+}
+
+#[tauri::command]
+pub fn focus_extend<R: Runtime>(app: AppHandle<R>, minutes: i64) -> Result<FocusStatus> {
+    extend(&app, minutes)
+}
+
+#[tauri::command]
+pub fn focus_complete<R: Runtime>(app: AppHandle<R>) -> Result<FocusSummary> {
+    complete(&app)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+
+    use super::*;
+    use crate::store::UserPreferences;
+    use crate::testing::block;
+
+    fn summary(started_at: DateTime<Utc>, worked_minutes: i64) -> FocusSummary {
+        FocusSummary {
+            block_id: "b1".into(),
+            started_at,
+            ended_at: started_at + chrono::Duration::minutes(worked_minutes),
+            planned_minutes: 60,
+            worked_minutes,
+            interruptions: 1,
+        }
+    }
+
+    #[test]
+    fn record_creates_the_schedule_and_queues_increments() {
+        let store = Store::open_in_memory().unwrap();
+        let mut preferences = UserPreferences::defaults("u1");
+        preferences.timezone = "Asia/Tokyo".into();
+        store.save_preferences(&preferences).unwrap();
+        // 23:30 UTC on the 2nd is the morning of the 3rd in Tokyo.
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 23, 30, 0).unwrap();
+        store
+            .save_time_block(&block("b1", start, start + chrono::Duration::hours(1)))
+            .unwrap();
+        let day = NaiveDate::from_ymd_opt(2026, 3, 3).unwrap();
+        assert!(store.get_schedule(day).unwrap().is_none());
+
+        record(&store, &summary(start, 25)).unwrap();
+        record(&store, &summary(start + chrono::Duration::minutes(30), 20)).unwrap();
+
+        let schedule = store.get_schedule(day).unwrap().unwrap();
+        assert_eq!(schedule.user_id, "u1");
+        assert_eq!(schedule.stats["focusMinutes"], 45);
+        assert_eq!(schedule.stats["tasksCompleted"], 0);
+        let block = store.get_time_block("b1").unwrap().unwrap();
+        assert_eq!(block.metadata["focus"]["actualMinutes"], 20);
+
+        let ops = store.queued_operations().unwrap();
+        assert_eq!(ops.len(), 2);
+        for (op, minutes) in ops.iter().zip([25, 20]) {
+            assert_eq!(op.table, Table::DailySchedules);
+            assert_eq!(op.action, Action::Increment);
+            assert_eq!(op.row_id.as_deref(), Some(schedule.id.as_str()));
+            assert_eq!(
+                op.payload,
+                json!({ "schedule_date": "2026-03-03", "deltas": { "focusMinutes": minutes } })
+            );
+        }
+    }
+
+    #[test]
+    fn record_adds_to_an_existing_schedule() {
+        let store = Store::open_in_memory().unwrap();
+        let mut preferences = UserPreferences::defaults("u1");
+        preferences.timezone = "UTC".into();
+        store.save_preferences(&preferences).unwrap();
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap();
+        store
+            .save_time_block(&block("b1", start, start + chrono::Duration::hours(1)))
+            .unwrap();
+        let day = start.date_naive();
+        store
+            .save_schedule(&DailySchedule {
+                id: "s1".into(),
+                user_id: "u1".into(),
+                schedule_date: day,
+                stats: json!({ "focusMinutes": 10, "tasksCompleted": 3 }),
+                created_at: start,
+                updated_at: start,
+            })
+            .unwrap();
+
+        record(&store, &summary(start, 25)).unwrap();
+
+        let schedule = store.get_schedule(day).unwrap().unwrap();
+        assert_eq!(schedule.id, "s1");
+        assert_eq!(
+            schedule.stats,
+            json!({ "focusMinutes": 35, "tasksCompleted": 3 })
+        );
+        let ops = store.queued_operations().unwrap();
+        assert_eq!(ops[0].payload["deltas"], json!({ "focusMinutes": 25 }));
+    }
+}
@@ -0,0 +1,335 @@
+//! The focus session state machine. Worked time is measured on the
+//! monotonic clock, which doesn't jump when the wall clock is adjusted or
+//! the machine suspends; callers pass `now` in so the machine itself has no
+//! hidden clock.
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Pomodoro-style cycles of work and break inside one session. Break time
+/// isn't focus time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Pomodoro {
+    pub work_minutes: u32,
+    pub break_minutes: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Work,
+    Break,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PauseReason {
+    User,
+    Idle,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusSession {
+    pub block_id: String,
+    pub started_at: DateTime<Utc>,
+    pub planned_minutes: i64,
+    pub pomodoro: Option<Pomodoro>,
+    pub phase: Phase,
+    pub paused: Option<PauseReason>,
+    /// Times the user walked away mid-session.
+    pub interruptions: u32,
+    /// Work time up to the start of the current running stretch.
+    worked: Duration,
+    /// Time into the current phase up to the start of the running stretch.
+    phase_elapsed: Duration,
+    /// Start of the current running stretch; `None` while paused or right
+    /// after being restored from disk.
+    #[serde(skip)]
+    running_since: Option<Instant>,
+}
+
+impl FocusSession {
+    pub fn start(
+        block_id: String,
+        planned_minutes: i64,
+        pomodoro: Option<Pomodoro>,
+        started_at: DateTime<Utc>,
+        now: Instant,
+    ) -> Self {
+        Self {
+            block_id,
+            started_at,
+            planned_minutes: planned_minutes.max(1),
+            pomodoro: pomodoro.map(|p| Pomodoro {
+                work_minutes: p.work_minutes.max(1),
+                break_minutes: p.break_minutes.max(1),
+            }),
+            phase: Phase::Work,
+            paused: None,
+            interruptions: 0,
+            worked: Duration::ZERO,
+            phase_elapsed: Duration::ZERO,
+            running_since: Some(now),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.paused.is_none()
+    }
+
+    fn phase_length(&self) -> Option<Duration> {
+        let pomodoro = self.pomodoro?;
+        let minutes = match self.phase {
+            Phase::Work => pomodoro.work_minutes,
+            Phase::Break => pomodoro.break_minutes,
+        };
+        Some(Duration::from_secs(u64::from(minutes) * 60))
+    }
+
+    /// Move `elapsed` of running time through the phases. Returns the phase
+    /// entered last, if any changed.
+    fn advance(&mut self, mut elapsed: Duration) -> Option<Phase> {
+        let mut entered = None;
+        loop {
+            let length = self.phase_length();
+            let step = match length {
+                Some(length) => elapsed.min(length.saturating_sub(self.phase_elapsed)),
+                None => elapsed,
+            };
+            if self.phase == Phase::Work {
+                self.worked += step;
+            }
+            self.phase_elapsed += step;
+            elapsed -= step;
+
+            if length.is_some_and(|length| self.phase_elapsed >= length) {
+                self.phase = match self.phase {
+                    Phase::Work => Phase::Break,
+                    Phase::Break => Phase::Work,
+                };
+                self.phase_elapsed = Duration::ZERO;
+                entered = Some(self.phase);
+            }
+            if elapsed.is_zero() {
+                return entered;
+            }
+        }
+    }
+
+    /// Fold the running stretch up to `now` into the totals. Returns the
+    /// phase entered, if a pomodoro boundary was crossed.
+    pub fn checkpoint(&mut self, now: Instant) -> Option<Phase> {
+        if self.paused.is_some() {
+            return None;
+        }
+        let since = self.running_since.replace(now)?;
+        self.advance(now.saturating_duration_since(since))
+    }
+
+    pub fn pause(&mut self, reason: PauseReason, now: Instant) -> bool {
+        if self.paused.is_some() {
+            return false;
+        }
+        self.checkpoint(now);
+        self.running_since = None;
+        self.paused = Some(reason);
+        if reason == PauseReason::Idle {
+            self.interruptions += 1;
+        }
+        true
+    }
+
+    pub fn resume(&mut self, now: Instant) -> bool {
+        if self.paused.take().is_none() {
+            return false;
+        }
+        self.running_since = Some(now);
+        true
+    }
+
+    /// Carry on counting after a restart. Time while the app wasn't
+    /// running isn't counted; the last checkpoint is all that's known.
+    pub fn restored(mut self, now: Instant) -> Self {
+        if self.paused.is_none() {
+            self.running_since = Some(now);
+        }
+        self
+    }
+
+    /// Take back time already counted as work, e.g. the stretch before the
+    /// idle monitor noticed the user had left.
+    pub fn deduct(&mut self, time: Duration) {
+        self.worked = self.worked.saturating_sub(time);
+        self.phase_elapsed = self.phase_elapsed.saturating_sub(time);
+    }
+
+    pub fn extend(&mut self, minutes: i64) {
+        self.planned_minutes = (self.planned_minutes + minutes).max(1);
+    }
+
+    /// Work time as of `now`, without changing the session.
+    pub fn worked(&self, now: Instant) -> Duration {
+        let mut session = self.clone();
+        session.checkpoint(now);
+        session.worked
+    }
+
+    pub fn remaining(&self, now: Instant) -> Duration {
+        let planned = Duration::from_secs(self.planned_minutes as u64 * 60);
+        planned.saturating_sub(self.worked(now))
+    }
+
+    /// Time left in the current pomodoro phase.
+    pub fn phase_remaining(&self, now: Instant) -> Option<Duration> {
+        let mut session = self.clone();
+        session.checkpoint(now);
+        Some(
+            session
+                .phase_length()?
+                .saturating_sub(session.phase_elapsed),
+        )
+    }
+
+    /// Whole minutes worked, to the nearest minute.
+    pub fn worked_minutes(&self, now: Instant) -> i64 {
+        ((self.worked(now).as_secs() + 30) / 60) as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn session(pomodoro: Option<Pomodoro>, t0: Instant) -> FocusSession {
+        FocusSession::start("b1".into(), 60, pomodoro, Utc::now(), t0)
+    }
+
+    fn pomodoro() -> Option<Pomodoro> {
+        Some(Pomodoro {
+            work_minutes: 25,
+            break_minutes: 5,
+        })
+    }
+
+    #[test]
+    fn pomodoro_rolls_over_between_work_and_break() {
+        let t0 = Instant::now();
+        let mut session = session(pomodoro(), t0);
+
+        assert_eq!(session.checkpoint(t0 + 10 * MINUTE), None);
+        assert_eq!(session.phase_remaining(t0 + 10 * MINUTE), Some(15 * MINUTE));
+
+        assert_eq!(session.checkpoint(t0 + 27 * MINUTE), Some(Phase::Break));
+        assert_eq!(session.phase_remaining(t0 + 27 * MINUTE), Some(3 * MINUTE));
+        // Break time isn't work time.
+        assert_eq!(session.worked(t0 + 30 * MINUTE), 25 * MINUTE);
+
+        assert_eq!(session.checkpoint(t0 + 31 * MINUTE), Some(Phase::Work));
+        assert_eq!(session.worked(t0 + 31 * MINUTE), 26 * MINUTE);
+    }
+
+    #[test]
+    fn one_long_stretch_crosses_several_phases() {
+        let t0 = Instant::now();
+        let mut session = session(pomodoro(), t0);
+
+        // Two full cycles and ten minutes into the third work phase.
+        assert_eq!(session.checkpoint(t0 + 70 * MINUTE), Some(Phase::Work));
+        assert_eq!(session.phase, Phase::Work);
+        assert_eq!(session.worked(t0 + 70 * MINUTE), 60 * MINUTE);
+        assert_eq!(session.phase_remaining(t0 + 70 * MINUTE), Some(15 * MINUTE));
+    }
+
+    #[test]
+    fn checkpointing_often_counts_the_same_as_once() {
+        let t0 = Instant::now();
+        let mut often = session(pomodoro(), t0);
+        for minute in 1..=70 {
+            often.checkpoint(t0 + minute * MINUTE);
+        }
+        let once = session(pomodoro(), t0);
+        assert_eq!(
+            often.worked(t0 + 70 * MINUTE),
+            once.worked(t0 + 70 * MINUTE)
+        );
+        assert_eq!(often.phase, Phase::Work);
+    }
+
+    #[test]
+    fn paused_time_isnt_counted() {
+        let t0 = Instant::now();
+        let mut session = session(None, t0);
+
+        assert!(session.pause(PauseReason::User, t0 + 10 * MINUTE));
+        assert!(!session.pause(PauseReason::User, t0 + 11 * MINUTE));
+        assert_eq!(session.worked(t0 + 40 * MINUTE), 10 * MINUTE);
+        assert_eq!(session.interruptions, 0);
+
+        assert!(session.resume(t0 + 40 * MINUTE));
+        assert!(!session.resume(t0 + 41 * MINUTE));
+        assert_eq!(session.worked(t0 + 45 * MINUTE), 15 * MINUTE);
+        assert_eq!(session.remaining(t0 + 45 * MINUTE), 45 * MINUTE);
+    }
+
+    #[test]
+    fn going_idle_counts_as_an_interruption() {
+        let t0 = Instant::now();
+        let mut session = session(None, t0);
+
+        session.pause(PauseReason::Idle, t0 + 5 * MINUTE);
+        session.resume(t0 + 6 * MINUTE);
+        session.pause(PauseReason::Idle, t0 + 7 * MINUTE);
+        assert_eq!(session.interruptions, 2);
+        assert_eq!(session.paused, Some(PauseReason::Idle));
+    }
+
+    #[test]
+    fn deduct_gives_back_work_and_phase_time() {
+        let t0 = Instant::now();
+        let mut session = session(pomodoro(), t0);
+
+        session.pause(PauseReason::Idle, t0 + 20 * MINUTE);
+        session.deduct(5 * MINUTE);
+        assert_eq!(session.worked(t0 + 30 * MINUTE), 15 * MINUTE);
+        assert_eq!(session.phase_remaining(t0 + 30 * MINUTE), Some(10 * MINUTE));
+
+        // Never below zero.
+        session.deduct(60 * MINUTE);
+        assert_eq!(session.worked(t0 + 30 * MINUTE), Duration::ZERO);
+        assert_eq!(session.phase_remaining(t0 + 30 * MINUTE), Some(25 * MINUTE));
+    }
+
+    #[test]
+    fn extend_and_rounding() {
+        let t0 = Instant::now();
+        let mut session = session(None, t0);
+
+        session.extend(15);
+        assert_eq!(session.planned_minutes, 75);
+        session.extend(-500);
+        assert_eq!(session.planned_minutes, 1);
+
+        assert_eq!(session.worked_minutes(t0 + Duration::from_secs(89)), 1);
+        assert_eq!(session.worked_minutes(t0 + Duration::from_secs(90)), 2);
+    }
+
+    #[test]
+    fn a_restored_session_doesnt_count_the_downtime() {
+        let t0 = Instant::now();
+        let mut session = session(None, t0);
+        session.checkpoint(t0 + 10 * MINUTE);
+
+        let json = serde_json::to_string(&session).unwrap();
+        let later = t0 + 120 * MINUTE;
+        let restored = serde_json::from_str::<FocusSession>(&json)
+            .unwrap()
+            .restored(later);
+        assert_eq!(restored.worked(later + 5 * MINUTE), 15 * MINUTE);
+    }
+}
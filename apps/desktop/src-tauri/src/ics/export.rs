@@ -0,0 +1,66 @@
+//! Writing time blocks as VEVENTs.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use super::format::{escape, Writer};
+use super::{ENERGY_PROPERTY, TASK_ID_PARAM, TASK_PROPERTY, TYPE_PROPERTY, UID_DOMAIN};
+use crate::store::{BlockType, TimeBlock};
+
+const PRODID: &str = "-//Dayli//Dayli Desktop//EN";
+
+fn stamp(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// A calendar of `blocks`. `task_titles` names the assigned tasks; ones
+/// missing from it are left out.
+pub fn calendar(blocks: &[TimeBlock], task_titles: &HashMap<String, String>) -> String {
+    let mut out = Writer::default();
+    out.line("BEGIN:VCALENDAR");
+    out.line("VERSION:2.0");
+    out.line(&format!("PRODID:{PRODID}"));
+    out.line("CALSCALE:GREGORIAN");
+    out.line("METHOD:PUBLISH");
+    let now = Utc::now();
+    for block in blocks {
+        out.line("BEGIN:VEVENT");
+        out.line(&format!("UID:{}@{UID_DOMAIN}", block.id));
+        out.line(&format!("DTSTAMP:{}", stamp(now)));
+        out.line(&format!("LAST-MODIFIED:{}", stamp(block.updated_at)));
+        out.line(&format!("DTSTART:{}", stamp(block.start_time)));
+        out.line(&format!("DTEND:{}", stamp(block.end_time)));
+        out.property("SUMMARY", &block.title);
+        if let Some(description) = &block.description {
+            out.property("DESCRIPTION", description);
+        }
+        if let Some(location) = block
+            .metadata
+            .get("location")
+            .and_then(|value| value.as_str())
+        {
+            out.property("LOCATION", location);
+        }
+        // Breaks don't make you busy to other people's calendars.
+        if block.block_type == BlockType::Break {
+            out.line("TRANSP:TRANSPARENT");
+        }
+        out.line(&format!("{TYPE_PROPERTY}:{}", block.block_type));
+        out.line(&format!("{ENERGY_PROPERTY}:{}", block.energy_level));
+        let mut assigned = block.assigned_tasks.clone();
+        assigned.sort_by_key(|assignment| assignment.position);
+        for assignment in assigned {
+            if let Some(title) = task_titles.get(&assignment.id) {
+                out.line(&format!(
+                    "{TASK_PROPERTY};{TASK_ID_PARAM}={}:{}",
+                    assignment.id,
+                    escape(title)
+                ));
+            }
+        }
+        out.line("END:VEVENT");
+    }
+    out.line("END:VCALENDAR");
+    out.finish()
+}
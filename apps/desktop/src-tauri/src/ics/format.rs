@@ -0,0 +1,301 @@
+//! The iCalendar text format: content lines, folding, escaping and the
+//! BEGIN/END component tree.
+
+use crate::error::{Error, Result};
+
+/// Lines are folded to at most this many octets, CRLF excluded.
+const FOLD_AT: usize = 75;
+
+/// One unfolded `NAME;PARAM=value:VALUE` line.
+#[derive(Debug, Clone)]
+pub struct Line {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl Line {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn parse(line: &str) -> Option<Self> {
+        // The value starts at the first colon outside a quoted parameter.
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(at, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(at),
+            _ => None,
+        })?;
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+        let mut parts = split_unquoted(head, ';').into_iter();
+        let name = parts.next()?.to_ascii_uppercase();
+        let params = parts
+            .filter_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                Some((key.to_ascii_uppercase(), value.trim_matches('"').to_owned()))
+            })
+            .collect();
+        Some(Self {
+            name,
+            params,
+            value: value.to_owned(),
+        })
+    }
+}
+
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut from = 0;
+    for (at, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&text[from..at]);
+            from = at + 1;
+        }
+    }
+    parts.push(&text[from..]);
+    parts
+}
+
+/// A component and what's nested in it, e.g. a VEVENT inside a VCALENDAR.
+#[derive(Debug, Clone, Default)]
+pub struct Component {
+    pub name: String,
+    pub properties: Vec<Line>,
+    pub children: Vec<Component>,
+}
+
+impl Component {
+    pub fn property(&self, name: &str) -> Option<&Line> {
+        self.properties.iter().find(|line| line.name == name)
+    }
+
+    pub fn properties<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Line> + 'a {
+        self.properties.iter().filter(move |line| line.name == name)
+    }
+
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Component> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// The unescaped text of a property.
+    pub fn text(&self, name: &str) -> Option<String> {
+        self.property(name)
+            .map(|line| unescape(&line.value))
+            .filter(|text| !text.is_empty())
+    }
+}
+
+/// Parse a file into its top-level VCALENDAR.
+pub fn parse(text: &str) -> Result<Component> {
+    let mut unfolded: Vec<String> = Vec::new();
+    for raw in text.lines() {
+        match (raw.strip_prefix([' ', '\t']), unfolded.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ if raw.trim().is_empty() => {}
+            _ => unfolded.push(raw.to_owned()),
+        }
+    }
+
+    let mut stack = vec![Component::default()];
+    for raw in &unfolded {
+        let Some(line) = Line::parse(raw) else {
+            continue;
+        };
+        match line.name.as_str() {
+            "BEGIN" => stack.push(Component {
+                name: line.value.to_ascii_uppercase(),
+                ..Component::default()
+            }),
+            "END" => {
+                if stack.len() < 2 {
+                    return Err(Error::Ics(format!("unexpected END:{}", line.value)));
+                }
+                let done = stack.pop().unwrap_or_default();
+                if !done.name.eq_ignore_ascii_case(&line.value) {
+                    return Err(Error::Ics(format!(
+                        "END:{} closes {}",
+                        line.value, done.name
+                    )));
+                }
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(done);
+                }
+            }
+            _ => {
+                if let Some(current) = stack.last_mut() {
+                    current.properties.push(line);
+                }
+            }
+        }
+    }
+    if stack.len() > 1 {
+        return Err(Error::Ics("the file ends inside a component".into()));
+    }
+    stack
+        .pop()
+        .and_then(|root| {
+            root.children
+                .into_iter()
+                .find(|child| child.name == "VCALENDAR")
+        })
+        .ok_or_else(|| Error::Ics("not an iCalendar file".into()))
+}
+
+pub fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => text.push('\n'),
+            Some(other) => text.push(other),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
+
+pub fn escape(text: &str) -> String {
+    let mut value = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                value.push('\\');
+                value.push(c);
+            }
+            '\n' => value.push_str("\\n"),
+            '\r' => {}
+            _ => value.push(c),
+        }
+    }
+    value
+}
+
+/// Builds a file with CRLF line endings, folding long lines.
+#[derive(Debug, Default)]
+pub struct Writer {
+    out: String,
+}
+
+impl Writer {
+    /// Append a content line; `line` is written as given, so escape text
+    /// values first.
+    pub fn line(&mut self, line: &str) {
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > FOLD_AT {
+                self.out.push_str("\r\n ");
+                width = 1;
+            }
+            self.out.push(c);
+            width += c.len_utf8();
+        }
+        self.out.push_str("\r\n");
+    }
+
+    pub fn property(&mut self, name: &str, text: &str) {
+        self.line(&format!("{name}:{}", escape(text)));
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unfolds_continuation_lines() {
+        let calendar = parse(
+            "BEGIN:VCALENDAR\r\n\
+             BEGIN:VEVENT\r\n\
+             SUMMARY:Quarterly plan\r\n ning review\r\n\
+             DESCRIPTION:tab\r\n\tfolded\r\n\
+             END:VEVENT\r\n\
+             END:VCALENDAR\r\n",
+        )
+        .unwrap();
+        let event = calendar.children("VEVENT").next().unwrap();
+        assert_eq!(event.text("SUMMARY").unwrap(), "Quarterly planning review");
+        assert_eq!(event.text("DESCRIPTION").unwrap(), "tabfolded");
+    }
+
+    #[test]
+    fn params_and_quoted_colons() {
+        let line = Line::parse(
+            "attendee;CN=\"Doe; Jane\";x-url=\"https://example.com:8443\":mailto:jane@example.com",
+        )
+        .unwrap();
+        assert_eq!(line.name, "ATTENDEE");
+        assert_eq!(line.param("cn"), Some("Doe; Jane"));
+        assert_eq!(line.param("X-URL"), Some("https://example.com:8443"));
+        assert_eq!(line.value, "mailto:jane@example.com");
+        assert!(Line::parse("no colon here").is_none());
+    }
+
+    #[test]
+    fn escaping_round_trips() {
+        let text = "Plan; review, ship\\deploy\nthen rest";
+        let escaped = escape(text);
+        assert_eq!(escaped, "Plan\\; review\\, ship\\\\deploy\\nthen rest");
+        assert_eq!(unescape(&escaped), text);
+        assert_eq!(escape("a\r\nb"), "a\\nb");
+        assert_eq!(unescape("A\\NB\\"), "A\nB\\");
+    }
+
+    #[test]
+    fn writer_folds_at_75_octets() {
+        // Two-byte characters, so a fold lands mid-way through one unless
+        // the writer counts octets.
+        let summary = format!("Réunion {}", "é".repeat(100));
+        let mut out = Writer::default();
+        out.line("BEGIN:VCALENDAR");
+        out.property("SUMMARY", &summary);
+        out.line("END:VCALENDAR");
+        let text = out.finish();
+
+        assert!(text.ends_with("\r\n"));
+        for line in text.split("\r\n") {
+            assert!(line.len() <= FOLD_AT, "{} octets: {line:?}", line.len());
+        }
+        assert!(text.contains("\r\n "));
+        assert_eq!(parse(&text).unwrap().text("SUMMARY").unwrap(), summary);
+    }
+
+    #[test]
+    fn nesting_errors() {
+        assert!(matches!(
+            parse("BEGIN:VCALENDAR\nBEGIN:VEVENT\nEND:VCALENDAR\n"),
+            Err(Error::Ics(_))
+        ));
+        assert!(matches!(parse("END:VEVENT\n"), Err(Error::Ics(_))));
+        assert!(matches!(
+            parse("BEGIN:VCALENDAR\nBEGIN:VEVENT\n"),
+            Err(Error::Ics(_))
+        ));
+        assert!(matches!(
+            parse("BEGIN:VCARD\nEND:VCARD\n"),
+            Err(Error::Ics(_))
+        ));
+        // Lower-case names and LF-only endings are read anyway.
+        assert_eq!(
+            parse("begin:vcalendar\nend:vcalendar\n").unwrap().name,
+            "VCALENDAR"
+        );
+    }
+}
@@ -0,0 +1,328 @@
+//! Reading a calendar's VEVENTs as concrete occurrences. Recurring events are
+//! expanded with their RRULE and RDATEs, minus EXDATEs, with RECURRENCE-ID
+//! overrides swapped in for the instances they replace.
+
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+
+use super::format::{Component, Line};
+use super::zone::{VTimeZone, Zone};
+use super::{ENERGY_PROPERTY, TASK_ID_PARAM, TASK_PROPERTY, TYPE_PROPERTY};
+use crate::rrule::RRule;
+use crate::store::{BlockType, EnergyLevel};
+
+/// One occurrence of an event.
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// The UID, plus the instance's start for occurrences of a recurring
+    /// event, the way Google Calendar names instances.
+    pub event_id: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub summary: Option<String>,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub block_type: Option<BlockType>,
+    pub energy_level: Option<EnergyLevel>,
+    pub task_ids: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Events {
+    pub events: Vec<Event>,
+    /// Events that can't be time blocks: all-day or zero-length ones, and
+    /// ones with a rule we can't expand.
+    pub skipped: usize,
+}
+
+enum When {
+    Day(NaiveDate),
+    At(NaiveDateTime, Zone),
+}
+
+impl When {
+    fn utc(&self) -> DateTime<Utc> {
+        match self {
+            When::Day(date) => date.and_time(chrono::NaiveTime::MIN).and_utc(),
+            When::At(local, zone) => zone.to_utc(*local),
+        }
+    }
+}
+
+struct Zones(HashMap<String, Rc<VTimeZone>>);
+
+impl Zones {
+    fn when(&self, line: &Line, value: &str) -> Option<When> {
+        if line.param("VALUE") == Some("DATE") || value.len() == 8 {
+            return NaiveDate::parse_from_str(value, "%Y%m%d")
+                .ok()
+                .map(When::Day);
+        }
+        let (naive, utc) = match value.strip_suffix('Z') {
+            Some(naive) => (naive, true),
+            None => (value, false),
+        };
+        let local = NaiveDateTime::parse_from_str(naive, "%Y%m%dT%H%M%S").ok()?;
+        let zone = match line.param("TZID") {
+            _ if utc => Zone::Utc,
+            Some(tzid) => Zone::find(tzid, &self.0),
+            None => Zone::Floating,
+        };
+        Some(When::At(local, zone))
+    }
+
+    fn property(&self, component: &Component, name: &str) -> Option<When> {
+        let line = component.property(name)?;
+        self.when(line, &line.value)
+    }
+
+    /// Every instant listed in `name` properties, e.g. EXDATE.
+    fn list(&self, component: &Component, name: &str) -> Vec<DateTime<Utc>> {
+        component
+            .properties(name)
+            .flat_map(|line| {
+                line.value
+                    .split(',')
+                    .filter_map(|value| self.when(line, value))
+            })
+            .map(|when| when.utc())
+            .collect()
+    }
+
+    /// An event's start and length, if it's a timed event.
+    fn span(&self, component: &Component) -> Option<(NaiveDateTime, Zone, Duration)> {
+        let When::At(local, zone) = self.property(component, "DTSTART")? else {
+            return None;
+        };
+        let start = zone.to_utc(local);
+        let length = match self.property(component, "DTEND") {
+            Some(end) => end.utc() - start,
+            None => parse_duration(&component.property("DURATION")?.value)?,
+        };
+        (length > Duration::zero()).then_some((local, zone, length))
+    }
+}
+
+/// An RFC 5545 duration such as `PT1H30M` or `P1D`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let (sign, rest) = match value.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, value.trim_start_matches('+')),
+    };
+    let rest = rest.strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' => number.push(c),
+            unit => {
+                let n: i64 = std::mem::take(&mut number).parse().ok()?;
+                total += match (unit, in_time) {
+                    ('W', false) => Duration::weeks(n),
+                    ('D', false) => Duration::days(n),
+                    ('H', true) => Duration::hours(n),
+                    ('M', true) => Duration::minutes(n),
+                    ('S', true) => Duration::seconds(n),
+                    _ => return None,
+                };
+            }
+        }
+    }
+    number.is_empty().then_some(total * sign)
+}
+
+fn cancelled(component: &Component) -> bool {
+    component
+        .property("STATUS")
+        .is_some_and(|line| line.value.eq_ignore_ascii_case("CANCELLED"))
+}
+
+fn instance_id(uid: &str, start: DateTime<Utc>) -> String {
+    format!("{uid}_{}", start.format("%Y%m%dT%H%M%SZ"))
+}
+
+/// An occurrence with its fields from `component`, falling back to the
+/// recurring event it overrides.
+fn event(
+    component: &Component,
+    master: Option<&Component>,
+    event_id: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Event {
+    let text = |name: &str| {
+        component
+            .text(name)
+            .or_else(|| master.and_then(|master| master.text(name)))
+    };
+    let tasks = match component.property(TASK_PROPERTY) {
+        Some(_) => component,
+        None => master.unwrap_or(component),
+    };
+    Event {
+        event_id,
+        start,
+        end,
+        summary: text("SUMMARY"),
+        description: text("DESCRIPTION"),
+        location: text("LOCATION"),
+        block_type: text(TYPE_PROPERTY).and_then(|value| value.parse().ok()),
+        energy_level: text(ENERGY_PROPERTY).and_then(|value| value.parse().ok()),
+        task_ids: tasks
+            .properties(TASK_PROPERTY)
+            .filter_map(|line| line.param(TASK_ID_PARAM).map(str::to_owned))
+            .collect(),
+    }
+}
+
+/// The occurrences in `calendar`. Recurring events are expanded only over
+/// `from..to`; single events are read wherever they fall.
+pub fn events(calendar: &Component, from: DateTime<Utc>, to: DateTime<Utc>) -> Events {
+    let zones = Zones(
+        calendar
+            .children("VTIMEZONE")
+            .filter_map(VTimeZone::parse)
+            .map(|(tzid, zone)| (tzid, Rc::new(zone)))
+            .collect(),
+    );
+
+    let mut found = Events::default();
+    let mut masters = Vec::new();
+    let mut overrides: HashMap<(String, DateTime<Utc>), &Component> = HashMap::new();
+    for component in calendar.children("VEVENT") {
+        let Some(uid) = component.text("UID") else {
+            found.skipped += 1;
+            continue;
+        };
+        match zones.property(component, "RECURRENCE-ID") {
+            Some(instance) => {
+                overrides.insert((uid, instance.utc()), component);
+            }
+            None => masters.push((uid, component)),
+        }
+    }
+
+    let mut master_by_uid = HashMap::new();
+    for (uid, component) in masters {
+        if cancelled(component) {
+            continue;
+        }
+        let Some((local, zone, length)) = zones.span(component) else {
+            found.skipped += 1;
+            continue;
+        };
+        let rule = match component.property("RRULE") {
+            None => None,
+            Some(line) => match line.value.parse::<RRule>() {
+                Ok(rule) => Some(rule),
+                Err(err) => {
+                    log::warn!("skipping event {uid}: {err}");
+                    found.skipped += 1;
+                    continue;
+                }
+            },
+        };
+        let rdates = zones.list(component, "RDATE");
+        master_by_uid.insert(uid.clone(), component);
+
+        let start = zone.to_utc(local);
+        if rule.is_none() && rdates.is_empty() {
+            found
+                .events
+                .push(event(component, None, uid, start, start + length));
+            continue;
+        }
+
+        let exdates: HashSet<_> = zones.list(component, "EXDATE").into_iter().collect();
+        let mut starts: Vec<DateTime<Utc>> = match &rule {
+            Some(rule) => rule
+                .occurrences(local, |local| zone.to_utc(local))
+                .map(|local| zone.to_utc(local))
+                .take_while(|&start| start < to)
+                .collect(),
+            None => vec![start],
+        };
+        starts.extend(rdates.into_iter().filter(|&start| start < to));
+        starts.sort();
+        starts.dedup();
+        for start in starts {
+            if exdates.contains(&start) {
+                continue;
+            }
+            let event_id = instance_id(&uid, start);
+            match overrides.remove(&(uid.clone(), start)) {
+                Some(moved) => {
+                    if cancelled(moved) {
+                        continue;
+                    }
+                    let (start, end) = match zones.span(moved) {
+                        Some((local, zone, length)) => {
+                            let start = zone.to_utc(local);
+                            (start, start + length)
+                        }
+                        None => (start, start + length),
+                    };
+                    if end > from {
+                        found
+                            .events
+                            .push(event(moved, Some(component), event_id, start, end));
+                    }
+                }
+                None if start + length > from => {
+                    found
+                        .events
+                        .push(event(component, None, event_id, start, start + length));
+                }
+                None => {}
+            }
+        }
+    }
+
+    // Overrides that moved an instance into the window from outside it, or
+    // whose recurring event isn't in the file.
+    for ((uid, instance), component) in overrides {
+        if cancelled(component) {
+            continue;
+        }
+        let Some((local, zone, length)) = zones.span(component) else {
+            found.skipped += 1;
+            continue;
+        };
+        let start = zone.to_utc(local);
+        if start < to && start + length > from {
+            let master = master_by_uid.get(&uid).copied();
+            found.events.push(event(
+                component,
+                master,
+                instance_id(&uid, instance),
+                start,
+                start + length,
+            ));
+        }
+    }
+
+    found.events.sort_by_key(|event| event.start);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        let minutes = |value| parse_duration(value).map(|length| length.num_minutes());
+        assert_eq!(minutes("PT1H30M"), Some(90));
+        assert_eq!(minutes("P1D"), Some(24 * 60));
+        assert_eq!(minutes("P1W"), Some(7 * 24 * 60));
+        assert_eq!(minutes("P1DT2H"), Some(26 * 60));
+        assert_eq!(minutes("-PT15M"), Some(-15));
+        for bad in ["", "1H", "PT1", "P1H", "PT1D"] {
+            assert_eq!(minutes(bad), None, "{bad:?}");
+        }
+    }
+}
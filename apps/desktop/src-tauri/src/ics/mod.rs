@@ -0,0 +1,421 @@
+//! iCalendar (`.ics`) files, for moving time blocks to and from calendars
+//! without going through Google. Exported blocks keep their type, energy
+//! level and assigned tasks in `X-DAYLI-*` properties, so a round trip
+//! through a file restores them. Imported events become `calendar` blocks.
+
+mod export;
+mod format;
+mod import;
+mod zone;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::error::{Error, Result};
+use crate::store::{Assignment, BlockType, Store, TimeBlock};
+use crate::vault::TokenVault;
+
+pub use import::{Event, Events};
+
+pub(crate) const TYPE_PROPERTY: &str = "X-DAYLI-BLOCK-TYPE";
+pub(crate) const ENERGY_PROPERTY: &str = "X-DAYLI-ENERGY";
+/// One per assigned task, in order, with the task title as the value.
+pub(crate) const TASK_PROPERTY: &str = "X-DAYLI-TASK";
+pub(crate) const TASK_ID_PARAM: &str = "X-DAYLI-TASK-ID";
+/// Exported UIDs are `<block id>@dayli`.
+pub(crate) const UID_DOMAIN: &str = "dayli";
+
+/// How far ahead recurring events are expanded by default on import.
+const IMPORT_DAYS: i64 = 90;
+
+/// The blocks starting on the user's days `from..=to`, as a calendar file.
+pub fn export(store: &Store, from: NaiveDate, to: NaiveDate) -> Result<String> {
+    let (start, _) = store.day_bounds(from)?;
+    let (_, end) = store.day_bounds(to)?;
+    let blocks = store.list_time_blocks(start, end)?;
+    let mut titles = HashMap::new();
+    for assignment in blocks.iter().flat_map(|block| &block.assigned_tasks) {
+        if let Some(task) = store.get_task(&assignment.id)? {
+            titles.insert(task.id, task.title);
+        }
+    }
+    Ok(export::calendar(&blocks, &titles))
+}
+
+/// The occurrences in a calendar file, expanding recurring events over
+/// `from..to`.
+pub fn read(text: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Events> {
+    Ok(import::events(&format::parse(text)?, from, to))
+}
+
+/// The block an event becomes. Blocks we exported come back under their own
+/// id; anything else gets an id derived from its event id, so importing the
+/// same file again updates rather than duplicates.
+fn to_block(store: &Store, event: Event, user_id: &str) -> Result<TimeBlock> {
+    let own_id = event
+        .event_id
+        .strip_suffix(&format!("@{UID_DOMAIN}"))
+        .and_then(|id| uuid::Uuid::parse_str(id).ok());
+    let id = own_id
+        .unwrap_or_else(|| {
+            let name = format!("ics:{}", event.event_id);
+            uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, name.as_bytes())
+        })
+        .to_string();
+    let existing = store.get_time_block(&id)?;
+
+    let mut assigned_tasks = Vec::new();
+    for task_id in event.task_ids {
+        if store.get_task(&task_id)?.is_some() {
+            assigned_tasks.push(Assignment {
+                id: task_id,
+                position: assigned_tasks.len() as i64,
+            });
+        }
+    }
+    let mut metadata = existing
+        .as_ref()
+        .map(|block| block.metadata.clone())
+        .filter(|metadata| metadata.is_object())
+        .unwrap_or_else(|| json!({}));
+    if let Some(location) = event.location {
+        metadata["location"] = json!(location);
+    }
+
+    let now = Utc::now();
+    Ok(TimeBlock {
+        id,
+        user_id: user_id.to_owned(),
+        daily_schedule_id: existing
+            .as_ref()
+            .and_then(|block| block.daily_schedule_id.clone()),
+        start_time: event.start,
+        end_time: event.end,
+        block_type: event.block_type.unwrap_or(BlockType::Meeting),
+        title: event.summary.unwrap_or_else(|| "Untitled event".into()),
+        description: event.description,
+        source: match &existing {
+            Some(block) => block.source.clone(),
+            None => Some("calendar".into()),
+        },
+        calendar_event_id: match (&existing, own_id) {
+            (Some(block), Some(_)) => block.calendar_event_id.clone(),
+            (None, Some(_)) => None,
+            _ => Some(event.event_id),
+        },
+        metadata,
+        conflict_group: existing.as_ref().map_or(0, |block| block.conflict_group),
+        energy_level: event.energy_level.unwrap_or_default(),
+        assigned_tasks,
+        assigned_emails: existing
+            .as_ref()
+            .map(|block| block.assigned_emails.clone())
+            .unwrap_or_default(),
+        created_at: existing.as_ref().map_or(now, |block| block.created_at),
+        updated_at: now,
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportSummary {
+    pub path: PathBuf,
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+/// Save the events of a calendar file as blocks.
+pub fn import<R: Runtime>(
+    app: &AppHandle<R>,
+    text: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<(usize, usize)> {
+    let store = app.state::<Store>();
+    let user_id = match app.state::<TokenVault>().user_id()? {
+        Some(id) => id,
+        None => store
+            .get_preferences()?
+            .map(|preferences| preferences.user_id)
+            .ok_or_else(|| Error::Ics("sign in before importing events".into()))?,
+    };
+    let events = read(text, from, to)?;
+    let imported = events.events.len();
+    for event in events.events {
+        let block = to_block(&store, event, &user_id)?;
+        store.save_time_block(&block)?;
+    }
+    crate::reminders::reschedule(app);
+    Ok((imported, events.skipped))
+}
+
+/// Export the days `from..=to` in the user's zone to a file they pick. `None`
+/// when they cancel.
+#[tauri::command]
+pub async fn ics_export<R: Runtime>(
+    app: AppHandle<R>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Option<PathBuf>> {
+    let text = export(&app.state::<Store>(), from, to)?;
+    let name = if from == to {
+        format!("dayli-{from}.ics")
+    } else {
+        format!("dayli-{from}-to-{to}.ics")
+    };
+    let Some(picked) = app
+        .dialog()
+        .file()
+        .add_filter("iCalendar", &["ics"])
+        .set_file_name(name)
+        .blocking_save_file()
+    else {
+        return Ok(None);
+    };
+    let path = picked
+        .as_path()
+        .map(|path| path.to_path_buf())
+        .ok_or_else(|| Error::Ics("pick a local file".into()))?;
+    std::fs::write(&path, text)?;
+    Ok(Some(path))
+}
+
+/// Import a file the user picks. Recurring events are expanded over the
+/// user's days `from..=to`, by default the next 90 days. `None` when they
+/// cancel.
+#[tauri::command]
+pub async fn ics_import<R: Runtime>(
+    app: AppHandle<R>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<Option<ImportSummary>> {
+    let Some(picked) = app
+        .dialog()
+        .file()
+        .add_filter("iCalendar", &["ics"])
+        .blocking_pick_file()
+    else {
+        return Ok(None);
+    };
+    let path = picked
+        .as_path()
+        .map(|path| path.to_path_buf())
+        .ok_or_else(|| Error::Ics("pick a local file".into()))?;
+    let text = std::fs::read_to_string(&path)?;
+
+    let store = app.state::<Store>();
+    let today = store.today()?;
+    let from = from.unwrap_or(today);
+    let to = to.unwrap_or(today + Duration::days(IMPORT_DAYS - 1));
+    let (start, _) = store.day_bounds(from)?;
+    let (_, end) = store.day_bounds(to)?;
+    let (imported, skipped) = import(&app, &text, start, end)?;
+    Ok(Some(ImportSummary {
+        path,
+        imported,
+        skipped,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::EnergyLevel;
+    use crate::testing::{block, task, TestApp};
+    use chrono::TimeZone;
+
+    /// A weekly standup in a zone only the file defines, with central
+    /// European rules: it starts before the 29 March change to summer time,
+    /// loses one instance to an EXDATE and has another moved.
+    const STANDUP: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+PRODID:-//Example//Calendar//EN\r
+BEGIN:VTIMEZONE\r
+TZID:Dayli Test Time\r
+BEGIN:STANDARD\r
+DTSTART:19701025T030000\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+END:STANDARD\r
+BEGIN:DAYLIGHT\r
+DTSTART:19700329T020000\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0200\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r
+END:DAYLIGHT\r
+END:VTIMEZONE\r
+BEGIN:VEVENT\r
+UID:standup@example.com\r
+DTSTART;TZID=Dayli Test Time:20260323T090000\r
+DTEND;TZID=Dayli Test Time:20260323T093000\r
+RRULE:FREQ=WEEKLY;COUNT=4\r
+EXDATE;TZID=Dayli Test Time:20260406T090000\r
+SUMMARY:Standup\r
+LOCATION:Room 4\\, second floor\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:standup@example.com\r
+RECURRENCE-ID;TZID=Dayli Test Time:20260330T090000\r
+DTSTART;TZID=Dayli Test Time:20260330T100000\r
+DTEND;TZID=Dayli Test Time:20260330T103000\r
+SUMMARY:Standup (moved)\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:offsite@example.com\r
+DTSTART;VALUE=DATE:20260325\r
+DTEND;VALUE=DATE:20260326\r
+SUMMARY:Offsite\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    fn at(m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, m, d, h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn expands_recurring_events_on_import() {
+        let found = read(STANDUP, at(3, 1, 0, 0), at(5, 1, 0, 0)).unwrap();
+        // The all-day offsite can't be a block.
+        assert_eq!(found.skipped, 1);
+        let events: Vec<_> = found
+            .events
+            .iter()
+            .map(|event| (event.event_id.as_str(), event.start, event.end))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                // 09:00 in winter time, then summer time; 6 April is excluded.
+                (
+                    "standup@example.com_20260323T080000Z",
+                    at(3, 23, 8, 0),
+                    at(3, 23, 8, 30)
+                ),
+                (
+                    "standup@example.com_20260330T070000Z",
+                    at(3, 30, 8, 0),
+                    at(3, 30, 8, 30)
+                ),
+                (
+                    "standup@example.com_20260413T070000Z",
+                    at(4, 13, 7, 0),
+                    at(4, 13, 7, 30)
+                ),
+            ]
+        );
+
+        let moved = &found.events[1];
+        assert_eq!(moved.summary.as_deref(), Some("Standup (moved)"));
+        // What the override leaves out comes from the recurring event.
+        assert_eq!(moved.location.as_deref(), Some("Room 4, second floor"));
+        assert_eq!(found.events[0].summary.as_deref(), Some("Standup"));
+    }
+
+    #[test]
+    fn expands_only_over_the_window() {
+        let found = read(STANDUP, at(4, 1, 0, 0), at(4, 10, 0, 0)).unwrap();
+        assert!(found.events.is_empty());
+        let found = read(STANDUP, at(3, 30, 8, 15), at(4, 14, 0, 0)).unwrap();
+        let starts: Vec<_> = found.events.iter().map(|event| event.start).collect();
+        assert_eq!(starts, [at(3, 30, 8, 0), at(4, 13, 7, 0)]);
+    }
+
+    #[test]
+    fn cancelled_instances_are_dropped() {
+        let text = STANDUP.replace(
+            "SUMMARY:Standup (moved)\r\n",
+            "SUMMARY:Standup (moved)\r\nSTATUS:CANCELLED\r\n",
+        );
+        let found = read(&text, at(3, 1, 0, 0), at(5, 1, 0, 0)).unwrap();
+        let starts: Vec<_> = found.events.iter().map(|event| event.start).collect();
+        assert_eq!(starts, [at(3, 23, 8, 0), at(4, 13, 7, 0)]);
+    }
+
+    #[test]
+    fn exported_blocks_round_trip() {
+        let id = "0b6f6f5e-5b7e-4c39-9d0e-4a1d1f3f9c11";
+        let mut exported = block(id, at(3, 2, 9, 0), at(3, 2, 10, 30));
+        exported.title = "Plan Q2; budget, hiring \\ roadmap".into();
+        exported.description = Some(format!("Line one\n{}", "long text ".repeat(20)));
+        exported.metadata = json!({ "location": "Café Nord" });
+        exported.block_type = BlockType::Break;
+        exported.energy_level = EnergyLevel::Low;
+        exported.assigned_tasks = vec![
+            Assignment {
+                id: "t2".into(),
+                position: 1,
+            },
+            Assignment {
+                id: "t1".into(),
+                position: 0,
+            },
+            Assignment {
+                id: "gone".into(),
+                position: 2,
+            },
+        ];
+        let titles = HashMap::from([
+            ("t1".to_owned(), "First, task".to_owned()),
+            ("t2".to_owned(), "Second".to_owned()),
+        ]);
+        let text = export::calendar(&[exported.clone()], &titles);
+        assert!(text.contains("TRANSP:TRANSPARENT\r\n"));
+
+        let found = read(&text, at(3, 1, 0, 0), at(3, 3, 0, 0)).unwrap();
+        assert_eq!(found.skipped, 0);
+        let [event] = &found.events[..] else {
+            panic!("one event, got {:?}", found.events);
+        };
+        assert_eq!(event.event_id, format!("{id}@dayli"));
+        assert_eq!(
+            (event.start, event.end),
+            (exported.start_time, exported.end_time)
+        );
+        assert_eq!(event.summary.as_ref(), Some(&exported.title));
+        assert_eq!(event.description, exported.description);
+        assert_eq!(event.location.as_deref(), Some("Café Nord"));
+        assert_eq!(event.block_type, Some(BlockType::Break));
+        assert_eq!(event.energy_level, Some(EnergyLevel::Low));
+        assert_eq!(event.task_ids, ["t1", "t2"]);
+
+        // Back as a block it keeps its id; assignments to tasks that no
+        // longer exist are dropped.
+        let store = Store::open_in_memory().unwrap();
+        store.save_task(&task("t1", "First, task")).unwrap();
+        let restored = to_block(&store, event.clone(), "u1").unwrap();
+        assert_eq!(restored.id, id);
+        assert_eq!(restored.title, exported.title);
+        assert_eq!(restored.block_type, BlockType::Break);
+        assert_eq!(restored.metadata["location"], "Café Nord");
+        assert_eq!(restored.calendar_event_id, None);
+        assert_eq!(restored.assigned_tasks.len(), 1);
+        assert_eq!(restored.assigned_tasks[0].id, "t1");
+    }
+
+    #[test]
+    fn importing_again_updates_the_same_blocks() {
+        let app = TestApp::new();
+        let (from, to) = (at(3, 1, 0, 0), at(5, 1, 0, 0));
+        assert_eq!(import(app.handle(), STANDUP, from, to).unwrap(), (3, 1));
+        assert_eq!(import(app.handle(), STANDUP, from, to).unwrap(), (3, 1));
+
+        let blocks = app.store().list_time_blocks(from, to).unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert!(blocks.iter().all(|block| block.user_id == "u1"
+            && block.block_type == BlockType::Meeting
+            && block.source.as_deref() == Some("calendar")));
+        assert_eq!(
+            blocks[1].calendar_event_id.as_deref(),
+            Some("standup@example.com_20260330T070000Z")
+        );
+        assert_eq!(blocks[1].metadata["location"], "Room 4, second floor");
+    }
+}
@@ -0,0 +1,299 @@
+//! Placing an event's wall-clock times on the timeline. A TZID is looked up
+//! as an IANA name first, since the tz database knows every past rule, and
+//! otherwise in the file's own VTIMEZONE definitions (Outlook writes names
+//! like "Pacific Standard Time").
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+
+use super::format::Component;
+use crate::rrule::RRule;
+use crate::schedule::time::zoned_instant;
+
+/// Transitions after this are never looked at; rules are expanded from
+/// their DTSTART up to the instant being converted.
+const MAX_TRANSITIONS: usize = 2_000;
+
+#[derive(Debug, Clone)]
+pub enum Zone {
+    Utc,
+    /// No zone at all: the time is read in the system timezone.
+    Floating,
+    Named(Tz),
+    Defined(Rc<VTimeZone>),
+}
+
+impl Zone {
+    pub fn find(tzid: &str, defined: &HashMap<String, Rc<VTimeZone>>) -> Self {
+        // Some writers prefix IANA names, e.g. "/mozilla.org/20050126_1/Europe/Paris".
+        let iana = tzid.rsplit_once("/mozilla.org/").map_or(tzid, |(_, rest)| {
+            rest.split_once('/').map_or(rest, |(_, name)| name)
+        });
+        if let Ok(tz) = iana.parse::<Tz>() {
+            return Self::Named(tz);
+        }
+        match defined.get(tzid) {
+            Some(zone) => Self::Defined(zone.clone()),
+            None => Self::Floating,
+        }
+    }
+
+    pub fn to_utc(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        match self {
+            Self::Utc => local.and_utc(),
+            Self::Floating => zoned_instant(&chrono::Local, local),
+            Self::Named(tz) => zoned_instant(tz, local),
+            Self::Defined(zone) => zone.to_utc(local),
+        }
+    }
+}
+
+/// One STANDARD or DAYLIGHT block: from `start` (in `offset_from` wall-clock
+/// time), and on each recurrence of `rule`, the offset becomes `offset_to`.
+#[derive(Debug, Clone)]
+struct Observance {
+    start: NaiveDateTime,
+    offset_from: FixedOffset,
+    offset_to: FixedOffset,
+    rule: Option<RRule>,
+    dates: Vec<NaiveDateTime>,
+}
+
+impl Observance {
+    /// The last onset at or before `at`.
+    fn last_onset(&self, at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let to_utc = |local: NaiveDateTime| (local - self.offset_from).and_utc();
+        let ruled: Vec<NaiveDateTime> = match &self.rule {
+            Some(rule) => rule
+                .occurrences(self.start, to_utc)
+                .take(MAX_TRANSITIONS)
+                .take_while(|&local| to_utc(local) <= at)
+                .collect(),
+            None => vec![self.start],
+        };
+        ruled
+            .into_iter()
+            .chain(self.dates.iter().copied())
+            .map(to_utc)
+            .filter(|&onset| onset <= at)
+            .max()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct VTimeZone {
+    observances: Vec<Observance>,
+}
+
+fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let (sign, digits) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => return None,
+    };
+    if digits.len() != 4 && digits.len() != 6 {
+        return None;
+    }
+    let hours: i32 = digits.get(0..2)?.parse().ok()?;
+    let minutes: i32 = digits.get(2..4)?.parse().ok()?;
+    let seconds: i32 = digits.get(4..6).map_or(Some(0), |s| s.parse().ok())?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60 + seconds))
+}
+
+fn parse_local(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S").ok()
+}
+
+impl VTimeZone {
+    /// The TZID and definition of a VTIMEZONE component.
+    pub fn parse(component: &Component) -> Option<(String, Self)> {
+        let tzid = component.property("TZID")?.value.clone();
+        let observances: Vec<Observance> = component
+            .children
+            .iter()
+            .filter(|child| child.name == "STANDARD" || child.name == "DAYLIGHT")
+            .filter_map(|child| {
+                Some(Observance {
+                    start: parse_local(&child.property("DTSTART")?.value)?,
+                    offset_from: parse_offset(&child.property("TZOFFSETFROM")?.value)?,
+                    offset_to: parse_offset(&child.property("TZOFFSETTO")?.value)?,
+                    rule: child
+                        .property("RRULE")
+                        .and_then(|line| line.value.parse().ok()),
+                    dates: child
+                        .properties("RDATE")
+                        .flat_map(|line| line.value.split(','))
+                        .filter_map(parse_local)
+                        .collect(),
+                })
+            })
+            .collect();
+        (!observances.is_empty()).then_some((tzid, Self { observances }))
+    }
+
+    fn offset_at(&self, at: DateTime<Utc>) -> FixedOffset {
+        self.observances
+            .iter()
+            .filter_map(|observance| Some((observance.last_onset(at)?, observance.offset_to)))
+            .max_by_key(|&(onset, _)| onset)
+            .map(|(_, offset)| offset)
+            .or_else(|| {
+                self.observances
+                    .iter()
+                    .min_by_key(|observance| observance.start)
+                    .map(|observance| observance.offset_from)
+            })
+            .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap())
+    }
+
+    /// Like [`zoned_instant`]: the earlier reading of a repeated time, and
+    /// the offset from before a skipped one.
+    fn to_utc(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        let mut offsets: Vec<FixedOffset> = self
+            .observances
+            .iter()
+            .flat_map(|observance| [observance.offset_from, observance.offset_to])
+            .collect();
+        // Larger offsets first, so the first valid reading is the earliest.
+        offsets.sort_by_key(|offset| std::cmp::Reverse(offset.local_minus_utc()));
+        offsets.dedup();
+        for offset in offsets {
+            let at = (local - offset).and_utc();
+            if self.offset_at(at) == offset {
+                return at;
+            }
+        }
+        // Transitions are months apart, so a day and a half earlier is
+        // safely before the gap.
+        let before = self.offset_at(local.and_utc() - Duration::hours(36));
+        (local - before).and_utc()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ics::format::parse;
+    use chrono::{Datelike, NaiveDate};
+
+    /// Central European rules under a name the tz database doesn't know,
+    /// the way Outlook writes them.
+    const FIXTURE: &str = "BEGIN:VCALENDAR\r
+BEGIN:VTIMEZONE\r
+TZID:Dayli Test Time\r
+BEGIN:STANDARD\r
+DTSTART:19701025T030000\r
+TZOFFSETFROM:+0200\r
+TZOFFSETTO:+0100\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+END:STANDARD\r
+BEGIN:DAYLIGHT\r
+DTSTART:19700329T020000\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0200\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r
+END:DAYLIGHT\r
+END:VTIMEZONE\r
+END:VCALENDAR\r
+";
+
+    fn fixture() -> (String, VTimeZone) {
+        let calendar = parse(FIXTURE).unwrap();
+        let component = &calendar.children[0];
+        VTimeZone::parse(component).unwrap()
+    }
+
+    fn local(m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    fn utc(m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        local(m, d, h, min).and_utc()
+    }
+
+    #[test]
+    fn offsets() {
+        let offset = |value| parse_offset(value).map(|offset| offset.local_minus_utc());
+        assert_eq!(offset("+0100"), Some(3600));
+        assert_eq!(offset("-0800"), Some(-8 * 3600));
+        assert_eq!(offset("+0530"), Some(5 * 3600 + 30 * 60));
+        assert_eq!(offset("-001530"), Some(-(15 * 60 + 30)));
+        for bad in ["", "0100", "+1", "+01:00", "+01000", "+ab00"] {
+            assert_eq!(offset(bad), None, "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn defined_zone_crosses_dst() {
+        let (tzid, zone) = fixture();
+        assert_eq!(tzid, "Dayli Test Time");
+
+        assert_eq!(zone.to_utc(local(1, 15, 12, 0)), utc(1, 15, 11, 0));
+        assert_eq!(zone.to_utc(local(7, 1, 12, 0)), utc(7, 1, 10, 0));
+
+        // Spring forward on 29 March: 02:00 becomes 03:00.
+        assert_eq!(zone.to_utc(local(3, 29, 1, 30)), utc(3, 29, 0, 30));
+        assert_eq!(zone.to_utc(local(3, 29, 3, 30)), utc(3, 29, 1, 30));
+        // A skipped time is read with the offset from before the gap.
+        assert_eq!(zone.to_utc(local(3, 29, 2, 30)), utc(3, 29, 1, 30));
+
+        // Fall back on 25 October: 03:00 becomes 02:00 again.
+        assert_eq!(zone.to_utc(local(10, 25, 1, 30)), utc(10, 24, 23, 30));
+        // A repeated time is read as its earlier instant.
+        assert_eq!(zone.to_utc(local(10, 25, 2, 30)), utc(10, 25, 0, 30));
+        assert_eq!(zone.to_utc(local(10, 25, 3, 30)), utc(10, 25, 2, 30));
+    }
+
+    #[test]
+    fn defined_zone_agrees_with_the_tz_database() {
+        let (_, zone) = fixture();
+        let berlin = Zone::Named(chrono_tz::Europe::Berlin);
+        let mut at = local(1, 1, 0, 30);
+        while at.date().year() == 2026 {
+            assert_eq!(zone.to_utc(at), berlin.to_utc(at), "{at}");
+            at += Duration::hours(5);
+        }
+        for at in [local(3, 29, 2, 30), local(10, 25, 2, 30)] {
+            assert_eq!(zone.to_utc(at), berlin.to_utc(at), "{at}");
+        }
+    }
+
+    #[test]
+    fn finds_zones_by_tzid() {
+        let (tzid, zone) = fixture();
+        let defined = HashMap::from([(tzid, Rc::new(zone))]);
+
+        assert!(matches!(
+            Zone::find("America/New_York", &defined),
+            Zone::Named(chrono_tz::America::New_York)
+        ));
+        assert!(matches!(
+            Zone::find("/mozilla.org/20050126_1/Europe/Paris", &defined),
+            Zone::Named(chrono_tz::Europe::Paris)
+        ));
+        assert!(matches!(
+            Zone::find("Dayli Test Time", &defined),
+            Zone::Defined(_)
+        ));
+        assert!(matches!(
+            Zone::find("Pacific Standard Time", &defined),
+            Zone::Floating
+        ));
+    }
+
+    #[test]
+    fn observances_need_dtstart_and_offsets() {
+        let calendar = parse(
+            "BEGIN:VCALENDAR\r\nBEGIN:VTIMEZONE\r\nTZID:Broken\r\nBEGIN:STANDARD\r\n\
+             TZOFFSETTO:+0100\r\nEND:STANDARD\r\nEND:VTIMEZONE\r\nEND:VCALENDAR\r\n",
+        )
+        .unwrap();
+        assert!(VTimeZone::parse(&calendar.children[0]).is_none());
+    }
+}
@@ -1,7 +1,227 @@
-#[cfg_attr(mobile, tauri::mobile_entry_point)]
-pub fn run() {
-    tauri::Builder::default()
+#[cfg(desktop)]
+pub mod activity;
+#[cfg(desktop)]
+pub mod api;
+pub mod auth;
+#[cfg(desktop)]
+pub mod autostart;
+#[cfg(desktop)]
+pub mod capture;
+#[cfg(desktop)]
+pub mod cli;
+mod config;
+pub mod deep_link;
+pub mod error;
+pub mod focus;
+#[cfg(desktop)]
+pub mod ics;
+mod logging;
+#[cfg(desktop)]
+pub mod mcp;
+#[cfg(desktop)]
+pub mod presence;
+pub mod queue;
+pub mod recurrence;
+pub mod reminders;
+pub mod rrule;
+pub mod schedule;
+#[cfg(desktop)]
+pub mod shield;
+#[cfg(desktop)]
+pub mod single_instance;
+pub mod store;
+mod supabase;
+pub mod sync;
+#[cfg(test)]
+mod testing;
+#[cfg(desktop)]
+pub mod tray;
+pub mod vault;
+#[cfg(desktop)]
+pub mod window_state;
+
+use tauri::{Manager, Runtime};
+
+pub use config::{AppConfig, GoogleConfig, SupabaseConfig};
+pub use error::{Error, Result};
+
+/// Build the app with all plugins, commands, state and setup hooks registered.
+pub fn builder<R: Runtime>(config: AppConfig) -> tauri::Builder<R> {
+    configure(tauri::Builder::<R>::new(), config)
+}
+
+/// Register everything [`builder`] does on an existing builder, so
+/// integration tests can start from `tauri::test::mock_builder()`.
+pub fn configure<R: Runtime>(builder: tauri::Builder<R>, config: AppConfig) -> tauri::Builder<R> {
+    #[cfg(desktop)]
+    let builder = capture::register_protocol(
+        builder
+            .plugin(single_instance::plugin())
+            .plugin(tauri_plugin_dialog::init())
+            .on_window_event(|window, event| {
+                window_state::on_window_event(window, event);
+                tray::on_window_event(window, event);
+            }),
+    );
+
+    builder
         .plugin(tauri_plugin_shell::init())
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .plugin(tauri_plugin_deep_link::init())
+        .manage(config)
+        .manage(auth::AuthState::default())
+        .manage(queue::Connectivity::default())
+        .invoke_handler(tauri::generate_handler![
+            auth::auth_begin,
+            vault::vault_get,
+            vault::vault_set,
+            vault::vault_delete,
+            store::commands::store_today,
+            store::commands::store_day,
+            store::commands::store_list_tasks,
+            store::commands::store_save_task,
+            store::commands::store_list_emails,
+            store::commands::store_save_email,
+            store::commands::store_save_schedule,
+            store::commands::store_save_time_block,
+            store::commands::store_delete_time_block,
+            store::commands::store_complete_time_block,
+            store::commands::store_get_preferences,
+            store::commands::store_save_preferences,
+            queue::queue_enqueue,
+            queue::queue_list,
+            queue::queue_retry,
+            queue::queue_discard,
+            queue::queue_set_online,
+            sync::sync_run,
+            sync::sync_conflicts,
+            sync::sync_resolve,
+            schedule::conflicts::schedule_conflicts,
+            schedule::gaps::schedule_find_gaps,
+            schedule::planner::schedule_plan_day,
+            schedule::time::schedule_day_times,
+            schedule::time::schedule_zone_status,
+            schedule::time::schedule_set_travel_mode,
+            reminders::reminders_upcoming,
+            reminders::reminders_set_lead_minutes,
+            reminders::reminders_act,
+            recurrence::recurrence_list,
+            recurrence::recurrence_save,
+            recurrence::recurrence_delete,
+            recurrence::recurrence_skip,
+            recurrence::recurrence_override,
+            recurrence::recurrence_materialize,
+            focus::focus_status,
+            focus::focus_start,
+            focus::focus_pause,
+            focus::focus_resume,
+            focus::focus_extend,
+            focus::focus_complete,
+            #[cfg(desktop)]
+            autostart::get_autostart,
+            #[cfg(desktop)]
+            autostart::set_autostart,
+            #[cfg(desktop)]
+            presence::presence_status,
+            #[cfg(desktop)]
+            presence::presence_set_idle_minutes,
+            #[cfg(desktop)]
+            presence::presence_away_intervals,
+            #[cfg(desktop)]
+            shield::shield_status,
+            #[cfg(desktop)]
+            shield::shield_get_settings,
+            #[cfg(desktop)]
+            shield::shield_set_settings,
+            #[cfg(desktop)]
+            activity::activity_get_enabled,
+            #[cfg(desktop)]
+            activity::activity_set_enabled,
+            #[cfg(desktop)]
+            activity::activity_block_summaries,
+            #[cfg(desktop)]
+            activity::activity_export_patterns,
+            #[cfg(desktop)]
+            activity::activity_publish_patterns,
+            #[cfg(desktop)]
+            api::api_status,
+            #[cfg(desktop)]
+            api::api_set_settings,
+            #[cfg(desktop)]
+            api::api_rotate_token,
+            #[cfg(desktop)]
+            ics::ics_export,
+            #[cfg(desktop)]
+            ics::ics_import,
+            #[cfg(all(desktop, unix))]
+            mcp::socket::mcp_status,
+            #[cfg(all(desktop, unix))]
+            mcp::socket::mcp_set_enabled,
+            #[cfg(desktop)]
+            capture::capture_submit,
+            #[cfg(desktop)]
+            capture::capture_dismiss,
+            #[cfg(desktop)]
+            capture::capture_get_shortcut,
+            #[cfg(desktop)]
+            capture::capture_set_shortcut,
+        ])
+        .setup(|app| {
+            vault::init(app)?;
+            store::init(app)?;
+            queue::spawn_drain(app.handle().clone());
+            sync::spawn(app.handle().clone());
+            reminders::init(app)?;
+            recurrence::init(app)?;
+            schedule::time::init(app)?;
+            #[cfg(desktop)]
+            {
+                window_state::init(app)?;
+                tray::init(app)?;
+                capture::init(app)?;
+                autostart::init(app);
+                presence::init(app)?;
+                activity::init(app)?;
+            }
+            focus::init(app)?;
+            #[cfg(desktop)]
+            {
+                shield::init(app)?;
+                api::init(app)?;
+                #[cfg(unix)]
+                mcp::init(app)?;
+            }
+            deep_link::init(app);
+
+            // Enable devtools in debug mode
+            #[cfg(debug_assertions)]
+            if app.state::<AppConfig>().open_devtools {
+                if let Some(window) = app.get_webview_window("main") {
+                    window.open_devtools();
+                }
+            }
+
+            Ok(())
+        })
+}
+
+pub fn run(config: AppConfig) {
+    logging::init();
+    builder::<tauri::Wry>(config)
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|_app, _event| {
+            #[cfg(desktop)]
+            if let tauri::RunEvent::Exit = _event {
+                shield::lower(_app);
+                api::shutdown(_app);
+                #[cfg(unix)]
+                mcp::shutdown(_app);
+            }
+        });
+}
+
+#[cfg(mobile)]
+#[tauri::mobile_entry_point]
+fn mobile_main() {
+    run(AppConfig::default());
 }
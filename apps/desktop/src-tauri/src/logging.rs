@@ -0,0 +1,48 @@
+//! A `log` backend writing to stderr. The level comes from `DAYLI_LOG`
+//! (`error`, `warn`, `info`, `debug`, `trace` or `off`), `info` by default.
+
+use std::io::Write;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+const LEVEL_VAR: &str = "DAYLI_LOG";
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let _ = writeln!(
+            std::io::stderr().lock(),
+            "{} [{}] {}: {}",
+            chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+fn level() -> LevelFilter {
+    std::env::var(LEVEL_VAR)
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(LevelFilter::Info)
+}
+
+/// Install the logger. Later calls, e.g. from tests, are no-ops.
+pub fn init() {
+    if log::set_logger(&StderrLogger).is_ok() {
+        log::set_max_level(level());
+    }
+}
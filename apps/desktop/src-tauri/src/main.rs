@@ -1,22 +1,10 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use tauri::Manager;
-
 fn main() {
-    tauri::Builder::default()
-        .plugin(tauri_plugin_shell::init())
-        .setup(|app| {
-            let window = app.get_webview_window("main").unwrap();
-            
-            // Enable devtools in debug mode
-            #[cfg(debug_assertions)]
-            {
-                window.open_devtools();
-            }
-            
-            Ok(())
-        })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = dayli_lib::cli::run(&args) {
+        std::process::exit(code);
+    }
+    dayli_lib::run(dayli_lib::AppConfig::default());
 }
@@ -0,0 +1,668 @@
+//! A Model Context Protocol server over the local store, so other AI
+//! assistants and editors can read and adjust the schedule. It speaks
+//! newline-delimited JSON-RPC on stdio (`dayli mcp`, which opens the store
+//! directly) and, when enabled, on `mcp.sock` in the app data dir of the
+//! running app.
+//!
+//! Write tools never apply without the user's say-so, and the model never
+//! gets to give it. Over stdio the server asks through MCP elicitation, which
+//! the client shows to the user directly; clients that can't elicit can only
+//! read. Inside the app a dialog asks instead.
+
+mod tools;
+
+use std::io::{BufRead, Lines, Write};
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+use crate::store::Store;
+
+/// The newest protocol version spoken, the first with elicitation.
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+/// Older versions still accepted; clients on them can only read.
+const OLDER_VERSIONS: &[&str] = &["2025-03-26", "2024-11-05"];
+
+/// How long the user has to answer an approval request.
+const APPROVAL_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Answer to requests that arrive while an approval is pending.
+const BUSY: i64 = -32000;
+
+/// Who asks the user to approve a write.
+pub enum Approver<'a> {
+    /// The client, through `elicitation/create`.
+    Client,
+    /// Something outside the protocol, e.g. a dialog in the app. Gets what
+    /// would change and returns whether the user approved it.
+    Ask(Box<dyn Fn(&str) -> bool + 'a>),
+}
+
+/// The client end of a connection, for requests the server makes while
+/// handling a call.
+pub trait Peer {
+    fn request(&mut self, method: &str, params: Value) -> Result<Value>;
+}
+
+/// One client connection.
+pub struct Session<'a> {
+    store: &'a Store,
+    approver: Approver<'a>,
+    /// Whether the client said it can handle elicitation.
+    elicits: bool,
+    approval_window: Duration,
+    /// Called after a write is applied.
+    on_write: Box<dyn Fn() + 'a>,
+}
+
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({ "content": [{ "type": "text", "text": text }], "isError": is_error })
+}
+
+fn list_tools() -> Value {
+    let tools: Vec<Value> = tools::TOOLS
+        .iter()
+        .map(|tool| {
+            json!({
+                "name": tool.name,
+                "description": tool.description,
+                "inputSchema": (tool.input_schema)(),
+                "annotations": { "readOnlyHint": !tool.writes },
+            })
+        })
+        .collect();
+    json!({ "tools": tools })
+}
+
+impl<'a> Session<'a> {
+    pub fn new(store: &'a Store, approver: Approver<'a>, on_write: impl Fn() + 'a) -> Self {
+        Self {
+            store,
+            approver,
+            elicits: false,
+            approval_window: APPROVAL_WINDOW,
+            on_write: Box::new(on_write),
+        }
+    }
+
+    fn initialize(&mut self, params: &Value) -> Value {
+        let requested = params["protocolVersion"].as_str().unwrap_or_default();
+        let version = if OLDER_VERSIONS.contains(&requested) {
+            requested
+        } else {
+            PROTOCOL_VERSION
+        };
+        self.elicits =
+            version == PROTOCOL_VERSION && params["capabilities"]["elicitation"].is_object();
+        json!({
+            "protocolVersion": version,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "dayli", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    /// Ask the user through the client. Declining, cancelling or answering
+    /// anything but an explicit yes is a no.
+    fn elicit(&self, description: &str, peer: &mut dyn Peer) -> Result<bool> {
+        let answer = peer.request(
+            "elicitation/create",
+            json!({
+                "message": format!("An assistant wants to change your Dayli schedule:\n{description}"),
+                "requestedSchema": {
+                    "type": "object",
+                    "properties": {
+                        "approve": { "type": "boolean", "title": "Make this change" }
+                    },
+                    "required": ["approve"]
+                }
+            }),
+        )?;
+        Ok(answer["action"] == "accept" && answer["content"]["approve"] == true)
+    }
+
+    /// Apply a write once the user approves it. The change is worked out
+    /// again afterwards and must still be the one they saw.
+    fn write(
+        &mut self,
+        tool: &'static tools::Tool,
+        arguments: Value,
+        peer: &mut dyn Peer,
+    ) -> Result<String> {
+        let zone = self.store.zone()?;
+        let description = tools::plan(self.store, tool.name, &arguments)?.describe(zone);
+        let asked = Instant::now();
+        let approved = match &self.approver {
+            Approver::Ask(ask) => ask(&description),
+            Approver::Client if self.elicits => self.elicit(&description, peer)?,
+            Approver::Client => {
+                return Err(Error::Mcp(
+                    "this client can't ask the user to approve changes (MCP elicitation), so \
+                     Dayli won't make them; the user can make this change in the app"
+                        .into(),
+                ))
+            }
+        };
+        if !approved {
+            return Err(Error::Mcp(
+                "the user didn't approve the change; nothing changed".into(),
+            ));
+        }
+        if asked.elapsed() > self.approval_window {
+            return Err(Error::Mcp(
+                "the approval came too late; nothing changed".into(),
+            ));
+        }
+
+        let change = tools::plan(self.store, tool.name, &arguments)?;
+        if change.describe(zone) != description {
+            return Err(Error::Mcp(
+                "the schedule changed while waiting for approval; nothing changed".into(),
+            ));
+        }
+        let applied = change.apply(self.store)?;
+        (self.on_write)();
+        Ok(serde_json::to_string_pretty(&applied)?)
+    }
+
+    fn call_tool(
+        &mut self,
+        params: &Value,
+        peer: &mut dyn Peer,
+    ) -> std::result::Result<Value, (i64, String)> {
+        let name = params["name"].as_str().unwrap_or_default();
+        let tool = tools::find(name).ok_or((INVALID_PARAMS, format!("unknown tool {name:?}")))?;
+        let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+        let result = if tool.writes {
+            self.write(tool, arguments, peer)
+        } else {
+            tools::read(self.store, tool.name, &arguments)
+                .and_then(|value| Ok(serde_json::to_string_pretty(&value)?))
+        };
+        Ok(match result {
+            Ok(text) => tool_result(text, false),
+            Err(err) => tool_result(err.to_string(), true),
+        })
+    }
+
+    /// Answer one JSON-RPC message; notifications and stray responses get no
+    /// answer. Requests the server needs to make meanwhile go through `peer`.
+    pub fn handle(&mut self, message: &Value, peer: &mut dyn Peer) -> Option<Value> {
+        let id = message.get("id")?.clone();
+        let method = message.get("method")?.as_str().unwrap_or_default();
+        let result = match method {
+            "initialize" => Ok(self.initialize(&message["params"])),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(list_tools()),
+            "tools/call" => self.call_tool(&message["params"], peer),
+            other => Err((METHOD_NOT_FOUND, format!("unknown method {other:?}"))),
+        };
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, message),
+        })
+    }
+
+    /// Serve newline-delimited JSON-RPC until the reader closes.
+    pub fn serve(&mut self, reader: impl BufRead, writer: impl Write) -> Result<()> {
+        let mut connection = Connection {
+            lines: reader.lines(),
+            writer,
+            next_id: 0,
+        };
+        while let Some(line) = connection.lines.next() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => self.handle(&message, &mut connection),
+                Err(err) => Some(error_response(Value::Null, PARSE_ERROR, err.to_string())),
+            };
+            if let Some(response) = response {
+                connection.send(&response)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// A newline-delimited JSON-RPC stream.
+struct Connection<R, W> {
+    lines: Lines<R>,
+    writer: W,
+    next_id: u64,
+}
+
+impl<R: BufRead, W: Write> Connection<R, W> {
+    fn send(&mut self, message: &Value) -> Result<()> {
+        writeln!(self.writer, "{message}")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<R: BufRead, W: Write> Peer for Connection<R, W> {
+    /// Send a request and wait for its answer. Requests from the client in
+    /// the meantime are turned away and notifications dropped.
+    fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        self.next_id += 1;
+        let id = json!(format!("dayli-{}", self.next_id));
+        self.send(&json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))?;
+        loop {
+            let line = self.lines.next().ok_or_else(|| {
+                Error::Mcp(format!("the client went away before answering {method}"))
+            })??;
+            let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            if message.get("method").is_some() {
+                if let Some(other) = message.get("id") {
+                    self.send(&error_response(
+                        other.clone(),
+                        BUSY,
+                        "waiting for the user to approve a change".into(),
+                    ))?;
+                }
+                continue;
+            }
+            if message["id"] != id {
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                return Err(Error::Mcp(format!(
+                    "{method} failed: {}",
+                    error["message"].as_str().unwrap_or("unknown error")
+                )));
+            }
+            return Ok(message["result"].clone());
+        }
+    }
+}
+
+/// Serve on stdin and stdout, for `dayli mcp`.
+pub fn serve_stdio(store: &Store) -> Result<()> {
+    let stdin = std::io::stdin();
+    Session::new(store, Approver::Client, || {}).serve(stdin.lock(), std::io::stdout().lock())
+}
+
+#[cfg(unix)]
+pub mod socket;
+#[cfg(unix)]
+pub use socket::{init, shutdown};
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::collections::VecDeque;
+
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+    use crate::store::UserPreferences;
+
+    /// A client that answers the server's requests from a script.
+    #[derive(Default)]
+    struct Script {
+        answers: VecDeque<Value>,
+        asked: Vec<(String, Value)>,
+    }
+
+    impl Script {
+        fn answering(answers: impl IntoIterator<Item = Value>) -> Self {
+            Self {
+                answers: answers.into_iter().collect(),
+                asked: Vec::new(),
+            }
+        }
+    }
+
+    impl Peer for Script {
+        fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+            self.asked.push((method.to_owned(), params));
+            self.answers
+                .pop_front()
+                .ok_or_else(|| Error::Mcp("no scripted answer".into()))
+        }
+    }
+
+    fn store() -> Store {
+        let store = Store::open_in_memory().unwrap();
+        let mut preferences = UserPreferences::defaults("u1");
+        preferences.timezone = "UTC".into();
+        store.save_preferences(&preferences).unwrap();
+        store
+    }
+
+    fn initialize(session: &mut Session<'_>, version: &str, elicitation: bool) -> Value {
+        let capabilities = if elicitation {
+            json!({ "elicitation": {} })
+        } else {
+            json!({})
+        };
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": { "protocolVersion": version, "capabilities": capabilities },
+        });
+        session.handle(&message, &mut Script::default()).unwrap()["result"].clone()
+    }
+
+    fn call(session: &mut Session<'_>, peer: &mut Script, tool: &str, arguments: Value) -> Value {
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": { "name": tool, "arguments": arguments },
+        });
+        session.handle(&message, peer).unwrap()["result"].clone()
+    }
+
+    fn create_task(session: &mut Session<'_>, peer: &mut Script) -> Value {
+        call(
+            session,
+            peer,
+            "task_createTask",
+            json!({ "title": "Write the report", "estimatedMinutes": 45 }),
+        )
+    }
+
+    fn accept(approve: bool) -> Value {
+        json!({ "action": "accept", "content": { "approve": approve } })
+    }
+
+    #[test]
+    fn tools_list_their_schemas() {
+        let store = store();
+        let mut session = Session::new(&store, Approver::Client, || {});
+        let message = json!({ "jsonrpc": "2.0", "id": 7, "method": "tools/list" });
+        let response = session.handle(&message, &mut Script::default()).unwrap();
+        assert_eq!(response["id"], 7);
+        let tools = response["result"]["tools"].as_array().unwrap();
+        assert_eq!(tools.len(), tools::TOOLS.len());
+        for (listed, tool) in tools.iter().zip(tools::TOOLS) {
+            assert_eq!(listed["name"], tool.name);
+            assert_eq!(listed["inputSchema"]["type"], "object");
+            assert!(listed["inputSchema"]["properties"].is_object());
+            assert_eq!(listed["annotations"]["readOnlyHint"], !tool.writes);
+            // Nothing the model could fill in to approve a write itself.
+            assert_eq!(listed["inputSchema"], (tool.input_schema)());
+        }
+        let create = tools
+            .iter()
+            .find(|tool| tool["name"] == "task_createTask")
+            .unwrap();
+        assert_eq!(create["inputSchema"]["required"], json!(["title"]));
+    }
+
+    #[test]
+    fn initialize_negotiates_the_version_and_elicitation() {
+        let store = store();
+        let mut session = Session::new(&store, Approver::Client, || {});
+        assert_eq!(
+            initialize(&mut session, "2025-06-18", true)["protocolVersion"],
+            PROTOCOL_VERSION
+        );
+        assert!(session.elicits);
+        assert_eq!(
+            initialize(&mut session, "2024-11-05", true)["protocolVersion"],
+            "2024-11-05"
+        );
+        assert!(!session.elicits, "elicitation is newer than 2024-11-05");
+        assert_eq!(
+            initialize(&mut session, "1999-01-01", false)["protocolVersion"],
+            PROTOCOL_VERSION
+        );
+        assert!(!session.elicits);
+    }
+
+    #[test]
+    fn an_approved_write_is_applied() {
+        let store = store();
+        let writes = Cell::new(0);
+        let mut session = Session::new(&store, Approver::Client, || writes.set(writes.get() + 1));
+        initialize(&mut session, PROTOCOL_VERSION, true);
+
+        let mut client = Script::answering([accept(true)]);
+        let result = create_task(&mut session, &mut client);
+        assert_eq!(result["isError"], false, "{result}");
+        let (method, params) = &client.asked[0];
+        assert_eq!(method, "elicitation/create");
+        assert!(params["message"]
+            .as_str()
+            .unwrap()
+            .contains("Create task \"Write the report\" (45 min)"));
+        assert_eq!(
+            params["requestedSchema"]["properties"]["approve"]["type"],
+            "boolean"
+        );
+
+        let tasks = store.list_tasks(None).unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].title, "Write the report");
+        assert_eq!(tasks[0].source.as_deref(), Some("ai"));
+        assert_eq!(writes.get(), 1);
+    }
+
+    #[test]
+    fn anything_but_an_explicit_yes_changes_nothing() {
+        let store = store();
+        let mut session = Session::new(&store, Approver::Client, || panic!("nothing is written"));
+        initialize(&mut session, PROTOCOL_VERSION, true);
+
+        for answer in [
+            accept(false),
+            json!({ "action": "accept", "content": {} }),
+            json!({ "action": "accept", "content": { "approve": "yes" } }),
+            json!({ "action": "decline" }),
+            json!({ "action": "cancel" }),
+        ] {
+            let mut client = Script::answering([answer.clone()]);
+            let result = create_task(&mut session, &mut client);
+            assert_eq!(result["isError"], true, "{answer}");
+        }
+        // A client error is no approval either.
+        let result = create_task(&mut session, &mut Script::default());
+        assert_eq!(result["isError"], true);
+        assert!(store.list_tasks(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn clients_that_cant_elicit_cant_write() {
+        let store = store();
+        let mut session = Session::new(&store, Approver::Client, || {});
+        initialize(&mut session, "2024-11-05", false);
+
+        // Arguments the model makes up don't get it through either.
+        let mut client = Script::answering([accept(true)]);
+        let result = call(
+            &mut session,
+            &mut client,
+            "task_createTask",
+            json!({ "title": "Sneaky", "confirmationToken": "anything" }),
+        );
+        assert_eq!(result["isError"], true);
+        assert!(client.asked.is_empty());
+        assert!(store.list_tasks(None).unwrap().is_empty());
+
+        // Reads still work.
+        let result = call(&mut session, &mut client, "task_viewTasks", json!({}));
+        assert_eq!(result["isError"], false);
+    }
+
+    #[test]
+    fn the_app_can_ask_instead() {
+        let store = store();
+        let asked = std::cell::RefCell::new(Vec::new());
+        let answer = Cell::new(false);
+        let ask = |description: &str| {
+            asked.borrow_mut().push(description.to_owned());
+            answer.get()
+        };
+        let mut session = Session::new(&store, Approver::Ask(Box::new(ask)), || {});
+        // No elicitation needed, and the client is never asked.
+        initialize(&mut session, "2024-11-05", false);
+
+        let mut client = Script::default();
+        assert_eq!(create_task(&mut session, &mut client)["isError"], true);
+        answer.set(true);
+        assert_eq!(create_task(&mut session, &mut client)["isError"], false);
+        assert!(client.asked.is_empty());
+        assert_eq!(asked.borrow().len(), 2);
+        assert_eq!(store.list_tasks(None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn a_late_approval_changes_nothing() {
+        let store = store();
+        let mut session = Session::new(&store, Approver::Client, || {});
+        session.approval_window = Duration::ZERO;
+        initialize(&mut session, PROTOCOL_VERSION, true);
+
+        let result = create_task(&mut session, &mut Script::answering([accept(true)]));
+        assert_eq!(result["isError"], true);
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("too late"));
+        assert!(store.list_tasks(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_change_that_went_stale_while_waiting_is_refused() {
+        let store = store();
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap();
+        let mut block = crate::testing::block("b1", start, start + chrono::Duration::hours(1));
+        block.title = "Deep work".into();
+        store.save_time_block(&block).unwrap();
+        // Someone renames the block while the dialog is up.
+        let ask = |_: &str| {
+            let mut renamed = block.clone();
+            renamed.title = "Planning".into();
+            store.save_time_block(&renamed).unwrap();
+            true
+        };
+        let mut session = Session::new(&store, Approver::Ask(Box::new(ask)), || {});
+        let arguments = json!({
+            "blockDescription": "b1",
+            "newStartTime": "14:00",
+            "newEndTime": "15:00",
+            "date": "2026-03-02",
+        });
+        let result = call(
+            &mut session,
+            &mut Script::default(),
+            "schedule_moveTimeBlock",
+            arguments,
+        );
+        assert_eq!(result["isError"], true);
+        let kept = store.get_time_block("b1").unwrap().unwrap();
+        assert_eq!(kept.start_time, start);
+    }
+
+    #[test]
+    fn moves_are_described_and_made_in_the_users_zone() {
+        let store = Store::open_in_memory().unwrap();
+        let mut preferences = UserPreferences::defaults("u1");
+        preferences.timezone = "Asia/Tokyo".into();
+        store.save_preferences(&preferences).unwrap();
+        // 09:00 on 2 March in Tokyo, still 1 March in UTC.
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 0, 0, 0).unwrap();
+        let mut block = crate::testing::block("b1", start, start + chrono::Duration::hours(1));
+        block.title = "Deep work".into();
+        store.save_time_block(&block).unwrap();
+
+        let asked = std::cell::RefCell::new(String::new());
+        let ask = |description: &str| {
+            *asked.borrow_mut() = description.to_owned();
+            true
+        };
+        let mut session = Session::new(&store, Approver::Ask(Box::new(ask)), || {});
+        let arguments = json!({
+            "blockDescription": "Deep work",
+            "newStartTime": "14:00",
+            "newEndTime": "15:00",
+            "date": "2026-03-02",
+        });
+        let result = call(
+            &mut session,
+            &mut Script::default(),
+            "schedule_moveTimeBlock",
+            arguments,
+        );
+        assert_eq!(result["isError"], false, "{result}");
+        drop(session);
+        assert_eq!(
+            asked.into_inner(),
+            "Move \"Deep work\" to 14:00 2026-03-02 – 15:00 2026-03-02"
+        );
+        let moved = store.get_time_block("b1").unwrap().unwrap();
+        assert_eq!(
+            moved.start_time,
+            Utc.with_ymd_and_hms(2026, 3, 2, 5, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn notifications_and_stray_responses_get_no_answer() {
+        let store = store();
+        let mut session = Session::new(&store, Approver::Client, || {});
+        let mut client = Script::default();
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert_eq!(session.handle(&notification, &mut client), None);
+        let response = json!({ "jsonrpc": "2.0", "id": "dayli-9", "result": {} });
+        assert_eq!(session.handle(&response, &mut client), None);
+        let unknown = json!({ "jsonrpc": "2.0", "id": 4, "method": "resources/list" });
+        assert_eq!(
+            session.handle(&unknown, &mut client).unwrap()["error"]["code"],
+            METHOD_NOT_FOUND
+        );
+    }
+
+    #[test]
+    fn serve_asks_and_waits_for_the_answer() {
+        let store = store();
+        let mut session = Session::new(&store, Approver::Client, || {});
+        let input = [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {
+                "protocolVersion": PROTOCOL_VERSION, "capabilities": { "elicitation": {} } } }),
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {
+                "name": "task_createTask", "arguments": { "title": "Write" } } }),
+            // While the user decides.
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "ping" }),
+            json!({ "jsonrpc": "2.0", "id": "dayli-1", "result": accept(true) }),
+        ]
+        .iter()
+        .map(Value::to_string)
+        .collect::<Vec<_>>()
+        .join("\n");
+        let mut output = Vec::new();
+        session.serve(input.as_bytes(), &mut output).unwrap();
+
+        let output: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(output.len(), 4);
+        assert_eq!(output[0]["id"], 1);
+        assert_eq!(output[1]["method"], "elicitation/create");
+        assert_eq!(output[1]["id"], "dayli-1");
+        assert_eq!(
+            (output[2]["id"].clone(), output[2]["error"]["code"].clone()),
+            (json!(3), json!(BUSY))
+        );
+        assert_eq!(output[3]["id"], 2);
+        assert_eq!(output[3]["result"]["isError"], false);
+        assert_eq!(store.list_tasks(None).unwrap().len(), 1);
+    }
+}
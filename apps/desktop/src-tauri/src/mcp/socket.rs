@@ -0,0 +1,143 @@
+//! The MCP server inside the running app, on a Unix socket only the user can
+//! open. Each write waits for the user to approve it in a dialog, and
+//! refreshes reminders and the tray straight away once applied.
+
+use std::fs;
+use std::io::BufReader;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, Runtime, State};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use super::{Approver, Session};
+use crate::error::Result;
+use crate::store::Store;
+
+const ENABLED_SETTING: &str = "mcp.enabled";
+const SOCKET_FILE: &str = "mcp.sock";
+
+struct Listening {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+pub struct McpSocket {
+    listening: Mutex<Option<Listening>>,
+}
+
+fn serve_client<R: Runtime>(app: &AppHandle<R>, stream: UnixStream) -> Result<()> {
+    let store = app.state::<Store>();
+    let reader = BufReader::new(stream.try_clone()?);
+    let approve = |description: &str| {
+        app.dialog()
+            .message(description)
+            .title("Allow this change from an AI assistant?")
+            .kind(MessageDialogKind::Warning)
+            .buttons(MessageDialogButtons::OkCancelCustom(
+                "Make change".into(),
+                "Don't allow".into(),
+            ))
+            .blocking_show()
+    };
+    let mut session = Session::new(&store, Approver::Ask(Box::new(approve)), || {
+        crate::reminders::reschedule(app);
+        crate::tray::refresh(app);
+    });
+    session.serve(reader, stream)
+}
+
+fn start<R: Runtime>(app: &AppHandle<R>) -> Result<()> {
+    let path = app.path().app_local_data_dir()?.join(SOCKET_FILE);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let _ = fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let stop = stop.clone();
+        let app = app.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stop.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else { continue };
+                let app = app.clone();
+                std::thread::spawn(move || {
+                    if let Err(err) = serve_client(&app, stream) {
+                        log::error!("mcp client failed: {err}");
+                    }
+                });
+            }
+        });
+    }
+    *app.state::<McpSocket>().listening.lock().unwrap() = Some(Listening { path, stop });
+    Ok(())
+}
+
+/// Close the socket; called on exit and when the server is disabled.
+pub fn shutdown<R: Runtime>(app: &AppHandle<R>) {
+    let Some(socket) = app.try_state::<McpSocket>() else {
+        return;
+    };
+    let Some(listening) = socket.listening.lock().unwrap().take() else {
+        return;
+    };
+    listening.stop.store(true, Ordering::SeqCst);
+    // Wake the accept loop so it sees the flag.
+    let _ = UnixStream::connect(&listening.path);
+    let _ = fs::remove_file(&listening.path);
+}
+
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    app.manage(McpSocket::default());
+    if app
+        .state::<Store>()
+        .setting(ENABLED_SETTING)?
+        .unwrap_or(false)
+    {
+        if let Err(err) = start(app.handle()) {
+            log::error!("failed to start mcp socket: {err}");
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct McpStatus {
+    pub enabled: bool,
+    pub socket: Option<PathBuf>,
+}
+
+#[tauri::command]
+pub fn mcp_status(socket: State<'_, McpSocket>) -> McpStatus {
+    let listening = socket.listening.lock().unwrap();
+    McpStatus {
+        enabled: listening.is_some(),
+        socket: listening.as_ref().map(|listening| listening.path.clone()),
+    }
+}
+
+#[tauri::command]
+pub fn mcp_set_enabled<R: Runtime>(
+    app: AppHandle<R>,
+    enabled: bool,
+    store: State<'_, Store>,
+) -> Result<McpStatus> {
+    store.set_setting(ENABLED_SETTING, &enabled)?;
+    shutdown(&app);
+    if enabled {
+        start(&app)?;
+    }
+    Ok(mcp_status(app.state::<McpSocket>()))
+}
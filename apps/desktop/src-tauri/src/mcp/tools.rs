@@ -0,0 +1,505 @@
+//! The tools the MCP server offers, mirroring the web chat's tool registry
+//! (`apps/web/modules/ai/tools`) over the local store. Names and arguments
+//! match the web tools so prompts written for one work with the other.
+
+use chrono::{NaiveDate, NaiveTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::{Error, Result};
+use crate::schedule::gaps::{find_gaps, Between, DEFAULT_MIN_MINUTES};
+use crate::schedule::time::Zone;
+use crate::store::{Store, Task, TimeBlock, UserPreferences};
+
+pub struct Tool {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Writes wait for the caller to confirm them.
+    pub writes: bool,
+    pub input_schema: fn() -> Value,
+}
+
+pub const TOOLS: &[Tool] = &[
+    Tool {
+        name: "schedule_viewSchedule",
+        description: "View the schedule for a date with all time blocks and their assigned tasks",
+        writes: false,
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "date": { "type": "string", "description": "YYYY-MM-DD, defaults to today" }
+                }
+            })
+        },
+    },
+    Tool {
+        name: "schedule_findGaps",
+        description: "Find free time on a date within the user's work hours",
+        writes: false,
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "date": { "type": "string", "description": "YYYY-MM-DD, defaults to today" },
+                    "minDuration": { "type": "number", "minimum": 15, "description": "Minimum gap in minutes" },
+                    "timeRange": {
+                        "type": "object",
+                        "description": "Only look within this part of the day",
+                        "properties": {
+                            "start": { "type": "string", "description": "HH:mm" },
+                            "end": { "type": "string", "description": "HH:mm" }
+                        },
+                        "required": ["start", "end"]
+                    }
+                }
+            })
+        },
+    },
+    Tool {
+        name: "task_viewTasks",
+        description: "List tasks, optionally by status (backlog, scheduled, completed, cancelled)",
+        writes: false,
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "status": { "type": "string", "enum": ["backlog", "scheduled", "completed", "cancelled"] }
+                }
+            })
+        },
+    },
+    Tool {
+        name: "task_createTask",
+        description: "Create a backlog task",
+        writes: true,
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "title": { "type": "string", "description": "Task title" },
+                    "estimatedMinutes": { "type": "number", "default": 30 },
+                    "description": { "type": "string" },
+                    "priority": { "type": "string", "enum": ["high", "medium", "low"], "default": "medium" }
+                },
+                "required": ["title"]
+            })
+        },
+    },
+    Tool {
+        name: "schedule_moveTimeBlock",
+        description: "Move a time block to new start and end times on its day",
+        writes: true,
+        input_schema: || {
+            json!({
+                "type": "object",
+                "properties": {
+                    "blockDescription": { "type": "string", "description": "The block's id, or part of its title" },
+                    "newStartTime": { "type": "string", "description": "e.g. \"9am\", \"3:30 pm\", \"14:00\"" },
+                    "newEndTime": { "type": "string", "description": "e.g. \"10am\", \"4:30 pm\", \"15:00\"" },
+                    "date": { "type": "string", "description": "YYYY-MM-DD, defaults to today" }
+                },
+                "required": ["blockDescription", "newStartTime", "newEndTime"]
+            })
+        },
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Tool> {
+    TOOLS.iter().find(|tool| tool.name == name)
+}
+
+fn invalid(message: impl Into<String>) -> Error {
+    Error::Mcp(message.into())
+}
+
+fn args<T: serde::de::DeserializeOwned>(arguments: &Value) -> Result<T> {
+    let arguments = if arguments.is_null() {
+        json!({})
+    } else {
+        arguments.clone()
+    };
+    serde_json::from_value(arguments).map_err(|err| invalid(format!("invalid arguments: {err}")))
+}
+
+/// `date`, or today in the user's zone.
+fn date_or_today(store: &Store, date: Option<&str>) -> Result<NaiveDate> {
+    match date {
+        Some(date) => date
+            .parse()
+            .map_err(|_| invalid(format!("{date:?} isn't a YYYY-MM-DD date"))),
+        None => store.today(),
+    }
+}
+
+/// A clock time like `9am`, `3:30 pm`, `14:00` or `9`.
+fn parse_clock(text: &str) -> Result<NaiveTime> {
+    let lower = text.trim().to_lowercase().replace([' ', '.'], "");
+    let (digits, offset) = match (lower.strip_suffix("am"), lower.strip_suffix("pm")) {
+        (Some(digits), _) => (digits, Some(0)),
+        (_, Some(digits)) => (digits, Some(12)),
+        _ => (lower.as_str(), None),
+    };
+    let (hour, minute) = digits.split_once(':').unwrap_or((digits, "0"));
+    let parsed = hour.parse::<u32>().ok().zip(minute.parse::<u32>().ok());
+    let time = parsed.and_then(|(hour, minute)| {
+        let hour = match offset {
+            Some(_) if !(1..=12).contains(&hour) => return None,
+            Some(offset) => hour % 12 + offset,
+            None => hour,
+        };
+        NaiveTime::from_hms_opt(hour, minute, 0)
+    });
+    time.ok_or_else(|| invalid(format!("can't read the time {text:?}")))
+}
+
+fn preferences(store: &Store) -> Result<UserPreferences> {
+    Ok(store
+        .get_preferences()?
+        .unwrap_or_else(|| UserPreferences::defaults("")))
+}
+
+/// Tools that only read.
+pub fn read(store: &Store, name: &str, arguments: &Value) -> Result<Value> {
+    match name {
+        "schedule_viewSchedule" => {
+            #[derive(Deserialize)]
+            struct Args {
+                date: Option<String>,
+            }
+            let Args { date } = args(arguments)?;
+            let day = store.day_schedule(date_or_today(store, date.as_deref())?)?;
+            let blocks = day
+                .time_blocks
+                .iter()
+                .map(|block| {
+                    let tasks: Vec<Task> = block
+                        .assigned_tasks
+                        .iter()
+                        .filter_map(|assignment| store.get_task(&assignment.id).ok().flatten())
+                        .collect();
+                    let mut value = json!(block);
+                    value["tasks"] = json!(tasks);
+                    value
+                })
+                .collect::<Vec<_>>();
+            Ok(json!({ "date": day.date, "schedule": day.schedule, "blocks": blocks }))
+        }
+        "schedule_findGaps" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Range {
+                start: String,
+                end: String,
+            }
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Args {
+                date: Option<String>,
+                min_duration: Option<i64>,
+                time_range: Option<Range>,
+            }
+            let Args {
+                date,
+                min_duration,
+                time_range,
+            } = args(arguments)?;
+            let date = date_or_today(store, date.as_deref())?;
+            let between = time_range
+                .map(|range| -> Result<Between> {
+                    Ok(Between {
+                        start: parse_clock(&range.start)?,
+                        end: parse_clock(&range.end)?,
+                    })
+                })
+                .transpose()?;
+            let preferences = preferences(store)?;
+            let (start, end) = preferences.zone().day_bounds(date);
+            let blocks = store.list_time_blocks(start, end)?;
+            let min_minutes = min_duration.unwrap_or(DEFAULT_MIN_MINUTES).max(1);
+            Ok(json!(find_gaps(
+                &preferences,
+                &blocks,
+                date,
+                min_minutes,
+                between
+            )))
+        }
+        "task_viewTasks" => {
+            #[derive(Deserialize)]
+            struct Args {
+                status: Option<String>,
+            }
+            let Args { status } = args(arguments)?;
+            Ok(json!(store.list_tasks(status.as_deref())?))
+        }
+        other => Err(invalid(format!("unknown tool {other}"))),
+    }
+}
+
+/// A write worked out but not applied, for the caller to confirm.
+pub enum Change {
+    CreateTask(Task),
+    MoveBlock(TimeBlock),
+}
+
+impl Change {
+    /// The change as the user is asked to approve it, with times in `zone`.
+    pub fn describe(&self, zone: Zone) -> String {
+        match self {
+            Change::CreateTask(task) => {
+                let mut text = format!("Create task \"{}\"", task.title);
+                if let Some(minutes) = task.estimated_minutes {
+                    text.push_str(&format!(" ({minutes} min)"));
+                }
+                text
+            }
+            Change::MoveBlock(block) => {
+                let local =
+                    |at: chrono::DateTime<Utc>| zone.local(at).format("%H:%M %Y-%m-%d").to_string();
+                format!(
+                    "Move \"{}\" to {} – {}",
+                    block.title,
+                    local(block.start_time),
+                    local(block.end_time)
+                )
+            }
+        }
+    }
+
+    pub fn apply(self, store: &Store) -> Result<Value> {
+        match self {
+            Change::CreateTask(task) => {
+                store.save_task(&task)?;
+                Ok(json!(task))
+            }
+            Change::MoveBlock(block) => {
+                store.save_time_block(&block)?;
+                Ok(json!(block))
+            }
+        }
+    }
+}
+
+fn find_block(store: &Store, description: &str, date: NaiveDate) -> Result<TimeBlock> {
+    if let Some(block) = store.get_time_block(description)? {
+        return Ok(block);
+    }
+    let needle = description.to_lowercase();
+    let mut matches: Vec<TimeBlock> = store
+        .blocks_for_date(date)?
+        .into_iter()
+        .filter(|block| block.title.to_lowercase().contains(&needle))
+        .collect();
+    match matches.len() {
+        1 => Ok(matches.remove(0)),
+        0 => Err(Error::NotFound(format!(
+            "no block on {date} matches {description:?}"
+        ))),
+        _ => {
+            let titles: Vec<&str> = matches.iter().map(|block| block.title.as_str()).collect();
+            Err(invalid(format!(
+                "{description:?} matches several blocks: {}",
+                titles.join(", ")
+            )))
+        }
+    }
+}
+
+/// Work out what a write tool would change, without changing it.
+pub fn plan(store: &Store, name: &str, arguments: &Value) -> Result<Change> {
+    match name {
+        "task_createTask" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Args {
+                title: String,
+                estimated_minutes: Option<i64>,
+                description: Option<String>,
+                priority: Option<String>,
+            }
+            let Args {
+                title,
+                estimated_minutes,
+                description,
+                priority,
+            } = args(arguments)?;
+            if title.trim().is_empty() {
+                return Err(invalid("a task needs a title"));
+            }
+            if let Some(priority) = &priority {
+                if !["high", "medium", "low"].contains(&priority.as_str()) {
+                    return Err(invalid(format!("unknown priority {priority:?}")));
+                }
+            }
+            let user_id = preferences(store)?.user_id;
+            if user_id.is_empty() {
+                return Err(invalid("sign in to Dayli before adding tasks"));
+            }
+            let captured = crate::capture::Captured {
+                title: title.trim().to_owned(),
+                estimated_minutes: Some(estimated_minutes.unwrap_or(30)),
+                priority: Some(priority.unwrap_or_else(|| "medium".into())),
+                due: None,
+                tags: Vec::new(),
+            };
+            let mut task = crate::capture::to_task(captured, user_id);
+            task.description = description;
+            task.source = Some("ai".into());
+            Ok(Change::CreateTask(task))
+        }
+        "schedule_moveTimeBlock" => {
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct Args {
+                block_description: String,
+                new_start_time: String,
+                new_end_time: String,
+                date: Option<String>,
+            }
+            let Args {
+                block_description,
+                new_start_time,
+                new_end_time,
+                date,
+            } = args(arguments)?;
+            let date = date_or_today(store, date.as_deref())?;
+            let mut block = find_block(store, &block_description, date)?;
+            let zone = store.zone()?;
+            let start = zone.instant(date, parse_clock(&new_start_time)?);
+            let end = zone.instant(date, parse_clock(&new_end_time)?);
+            if end <= start {
+                return Err(invalid("the new end time must be after the start"));
+            }
+            block.start_time = start;
+            block.end_time = end;
+            block.updated_at = Utc::now();
+            Ok(Change::MoveBlock(block))
+        }
+        other => Err(invalid(format!("unknown tool {other}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+    use crate::testing::block;
+
+    fn store() -> Store {
+        let store = Store::open_in_memory().unwrap();
+        let mut preferences = UserPreferences::defaults("u1");
+        preferences.timezone = "Europe/Berlin".into();
+        store.save_preferences(&preferences).unwrap();
+        store
+    }
+
+    #[test]
+    fn planned_tasks_save_to_the_store() {
+        let store = store();
+        let arguments = json!({
+            "title": "  Write the report ",
+            "description": "For the board",
+            "priority": "high",
+        });
+        let change = plan(&store, "task_createTask", &arguments).unwrap();
+        assert!(store.list_tasks(None).unwrap().is_empty(), "only planned");
+        let applied = change.apply(&store).unwrap();
+
+        let tasks = store.list_tasks(None).unwrap();
+        assert_eq!(tasks.len(), 1);
+        let task = &tasks[0];
+        assert_eq!(applied["id"], json!(task.id));
+        assert_eq!(task.user_id, "u1");
+        assert_eq!(task.title, "Write the report");
+        assert_eq!(task.description.as_deref(), Some("For the board"));
+        assert_eq!(task.priority.as_deref(), Some("high"));
+        assert_eq!(task.estimated_minutes, Some(30));
+        assert_eq!(task.source.as_deref(), Some("ai"));
+    }
+
+    #[test]
+    fn planned_moves_save_to_the_store() {
+        let store = store();
+        // 09:00–10:00 in Berlin.
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 8, 0, 0).unwrap();
+        let mut planning = block("b1", start, start + Duration::hours(1));
+        planning.title = "Sprint planning".into();
+        store.save_time_block(&planning).unwrap();
+
+        let arguments = json!({
+            "blockDescription": "sprint",
+            "newStartTime": "2pm",
+            "newEndTime": "15:30",
+            "date": "2026-03-02",
+        });
+        let change = plan(&store, "schedule_moveTimeBlock", &arguments).unwrap();
+        assert_eq!(
+            change.describe(store.zone().unwrap()),
+            "Move \"Sprint planning\" to 14:00 2026-03-02 – 15:30 2026-03-02"
+        );
+        assert_eq!(
+            store.get_time_block("b1").unwrap().unwrap().start_time,
+            start
+        );
+        change.apply(&store).unwrap();
+
+        let moved = store.get_time_block("b1").unwrap().unwrap();
+        assert_eq!(moved.title, "Sprint planning");
+        assert_eq!(
+            moved.start_time,
+            Utc.with_ymd_and_hms(2026, 3, 2, 13, 0, 0).unwrap()
+        );
+        assert_eq!(
+            moved.end_time,
+            Utc.with_ymd_and_hms(2026, 3, 2, 14, 30, 0).unwrap()
+        );
+        assert_eq!(
+            store
+                .list_time_blocks(start, start + Duration::days(1))
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn plans_that_cant_apply_are_refused() {
+        let store = store();
+        let start = Utc.with_ymd_and_hms(2026, 3, 2, 8, 0, 0).unwrap();
+        for id in ["b1", "b2"] {
+            store
+                .save_time_block(&block(id, start, start + Duration::hours(1)))
+                .unwrap();
+        }
+        let move_to = |description: &str, from: &str, to: &str| {
+            let arguments = json!({
+                "blockDescription": description,
+                "newStartTime": from,
+                "newEndTime": to,
+                "date": "2026-03-02",
+            });
+            plan(&store, "schedule_moveTimeBlock", &arguments)
+        };
+        assert!(matches!(move_to("b", "9", "10"), Err(Error::Mcp(_))));
+        assert!(matches!(
+            move_to("lunch", "9", "10"),
+            Err(Error::NotFound(_))
+        ));
+        assert!(matches!(move_to("b1", "10", "9"), Err(Error::Mcp(_))));
+        assert!(move_to("b1", "9", "10").is_ok());
+
+        let create = |arguments: Value| plan(&store, "task_createTask", &arguments);
+        assert!(create(json!({ "title": " " })).is_err());
+        assert!(create(json!({ "title": "Report", "priority": "urgent" })).is_err());
+        let signed_out = Store::open_in_memory().unwrap();
+        assert!(plan(
+            &signed_out,
+            "task_createTask",
+            &json!({ "title": "Report" })
+        )
+        .is_err());
+        assert!(plan(&store, "task_viewTasks", &json!({})).is_err());
+    }
+}
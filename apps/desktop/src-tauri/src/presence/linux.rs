@@ -0,0 +1,95 @@
+//! Input idle time on Linux. Wayland compositors don't expose it to ordinary
+//! clients, so the session's D-Bus services are asked first: Mutter's idle
+//! monitor on GNOME, then the freedesktop screensaver that KDE implements.
+//! On X11 the XScreenSaver extension answers directly.
+
+use std::time::Duration;
+
+use x11rb::connection::Connection as _;
+use x11rb::protocol::screensaver::ConnectionExt as _;
+use x11rb::rust_connection::RustConnection;
+
+use super::IdleSource;
+
+enum Backend {
+    Mutter(zbus::blocking::Connection),
+    ScreenSaver(zbus::blocking::Connection),
+    X11 {
+        conn: Box<RustConnection>,
+        root: u32,
+    },
+}
+
+impl Backend {
+    fn idle_for(&self) -> Option<Duration> {
+        match self {
+            Backend::Mutter(conn) => {
+                let reply = conn
+                    .call_method(
+                        Some("org.gnome.Mutter.IdleMonitor"),
+                        "/org/gnome/Mutter/IdleMonitor/Core",
+                        Some("org.gnome.Mutter.IdleMonitor"),
+                        "GetIdletime",
+                        &(),
+                    )
+                    .ok()?;
+                let millis: u64 = reply.body().deserialize().ok()?;
+                Some(Duration::from_millis(millis))
+            }
+            Backend::ScreenSaver(conn) => {
+                let reply = conn
+                    .call_method(
+                        Some("org.freedesktop.ScreenSaver"),
+                        "/org/freedesktop/ScreenSaver",
+                        Some("org.freedesktop.ScreenSaver"),
+                        "GetSessionIdleTime",
+                        &(),
+                    )
+                    .ok()?;
+                let millis: u32 = reply.body().deserialize().ok()?;
+                Some(Duration::from_millis(millis.into()))
+            }
+            Backend::X11 { conn, root } => {
+                let info = conn.screensaver_query_info(*root).ok()?.reply().ok()?;
+                Some(Duration::from_millis(info.ms_since_user_input.into()))
+            }
+        }
+    }
+}
+
+/// The first backend that answers on this session, re-probed if it stops
+/// answering (e.g. after the compositor restarts).
+#[derive(Default)]
+pub struct SystemIdle {
+    backend: Option<Backend>,
+}
+
+impl SystemIdle {
+    fn probe() -> Option<Backend> {
+        let mut candidates = Vec::new();
+        if let Ok(conn) = zbus::blocking::Connection::session() {
+            candidates.push(Backend::Mutter(conn.clone()));
+            candidates.push(Backend::ScreenSaver(conn));
+        }
+        if let Ok((conn, screen)) = x11rb::connect(None) {
+            let root = conn.setup().roots[screen].root;
+            candidates.push(Backend::X11 {
+                conn: Box::new(conn),
+                root,
+            });
+        }
+        candidates
+            .into_iter()
+            .find(|backend| backend.idle_for().is_some())
+    }
+}
+
+impl IdleSource for SystemIdle {
+    fn idle_for(&mut self) -> Option<Duration> {
+        if let Some(idle) = self.backend.as_ref().and_then(Backend::idle_for) {
+            return Some(idle);
+        }
+        self.backend = Self::probe();
+        self.backend.as_ref().and_then(Backend::idle_for)
+    }
+}
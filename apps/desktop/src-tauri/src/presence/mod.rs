@@ -0,0 +1,500 @@
+//! Whether the user is actually at the machine. An idle monitor polls the
+//! OS for time since the last keyboard or mouse input; once that passes the
+//! threshold the user is away until input resumes. Each away stretch is
+//! recorded locally with the block it interrupted, so focus time can be
+//! counted as time present rather than time scheduled, and the planner can
+//! learn how long focus really lasts.
+//!
+//! The idle source and the clock are traits so the state machine in
+//! [`IdleMonitor`] can be driven without a desktop session.
+
+#[cfg(target_os = "linux")]
+mod linux;
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Row};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tokio::sync::watch;
+
+use crate::error::Result;
+use crate::store::{BlockType, Store};
+
+pub const IDLE_EVENT: &str = "presence://idle";
+pub const ACTIVE_EVENT: &str = "presence://active";
+
+const IDLE_MINUTES_SETTING: &str = "presence.idle_minutes";
+const DEFAULT_IDLE_MINUTES: i64 = 5;
+const POLL_INTERVAL: StdDuration = StdDuration::from_secs(10);
+
+/// Time since the last user input, or `None` if it can't be told.
+pub trait IdleSource: Send {
+    fn idle_for(&mut self) -> Option<StdDuration>;
+}
+
+pub trait Clock: Send {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Used where there's no way to read idle time; the user always counts as
+/// present.
+pub struct NoIdleSource;
+
+impl IdleSource for NoIdleSource {
+    fn idle_for(&mut self) -> Option<StdDuration> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    /// No input since `since`, which is now longer ago than the threshold.
+    Idle { since: DateTime<Utc> },
+    /// Input resumed at `until` after being away since `since`.
+    Active {
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    },
+}
+
+/// Turns idle-time readings into away/back transitions.
+pub struct IdleMonitor<S, C> {
+    source: S,
+    clock: C,
+    threshold: Duration,
+    away_since: Option<DateTime<Utc>>,
+    last_poll: Option<DateTime<Utc>>,
+}
+
+impl<S: IdleSource, C: Clock> IdleMonitor<S, C> {
+    pub fn new(source: S, clock: C, threshold: Duration) -> Self {
+        Self {
+            source,
+            clock,
+            threshold,
+            away_since: None,
+            last_poll: None,
+        }
+    }
+
+    pub fn set_threshold(&mut self, threshold: Duration) {
+        self.threshold = threshold;
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.away_since.is_some()
+    }
+
+    pub fn poll(&mut self) -> Option<Transition> {
+        let now = self.clock.now();
+        let last_poll = self.last_poll.replace(now);
+        let idle = Duration::from_std(self.source.idle_for()?).unwrap_or(Duration::zero());
+        let last_input = now - idle;
+
+        match self.away_since {
+            None if idle >= self.threshold => {
+                self.away_since = Some(last_input);
+                Some(Transition::Idle { since: last_input })
+            }
+            Some(since) if idle < self.threshold => {
+                self.away_since = None;
+                Some(Transition::Active {
+                    since,
+                    until: last_input,
+                })
+            }
+            // Nothing polled while the machine was suspended, and waking it
+            // resets the idle time; a gap longer than the threshold between
+            // polls was time away all the same.
+            None => match last_poll {
+                Some(last_poll) if last_input - last_poll >= self.threshold => {
+                    Some(Transition::Active {
+                        since: last_poll,
+                        until: last_input,
+                    })
+                }
+                _ => None,
+            },
+            Some(_) => None,
+        }
+    }
+}
+
+/// A stretch of time away from the machine.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AwayInterval {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub block_id: Option<String>,
+}
+
+impl AwayInterval {
+    const COLUMNS: &'static str = "started_at, ended_at, block_id";
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            started_at: row.get(0)?,
+            ended_at: row.get(1)?,
+            block_id: row.get(2)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdleEvent {
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveEvent {
+    pub away: AwayInterval,
+    pub away_minutes: i64,
+}
+
+impl Store {
+    pub fn record_away(&self, away: &AwayInterval) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO away_intervals (started_at, ended_at, block_id) VALUES (?1, ?2, ?3)",
+            params![away.started_at, away.ended_at, away.block_id],
+        )?;
+        Ok(())
+    }
+
+    /// Away intervals overlapping `[start, end)`.
+    pub fn away_intervals(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<AwayInterval>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM away_intervals
+             WHERE started_at < ?2 AND ended_at > ?1
+             ORDER BY started_at",
+            AwayInterval::COLUMNS
+        ))?;
+        let rows = stmt.query_map(params![start, end], AwayInterval::from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// How much of `[start, end)` the user spent away.
+    pub fn away_time(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Duration> {
+        Ok(self
+            .away_intervals(start, end)?
+            .iter()
+            .map(|away| away.ended_at.min(end) - away.started_at.max(start))
+            .filter(|overlap| *overlap > Duration::zero())
+            .sum())
+    }
+}
+
+/// Presence as seen by the rest of the backend: when the user went away, or
+/// `None` while they're here. Focus accounting subscribes to pause while the
+/// user is away.
+pub struct Presence {
+    away_since: watch::Sender<Option<DateTime<Utc>>>,
+    idle_minutes: AtomicI64,
+}
+
+impl Default for Presence {
+    fn default() -> Self {
+        Self {
+            away_since: watch::channel(None).0,
+            idle_minutes: AtomicI64::new(DEFAULT_IDLE_MINUTES),
+        }
+    }
+}
+
+impl Presence {
+    pub fn is_idle(&self) -> bool {
+        self.away_since.borrow().is_some()
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<Option<DateTime<Utc>>> {
+        self.away_since.subscribe()
+    }
+
+    fn threshold(&self) -> Duration {
+        Duration::minutes(self.idle_minutes.load(Ordering::Relaxed).max(1))
+    }
+}
+
+/// The block running at `at`, preferring a focus block where they overlap.
+fn block_at(store: &Store, at: DateTime<Utc>) -> Option<String> {
+    let blocks = store.blocks_at(at).ok()?;
+    blocks
+        .iter()
+        .find(|block| block.block_type == BlockType::Focus)
+        .or(blocks.first())
+        .map(|block| block.id.clone())
+}
+
+fn emit<R: Runtime, S: Serialize + Clone>(app: &AppHandle<R>, event: &str, payload: S) {
+    if let Err(err) = app.emit(event, payload) {
+        log::warn!("failed to emit {event}: {err}");
+    }
+}
+
+fn apply<R: Runtime>(app: &AppHandle<R>, transition: Transition) {
+    let presence = app.state::<Presence>();
+    match transition {
+        Transition::Idle { since } => {
+            presence.away_since.send_replace(Some(since));
+            emit(app, IDLE_EVENT, IdleEvent { since });
+        }
+        Transition::Active { since, until } => {
+            presence.away_since.send_replace(None);
+            let store = app.state::<Store>();
+            let away = AwayInterval {
+                started_at: since,
+                ended_at: until,
+                block_id: block_at(&store, since),
+            };
+            if let Err(err) = store.record_away(&away) {
+                log::error!("failed to record away interval: {err}");
+            }
+            let away_minutes = (until - since).num_minutes();
+            emit(app, ACTIVE_EVENT, ActiveEvent { away, away_minutes });
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn system_source() -> linux::SystemIdle {
+    linux::SystemIdle::default()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn system_source() -> NoIdleSource {
+    NoIdleSource
+}
+
+/// Manage presence state and start polling. The OS calls block, so the
+/// monitor runs on its own thread.
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    let presence = Presence::default();
+    if let Some(minutes) = app.state::<Store>().setting(IDLE_MINUTES_SETTING)? {
+        presence.idle_minutes.store(minutes, Ordering::Relaxed);
+    }
+    let threshold = presence.threshold();
+    app.manage(presence);
+
+    let app = app.handle().clone();
+    std::thread::spawn(move || {
+        let mut monitor = IdleMonitor::new(system_source(), SystemClock, threshold);
+        loop {
+            monitor.set_threshold(app.state::<Presence>().threshold());
+            if let Some(transition) = monitor.poll() {
+                apply(&app, transition);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresenceStatus {
+    pub idle: bool,
+    pub idle_minutes: i64,
+}
+
+#[tauri::command]
+pub fn presence_status(presence: State<'_, Presence>) -> PresenceStatus {
+    PresenceStatus {
+        idle: presence.is_idle(),
+        idle_minutes: presence.idle_minutes.load(Ordering::Relaxed),
+    }
+}
+
+#[tauri::command]
+pub fn presence_set_idle_minutes(
+    minutes: i64,
+    presence: State<'_, Presence>,
+    store: State<'_, Store>,
+) -> Result<()> {
+    let minutes = minutes.max(1);
+    store.set_setting(IDLE_MINUTES_SETTING, &minutes)?;
+    presence.idle_minutes.store(minutes, Ordering::Relaxed);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn presence_away_intervals(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    store: State<'_, Store>,
+) -> Result<Vec<AwayInterval>> {
+    store.away_intervals(start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// A clock and idle source the test moves by hand.
+    #[derive(Clone)]
+    struct Fake {
+        now: Arc<Mutex<DateTime<Utc>>>,
+        idle: Arc<Mutex<Option<StdDuration>>>,
+    }
+
+    impl IdleSource for Fake {
+        fn idle_for(&mut self) -> Option<StdDuration> {
+            *self.idle.lock().unwrap()
+        }
+    }
+
+    impl Clock for Fake {
+        fn now(&self) -> DateTime<Utc> {
+            *self.now.lock().unwrap()
+        }
+    }
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap()
+    }
+
+    fn monitor() -> (IdleMonitor<Fake, Fake>, Fake) {
+        let fake = Fake {
+            now: Arc::new(Mutex::new(start())),
+            idle: Arc::new(Mutex::new(Some(StdDuration::ZERO))),
+        };
+        let monitor = IdleMonitor::new(fake.clone(), fake.clone(), Duration::minutes(5));
+        (monitor, fake)
+    }
+
+    impl Fake {
+        /// Move the clock to `at` seconds after the start with the last
+        /// input `idle` seconds ago, and poll.
+        fn poll(
+            &self,
+            monitor: &mut IdleMonitor<Fake, Fake>,
+            at: i64,
+            idle: Option<u64>,
+        ) -> Option<Transition> {
+            *self.now.lock().unwrap() = start() + Duration::seconds(at);
+            *self.idle.lock().unwrap() = idle.map(StdDuration::from_secs);
+            monitor.poll()
+        }
+    }
+
+    fn at(seconds: i64) -> DateTime<Utc> {
+        start() + Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn goes_idle_once_the_threshold_passes() {
+        let (mut monitor, fake) = monitor();
+        assert_eq!(fake.poll(&mut monitor, 0, Some(0)), None);
+        assert_eq!(fake.poll(&mut monitor, 299, Some(299)), None);
+        assert!(!monitor.is_idle());
+
+        // Away since the last input, not since the threshold passed.
+        assert_eq!(
+            fake.poll(&mut monitor, 310, Some(300)),
+            Some(Transition::Idle { since: at(10) })
+        );
+        assert!(monitor.is_idle());
+        assert_eq!(fake.poll(&mut monitor, 320, Some(310)), None);
+        assert_eq!(fake.poll(&mut monitor, 900, Some(890)), None);
+    }
+
+    #[test]
+    fn input_brings_the_user_back() {
+        let (mut monitor, fake) = monitor();
+        fake.poll(&mut monitor, 0, Some(0));
+        fake.poll(&mut monitor, 400, Some(400));
+
+        assert_eq!(
+            fake.poll(&mut monitor, 1210, Some(10)),
+            Some(Transition::Active {
+                since: at(0),
+                until: at(1200),
+            })
+        );
+        assert!(!monitor.is_idle());
+        assert_eq!(fake.poll(&mut monitor, 1220, Some(0)), None);
+    }
+
+    #[test]
+    fn a_suspend_longer_than_the_threshold_is_time_away() {
+        let (mut monitor, fake) = monitor();
+        fake.poll(&mut monitor, 0, Some(0));
+        fake.poll(&mut monitor, 10, Some(0));
+
+        // Waking resets the idle time, so only the gap between polls shows.
+        assert_eq!(
+            fake.poll(&mut monitor, 1810, Some(2)),
+            Some(Transition::Active {
+                since: at(10),
+                until: at(1808),
+            })
+        );
+        assert!(!monitor.is_idle());
+
+        // A short gap is just a slow poll.
+        assert_eq!(fake.poll(&mut monitor, 2000, Some(1)), None);
+    }
+
+    #[test]
+    fn unknown_idle_time_changes_nothing() {
+        let (mut monitor, fake) = monitor();
+        assert_eq!(fake.poll(&mut monitor, 0, None), None);
+        assert_eq!(fake.poll(&mut monitor, 3600, None), None);
+        assert!(!monitor.is_idle());
+    }
+
+    #[test]
+    fn a_lower_threshold_applies_on_the_next_poll() {
+        let (mut monitor, fake) = monitor();
+        assert_eq!(fake.poll(&mut monitor, 120, Some(120)), None);
+        monitor.set_threshold(Duration::minutes(1));
+        assert_eq!(
+            fake.poll(&mut monitor, 130, Some(130)),
+            Some(Transition::Idle { since: at(0) })
+        );
+    }
+
+    #[test]
+    fn away_time_is_clipped_to_the_range() {
+        let store = Store::open_in_memory().unwrap();
+        for (from, to) in [(0, 600), (1200, 1500), (3000, 4000)] {
+            store
+                .record_away(&AwayInterval {
+                    started_at: at(from),
+                    ended_at: at(to),
+                    block_id: None,
+                })
+                .unwrap();
+        }
+        assert_eq!(store.away_intervals(at(300), at(3000)).unwrap().len(), 2);
+        assert_eq!(
+            store.away_time(at(300), at(3500)).unwrap(),
+            Duration::seconds(300 + 300 + 500)
+        );
+        assert_eq!(
+            store.away_time(at(600), at(1200)).unwrap(),
+            Duration::zero()
+        );
+    }
+}
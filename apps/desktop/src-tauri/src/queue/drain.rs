@@ -0,0 +1,406 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use chrono::Utc;
+use reqwest::{Method, StatusCode};
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::sync::Notify;
+
+use super::{emit_status, Action, OperationStatus, QueuedOperation, StatusEvent};
+use crate::config::AppConfig;
+use crate::store::Store;
+use crate::supabase::Rest;
+use crate::vault::TokenVault;
+
+/// While offline, check whether the server is reachable this often.
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Whether the server is believed to be reachable, and a way to wake the
+/// drain task when that or the queue changes.
+pub struct Connectivity {
+    online: AtomicBool,
+    notify: Notify,
+}
+
+impl Default for Connectivity {
+    fn default() -> Self {
+        Self {
+            online: AtomicBool::new(true),
+            notify: Notify::new(),
+        }
+    }
+}
+
+impl Connectivity {
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::SeqCst)
+    }
+
+    pub fn set_online(&self, online: bool) {
+        self.online.store(online, Ordering::SeqCst);
+        if online {
+            self.wake();
+        }
+    }
+
+    pub fn wake(&self) {
+        self.notify.notify_one();
+    }
+}
+
+enum Outcome {
+    Done,
+    /// Worth retrying: the network or the server had a problem.
+    Transient(String),
+    /// The server rejected the operation; retrying won't help.
+    Rejected(String),
+}
+
+/// Start the background task that replays the queue against Supabase.
+pub fn spawn_drain<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let client = reqwest::Client::new();
+        if let Err(err) = app.state::<Store>().recover_in_flight() {
+            log::error!("failed to recover queued operations: {err}");
+        }
+
+        loop {
+            let connectivity = app.state::<Connectivity>();
+            if !connectivity.is_online() && probe(&app, &client).await {
+                if let Err(err) = app.state::<Store>().retry_all_now() {
+                    log::error!("failed to update operation queue: {err}");
+                }
+                connectivity.set_online(true);
+            }
+
+            let wait = drain_once(&app, &client).await;
+            let wait = if connectivity.is_online() {
+                wait
+            } else {
+                Some(wait.map_or(PROBE_INTERVAL, |w| w.min(PROBE_INTERVAL)))
+            };
+            match wait {
+                Some(wait) => {
+                    let _ = tokio::time::timeout(wait, connectivity.notify.notified()).await;
+                }
+                None => connectivity.notify.notified().await,
+            }
+        }
+    });
+}
+
+/// Push due operations until the queue is empty or the head isn't due.
+/// Returns how long to wait before the head is due, if there is one.
+async fn drain_once<R: Runtime>(app: &AppHandle<R>, client: &reqwest::Client) -> Option<Duration> {
+    let store = app.state::<Store>();
+    let connectivity = app.state::<Connectivity>();
+
+    loop {
+        let op = match store.next_operation() {
+            Ok(Some(op)) => op,
+            Ok(None) => return None,
+            Err(err) => {
+                log::error!("failed to read operation queue: {err}");
+                return Some(PROBE_INTERVAL);
+            }
+        };
+
+        let due_in = op.next_attempt_at - Utc::now();
+        if due_in > chrono::Duration::zero() {
+            return Some(due_in.to_std().unwrap_or(PROBE_INTERVAL));
+        }
+
+        if let Err(err) = store.mark_in_flight(&op.id) {
+            log::error!("failed to update operation queue: {err}");
+            return Some(PROBE_INTERVAL);
+        }
+        let op = QueuedOperation {
+            attempts: op.attempts + 1,
+            ..op
+        };
+        emit_status(app, StatusEvent::new(&op, OperationStatus::InFlight, None));
+
+        let result = match execute(app, client, &op).await {
+            Outcome::Done => {
+                connectivity.set_online(true);
+                store
+                    .complete_operation(&op.id)
+                    .map(|_| StatusEvent::new(&op, OperationStatus::Succeeded, None))
+            }
+            Outcome::Rejected(error) => store
+                .fail_operation(&op.id, &error)
+                .map(|_| StatusEvent::new(&op, OperationStatus::Failed, Some(error))),
+            Outcome::Transient(error) => {
+                // Keep the queue in order: nothing behind the head goes out
+                // until it does. Only a rejection lets later rows past.
+                return match store.retry_operation_later(&op, &error) {
+                    Ok(next) => {
+                        emit_status(
+                            app,
+                            StatusEvent::new(&op, OperationStatus::Pending, Some(error)),
+                        );
+                        Some((next - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+                    }
+                    Err(err) => {
+                        log::error!("failed to update operation queue: {err}");
+                        Some(PROBE_INTERVAL)
+                    }
+                };
+            }
+        };
+        match result {
+            Ok(event) => emit_status(app, event),
+            Err(err) => log::error!("failed to update operation queue: {err}"),
+        }
+    }
+}
+
+async fn execute<R: Runtime>(
+    app: &AppHandle<R>,
+    client: &reqwest::Client,
+    op: &QueuedOperation,
+) -> Outcome {
+    let config = app.state::<AppConfig>();
+    let vault = app.state::<TokenVault>();
+    let connectivity = app.state::<Connectivity>();
+    let rest = match Rest::from_vault(client, &config.supabase, &vault) {
+        Ok(rest) => rest,
+        Err(err) => return Outcome::Transient(err.to_string()),
+    };
+
+    let table = op.table.as_str();
+    let by_id = op
+        .row_id
+        .as_deref()
+        .map(|id| format!("{table}?id=eq.{}", urlencoding(id)));
+    let request = match (op.action, by_id) {
+        // Inserts are replayed as "insert unless present" so a retry after a
+        // lost response doesn't create a duplicate row.
+        (Action::Insert, _) => rest
+            .request(Method::POST, table)
+            .header("Prefer", "resolution=ignore-duplicates,return=minimal")
+            .json(&op.payload),
+        (Action::Upsert, _) => rest
+            .request(Method::POST, table)
+            .header("Prefer", "resolution=merge-duplicates,return=minimal")
+            .json(&op.payload),
+        (Action::Update, Some(path)) => rest
+            .request(Method::PATCH, &path)
+            .header("Prefer", "return=minimal")
+            .json(&op.payload),
+        (Action::Delete, Some(path)) => rest.request(Method::DELETE, &path),
+        // The server records the key, so a replay doesn't count twice.
+        (Action::Increment, _) => {
+            let mut args = op.payload.clone();
+            if let Some(args) = args.as_object_mut() {
+                args.insert("idempotency_key".into(), op.idempotency_key.clone().into());
+            }
+            rest.request(Method::POST, &format!("rpc/increment_{table}"))
+                .json(&args)
+        }
+        (Action::Update | Action::Delete, None) => {
+            return Outcome::Rejected(format!("{} needs a row id", op.action.as_str()));
+        }
+    };
+
+    let response = match request
+        .header("Idempotency-Key", &op.idempotency_key)
+        .send()
+        .await
+    {
+        Ok(response) => response,
+        Err(err) => {
+            connectivity.set_online(false);
+            return Outcome::Transient(err.to_string());
+        }
+    };
+
+    let status = response.status();
+    if status.is_success() {
+        return Outcome::Done;
+    }
+    let body = response.text().await.unwrap_or_default();
+    let message = format!("{status}: {body}");
+    match status {
+        StatusCode::UNAUTHORIZED | StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => {
+            Outcome::Transient(message)
+        }
+        status if status.is_server_error() => Outcome::Transient(message),
+        _ => Outcome::Rejected(message),
+    }
+}
+
+/// Whether the auth server answers its health check.
+async fn probe<R: Runtime>(app: &AppHandle<R>, client: &reqwest::Client) -> bool {
+    let config = app.state::<AppConfig>();
+    client
+        .get(format!("{}/auth/v1/health", config.supabase.url))
+        .header("apikey", &config.supabase.anon_key)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success())
+}
+
+fn urlencoding(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+#[cfg(all(test, desktop))]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::{GoogleConfig, SupabaseConfig};
+    use crate::queue::{NewOperation, Table};
+    use crate::testing::TestApp;
+    use crate::vault::{Credential, Provider};
+
+    /// Answers each write by the row id it names: `bad-*` is rejected,
+    /// `down-*` finds the server in trouble, anything else succeeds.
+    struct Supabase {
+        server: Arc<tiny_http::Server>,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Supabase {
+        fn start() -> Self {
+            let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let (incoming, seen) = (server.clone(), requests.clone());
+            std::thread::spawn(move || {
+                for request in incoming.incoming_requests() {
+                    let url = request.url().to_owned();
+                    let status = match url.split("id=eq.").nth(1) {
+                        Some(id) if id.starts_with("bad") => 400,
+                        Some(id) if id.starts_with("down") => 503,
+                        _ => 204,
+                    };
+                    seen.lock()
+                        .unwrap()
+                        .push(format!("{} {url}", request.method()));
+                    let _ = request.respond(tiny_http::Response::empty(status));
+                }
+            });
+            Self { server, requests }
+        }
+
+        fn requests(&self) -> Vec<String> {
+            std::mem::take(&mut self.requests.lock().unwrap())
+        }
+    }
+
+    impl Drop for Supabase {
+        fn drop(&mut self) {
+            self.server.unblock();
+        }
+    }
+
+    fn signed_in(server: &Supabase) -> TestApp {
+        let app = TestApp::new();
+        app.app.manage(AppConfig {
+            open_devtools: false,
+            start_minimized: false,
+            tray: false,
+            supabase: SupabaseConfig {
+                url: format!("http://{}", server.server.server_addr()),
+                anon_key: "anon".into(),
+            },
+            google: GoogleConfig::default(),
+            hosts_path: Default::default(),
+        });
+        app.app
+            .state::<TokenVault>()
+            .set(
+                Provider::Supabase,
+                Credential {
+                    access_token: "token".into(),
+                    refresh_token: None,
+                    expires_at: None,
+                },
+            )
+            .unwrap();
+        app
+    }
+
+    fn update(store: &Store, row_id: &str) -> QueuedOperation {
+        store
+            .enqueue(NewOperation {
+                idempotency_key: None,
+                table: Table::UserPreferences,
+                action: Action::Update,
+                row_id: Some(row_id.into()),
+                payload: json!({ "timezone": "Europe/Berlin" }),
+            })
+            .unwrap()
+    }
+
+    fn drain(app: &TestApp) -> Option<Duration> {
+        tauri::async_runtime::block_on(drain_once(app.handle(), &reqwest::Client::new()))
+    }
+
+    #[test]
+    fn successes_are_dequeued_and_rejections_hold_their_row() {
+        let server = Supabase::start();
+        let app = signed_in(&server);
+        let store = app.store();
+        update(&store, "ok-1");
+        let rejected = update(&store, "bad-1");
+        let follower = update(&store, "bad-1");
+        update(&store, "ok-2");
+
+        assert_eq!(drain(&app), None);
+        assert_eq!(
+            server.requests(),
+            [
+                "PATCH /rest/v1/user_preferences?id=eq.ok-1",
+                "PATCH /rest/v1/user_preferences?id=eq.bad-1",
+                "PATCH /rest/v1/user_preferences?id=eq.ok-2",
+            ]
+        );
+        let left = store.queued_operations().unwrap();
+        assert_eq!(left.len(), 2);
+        assert_eq!(left[0].id, rejected.id);
+        assert_eq!(left[0].status, OperationStatus::Failed);
+        assert!(left[0].last_error.as_deref().unwrap().starts_with("400"));
+        assert_eq!(left[1].id, follower.id);
+        assert_eq!(left[1].status, OperationStatus::Pending);
+        assert_eq!(left[1].attempts, 0);
+
+        // Discarding the rejected write lets its follower out.
+        store.discard_operation(&rejected.id).unwrap();
+        drain(&app);
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn server_errors_back_off_and_keep_the_queue_in_order() {
+        let server = Supabase::start();
+        let app = signed_in(&server);
+        let store = app.store();
+        let down = update(&store, "down-1");
+        update(&store, "ok-1");
+
+        let wait = drain(&app).unwrap();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(5));
+        assert_eq!(
+            server.requests(),
+            ["PATCH /rest/v1/user_preferences?id=eq.down-1"]
+        );
+        let head = store.next_operation().unwrap().unwrap();
+        assert_eq!(head.id, down.id);
+        assert_eq!(head.status, OperationStatus::Pending);
+        assert_eq!(head.attempts, 1);
+        assert!(head.last_error.as_deref().unwrap().starts_with("503"));
+        assert!(head.next_attempt_at > Utc::now());
+        // The server answered, so it isn't marked offline.
+        assert!(app.app.state::<Connectivity>().is_online());
+
+        // Not due yet, so nothing goes out.
+        assert!(drain(&app).is_some());
+        assert!(server.requests().is_empty());
+        assert_eq!(store.queued_operations().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,564 @@
+//! Durable queue of writes to push to Supabase, replacing the webview's
+//! `localStorage` offline queue.
+//!
+//! Operations are journaled in the local store before anything else happens,
+//! so a crash or a restart never loses them, and nothing is evicted to make
+//! room. The drain task replays them in order with exponential backoff. An
+//! operation the server rejects is parked as failed; later operations on
+//! the same row wait behind it until it's retried or discarded, while other
+//! rows carry on.
+//!
+//! Each table is pushed by exactly one path, so no row goes out twice.
+//! Tasks, emails and time blocks are mirrored in the local store and belong
+//! to the sync engine, which pushes the rows local writes mark dirty. The
+//! queue carries everything else: schedule stat increments, preference
+//! changes and pattern exports. The webview can't queue writes to synced
+//! tables; it saves them to the local store instead.
+
+mod drain;
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{Emitter, Manager, Runtime, State};
+
+use crate::error::{Error, Result};
+use crate::store::Store;
+
+pub use drain::{spawn_drain, Connectivity};
+
+pub const STATUS_EVENT: &str = "queue://status";
+
+/// First retry delay; doubled on every failed attempt up to [`MAX_BACKOFF`].
+const BASE_BACKOFF: Duration = Duration::seconds(5);
+const MAX_BACKOFF: Duration = Duration::minutes(15);
+
+/// Supabase tables the queue may write to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Table {
+    Tasks,
+    Emails,
+    DailySchedules,
+    TimeBlocks,
+    UserPreferences,
+    UserPatterns,
+}
+
+impl Table {
+    /// Whether the sync engine pushes this table from the local store, in
+    /// which case the queue doesn't.
+    pub fn is_synced(self) -> bool {
+        matches!(self, Table::Tasks | Table::Emails | Table::TimeBlocks)
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Table::Tasks => "tasks",
+            Table::Emails => "emails",
+            Table::DailySchedules => "daily_schedules",
+            Table::TimeBlocks => "time_blocks",
+            Table::UserPreferences => "user_preferences",
+            Table::UserPatterns => "user_patterns",
+        }
+    }
+}
+
+impl FromStr for Table {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        serde_json::from_value(Value::String(s.to_owned()))
+            .map_err(|_| Error::Queue(format!("unknown table {s:?}")))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Insert,
+    Upsert,
+    Update,
+    Delete,
+    /// Add the payload's `deltas` to counters through the table's
+    /// `increment_<table>` function rather than overwriting them.
+    Increment,
+}
+
+impl Action {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Action::Insert => "insert",
+            Action::Upsert => "upsert",
+            Action::Update => "update",
+            Action::Delete => "delete",
+            Action::Increment => "increment",
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        serde_json::from_value(Value::String(s.to_owned()))
+            .map_err(|_| Error::Queue(format!("unknown action {s:?}")))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationStatus {
+    Pending,
+    InFlight,
+    Failed,
+    Succeeded,
+}
+
+impl OperationStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            OperationStatus::Pending => "pending",
+            OperationStatus::InFlight => "in_flight",
+            OperationStatus::Failed => "failed",
+            OperationStatus::Succeeded => "succeeded",
+        }
+    }
+}
+
+impl fmt::Display for OperationStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A write to enqueue. Re-enqueueing with the same idempotency key returns
+/// the operation already in the queue instead of adding a second one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewOperation {
+    pub idempotency_key: Option<String>,
+    pub table: Table,
+    pub action: Action,
+    pub row_id: Option<String>,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedOperation {
+    pub id: String,
+    pub idempotency_key: String,
+    pub table: Table,
+    pub action: Action,
+    pub row_id: Option<String>,
+    pub payload: Value,
+    pub status: OperationStatus,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl QueuedOperation {
+    const COLUMNS: &'static str = "id, idempotency_key, table_name, action, row_id, payload, \
+        status, attempts, next_attempt_at, last_error, created_at";
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let parse_err = |err: Error| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(err))
+        };
+        let status: String = row.get("status")?;
+        Ok(Self {
+            id: row.get("id")?,
+            idempotency_key: row.get("idempotency_key")?,
+            table: row
+                .get::<_, String>("table_name")?
+                .parse()
+                .map_err(parse_err)?,
+            action: row.get::<_, String>("action")?.parse().map_err(parse_err)?,
+            row_id: row.get("row_id")?,
+            payload: row.get("payload")?,
+            status: match status.as_str() {
+                "in_flight" => OperationStatus::InFlight,
+                "failed" => OperationStatus::Failed,
+                _ => OperationStatus::Pending,
+            },
+            attempts: row.get("attempts")?,
+            next_attempt_at: row.get("next_attempt_at")?,
+            last_error: row.get("last_error")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+}
+
+/// Emitted on [`STATUS_EVENT`] whenever an operation changes state.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusEvent {
+    pub id: String,
+    pub idempotency_key: String,
+    pub status: OperationStatus,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+impl StatusEvent {
+    fn new(op: &QueuedOperation, status: OperationStatus, error: Option<String>) -> Self {
+        Self {
+            id: op.id.clone(),
+            idempotency_key: op.idempotency_key.clone(),
+            status,
+            attempts: op.attempts,
+            error,
+        }
+    }
+}
+
+/// Journal operations on the local store's `operation_queue` table.
+impl Store {
+    pub fn enqueue(&self, op: NewOperation) -> Result<QueuedOperation> {
+        let id = uuid::Uuid::new_v4().to_string();
+        let key = op.idempotency_key.unwrap_or_else(|| id.clone());
+        let now = Utc::now();
+
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO operation_queue
+               (id, idempotency_key, table_name, action, row_id, payload, next_attempt_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+             ON CONFLICT (idempotency_key) DO NOTHING",
+            params![
+                id,
+                key,
+                op.table.as_str(),
+                op.action.as_str(),
+                op.row_id,
+                op.payload,
+                now,
+            ],
+        )?;
+        let queued = conn.query_row(
+            &format!(
+                "SELECT {} FROM operation_queue WHERE idempotency_key = ?1",
+                QueuedOperation::COLUMNS
+            ),
+            [key],
+            QueuedOperation::from_row,
+        )?;
+        Ok(queued)
+    }
+
+    pub fn queued_operations(&self) -> Result<Vec<QueuedOperation>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM operation_queue ORDER BY seq",
+            QueuedOperation::COLUMNS
+        ))?;
+        let ops = stmt
+            .query_map([], QueuedOperation::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(ops)
+    }
+
+    /// The oldest operation that isn't parked as failed or queued behind a
+    /// failed operation on the same row.
+    pub(crate) fn next_operation(&self) -> Result<Option<QueuedOperation>> {
+        let conn = self.conn();
+        let op = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM operation_queue AS op
+                     WHERE status != 'failed'
+                       AND NOT EXISTS (
+                         SELECT 1 FROM operation_queue AS blocker
+                         WHERE blocker.status = 'failed' AND blocker.seq < op.seq
+                           AND blocker.table_name = op.table_name
+                           AND blocker.row_id = op.row_id
+                       )
+                     ORDER BY seq LIMIT 1",
+                    QueuedOperation::COLUMNS
+                ),
+                [],
+                QueuedOperation::from_row,
+            )
+            .optional()?;
+        Ok(op)
+    }
+
+    pub(crate) fn mark_in_flight(&self, id: &str) -> Result<()> {
+        self.conn().execute(
+            "UPDATE operation_queue SET status = 'in_flight', attempts = attempts + 1 WHERE id = ?1",
+            [id],
+        )?;
+        Ok(())
+    }
+
+    pub(crate) fn complete_operation(&self, id: &str) -> Result<()> {
+        self.conn()
+            .execute("DELETE FROM operation_queue WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    /// Put an operation back in line after a transient failure.
+    pub(crate) fn retry_operation_later(
+        &self,
+        op: &QueuedOperation,
+        error: &str,
+    ) -> Result<DateTime<Utc>> {
+        let next = Utc::now() + backoff(op.attempts);
+        self.conn().execute(
+            "UPDATE operation_queue SET status = 'pending', next_attempt_at = ?2, last_error = ?3 WHERE id = ?1",
+            params![op.id, next, error],
+        )?;
+        Ok(next)
+    }
+
+    /// Park an operation the server rejected outright.
+    pub(crate) fn fail_operation(&self, id: &str, error: &str) -> Result<()> {
+        self.conn().execute(
+            "UPDATE operation_queue SET status = 'failed', last_error = ?2 WHERE id = ?1",
+            params![id, error],
+        )?;
+        Ok(())
+    }
+
+    /// Requeue a failed operation, or make a waiting one due now.
+    pub fn retry_operation(&self, id: &str) -> Result<()> {
+        self.conn().execute(
+            "UPDATE operation_queue SET status = 'pending', next_attempt_at = ?2 WHERE id = ?1",
+            params![id, Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    pub fn discard_operation(&self, id: &str) -> Result<()> {
+        self.complete_operation(id)
+    }
+
+    /// Make every waiting operation due now, e.g. after coming back online.
+    pub(crate) fn retry_all_now(&self) -> Result<()> {
+        self.conn().execute(
+            "UPDATE operation_queue SET next_attempt_at = ?1 WHERE status = 'pending'",
+            [Utc::now()],
+        )?;
+        Ok(())
+    }
+
+    /// Operations that were in flight when the app last stopped are retried.
+    pub(crate) fn recover_in_flight(&self) -> Result<()> {
+        self.conn().execute(
+            "UPDATE operation_queue SET status = 'pending' WHERE status = 'in_flight'",
+            [],
+        )?;
+        Ok(())
+    }
+}
+
+fn backoff(attempts: u32) -> Duration {
+    let factor = 1i32 << attempts.saturating_sub(1).min(16);
+    (BASE_BACKOFF * factor).min(MAX_BACKOFF)
+}
+
+pub(crate) fn emit_status<R: Runtime>(app: &tauri::AppHandle<R>, event: StatusEvent) {
+    if let Err(err) = app.emit(STATUS_EVENT, event) {
+        log::warn!("failed to emit queue status: {err}");
+    }
+}
+
+#[tauri::command]
+pub fn queue_enqueue<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    operation: NewOperation,
+    store: State<'_, Store>,
+    connectivity: State<'_, Connectivity>,
+) -> Result<QueuedOperation> {
+    if operation.table.is_synced() {
+        return Err(Error::Queue(format!(
+            "{} are pushed by sync; save them to the local store instead",
+            operation.table.as_str()
+        )));
+    }
+    let queued = store.enqueue(operation)?;
+    emit_status(&app, StatusEvent::new(&queued, queued.status, None));
+    connectivity.wake();
+    Ok(queued)
+}
+
+#[tauri::command]
+pub fn queue_list(store: State<'_, Store>) -> Result<Vec<QueuedOperation>> {
+    store.queued_operations()
+}
+
+#[tauri::command]
+pub fn queue_retry(
+    id: String,
+    store: State<'_, Store>,
+    connectivity: State<'_, Connectivity>,
+) -> Result<()> {
+    store.retry_operation(&id)?;
+    connectivity.wake();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn queue_discard(id: String, store: State<'_, Store>) -> Result<()> {
+    store.discard_operation(&id)
+}
+
+/// Called by the webview's `online`/`offline` listeners.
+#[tauri::command]
+pub fn queue_set_online<R: Runtime>(
+    app: tauri::AppHandle<R>,
+    online: bool,
+    connectivity: State<'_, Connectivity>,
+) -> Result<()> {
+    if online && !connectivity.is_online() {
+        app.state::<Store>().retry_all_now()?;
+    }
+    connectivity.set_online(online);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn op(key: Option<&str>, table: Table, action: Action, row_id: &str) -> NewOperation {
+        NewOperation {
+            idempotency_key: key.map(str::to_owned),
+            table,
+            action,
+            row_id: Some(row_id.to_owned()),
+            payload: json!({ "title": row_id }),
+        }
+    }
+
+    fn update(row_id: &str) -> NewOperation {
+        op(None, Table::Tasks, Action::Update, row_id)
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff(0), BASE_BACKOFF);
+        assert_eq!(backoff(1), BASE_BACKOFF);
+        assert_eq!(backoff(2), BASE_BACKOFF * 2);
+        assert_eq!(backoff(4), BASE_BACKOFF * 8);
+        assert_eq!(backoff(8), BASE_BACKOFF * 128);
+        assert_eq!(backoff(9), MAX_BACKOFF);
+        assert_eq!(backoff(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn enqueue_is_idempotent_by_key() {
+        let store = Store::open_in_memory().unwrap();
+        let first = store
+            .enqueue(op(Some("k1"), Table::Tasks, Action::Insert, "t1"))
+            .unwrap();
+        let again = store
+            .enqueue(op(Some("k1"), Table::Emails, Action::Delete, "other"))
+            .unwrap();
+        assert_eq!(again.id, first.id);
+        assert_eq!(again.table, Table::Tasks);
+        assert_eq!(again.payload, json!({ "title": "t1" }));
+
+        let keyless = store.enqueue(update("t1")).unwrap();
+        assert_eq!(keyless.idempotency_key, keyless.id);
+        assert_eq!(store.queued_operations().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn operations_come_out_in_order() {
+        let store = Store::open_in_memory().unwrap();
+        let ids: Vec<_> = ["a", "b", "c"]
+            .into_iter()
+            .map(|row| store.enqueue(update(row)).unwrap().id)
+            .collect();
+        for id in ids {
+            let next = store.next_operation().unwrap().unwrap();
+            assert_eq!(next.id, id);
+            store.complete_operation(&id).unwrap();
+        }
+        assert!(store.next_operation().unwrap().is_none());
+    }
+
+    #[test]
+    fn a_rejected_operation_holds_back_its_row_only() {
+        let store = Store::open_in_memory().unwrap();
+        let rejected = store.enqueue(update("t1")).unwrap();
+        let same_row = store.enqueue(update("t1")).unwrap();
+        let other_row = store.enqueue(update("t2")).unwrap();
+        let other_table = store
+            .enqueue(op(None, Table::TimeBlocks, Action::Update, "t1"))
+            .unwrap();
+
+        store.fail_operation(&rejected.id, "400: bad").unwrap();
+        assert_eq!(store.next_operation().unwrap().unwrap().id, other_row.id);
+        store.complete_operation(&other_row.id).unwrap();
+        assert_eq!(store.next_operation().unwrap().unwrap().id, other_table.id);
+        store.complete_operation(&other_table.id).unwrap();
+        assert!(store.next_operation().unwrap().is_none());
+
+        // Retrying the rejected one puts it back at the head of its row.
+        store.retry_operation(&rejected.id).unwrap();
+        assert_eq!(store.next_operation().unwrap().unwrap().id, rejected.id);
+        store.complete_operation(&rejected.id).unwrap();
+        assert_eq!(store.next_operation().unwrap().unwrap().id, same_row.id);
+    }
+
+    #[test]
+    fn discarding_a_rejected_operation_releases_its_row() {
+        let store = Store::open_in_memory().unwrap();
+        let rejected = store.enqueue(update("t1")).unwrap();
+        let behind = store.enqueue(update("t1")).unwrap();
+        store.fail_operation(&rejected.id, "400: bad").unwrap();
+        assert!(store.next_operation().unwrap().is_none());
+
+        store.discard_operation(&rejected.id).unwrap();
+        assert_eq!(store.next_operation().unwrap().unwrap().id, behind.id);
+    }
+
+    #[test]
+    fn in_flight_operations_are_recovered_as_pending() {
+        let store = Store::open_in_memory().unwrap();
+        let queued = store.enqueue(update("t1")).unwrap();
+        store.mark_in_flight(&queued.id).unwrap();
+        let parked = store.enqueue(update("t2")).unwrap();
+        store.fail_operation(&parked.id, "400: bad").unwrap();
+
+        store.recover_in_flight().unwrap();
+        let ops = store.queued_operations().unwrap();
+        assert_eq!(ops[0].status, OperationStatus::Pending);
+        assert_eq!(ops[0].attempts, 1);
+        assert_eq!(ops[1].status, OperationStatus::Failed);
+    }
+
+    #[test]
+    fn a_transient_failure_waits_for_the_backoff() {
+        let store = Store::open_in_memory().unwrap();
+        let queued = store.enqueue(update("t1")).unwrap();
+        store.mark_in_flight(&queued.id).unwrap();
+        let op = QueuedOperation {
+            attempts: 3,
+            ..queued
+        };
+        let before = Utc::now();
+        let next = store.retry_operation_later(&op, "503").unwrap();
+        assert!(next >= before + backoff(3));
+
+        let waiting = store.next_operation().unwrap().unwrap();
+        assert_eq!(waiting.status, OperationStatus::Pending);
+        assert_eq!(waiting.last_error.as_deref(), Some("503"));
+        assert!(waiting.next_attempt_at > Utc::now());
+
+        store.retry_all_now().unwrap();
+        assert!(store.next_operation().unwrap().unwrap().next_attempt_at <= Utc::now());
+    }
+}
@@ -0,0 +1,490 @@
+//! Writing a recurrence's occurrences into the store. Occurrences are placed
+//! by their wall-clock time in the recurrence's zone, so a 9:00 block stays
+//! at 9:00 on both sides of a DST change, and a time skipped by the change
+//! is read with the offset from before it. Windows are by an occurrence's
+//! original date; a moved occurrence still belongs to the day it was moved
+//! from.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use rusqlite::params;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use super::{Recurrence, RecurrenceKind};
+use crate::error::Result;
+use crate::schedule::time::zoned_instant;
+use crate::store::{Store, Task, TimeBlock};
+
+/// Tag on materialized tasks, next to their `due:` date.
+const TASK_TAG: &str = "recurring";
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Materialized {
+    /// Rows created or changed.
+    pub saved: usize,
+    /// Rows of occurrences that no longer exist.
+    pub removed: usize,
+}
+
+/// The id of the row an occurrence becomes, the same on every device.
+pub fn occurrence_id(recurrence_id: &str, occurrence: NaiveDateTime) -> String {
+    let name = format!(
+        "recurrence:{recurrence_id}/{}",
+        occurrence.format("%Y%m%dT%H%M%S")
+    );
+    uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, name.as_bytes()).to_string()
+}
+
+fn merge(target: &mut Value, changes: &Value) {
+    if let (Some(target), Some(changes)) = (target.as_object_mut(), changes.as_object()) {
+        for (key, value) in changes {
+            target.insert(key.clone(), value.clone());
+        }
+    }
+}
+
+fn due_tag(date: NaiveDate) -> String {
+    format!("due:{date}")
+}
+
+impl Recurrence {
+    fn to_utc(&self) -> impl Fn(NaiveDateTime) -> DateTime<Utc> {
+        let zone = self.timezone.parse::<Tz>().ok();
+        move |local| match &zone {
+            Some(tz) => zoned_instant(tz, local),
+            None => zoned_instant(&Local, local),
+        }
+    }
+
+    /// Original wall-clock starts of the occurrences on `from..=to`.
+    fn occurrences(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDateTime> {
+        let to_utc = self.to_utc();
+        self.rule
+            .occurrences(self.start, &to_utc)
+            .take_while(|at| at.date() <= to)
+            .filter(|at| at.date() >= from && !self.exdates.contains(at))
+            .collect()
+    }
+
+    /// The template with an occurrence's override applied.
+    fn row(&self, occurrence: NaiveDateTime) -> Value {
+        let mut row = if self.template.is_object() {
+            self.template.clone()
+        } else {
+            json!({})
+        };
+        if let Some(change) = self.override_for(occurrence) {
+            merge(&mut row, &change.changes);
+        }
+        let now = Utc::now();
+        merge(
+            &mut row,
+            &json!({
+                "id": occurrence_id(&self.id, occurrence),
+                "user_id": self.user_id,
+                "created_at": now,
+                "updated_at": now,
+            }),
+        );
+        row
+    }
+
+    fn block(&self, occurrence: NaiveDateTime) -> Result<TimeBlock> {
+        let change = self.override_for(occurrence);
+        let start = change.and_then(|change| change.start).unwrap_or(occurrence);
+        let minutes = change
+            .and_then(|change| change.duration_minutes)
+            .or(self.duration_minutes)
+            .unwrap_or(0);
+        // The length is elapsed time, so a block starting in a skipped hour
+        // isn't cut short by the jump.
+        let start_time = self.to_utc()(start);
+
+        let mut row = self.row(occurrence);
+        merge(
+            &mut row,
+            &json!({
+                "start_time": start_time,
+                "end_time": start_time + Duration::minutes(minutes),
+            }),
+        );
+        if row.get("source").is_none() {
+            row["source"] = json!("manual");
+        }
+        if !row["metadata"].is_object() {
+            row["metadata"] = json!({});
+        }
+        row["metadata"]["recurrence"] = json!({ "id": self.id, "occurrence": occurrence });
+        Ok(serde_json::from_value(row)?)
+    }
+
+    fn task(&self, occurrence: NaiveDateTime) -> Result<Task> {
+        let mut row = self.row(occurrence);
+        if row.get("source").is_none() {
+            row["source"] = json!("manual");
+        }
+        if row.get("status").is_none() {
+            row["status"] = json!("backlog");
+        }
+        row["source_id"] = json!(self.id);
+        let mut task: Task = serde_json::from_value(row)?;
+        task.tags.retain(|tag| !tag.starts_with("due:"));
+        task.tags.push(due_tag(occurrence.date()));
+        if !task.tags.iter().any(|tag| tag == TASK_TAG) {
+            task.tags.push(TASK_TAG.into());
+        }
+        Ok(task)
+    }
+}
+
+/// Save `block` unless the stored occurrence already matches it. What the
+/// user did to the occurrence (completing it, assigning emails, attaching
+/// it to a schedule) is kept; fields from the rule win.
+fn save_block(store: &Store, mut block: TimeBlock) -> Result<bool> {
+    if let Some(existing) = store.get_time_block(&block.id)? {
+        let mut metadata = existing.metadata.clone();
+        if !metadata.is_object() {
+            metadata = json!({});
+        }
+        metadata["recurrence"] = block.metadata["recurrence"].take();
+        block.metadata = metadata;
+        if block.assigned_tasks.is_empty() {
+            block.assigned_tasks = existing.assigned_tasks.clone();
+        }
+        block.assigned_emails = existing.assigned_emails.clone();
+        block.daily_schedule_id = existing.daily_schedule_id.clone();
+        block.conflict_group = existing.conflict_group;
+        block.created_at = existing.created_at;
+        block.updated_at = existing.updated_at;
+        if serde_json::to_value(&block)? == serde_json::to_value(&existing)? {
+            return Ok(false);
+        }
+        block.updated_at = Utc::now();
+    }
+    store.save_time_block(&block)?;
+    Ok(true)
+}
+
+/// Like [`save_block`], keeping a task's progress.
+fn save_task(store: &Store, mut task: Task) -> Result<bool> {
+    if let Some(existing) = store.get_task(&task.id)? {
+        task.completed = existing.completed;
+        task.status = existing.status.clone();
+        task.score = existing.score;
+        task.urgency = existing.urgency;
+        task.days_in_backlog = existing.days_in_backlog;
+        task.created_at = existing.created_at;
+        task.updated_at = existing.updated_at;
+        if serde_json::to_value(&task)? == serde_json::to_value(&existing)? {
+            return Ok(false);
+        }
+        task.updated_at = Utc::now();
+    }
+    store.save_task(&task)?;
+    Ok(true)
+}
+
+/// Delete the unfinished rows of `recurrence` on `from..=to` whose ids
+/// aren't in `keep`.
+fn remove_stale(
+    store: &Store,
+    recurrence: &Recurrence,
+    from: NaiveDate,
+    to: NaiveDate,
+    keep: &HashSet<String>,
+) -> Result<usize> {
+    let stale: Vec<String> = match recurrence.kind {
+        RecurrenceKind::TimeBlock => {
+            // By the occurrence's own date, like the window it was saved
+            // for, not by where a move put it.
+            let after = to.succ_opt().unwrap_or(to);
+            let conn = store.conn();
+            let mut stmt = conn.prepare(
+                "SELECT id FROM time_blocks
+                 WHERE json_extract(metadata, '$.recurrence.id') = ?1
+                   AND json_extract(metadata, '$.completedAt') IS NULL
+                   AND json_extract(metadata, '$.recurrence.occurrence') >= ?2
+                   AND json_extract(metadata, '$.recurrence.occurrence') < ?3",
+            )?;
+            let ids = stmt.query_map(params![recurrence.id, from, after], |row| row.get(0))?;
+            ids.collect::<rusqlite::Result<Vec<String>>>()?
+        }
+        RecurrenceKind::Task => {
+            let days: HashSet<String> = from
+                .iter_days()
+                .take_while(|date| *date <= to)
+                .map(due_tag)
+                .collect();
+            let conn = store.conn();
+            let mut stmt =
+                conn.prepare("SELECT id, tags FROM tasks WHERE source_id = ?1 AND completed = 0")?;
+            let rows = stmt.query_map([&recurrence.id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, Value>(1)?))
+            })?;
+            let mut ids = Vec::new();
+            for row in rows {
+                let (id, tags) = row?;
+                let in_window = tags.as_array().is_some_and(|tags| {
+                    tags.iter()
+                        .filter_map(Value::as_str)
+                        .any(|tag| days.contains(tag))
+                });
+                if in_window {
+                    ids.push(id);
+                }
+            }
+            ids
+        }
+    };
+
+    let mut removed = 0;
+    for id in stale.into_iter().filter(|id| !keep.contains(id)) {
+        match recurrence.kind {
+            RecurrenceKind::TimeBlock => store.delete_time_block(&id)?,
+            RecurrenceKind::Task => store.delete_task(&id)?,
+        }
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Delete every unfinished occurrence of `recurrence` on `from..=to`.
+pub(super) fn remove_occurrences(
+    store: &Store,
+    recurrence: &Recurrence,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<usize> {
+    remove_stale(store, recurrence, from, to, &HashSet::new())
+}
+
+impl Recurrence {
+    /// Bring this recurrence's rows on `from..=to` in line with the rule.
+    pub fn materialize(
+        &self,
+        store: &Store,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Materialized> {
+        let mut done = Materialized::default();
+        let mut keep = HashSet::new();
+        for occurrence in self.occurrences(from, to) {
+            let saved = match self.kind {
+                RecurrenceKind::TimeBlock => {
+                    let block = self.block(occurrence)?;
+                    keep.insert(block.id.clone());
+                    save_block(store, block)?
+                }
+                RecurrenceKind::Task => {
+                    let task = self.task(occurrence)?;
+                    keep.insert(task.id.clone());
+                    save_task(store, task)?
+                }
+            };
+            if saved {
+                done.saved += 1;
+            }
+        }
+        done.removed = remove_stale(store, self, from, to, &keep)?;
+        Ok(done)
+    }
+}
+
+/// Materialize every recurrence over the local days `from..=to`. A broken
+/// recurrence is logged and skipped so it can't hold up the others.
+pub fn materialize(store: &Store, from: NaiveDate, to: NaiveDate) -> Result<Materialized> {
+    let mut done = Materialized::default();
+    for recurrence in store.list_recurrences()? {
+        match recurrence.materialize(store, from, to) {
+            Ok(one) => {
+                done.saved += one.saved;
+                done.removed += one.removed;
+            }
+            Err(err) => log::error!("failed to materialize recurrence {}: {err}", recurrence.id),
+        }
+    }
+    Ok(done)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recurrence::Override;
+    use chrono::TimeZone;
+
+    fn local(m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2026, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    fn utc(m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, m, d, h, min, 0).unwrap()
+    }
+
+    fn day(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, m, d).unwrap()
+    }
+
+    /// A daily half-hour block in New York, where the clocks go forward on
+    /// 8 March 2026 and back on 1 November.
+    fn daily(start: NaiveDateTime) -> Recurrence {
+        Recurrence {
+            id: "r1".into(),
+            user_id: "u1".into(),
+            kind: RecurrenceKind::TimeBlock,
+            rule: "FREQ=DAILY".parse().unwrap(),
+            start,
+            timezone: "America/New_York".into(),
+            duration_minutes: Some(30),
+            template: json!({ "title": "Standup", "type": "meeting" }),
+            exdates: Vec::new(),
+            overrides: Vec::new(),
+            created_at: utc(3, 1, 0, 0),
+            updated_at: utc(3, 1, 0, 0),
+        }
+    }
+
+    fn spans(store: &Store) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        store
+            .list_time_blocks(utc(1, 1, 0, 0), utc(12, 31, 0, 0))
+            .unwrap()
+            .iter()
+            .map(|block| (block.start_time, block.end_time))
+            .collect()
+    }
+
+    #[test]
+    fn keeps_wall_clock_times_and_drops_exdates_across_spring_forward() {
+        let store = Store::open_in_memory().unwrap();
+        let mut recurrence = daily(local(3, 6, 9, 0));
+        let done = recurrence
+            .materialize(&store, day(3, 6), day(3, 10))
+            .unwrap();
+        assert_eq!((done.saved, done.removed), (5, 0));
+        let nine_am: Vec<_> = [(6, 14), (7, 14), (8, 13), (9, 13), (10, 13)]
+            .into_iter()
+            .map(|(d, h)| (utc(3, d, h, 0), utc(3, d, h, 30)))
+            .collect();
+        assert_eq!(spans(&store), nine_am);
+
+        // Nothing changed, so nothing is saved again.
+        let done = recurrence
+            .materialize(&store, day(3, 6), day(3, 10))
+            .unwrap();
+        assert_eq!((done.saved, done.removed), (0, 0));
+
+        // Skipping the day of the change removes just that block.
+        recurrence.exdates.push(local(3, 8, 9, 0));
+        let done = recurrence
+            .materialize(&store, day(3, 6), day(3, 10))
+            .unwrap();
+        assert_eq!((done.saved, done.removed), (0, 1));
+        let mut left = nine_am.clone();
+        left.remove(2);
+        assert_eq!(spans(&store), left);
+        assert!(store
+            .get_time_block(&occurrence_id("r1", local(3, 8, 9, 0)))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn places_skipped_and_repeated_times() {
+        let store = Store::open_in_memory().unwrap();
+        let mut recurrence = daily(local(3, 7, 2, 30));
+        recurrence.duration_minutes = Some(60);
+        recurrence
+            .materialize(&store, day(3, 7), day(3, 9))
+            .unwrap();
+        // 02:30 doesn't exist on 8 March; it's read with the offset from
+        // before the jump, and the block still lasts an hour.
+        assert_eq!(
+            spans(&store),
+            [
+                (utc(3, 7, 7, 30), utc(3, 7, 8, 30)),
+                (utc(3, 8, 7, 30), utc(3, 8, 8, 30)),
+                (utc(3, 9, 6, 30), utc(3, 9, 7, 30)),
+            ]
+        );
+
+        let store = Store::open_in_memory().unwrap();
+        let mut recurrence = daily(local(10, 31, 1, 30));
+        recurrence.duration_minutes = Some(60);
+        recurrence
+            .materialize(&store, day(10, 31), day(11, 2))
+            .unwrap();
+        // 01:30 happens twice on 1 November; the first one counts.
+        assert_eq!(
+            spans(&store),
+            [
+                (utc(10, 31, 5, 30), utc(10, 31, 6, 30)),
+                (utc(11, 1, 5, 30), utc(11, 1, 6, 30)),
+                (utc(11, 2, 6, 30), utc(11, 2, 7, 30)),
+            ]
+        );
+    }
+
+    #[test]
+    fn applies_overrides_across_the_change() {
+        let store = Store::open_in_memory().unwrap();
+        let mut recurrence = daily(local(3, 6, 9, 0));
+        // Monday's standup moves into Sunday's skipped hour, runs longer and
+        // is renamed.
+        recurrence.overrides.push(Override {
+            occurrence: local(3, 9, 9, 0),
+            start: Some(local(3, 8, 2, 30)),
+            duration_minutes: Some(45),
+            changes: json!({ "title": "Early standup" }),
+        });
+        // An override of a skipped occurrence doesn't bring it back.
+        recurrence.exdates.push(local(3, 10, 9, 0));
+        recurrence.overrides.push(Override {
+            occurrence: local(3, 10, 9, 0),
+            start: None,
+            duration_minutes: None,
+            changes: json!({ "title": "Skipped anyway" }),
+        });
+        recurrence
+            .materialize(&store, day(3, 8), day(3, 10))
+            .unwrap();
+
+        let blocks = store
+            .list_time_blocks(utc(3, 1, 0, 0), utc(3, 31, 0, 0))
+            .unwrap();
+        let titles: Vec<_> = blocks
+            .iter()
+            .map(|block| (block.title.as_str(), block.start_time, block.end_time))
+            .collect();
+        assert_eq!(
+            titles,
+            [
+                ("Early standup", utc(3, 8, 7, 30), utc(3, 8, 8, 15)),
+                ("Standup", utc(3, 8, 13, 0), utc(3, 8, 13, 30)),
+            ]
+        );
+        let moved = &blocks[0];
+        assert_eq!(moved.id, occurrence_id("r1", local(3, 9, 9, 0)));
+        assert_eq!(
+            moved.metadata["recurrence"],
+            json!({ "id": "r1", "occurrence": local(3, 9, 9, 0) })
+        );
+
+        // The moved block belongs to the day it was moved from, so it's
+        // neither saved nor removed with the day it was moved to.
+        let done = recurrence
+            .materialize(&store, day(3, 8), day(3, 8))
+            .unwrap();
+        assert_eq!((done.saved, done.removed), (0, 0));
+        let fresh = Store::open_in_memory().unwrap();
+        recurrence
+            .materialize(&fresh, day(3, 8), day(3, 8))
+            .unwrap();
+        assert_eq!(spans(&fresh), [(utc(3, 8, 13, 0), utc(3, 8, 13, 30))]);
+    }
+}
@@ -0,0 +1,325 @@
+//! Recurring time blocks and tasks. A recurrence is an RFC 5545 RRULE over
+//! wall-clock times in an IANA zone, with EXDATEs and per-occurrence
+//! overrides, plus a template for the rows it produces. Occurrences are
+//! materialized into `time_blocks` and `tasks` for a window of days, under
+//! ids derived from the recurrence and the occurrence, so materializing
+//! again updates rows instead of duplicating them, and the sync engine
+//! uploads them like any other row.
+//!
+//! Recurrences themselves live only in the local store.
+
+mod materialize;
+
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::error::{Error, Result};
+use crate::rrule::RRule;
+use crate::store::{json_column, to_json, Store};
+
+pub use materialize::{materialize, occurrence_id, Materialized};
+
+/// How many days ahead occurrences are kept materialized.
+pub const HORIZON_DAYS: i64 = 14;
+const REFRESH_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecurrenceKind {
+    TimeBlock,
+    Task,
+}
+
+impl RecurrenceKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            RecurrenceKind::TimeBlock => "time_block",
+            RecurrenceKind::Task => "task",
+        }
+    }
+}
+
+/// A change to one occurrence, keyed by its original start.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Override {
+    pub occurrence: NaiveDateTime,
+    /// A new wall-clock start, for a moved occurrence.
+    pub start: Option<NaiveDateTime>,
+    pub duration_minutes: Option<i64>,
+    /// Fields merged over the template, e.g. `{"title": "..."}`.
+    #[serde(default)]
+    pub changes: Value,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recurrence {
+    pub id: String,
+    pub user_id: String,
+    pub kind: RecurrenceKind,
+    pub rule: RRule,
+    /// Wall-clock start of the first occurrence, in `timezone`.
+    pub start: NaiveDateTime,
+    pub timezone: String,
+    /// Length of each block; unused for tasks.
+    pub duration_minutes: Option<i64>,
+    /// Fields copied to every occurrence: a partial `time_blocks` or `tasks`
+    /// row.
+    #[serde(default)]
+    pub template: Value,
+    #[serde(default)]
+    pub exdates: Vec<NaiveDateTime>,
+    #[serde(default)]
+    pub overrides: Vec<Override>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Recurrence {
+    const COLUMNS: &'static str = "id, user_id, kind, rule, start, timezone, duration_minutes, \
+        template, exdates, overrides, created_at, updated_at";
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let kind: String = row.get("kind")?;
+        let rule: String = row.get("rule")?;
+        let invalid = |err: String| {
+            rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, err.into())
+        };
+        Ok(Self {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            kind: match kind.as_str() {
+                "time_block" => RecurrenceKind::TimeBlock,
+                "task" => RecurrenceKind::Task,
+                other => return Err(invalid(format!("unknown recurrence kind {other:?}"))),
+            },
+            rule: rule.parse().map_err(invalid)?,
+            start: row.get("start")?,
+            timezone: row.get("timezone")?,
+            duration_minutes: row.get("duration_minutes")?,
+            template: row.get("template")?,
+            exdates: json_column(row, "exdates")?,
+            overrides: json_column(row, "overrides")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+
+    fn check(&self) -> Result<()> {
+        let invalid = |message: &str| Err(Error::Recurrence(message.into()));
+        if !self.template.is_object() {
+            return invalid("the template must be an object");
+        }
+        if !self.template["title"]
+            .as_str()
+            .is_some_and(|title| !title.trim().is_empty())
+        {
+            return invalid("the template needs a title");
+        }
+        if self.kind == RecurrenceKind::TimeBlock {
+            if !self.duration_minutes.is_some_and(|minutes| minutes > 0) {
+                return invalid("a recurring block needs a positive duration");
+            }
+            if !self.template["type"].is_string() {
+                return invalid("a recurring block's template needs a type");
+            }
+        }
+        Ok(())
+    }
+
+    fn override_for(&self, occurrence: NaiveDateTime) -> Option<&Override> {
+        self.overrides
+            .iter()
+            .find(|change| change.occurrence == occurrence)
+    }
+}
+
+impl Store {
+    pub fn list_recurrences(&self) -> Result<Vec<Recurrence>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM recurrences ORDER BY created_at",
+            Recurrence::COLUMNS
+        ))?;
+        let rows = stmt.query_map([], Recurrence::from_row)?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn get_recurrence(&self, id: &str) -> Result<Option<Recurrence>> {
+        Ok(self
+            .conn()
+            .query_row(
+                &format!(
+                    "SELECT {} FROM recurrences WHERE id = ?1",
+                    Recurrence::COLUMNS
+                ),
+                [id],
+                Recurrence::from_row,
+            )
+            .optional()?)
+    }
+
+    pub fn save_recurrence(&self, recurrence: &Recurrence) -> Result<()> {
+        self.conn().execute(
+            &format!(
+                "INSERT INTO recurrences ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                 ON CONFLICT (id) DO UPDATE SET
+                   user_id = excluded.user_id, kind = excluded.kind, rule = excluded.rule,
+                   start = excluded.start, timezone = excluded.timezone,
+                   duration_minutes = excluded.duration_minutes, template = excluded.template,
+                   exdates = excluded.exdates, overrides = excluded.overrides,
+                   updated_at = excluded.updated_at",
+                Recurrence::COLUMNS
+            ),
+            params![
+                recurrence.id,
+                recurrence.user_id,
+                recurrence.kind.as_str(),
+                recurrence.rule.to_string(),
+                recurrence.start,
+                recurrence.timezone,
+                recurrence.duration_minutes,
+                recurrence.template,
+                to_json(&recurrence.exdates)?,
+                to_json(&recurrence.overrides)?,
+                recurrence.created_at,
+                recurrence.updated_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_recurrence(&self, id: &str) -> Result<()> {
+        self.conn()
+            .execute("DELETE FROM recurrences WHERE id = ?1", [id])?;
+        Ok(())
+    }
+}
+
+/// The days from today in the user's zone that are kept materialized.
+fn horizon(store: &Store) -> Result<(NaiveDate, NaiveDate)> {
+    let today = store.today()?;
+    Ok((today, today + Duration::days(HORIZON_DAYS - 1)))
+}
+
+/// Materialize the coming days and reschedule reminders for the result.
+pub fn refresh<R: Runtime>(app: &AppHandle<R>) -> Result<Materialized> {
+    let store = app.state::<Store>();
+    let (from, to) = horizon(&store)?;
+    let done = materialize(&store, from, to)?;
+    crate::reminders::reschedule(app);
+    Ok(done)
+}
+
+/// Materialize now, then hourly so the horizon keeps moving forward.
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(err) = refresh(&handle) {
+                log::error!("failed to materialize recurrences: {err}");
+            }
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+        }
+    });
+    Ok(())
+}
+
+fn recurrence(store: &Store, id: &str) -> Result<Recurrence> {
+    store
+        .get_recurrence(id)?
+        .ok_or_else(|| Error::NotFound(format!("recurrence {id}")))
+}
+
+#[tauri::command]
+pub fn recurrence_list(store: State<'_, Store>) -> Result<Vec<Recurrence>> {
+    store.list_recurrences()
+}
+
+/// Create or replace a recurrence and materialize the coming days.
+#[tauri::command]
+pub fn recurrence_save<R: Runtime>(
+    app: AppHandle<R>,
+    mut recurrence: Recurrence,
+    store: State<'_, Store>,
+) -> Result<Materialized> {
+    recurrence.check()?;
+    recurrence.updated_at = Utc::now();
+    store.save_recurrence(&recurrence)?;
+    refresh(&app)
+}
+
+/// Delete a recurrence with its upcoming occurrences; past ones stay.
+#[tauri::command]
+pub fn recurrence_delete<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+    store: State<'_, Store>,
+) -> Result<Materialized> {
+    let existing = recurrence(&store, &id)?;
+    store.delete_recurrence(&id)?;
+    let (from, to) = horizon(&store)?;
+    let removed = materialize::remove_occurrences(&store, &existing, from, to)?;
+    crate::reminders::reschedule(&app);
+    Ok(Materialized { saved: 0, removed })
+}
+
+/// Skip one occurrence by adding it to the EXDATEs.
+#[tauri::command]
+pub fn recurrence_skip<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+    occurrence: NaiveDateTime,
+    store: State<'_, Store>,
+) -> Result<Materialized> {
+    let mut recurrence = recurrence(&store, &id)?;
+    if !recurrence.exdates.contains(&occurrence) {
+        recurrence.exdates.push(occurrence);
+        recurrence.exdates.sort();
+    }
+    recurrence
+        .overrides
+        .retain(|change| change.occurrence != occurrence);
+    recurrence.updated_at = Utc::now();
+    store.save_recurrence(&recurrence)?;
+    refresh(&app)
+}
+
+/// Change one occurrence, replacing any earlier override of it.
+#[tauri::command]
+pub fn recurrence_override<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+    change: Override,
+    store: State<'_, Store>,
+) -> Result<Materialized> {
+    let mut recurrence = recurrence(&store, &id)?;
+    if !change.changes.is_null() && !change.changes.is_object() {
+        return Err(Error::Recurrence("changes must be an object".into()));
+    }
+    recurrence
+        .overrides
+        .retain(|existing| existing.occurrence != change.occurrence);
+    recurrence.overrides.push(change);
+    recurrence.overrides.sort_by_key(|change| change.occurrence);
+    recurrence.updated_at = Utc::now();
+    store.save_recurrence(&recurrence)?;
+    refresh(&app)
+}
+
+/// Materialize every recurrence over the local days `from..=to`.
+#[tauri::command]
+pub fn recurrence_materialize<R: Runtime>(
+    app: AppHandle<R>,
+    from: NaiveDate,
+    to: NaiveDate,
+    store: State<'_, Store>,
+) -> Result<Materialized> {
+    let done = materialize(&store, from, to)?;
+    crate::reminders::reschedule(&app);
+    Ok(done)
+}
@@ -0,0 +1,489 @@
+//! Block-start reminders owned by the backend, so they keep firing when the
+//! webview reloads, crashes or is throttled in the background.
+//!
+//! The scheduler loads the next day of focus, meeting and email blocks from
+//! the local store and shows an OS notification `lead_minutes` before each.
+//! On Linux the notification carries Start, Snooze and Skip actions; the
+//! choice comes back to the webview as a [`ACTION_EVENT`].
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+use tokio::sync::Notify;
+
+use crate::error::Result;
+use crate::store::{BlockType, Store, TimeBlock};
+
+pub const ACTION_EVENT: &str = "reminder://action";
+
+const LEAD_MINUTES_SETTING: &str = "reminders.lead_minutes";
+const DEFAULT_LEAD_MINUTES: i64 = 5;
+const SNOOZE_MINUTES: i64 = 5;
+/// How far ahead to load blocks, and how often to reload regardless.
+const HORIZON: Duration = Duration::hours(24);
+const RELOAD_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReminderAction {
+    Start,
+    Snooze,
+    Skip,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionEvent {
+    pub block_id: String,
+    pub action: ReminderAction,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    pub block_id: String,
+    pub title: String,
+    pub block_type: BlockType,
+    pub starts_at: DateTime<Utc>,
+    pub fire_at: DateTime<Utc>,
+}
+
+#[derive(Default)]
+struct Inner {
+    lead_minutes: i64,
+    pending: Vec<Reminder>,
+    /// Blocks already reminded about or skipped; kept so a reload doesn't
+    /// remind twice.
+    done: HashSet<(String, DateTime<Utc>)>,
+    snoozed: Vec<Reminder>,
+}
+
+pub struct Reminders {
+    inner: Mutex<Inner>,
+    changed: Notify,
+}
+
+impl Default for Reminders {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                lead_minutes: DEFAULT_LEAD_MINUTES,
+                ..Inner::default()
+            }),
+            changed: Notify::new(),
+        }
+    }
+}
+
+fn wants_reminder(block: &TimeBlock) -> bool {
+    matches!(
+        block.block_type,
+        BlockType::Focus | BlockType::Meeting | BlockType::Email
+    )
+}
+
+impl Reminders {
+    /// Rebuild the pending reminders from `blocks`. Moved blocks get a new
+    /// reminder; deleted ones lose theirs.
+    pub fn load(&self, blocks: &[TimeBlock], now: DateTime<Utc>) {
+        let mut inner = self.inner.lock().unwrap();
+        // Only blocks starting after `now` are loaded, so older marks can
+        // never match again.
+        inner
+            .done
+            .retain(|(_, starts_at)| *starts_at > now - HORIZON);
+        let lead = Duration::minutes(inner.lead_minutes);
+        let mut pending: Vec<Reminder> = blocks
+            .iter()
+            .filter(|block| wants_reminder(block) && block.start_time > now)
+            .filter(|block| !inner.done.contains(&(block.id.clone(), block.start_time)))
+            .map(|block| Reminder {
+                block_id: block.id.clone(),
+                title: block.title.clone(),
+                block_type: block.block_type,
+                starts_at: block.start_time,
+                fire_at: block.start_time - lead,
+            })
+            .collect();
+        pending.sort_by_key(|reminder| reminder.fire_at);
+        inner.pending = pending;
+        inner
+            .snoozed
+            .retain(|snoozed| blocks.iter().any(|block| block.id == snoozed.block_id));
+        drop(inner);
+        self.changed.notify_one();
+    }
+
+    pub fn set_lead_minutes(&self, minutes: i64) {
+        self.inner.lock().unwrap().lead_minutes = minutes.max(0);
+    }
+
+    pub fn upcoming(&self) -> Vec<Reminder> {
+        let inner = self.inner.lock().unwrap();
+        let mut all: Vec<Reminder> = inner
+            .pending
+            .iter()
+            .chain(&inner.snoozed)
+            .cloned()
+            .collect();
+        all.sort_by_key(|reminder| reminder.fire_at);
+        all
+    }
+
+    /// Take every reminder due at `now`.
+    fn take_due(&self, now: DateTime<Utc>) -> Vec<Reminder> {
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+        let mut due = Vec::new();
+        for list in [&mut inner.pending, &mut inner.snoozed] {
+            let (ready, later): (Vec<_>, Vec<_>) =
+                list.drain(..).partition(|reminder| reminder.fire_at <= now);
+            *list = later;
+            due.extend(ready);
+        }
+        for reminder in &due {
+            inner
+                .done
+                .insert((reminder.block_id.clone(), reminder.starts_at));
+        }
+        due
+    }
+
+    fn next_fire(&self) -> Option<DateTime<Utc>> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .pending
+            .iter()
+            .chain(&inner.snoozed)
+            .map(|reminder| reminder.fire_at)
+            .min()
+    }
+
+    /// Remind again in a few minutes instead of at the usual time, even if
+    /// the reminder hasn't fired yet.
+    fn snooze(&self, reminder: Reminder, now: DateTime<Utc>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .pending
+            .retain(|pending| pending.block_id != reminder.block_id);
+        inner
+            .snoozed
+            .retain(|snoozed| snoozed.block_id != reminder.block_id);
+        inner
+            .done
+            .insert((reminder.block_id.clone(), reminder.starts_at));
+        inner.snoozed.push(Reminder {
+            fire_at: now + Duration::minutes(SNOOZE_MINUTES),
+            ..reminder
+        });
+        drop(inner);
+        self.changed.notify_one();
+    }
+
+    fn skip(&self, reminder: &Reminder) {
+        let mut inner = self.inner.lock().unwrap();
+        inner
+            .pending
+            .retain(|pending| pending.block_id != reminder.block_id);
+        inner
+            .snoozed
+            .retain(|snoozed| snoozed.block_id != reminder.block_id);
+        inner
+            .done
+            .insert((reminder.block_id.clone(), reminder.starts_at));
+    }
+}
+
+/// Reload reminders from the store, e.g. after blocks were saved or synced.
+pub fn reschedule<R: Runtime>(app: &AppHandle<R>) {
+    let now = Utc::now();
+    match app.state::<Store>().list_time_blocks(now, now + HORIZON) {
+        Ok(blocks) => app.state::<Reminders>().load(&blocks, now),
+        Err(err) => log::error!("failed to load blocks for reminders: {err}"),
+    }
+}
+
+/// Apply a reminder action and tell the webview about it.
+pub fn handle_action<R: Runtime>(app: &AppHandle<R>, reminder: Reminder, action: ReminderAction) {
+    let reminders = app.state::<Reminders>();
+    match action {
+        ReminderAction::Snooze => reminders.snooze(reminder.clone(), Utc::now()),
+        ReminderAction::Skip => reminders.skip(&reminder),
+        ReminderAction::Start => {}
+    }
+    let event = ActionEvent {
+        block_id: reminder.block_id,
+        action,
+    };
+    if let Err(err) = app.emit(ACTION_EVENT, event) {
+        log::warn!("failed to emit reminder action: {err}");
+    }
+}
+
+fn notify<R: Runtime>(app: &AppHandle<R>, reminder: Reminder) {
+    let minutes = (reminder.starts_at - Utc::now()).num_minutes().max(0);
+    let body = match minutes {
+        0 => "Starting now".to_owned(),
+        1 => "Starts in 1 minute".to_owned(),
+        n => format!("Starts in {n} minutes"),
+    };
+
+    let mut notification = notify_rust::Notification::new();
+    notification
+        .appname("Dayli")
+        .summary(&reminder.title)
+        .body(&body);
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        notification
+            .action("start", "Start")
+            .action("snooze", "Snooze 5m")
+            .action("skip", "Skip");
+        let app = app.clone();
+        // Waiting for the user's choice blocks, so it gets its own thread.
+        std::thread::spawn(move || match notification.show() {
+            Ok(handle) => handle.wait_for_action(|action| {
+                let action = match action {
+                    "start" | "default" => ReminderAction::Start,
+                    "snooze" => ReminderAction::Snooze,
+                    "skip" => ReminderAction::Skip,
+                    _ => return,
+                };
+                handle_action(&app, reminder, action);
+            }),
+            Err(err) => log::error!("failed to show reminder: {err}"),
+        });
+    }
+
+    #[cfg(not(all(unix, not(target_os = "macos"))))]
+    {
+        let _ = (app, reminder);
+        if let Err(err) = notification.show() {
+            log::error!("failed to show reminder: {err}");
+        }
+    }
+}
+
+/// Manage the scheduler and start its background task.
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    let reminders = Reminders::default();
+    if let Some(minutes) = app.state::<Store>().setting(LEAD_MINUTES_SETTING)? {
+        reminders.set_lead_minutes(minutes);
+    }
+    app.manage(reminders);
+    reschedule(app.handle());
+
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let mut last_reload = tokio::time::Instant::now();
+        loop {
+            let reminders = handle.state::<Reminders>();
+            if last_reload.elapsed() >= RELOAD_INTERVAL {
+                reschedule(&handle);
+                last_reload = tokio::time::Instant::now();
+            }
+
+            for reminder in reminders.take_due(Utc::now()) {
+                notify(&handle, reminder);
+            }
+
+            let wait = reminders
+                .next_fire()
+                .and_then(|at| (at - Utc::now()).to_std().ok())
+                .unwrap_or(RELOAD_INTERVAL)
+                .min(RELOAD_INTERVAL);
+            let _ = tokio::time::timeout(wait, reminders.changed.notified()).await;
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn reminders_upcoming(reminders: State<'_, Reminders>) -> Vec<Reminder> {
+    reminders.upcoming()
+}
+
+#[tauri::command]
+pub fn reminders_set_lead_minutes<R: Runtime>(
+    app: AppHandle<R>,
+    minutes: i64,
+    store: State<'_, Store>,
+) -> Result<()> {
+    store.set_setting(LEAD_MINUTES_SETTING, &minutes)?;
+    app.state::<Reminders>().set_lead_minutes(minutes);
+    reschedule(&app);
+    Ok(())
+}
+
+/// Apply an action chosen in the webview rather than on the notification.
+#[tauri::command]
+pub fn reminders_act<R: Runtime>(
+    app: AppHandle<R>,
+    block_id: String,
+    action: ReminderAction,
+) -> Result<()> {
+    let reminder = app
+        .state::<Store>()
+        .get_time_block(&block_id)?
+        .map(|block| Reminder {
+            block_id: block.id,
+            title: block.title,
+            block_type: block.block_type,
+            starts_at: block.start_time,
+            fire_at: Utc::now(),
+        });
+    if let Some(reminder) = reminder {
+        handle_action(&app, reminder, action);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::testing::block;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, hour, minute, 0).unwrap()
+    }
+
+    fn hour_long(id: &str, start: DateTime<Utc>, block_type: BlockType) -> TimeBlock {
+        TimeBlock {
+            block_type,
+            ..block(id, start, start + Duration::hours(1))
+        }
+    }
+
+    fn day() -> Vec<TimeBlock> {
+        vec![
+            hour_long("review", at(14, 0), BlockType::Meeting),
+            hour_long("write", at(10, 0), BlockType::Focus),
+            hour_long("lunch", at(12, 0), BlockType::Break),
+            hour_long("inbox", at(16, 0), BlockType::Email),
+            hour_long("started", at(8, 30), BlockType::Focus),
+        ]
+    }
+
+    fn ids(reminders: &[Reminder]) -> Vec<&str> {
+        reminders
+            .iter()
+            .map(|reminder| reminder.block_id.as_str())
+            .collect()
+    }
+
+    fn pending(reminders: &Reminders) -> Vec<String> {
+        ids(&reminders.upcoming())
+            .into_iter()
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn load_keeps_upcoming_blocks_worth_a_reminder() {
+        let reminders = Reminders::default();
+        reminders.load(&day(), at(9, 0));
+        let upcoming = reminders.upcoming();
+        assert_eq!(ids(&upcoming), ["write", "review", "inbox"]);
+        assert_eq!(upcoming[0].starts_at, at(10, 0));
+        assert_eq!(upcoming[0].fire_at, at(9, 55));
+        assert_eq!(reminders.next_fire(), Some(at(9, 55)));
+
+        reminders.set_lead_minutes(15);
+        reminders.load(&day(), at(9, 0));
+        assert_eq!(reminders.next_fire(), Some(at(9, 45)));
+    }
+
+    #[test]
+    fn take_due_fires_each_reminder_once() {
+        let reminders = Reminders::default();
+        reminders.load(&day(), at(9, 0));
+        assert!(reminders.take_due(at(9, 54)).is_empty());
+        assert_eq!(ids(&reminders.take_due(at(9, 55))), ["write"]);
+        assert!(reminders.take_due(at(9, 56)).is_empty());
+
+        // A reload doesn't bring it back, but moving the block does.
+        reminders.load(&day(), at(9, 56));
+        assert_eq!(pending(&reminders), ["review", "inbox"]);
+        let mut blocks = day();
+        blocks[1] = hour_long("write", at(11, 0), BlockType::Focus);
+        reminders.load(&blocks, at(9, 56));
+        assert_eq!(pending(&reminders), ["write", "review", "inbox"]);
+
+        assert_eq!(
+            ids(&reminders.take_due(at(18, 0))),
+            ["write", "review", "inbox"]
+        );
+        assert!(reminders.upcoming().is_empty());
+    }
+
+    #[test]
+    fn snoozing_reminds_again_later_and_only_then() {
+        let reminders = Reminders::default();
+        reminders.load(&day(), at(9, 0));
+        let write = reminders.take_due(at(9, 55)).remove(0);
+        reminders.snooze(write, at(9, 56));
+        assert!(reminders.take_due(at(10, 0)).is_empty());
+        assert_eq!(ids(&reminders.take_due(at(10, 1))), ["write"]);
+
+        // Snoozed before it fired: the pending reminder goes, so it only
+        // fires at the snoozed time.
+        let review = reminders.upcoming().remove(0);
+        assert_eq!(review.block_id, "review");
+        reminders.snooze(review.clone(), at(13, 0));
+        assert_eq!(reminders.upcoming()[0].fire_at, at(13, 5));
+        reminders.load(&day(), at(13, 1));
+        assert_eq!(ids(&reminders.take_due(at(13, 5))), ["review"]);
+        assert!(reminders.take_due(at(13, 55)).is_empty());
+
+        // Snoozing twice keeps one reminder.
+        reminders.snooze(review.clone(), at(13, 10));
+        reminders.snooze(review, at(13, 12));
+        assert_eq!(pending(&reminders), ["review", "inbox"]);
+        assert_eq!(reminders.upcoming()[0].fire_at, at(13, 17));
+
+        // Deleting the block drops its snoozed reminder.
+        let blocks: Vec<_> = day().into_iter().filter(|b| b.id != "review").collect();
+        reminders.load(&blocks, at(13, 13));
+        assert_eq!(pending(&reminders), ["inbox"]);
+    }
+
+    #[test]
+    fn skipped_reminders_stay_skipped_after_a_reload() {
+        let reminders = Reminders::default();
+        reminders.load(&day(), at(9, 0));
+        let write = reminders.upcoming().remove(0);
+        reminders.skip(&write);
+        assert_eq!(pending(&reminders), ["review", "inbox"]);
+
+        reminders.load(&day(), at(9, 30));
+        assert_eq!(pending(&reminders), ["review", "inbox"]);
+        assert!(reminders.take_due(at(9, 55)).is_empty());
+
+        // Skipping a snoozed reminder cancels it too.
+        let review = reminders.take_due(at(13, 55)).remove(0);
+        reminders.snooze(review.clone(), at(13, 55));
+        reminders.skip(&review);
+        assert!(reminders.take_due(at(14, 0)).is_empty());
+    }
+
+    #[test]
+    fn old_marks_are_pruned() {
+        let reminders = Reminders::default();
+        reminders.load(&day(), at(9, 0));
+        reminders.take_due(at(18, 0));
+        assert_eq!(reminders.inner.lock().unwrap().done.len(), 3);
+
+        let tomorrow = at(10, 30) + Duration::days(1);
+        reminders.load(&[], tomorrow);
+        assert_eq!(reminders.inner.lock().unwrap().done.len(), 2);
+        reminders.load(&[], tomorrow + Duration::hours(8));
+        assert!(reminders.inner.lock().unwrap().done.is_empty());
+    }
+}
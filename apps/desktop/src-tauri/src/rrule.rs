@@ -0,0 +1,870 @@
+//! RFC 5545 recurrence rules, expanded over wall-clock times so a 9am event
+//! stays at 9am across DST changes. Covers what calendars emit in practice:
+//! DAILY to YEARLY frequencies with INTERVAL, COUNT, UNTIL, BYDAY (with
+//! ordinals), BYMONTHDAY, BYMONTH, BYSETPOS and WKST. Sub-daily frequencies
+//! and the other BY parts are rejected rather than silently misread.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, Utc, Weekday};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Give up on a rule after this many periods in a row without an occurrence,
+/// e.g. the 30th of every February.
+const MAX_EMPTY_PERIODS: u32 = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// When a rule ends, in the same form as the event's start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Until {
+    Date(NaiveDate),
+    Local(NaiveDateTime),
+    Utc(DateTime<Utc>),
+}
+
+/// A BYDAY entry: a weekday, optionally the nth (or nth-from-last) of its
+/// month or year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeekdayNum {
+    pub ordinal: Option<i32>,
+    pub weekday: Weekday,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RRule {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<Until>,
+    pub by_day: Vec<WeekdayNum>,
+    pub by_month_day: Vec<i32>,
+    pub by_month: Vec<u32>,
+    pub by_set_pos: Vec<i32>,
+    pub week_start: Weekday,
+}
+
+impl RRule {
+    pub fn new(frequency: Frequency) -> Self {
+        Self {
+            frequency,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+            by_set_pos: Vec::new(),
+            week_start: Weekday::Mon,
+        }
+    }
+
+    /// Wall-clock start times of the occurrences, beginning with `start`.
+    /// `to_utc` places a wall-clock time in the event's zone; it's only used
+    /// to compare against a UTC `UNTIL`. Unbounded rules never end, so take
+    /// what you need.
+    pub fn occurrences<F>(&self, start: NaiveDateTime, to_utc: F) -> Occurrences<'_, F>
+    where
+        F: Fn(NaiveDateTime) -> DateTime<Utc>,
+    {
+        Occurrences {
+            rule: self,
+            start,
+            to_utc,
+            period: 0,
+            empty_periods: 0,
+            buffer: VecDeque::new(),
+            emitted: 0,
+            done: false,
+        }
+    }
+
+    fn matches_filters(&self, date: NaiveDate) -> bool {
+        self.by_month.is_empty() || self.by_month.contains(&date.month())
+    }
+
+    /// The candidate dates of period `index`, in order, before BYSETPOS.
+    fn period_dates(&self, start: NaiveDate, index: u32) -> Vec<NaiveDate> {
+        let step = index.saturating_mul(self.interval);
+        match self.frequency {
+            Frequency::Daily => {
+                let Some(date) = start.checked_add_signed(Duration::days(step.into())) else {
+                    return Vec::new();
+                };
+                let by_day = self.by_day.is_empty()
+                    || self.by_day.iter().any(|day| day.weekday == date.weekday());
+                let by_month_day = self.by_month_day.is_empty()
+                    || self
+                        .by_month_day
+                        .iter()
+                        .any(|&n| month_day_matches(date, n));
+                if by_day && by_month_day {
+                    vec![date]
+                } else {
+                    Vec::new()
+                }
+            }
+            Frequency::Weekly => {
+                let back = (7 + start.weekday().num_days_from_monday()
+                    - self.week_start.num_days_from_monday())
+                    % 7;
+                let Some(week) = start
+                    .checked_sub_signed(Duration::days(back.into()))
+                    .and_then(|week| week.checked_add_signed(Duration::weeks(step.into())))
+                else {
+                    return Vec::new();
+                };
+                week.iter_days()
+                    .take(7)
+                    .filter(|date| {
+                        if self.by_day.is_empty() {
+                            date.weekday() == start.weekday()
+                        } else {
+                            self.by_day.iter().any(|day| day.weekday == date.weekday())
+                        }
+                    })
+                    .collect()
+            }
+            Frequency::Monthly => {
+                let Some(first) = start
+                    .with_day(1)
+                    .and_then(|first| first.checked_add_months(Months::new(step)))
+                else {
+                    return Vec::new();
+                };
+                let days = month_days(first);
+                if self.by_day.is_empty() && self.by_month_day.is_empty() {
+                    return days
+                        .into_iter()
+                        .filter(|date| date.day() == start.day())
+                        .collect();
+                }
+                self.select(&days)
+            }
+            Frequency::Yearly => {
+                let Some(year) = i32::try_from(step)
+                    .ok()
+                    .and_then(|step| start.year().checked_add(step))
+                else {
+                    return Vec::new();
+                };
+                let month_first = |month| NaiveDate::from_ymd_opt(year, month, 1);
+                if !self.by_day.is_empty()
+                    && self.by_month.is_empty()
+                    && self.by_month_day.is_empty()
+                {
+                    // Ordinals count through the whole year.
+                    let Some(first) = month_first(1) else {
+                        return Vec::new();
+                    };
+                    let days: Vec<_> = first
+                        .iter_days()
+                        .take_while(|date| date.year() == year)
+                        .collect();
+                    return self.select(&days);
+                }
+                let months: Vec<u32> = if !self.by_month.is_empty() {
+                    self.by_month.clone()
+                } else if !self.by_month_day.is_empty() {
+                    (1..=12).collect()
+                } else {
+                    vec![start.month()]
+                };
+                let mut dates = Vec::new();
+                for month in months {
+                    let Some(first) = month_first(month) else {
+                        continue;
+                    };
+                    let days = month_days(first);
+                    if self.by_day.is_empty() && self.by_month_day.is_empty() {
+                        dates.extend(days.into_iter().filter(|date| date.day() == start.day()));
+                    } else {
+                        dates.extend(self.select(&days));
+                    }
+                }
+                dates.sort();
+                dates.dedup();
+                dates
+            }
+        }
+    }
+
+    /// The days of `scope` (a month or a year) picked by BYDAY and
+    /// BYMONTHDAY; when both are given a day must satisfy both.
+    fn select(&self, scope: &[NaiveDate]) -> Vec<NaiveDate> {
+        scope
+            .iter()
+            .enumerate()
+            .filter(|&(index, &date)| {
+                let by_month_day = self.by_month_day.is_empty()
+                    || self
+                        .by_month_day
+                        .iter()
+                        .any(|&n| month_day_matches(date, n));
+                let by_day = self.by_day.is_empty()
+                    || self.by_day.iter().any(|day| {
+                        if day.weekday != date.weekday() {
+                            return false;
+                        }
+                        match day.ordinal {
+                            None => true,
+                            Some(n) if n > 0 => (index / 7 + 1) as i32 == n,
+                            Some(n) => -(((scope.len() - 1 - index) / 7 + 1) as i32) == n,
+                        }
+                    });
+                by_month_day && by_day
+            })
+            .map(|(_, &date)| date)
+            .collect()
+    }
+
+    fn apply_set_pos(&self, dates: Vec<NaiveDate>) -> Vec<NaiveDate> {
+        if self.by_set_pos.is_empty() {
+            return dates;
+        }
+        let len = dates.len() as i32;
+        let mut picked: Vec<NaiveDate> = self
+            .by_set_pos
+            .iter()
+            .filter_map(|&pos| {
+                let index = if pos > 0 { pos - 1 } else { len + pos };
+                (0..len).contains(&index).then(|| dates[index as usize])
+            })
+            .collect();
+        picked.sort();
+        picked.dedup();
+        picked
+    }
+}
+
+fn month_days(first: NaiveDate) -> Vec<NaiveDate> {
+    first
+        .iter_days()
+        .take_while(|date| date.month() == first.month())
+        .collect()
+}
+
+fn month_day_matches(date: NaiveDate, n: i32) -> bool {
+    if n > 0 {
+        return date.day() as i32 == n;
+    }
+    let Some(next_month) = date
+        .with_day(1)
+        .and_then(|first| first.checked_add_months(Months::new(1)))
+    else {
+        return false;
+    };
+    let len = next_month.pred_opt().map_or(31, |last| last.day() as i32);
+    date.day() as i32 == len + n + 1
+}
+
+pub struct Occurrences<'a, F> {
+    rule: &'a RRule,
+    start: NaiveDateTime,
+    to_utc: F,
+    period: u32,
+    empty_periods: u32,
+    buffer: VecDeque<NaiveDateTime>,
+    emitted: u32,
+    done: bool,
+}
+
+impl<F: Fn(NaiveDateTime) -> DateTime<Utc>> Occurrences<'_, F> {
+    fn fill(&mut self) {
+        while self.buffer.is_empty() {
+            if self.empty_periods >= MAX_EMPTY_PERIODS {
+                self.done = true;
+                return;
+            }
+            let index = self.period;
+            self.period += 1;
+            let dates = self.rule.period_dates(self.start.date(), index);
+            let dates: Vec<_> = dates
+                .into_iter()
+                .filter(|&date| self.rule.matches_filters(date))
+                .collect();
+            let times = self
+                .rule
+                .apply_set_pos(dates)
+                .into_iter()
+                .map(|date| date.and_time(self.start.time()))
+                .filter(|&at| at >= self.start);
+            self.buffer.extend(times);
+            // DTSTART is always the first occurrence, even when the rule
+            // wouldn't produce it.
+            if index == 0 && self.buffer.front() != Some(&self.start) {
+                self.buffer.push_front(self.start);
+            }
+            self.empty_periods = if self.buffer.is_empty() {
+                self.empty_periods + 1
+            } else {
+                0
+            };
+        }
+    }
+
+    fn before_until(&self, at: NaiveDateTime) -> bool {
+        match self.rule.until {
+            None => true,
+            Some(Until::Date(date)) => at.date() <= date,
+            Some(Until::Local(until)) => at <= until,
+            Some(Until::Utc(until)) => (self.to_utc)(at) <= until,
+        }
+    }
+}
+
+impl<F: Fn(NaiveDateTime) -> DateTime<Utc>> Iterator for Occurrences<'_, F> {
+    type Item = NaiveDateTime;
+
+    fn next(&mut self) -> Option<NaiveDateTime> {
+        if self.done || self.rule.count.is_some_and(|count| self.emitted >= count) {
+            return None;
+        }
+        self.fill();
+        let at = self.buffer.pop_front()?;
+        if !self.before_until(at) {
+            self.done = true;
+            return None;
+        }
+        self.emitted += 1;
+        Some(at)
+    }
+}
+
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("MO", Weekday::Mon),
+    ("TU", Weekday::Tue),
+    ("WE", Weekday::Wed),
+    ("TH", Weekday::Thu),
+    ("FR", Weekday::Fri),
+    ("SA", Weekday::Sat),
+    ("SU", Weekday::Sun),
+];
+
+fn parse_weekday(code: &str) -> Result<Weekday, String> {
+    WEEKDAYS
+        .iter()
+        .find(|(text, _)| text.eq_ignore_ascii_case(code))
+        .map(|&(_, weekday)| weekday)
+        .ok_or_else(|| format!("unknown weekday {code:?}"))
+}
+
+fn weekday_code(weekday: Weekday) -> &'static str {
+    WEEKDAYS[weekday.num_days_from_monday() as usize].0
+}
+
+fn parse_list<T: FromStr>(value: &str, part: &str) -> Result<Vec<T>, String> {
+    value
+        .split(',')
+        .map(|item| {
+            item.trim()
+                .parse()
+                .map_err(|_| format!("bad {part} value {item:?}"))
+        })
+        .collect()
+}
+
+fn parse_until(value: &str) -> Result<Until, String> {
+    if let Some(utc) = value.strip_suffix('Z') {
+        return NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S")
+            .map(|at| Until::Utc(at.and_utc()))
+            .map_err(|_| format!("bad UNTIL {value:?}"));
+    }
+    if value.contains('T') {
+        return NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .map(Until::Local)
+            .map_err(|_| format!("bad UNTIL {value:?}"));
+    }
+    NaiveDate::parse_from_str(value, "%Y%m%d")
+        .map(Until::Date)
+        .map_err(|_| format!("bad UNTIL {value:?}"))
+}
+
+impl FromStr for RRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = s.strip_prefix("RRULE:").unwrap_or(s);
+        let mut frequency = None;
+        let mut rule = RRule::new(Frequency::Daily);
+        for part in s.split(';').filter(|part| !part.is_empty()) {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("bad rule part {part:?}"))?;
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        "YEARLY" => Frequency::Yearly,
+                        other => return Err(format!("unsupported FREQ {other}")),
+                    })
+                }
+                "INTERVAL" => {
+                    rule.interval = value
+                        .parse()
+                        .ok()
+                        .filter(|&interval| interval > 0)
+                        .ok_or_else(|| format!("bad INTERVAL {value:?}"))?
+                }
+                "COUNT" => {
+                    rule.count = Some(value.parse().map_err(|_| format!("bad COUNT {value:?}"))?)
+                }
+                "UNTIL" => rule.until = Some(parse_until(value)?),
+                "BYDAY" => {
+                    rule.by_day = value
+                        .split(',')
+                        .map(|item| {
+                            let item = item.trim();
+                            let split = item.len().saturating_sub(2);
+                            if !item.is_char_boundary(split) {
+                                return Err(format!("bad BYDAY value {item:?}"));
+                            }
+                            let (ordinal, code) = item.split_at(split);
+                            let ordinal = match ordinal {
+                                "" => None,
+                                ordinal => Some(
+                                    ordinal
+                                        .trim_start_matches('+')
+                                        .parse::<i32>()
+                                        .ok()
+                                        .filter(|&n| n != 0)
+                                        .ok_or_else(|| format!("bad BYDAY value {item:?}"))?,
+                                ),
+                            };
+                            Ok(WeekdayNum {
+                                ordinal,
+                                weekday: parse_weekday(code)?,
+                            })
+                        })
+                        .collect::<Result<_, String>>()?
+                }
+                "BYMONTHDAY" => rule.by_month_day = parse_list(value, "BYMONTHDAY")?,
+                "BYMONTH" => rule.by_month = parse_list(value, "BYMONTH")?,
+                "BYSETPOS" => rule.by_set_pos = parse_list(value, "BYSETPOS")?,
+                "WKST" => rule.week_start = parse_weekday(value)?,
+                other => return Err(format!("unsupported rule part {other}")),
+            }
+        }
+        rule.frequency = frequency.ok_or("a rule needs FREQ")?;
+        if rule.count.is_some() && rule.until.is_some() {
+            return Err("a rule can't have both COUNT and UNTIL".into());
+        }
+        Ok(rule)
+    }
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items.iter().map(T::to_string).collect::<Vec<_>>().join(",")
+}
+
+impl fmt::Display for RRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequency = match self.frequency {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={frequency}")?;
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={count}")?;
+        }
+        match self.until {
+            None => {}
+            Some(Until::Date(date)) => write!(f, ";UNTIL={}", date.format("%Y%m%d"))?,
+            Some(Until::Local(at)) => write!(f, ";UNTIL={}", at.format("%Y%m%dT%H%M%S"))?,
+            Some(Until::Utc(at)) => write!(f, ";UNTIL={}", at.format("%Y%m%dT%H%M%SZ"))?,
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|day| match day.ordinal {
+                    Some(n) => format!("{n}{}", weekday_code(day.weekday)),
+                    None => weekday_code(day.weekday).to_owned(),
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            write!(f, ";BYMONTHDAY={}", join(&self.by_month_day))?;
+        }
+        if !self.by_month.is_empty() {
+            write!(f, ";BYMONTH={}", join(&self.by_month))?;
+        }
+        if !self.by_set_pos.is_empty() {
+            write!(f, ";BYSETPOS={}", join(&self.by_set_pos))?;
+        }
+        if self.week_start != Weekday::Mon {
+            write!(f, ";WKST={}", weekday_code(self.week_start))?;
+        }
+        Ok(())
+    }
+}
+
+/// Rules serialize as their RFC 5545 text, e.g. `FREQ=WEEKLY;BYDAY=MO,WE`.
+impl Serialize for RRule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for RRule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::time::zoned_instant;
+
+    /// The zone of the RFC 5545 examples.
+    fn new_york(local: NaiveDateTime) -> DateTime<Utc> {
+        zoned_instant(&chrono_tz::America::New_York, local)
+    }
+
+    fn local(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").unwrap()
+    }
+
+    /// The dates of the first `n` occurrences of `rule` from `start`; every
+    /// occurrence keeps the start's wall-clock time.
+    fn expand(start: &str, rule: &str, n: usize) -> Vec<String> {
+        let start = local(start);
+        let rule: RRule = rule.parse().unwrap();
+        rule.occurrences(start, new_york)
+            .take(n)
+            .map(|at| {
+                assert_eq!(at.time(), start.time(), "{rule}");
+                at.format("%Y%m%d").to_string()
+            })
+            .collect()
+    }
+
+    fn dates(list: &str) -> Vec<String> {
+        list.split_whitespace().map(str::to_owned).collect()
+    }
+
+    // The examples of RFC 5545 section 3.8.5.3 that use the parts we support,
+    // in the order the RFC gives them.
+
+    #[test]
+    fn rfc_daily() {
+        assert_eq!(
+            expand("19970902T090000", "FREQ=DAILY;COUNT=10", 100),
+            dates("19970902 19970903 19970904 19970905 19970906 19970907 19970908 19970909 19970910 19970911")
+        );
+
+        let until = expand("19970902T090000", "FREQ=DAILY;UNTIL=19971224T000000Z", 1000);
+        assert_eq!(until.len(), 113);
+        assert_eq!(until.last().unwrap(), "19971223");
+
+        assert_eq!(
+            expand("19970902T090000", "FREQ=DAILY;INTERVAL=2", 6),
+            dates("19970902 19970904 19970906 19970908 19970910 19970912")
+        );
+        assert_eq!(
+            expand("19970902T090000", "FREQ=DAILY;INTERVAL=10;COUNT=5", 100),
+            dates("19970902 19970912 19970922 19971002 19971012")
+        );
+
+        // Every day in January, for 3 years, written both ways.
+        for rule in [
+            "FREQ=YEARLY;UNTIL=20000131T140000Z;BYMONTH=1;BYDAY=SU,MO,TU,WE,TH,FR,SA",
+            "FREQ=DAILY;UNTIL=20000131T140000Z;BYMONTH=1",
+        ] {
+            let january = expand("19980101T090000", rule, 1000);
+            assert_eq!(january.len(), 93, "{rule}");
+            assert_eq!(january[30], "19980131");
+            assert_eq!(january[31], "19990101");
+            assert_eq!(january.last().unwrap(), "20000131");
+        }
+    }
+
+    #[test]
+    fn rfc_weekly() {
+        let ten = expand("19970902T090000", "FREQ=WEEKLY;COUNT=10", 100);
+        assert_eq!(
+            ten,
+            dates("19970902 19970909 19970916 19970923 19970930 19971007 19971014 19971021 19971028 19971104")
+        );
+        let until = expand("19970902T090000", "FREQ=WEEKLY;UNTIL=19971224T000000Z", 100);
+        assert_eq!(until.len(), 17);
+        assert_eq!(until.last().unwrap(), "19971223");
+
+        assert_eq!(
+            expand("19970902T090000", "FREQ=WEEKLY;INTERVAL=2;WKST=SU", 13),
+            dates("19970902 19970916 19970930 19971014 19971028 19971111 19971125 19971209 19971223 19980106 19980120 19980203 19980217")
+        );
+
+        let tuesdays_and_thursdays =
+            dates("19970902 19970904 19970909 19970911 19970916 19970918 19970923 19970925 19970930 19971002");
+        assert_eq!(
+            expand(
+                "19970902T090000",
+                "FREQ=WEEKLY;UNTIL=19971007T000000Z;WKST=SU;BYDAY=TU,TH",
+                100
+            ),
+            tuesdays_and_thursdays
+        );
+        assert_eq!(
+            expand(
+                "19970902T090000",
+                "FREQ=WEEKLY;COUNT=10;WKST=SU;BYDAY=TU,TH",
+                100
+            ),
+            tuesdays_and_thursdays
+        );
+
+        assert_eq!(
+            expand(
+                "19970901T090000",
+                "FREQ=WEEKLY;INTERVAL=2;UNTIL=19971224T000000Z;WKST=SU;BYDAY=MO,WE,FR",
+                100
+            ),
+            dates(
+                "19970901 19970903 19970905 19970915 19970917 19970919 19970929 19971001 19971003 \
+                 19971013 19971015 19971017 19971027 19971029 19971031 19971110 19971112 19971114 \
+                 19971124 19971126 19971128 19971208 19971210 19971212 19971222"
+            )
+        );
+        assert_eq!(
+            expand(
+                "19970902T090000",
+                "FREQ=WEEKLY;INTERVAL=2;COUNT=8;WKST=SU;BYDAY=TU,TH",
+                100
+            ),
+            dates("19970902 19970904 19970916 19970918 19970930 19971002 19971014 19971016")
+        );
+    }
+
+    #[test]
+    fn rfc_monthly() {
+        assert_eq!(
+            expand("19970905T090000", "FREQ=MONTHLY;COUNT=10;BYDAY=1FR", 100),
+            dates("19970905 19971003 19971107 19971205 19980102 19980206 19980306 19980403 19980501 19980605")
+        );
+        assert_eq!(
+            expand(
+                "19970905T090000",
+                "FREQ=MONTHLY;UNTIL=19971224T000000Z;BYDAY=1FR",
+                100
+            ),
+            dates("19970905 19971003 19971107 19971205")
+        );
+        assert_eq!(
+            expand("19970907T090000", "FREQ=MONTHLY;INTERVAL=2;COUNT=10;BYDAY=1SU,-1SU", 100),
+            dates("19970907 19970928 19971102 19971130 19980104 19980125 19980301 19980329 19980503 19980531")
+        );
+        assert_eq!(
+            expand("19970922T090000", "FREQ=MONTHLY;COUNT=6;BYDAY=-2MO", 100),
+            dates("19970922 19971020 19971117 19971222 19980119 19980216")
+        );
+        assert_eq!(
+            expand("19970928T090000", "FREQ=MONTHLY;BYMONTHDAY=-3", 6),
+            dates("19970928 19971029 19971128 19971229 19980129 19980226")
+        );
+        assert_eq!(
+            expand("19970902T090000", "FREQ=MONTHLY;COUNT=10;BYMONTHDAY=2,15", 100),
+            dates("19970902 19970915 19971002 19971015 19971102 19971115 19971202 19971215 19980102 19980115")
+        );
+        assert_eq!(
+            expand("19970930T090000", "FREQ=MONTHLY;COUNT=10;BYMONTHDAY=1,-1", 100),
+            dates("19970930 19971001 19971031 19971101 19971130 19971201 19971231 19980101 19980131 19980201")
+        );
+        assert_eq!(
+            expand(
+                "19970910T090000",
+                "FREQ=MONTHLY;INTERVAL=18;COUNT=10;BYMONTHDAY=10,11,12,13,14,15",
+                100
+            ),
+            dates("19970910 19970911 19970912 19970913 19970914 19970915 19990310 19990311 19990312 19990313")
+        );
+        assert_eq!(
+            expand("19970902T090000", "FREQ=MONTHLY;INTERVAL=2;BYDAY=TU", 18),
+            dates(
+                "19970902 19970909 19970916 19970923 19970930 19971104 19971111 19971118 19971125 \
+                 19980106 19980113 19980120 19980127 19980303 19980310 19980317 19980324 19980331"
+            )
+        );
+    }
+
+    #[test]
+    fn rfc_yearly() {
+        assert_eq!(
+            expand("19970610T090000", "FREQ=YEARLY;COUNT=10;BYMONTH=6,7", 100),
+            dates("19970610 19970710 19980610 19980710 19990610 19990710 20000610 20000710 20010610 20010710")
+        );
+        assert_eq!(
+            expand("19970310T090000", "FREQ=YEARLY;INTERVAL=2;COUNT=10;BYMONTH=1,2,3", 100),
+            dates("19970310 19990110 19990210 19990310 20010110 20010210 20010310 20030110 20030210 20030310")
+        );
+        assert_eq!(
+            expand("19970519T090000", "FREQ=YEARLY;BYDAY=20MO", 3),
+            dates("19970519 19980518 19990517")
+        );
+        assert_eq!(
+            expand("19970313T090000", "FREQ=YEARLY;BYMONTH=3;BYDAY=TH", 11),
+            dates("19970313 19970320 19970327 19980305 19980312 19980319 19980326 19990304 19990311 19990318 19990325")
+        );
+        assert_eq!(
+            expand("19970605T090000", "FREQ=YEARLY;BYDAY=TH;BYMONTH=6,7,8", 13),
+            dates("19970605 19970612 19970619 19970626 19970703 19970710 19970717 19970724 19970731 19970807 19970814 19970821 19970828")
+        );
+        assert_eq!(
+            expand(
+                "19961105T090000",
+                "FREQ=YEARLY;INTERVAL=4;BYMONTH=11;BYDAY=TU;BYMONTHDAY=2,3,4,5,6,7,8",
+                3
+            ),
+            dates("19961105 20001107 20041102")
+        );
+    }
+
+    #[test]
+    fn rfc_combined_parts() {
+        // Friday the 13th; the RFC drops DTSTART with an EXDATE.
+        let fridays = expand("19970902T090000", "FREQ=MONTHLY;BYDAY=FR;BYMONTHDAY=13", 6);
+        assert_eq!(
+            fridays[1..],
+            dates("19980213 19980313 19981113 19990813 20001013")
+        );
+        // The first Saturday after the first Sunday of the month.
+        assert_eq!(
+            expand("19970913T090000", "FREQ=MONTHLY;BYDAY=SA;BYMONTHDAY=7,8,9,10,11,12,13", 10),
+            dates("19970913 19971011 19971108 19971213 19980110 19980207 19980307 19980411 19980509 19980613")
+        );
+        assert_eq!(
+            expand(
+                "19970904T090000",
+                "FREQ=MONTHLY;COUNT=3;BYDAY=TU,WE,TH;BYSETPOS=3",
+                100
+            ),
+            dates("19970904 19971007 19971106")
+        );
+        assert_eq!(
+            expand(
+                "19970929T090000",
+                "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-2",
+                7
+            ),
+            dates("19970929 19971030 19971127 19971230 19980129 19980226 19980330")
+        );
+    }
+
+    #[test]
+    fn rfc_week_start() {
+        assert_eq!(
+            expand(
+                "19970805T090000",
+                "FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=MO",
+                100
+            ),
+            dates("19970805 19970810 19970819 19970824")
+        );
+        assert_eq!(
+            expand(
+                "19970805T090000",
+                "FREQ=WEEKLY;INTERVAL=2;COUNT=4;BYDAY=TU,SU;WKST=SU",
+                100
+            ),
+            dates("19970805 19970817 19970819 19970831")
+        );
+    }
+
+    #[test]
+    fn rfc_invalid_dates_are_skipped() {
+        assert_eq!(
+            expand(
+                "20070115T090000",
+                "FREQ=MONTHLY;BYMONTHDAY=15,30;COUNT=5",
+                100
+            ),
+            dates("20070115 20070130 20070215 20070315 20070330")
+        );
+        // The 30th of February never comes.
+        assert_eq!(
+            expand("20070130T090000", "FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=30", 5),
+            dates("20070130")
+        );
+    }
+
+    #[test]
+    fn wall_clock_times_hold_across_dst() {
+        let rule: RRule = "FREQ=DAILY;COUNT=3".parse().unwrap();
+        let instants: Vec<_> = rule
+            .occurrences(local("19971025T090000"), new_york)
+            .map(new_york)
+            .collect();
+        assert_eq!(
+            instants,
+            [
+                local("19971025T130000").and_utc(),
+                local("19971026T140000").and_utc(),
+                local("19971027T140000").and_utc(),
+            ]
+        );
+
+        // A UTC UNTIL is compared in the event's zone: 9am on the day the
+        // clocks go back is 14:00Z, after this UNTIL.
+        assert_eq!(
+            expand("19971024T090000", "FREQ=DAILY;UNTIL=19971026T130000Z", 100),
+            dates("19971024 19971025")
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_rules() {
+        for rule in [
+            "FREQ=HOURLY;INTERVAL=3",
+            "FREQ=MINUTELY;INTERVAL=15",
+            "FREQ=YEARLY;BYYEARDAY=1,100,200",
+            "FREQ=YEARLY;BYWEEKNO=20;BYDAY=MO",
+            "FREQ=DAILY;BYHOUR=9,10",
+            "FREQ=DAILY;COUNT=5;UNTIL=19971224T000000Z",
+            "INTERVAL=2",
+            "FREQ=DAILY;INTERVAL=0",
+            "FREQ=MONTHLY;BYDAY=0MO",
+            "FREQ=WEEKLY;BYDAY=XX",
+            "FREQ=WEEKLY;BYDAY=é",
+        ] {
+            assert!(rule.parse::<RRule>().is_err(), "{rule}");
+        }
+    }
+
+    #[test]
+    fn text_round_trips() {
+        for rule in [
+            "FREQ=DAILY",
+            "FREQ=WEEKLY;INTERVAL=2;UNTIL=19971224T000000Z;BYDAY=MO,WE,FR;WKST=SU",
+            "FREQ=MONTHLY;COUNT=10;BYDAY=1SU,-1SU",
+            "FREQ=MONTHLY;BYDAY=MO,TU,WE,TH,FR;BYSETPOS=-2",
+            "FREQ=YEARLY;UNTIL=20000131;BYMONTHDAY=1,-1;BYMONTH=1,2",
+            "FREQ=YEARLY;UNTIL=20000131T090000",
+        ] {
+            let parsed: RRule = rule.parse().unwrap();
+            assert_eq!(parsed.to_string(), rule);
+            assert_eq!(serde_json::to_value(&parsed).unwrap(), rule);
+        }
+        let loose: RRule = "RRULE:freq=weekly;byday=+2mo;".parse().unwrap();
+        assert_eq!(loose.to_string(), "FREQ=WEEKLY;BYDAY=2MO");
+    }
+}
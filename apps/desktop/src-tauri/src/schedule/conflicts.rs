@@ -0,0 +1,412 @@
+//! Overlap detection following the `detect_time_block_conflicts()` trigger
+//! from `migrations/010_enhance_time_blocks.sql`.
+//!
+//! Like the trigger, blocks only conflict with blocks of the same user and
+//! the same `daily_schedule_id` (blocks without one never conflict), and
+//! overlap follows Postgres' `OVERLAPS`. The trigger only looks at the row
+//! being written, so its result depends on write order: the first of two
+//! overlapping blocks keeps 0 until it is written again. [`detect`] sees
+//! every block at once and flags each one that overlaps anything, which is
+//! what the trigger converges to once every block has been rewritten. The
+//! numbers differ too: the trigger hands out a fresh number per write while
+//! this module numbers connected overlaps (found with union-find) per day.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::Result;
+use crate::store::{BlockType, Store, TimeBlock};
+
+/// How disruptive an overlap is. Breaks and blocked-off time give way to
+/// anything, so overlaps involving one can be resolved by shortening it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Soft,
+    Hard,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Overlap {
+    pub first: String,
+    pub second: String,
+    pub severity: Severity,
+}
+
+/// Blocks connected by overlaps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictGroup {
+    pub group: i64,
+    pub block_ids: Vec<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// The worst overlap in the group.
+    pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflicts {
+    pub groups: Vec<ConflictGroup>,
+    pub overlaps: Vec<Overlap>,
+}
+
+/// Postgres `(s1, e1) OVERLAPS (s2, e2)`: periods are half-open, a period
+/// whose ends are equal is a single instant, and periods starting together
+/// always overlap.
+pub fn overlaps(
+    s1: DateTime<Utc>,
+    e1: DateTime<Utc>,
+    s2: DateTime<Utc>,
+    e2: DateTime<Utc>,
+) -> bool {
+    let (s1, e1) = if s1 > e1 { (e1, s1) } else { (s1, e1) };
+    let (s2, e2) = if s2 > e2 { (e2, s2) } else { (s2, e2) };
+    if s1 > s2 {
+        s1 < e2
+    } else if s1 < s2 {
+        s2 < e1
+    } else {
+        true
+    }
+}
+
+fn severity(a: BlockType, b: BlockType) -> Severity {
+    let yields = |block_type| matches!(block_type, BlockType::Break | BlockType::Blocked);
+    if yields(a) || yields(b) {
+        Severity::Soft
+    } else {
+        Severity::Hard
+    }
+}
+
+/// Find every overlap among `blocks` and group the blocks they connect.
+/// Groups are numbered from 1 in order of their earliest block.
+pub fn detect(blocks: &[TimeBlock]) -> Conflicts {
+    let mut order: Vec<usize> = (0..blocks.len()).collect();
+    order.sort_by(|&a, &b| {
+        (blocks[a].start_time, blocks[a].end_time, &blocks[a].id).cmp(&(
+            blocks[b].start_time,
+            blocks[b].end_time,
+            &blocks[b].id,
+        ))
+    });
+
+    // Union-find over block indices.
+    let mut parent: Vec<usize> = (0..blocks.len()).collect();
+    fn root(parent: &mut [usize], mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+
+    let mut overlaps_found = Vec::new();
+    for (n, &i) in order.iter().enumerate() {
+        for &j in &order[n + 1..] {
+            let (a, b) = (&blocks[i], &blocks[j]);
+            if a.user_id != b.user_id
+                || a.daily_schedule_id.is_none()
+                || a.daily_schedule_id != b.daily_schedule_id
+                || !overlaps(a.start_time, a.end_time, b.start_time, b.end_time)
+            {
+                continue;
+            }
+            overlaps_found.push(Overlap {
+                first: a.id.clone(),
+                second: b.id.clone(),
+                severity: severity(a.block_type, b.block_type),
+            });
+            let (ra, rb) = (root(&mut parent, i), root(&mut parent, j));
+            parent[ra.max(rb)] = ra.min(rb);
+        }
+    }
+
+    let mut groups: Vec<(usize, ConflictGroup)> = Vec::new();
+    for &i in &order {
+        let r = root(&mut parent, i);
+        let block = &blocks[i];
+        let in_conflict = overlaps_found
+            .iter()
+            .any(|o| o.first == block.id || o.second == block.id);
+        if !in_conflict {
+            continue;
+        }
+        match groups.iter_mut().find(|(root, _)| *root == r) {
+            Some((_, group)) => {
+                group.block_ids.push(block.id.clone());
+                group.start = group.start.min(block.start_time.min(block.end_time));
+                group.end = group.end.max(block.start_time.max(block.end_time));
+            }
+            None => {
+                let group = groups.len() as i64 + 1;
+                groups.push((
+                    r,
+                    ConflictGroup {
+                        group,
+                        block_ids: vec![block.id.clone()],
+                        start: block.start_time.min(block.end_time),
+                        end: block.start_time.max(block.end_time),
+                        severity: Severity::Soft,
+                    },
+                ));
+            }
+        }
+    }
+
+    let mut groups: Vec<ConflictGroup> = groups.into_iter().map(|(_, group)| group).collect();
+    for group in &mut groups {
+        group.severity = overlaps_found
+            .iter()
+            .filter(|o| group.block_ids.contains(&o.first))
+            .map(|o| o.severity)
+            .max()
+            .unwrap_or(Severity::Soft);
+    }
+
+    Conflicts {
+        groups,
+        overlaps: overlaps_found,
+    }
+}
+
+/// Set each block's `conflict_group` from [`detect`]: its group number, or
+/// 0 if it overlaps nothing.
+pub fn assign_groups(blocks: &mut [TimeBlock]) -> Conflicts {
+    let conflicts = detect(blocks);
+    for block in blocks.iter_mut() {
+        block.conflict_group = conflicts
+            .groups
+            .iter()
+            .find(|group| group.block_ids.contains(&block.id))
+            .map_or(0, |group| group.group);
+    }
+    conflicts
+}
+
+#[tauri::command]
+pub fn schedule_conflicts(date: NaiveDate, store: State<'_, Store>) -> Result<Conflicts> {
+    Ok(detect(&store.blocks_for_date(date)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 2, hour, minute, 0).unwrap()
+    }
+
+    fn block(
+        id: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        block_type: BlockType,
+    ) -> TimeBlock {
+        TimeBlock {
+            daily_schedule_id: Some("day".into()),
+            block_type,
+            ..crate::testing::block(id, start, end)
+        }
+    }
+
+    fn focus(id: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> TimeBlock {
+        block(id, start, end, BlockType::Focus)
+    }
+
+    #[test]
+    fn overlap_follows_postgres() {
+        // Touching periods don't overlap; shared starts always do.
+        assert!(!overlaps(at(9, 0), at(10, 0), at(10, 0), at(11, 0)));
+        assert!(overlaps(at(9, 0), at(10, 0), at(9, 59), at(11, 0)));
+        assert!(overlaps(at(9, 0), at(9, 0), at(9, 0), at(10, 0)));
+        assert!(!overlaps(at(10, 0), at(10, 0), at(9, 0), at(10, 0)));
+        // Reversed periods are put in order first.
+        assert!(overlaps(at(10, 0), at(9, 0), at(9, 30), at(9, 45)));
+    }
+
+    #[test]
+    fn chained_overlaps_form_one_group() {
+        let blocks = [
+            focus("c", at(10, 30), at(12, 0)),
+            focus("a", at(9, 0), at(10, 0)),
+            focus("b", at(9, 30), at(11, 0)),
+            focus("d", at(13, 0), at(14, 0)),
+            focus("e", at(13, 30), at(14, 30)),
+            focus("lone", at(16, 0), at(17, 0)),
+        ];
+        let conflicts = detect(&blocks);
+        let groups: Vec<(i64, Vec<&str>)> = conflicts
+            .groups
+            .iter()
+            .map(|g| (g.group, g.block_ids.iter().map(String::as_str).collect()))
+            .collect();
+        assert_eq!(groups, [(1, vec!["a", "b", "c"]), (2, vec!["d", "e"])]);
+        assert_eq!(
+            (conflicts.groups[0].start, conflicts.groups[0].end),
+            (at(9, 0), at(12, 0))
+        );
+        assert_eq!(conflicts.overlaps.len(), 3);
+    }
+
+    #[test]
+    fn only_blocks_of_the_same_user_and_day_conflict() {
+        let mut other_user = focus("other-user", at(9, 0), at(10, 0));
+        other_user.user_id = "u2".into();
+        let mut other_day = focus("other-day", at(9, 0), at(10, 0));
+        other_day.daily_schedule_id = Some("tomorrow".into());
+        let mut unscheduled = [
+            focus("x", at(9, 0), at(10, 0)),
+            focus("y", at(9, 0), at(10, 0)),
+        ];
+        for block in &mut unscheduled {
+            block.daily_schedule_id = None;
+        }
+
+        let mut blocks = vec![focus("mine", at(9, 0), at(10, 0)), other_user, other_day];
+        blocks.extend(unscheduled);
+        assert_eq!(detect(&blocks), Conflicts::default());
+    }
+
+    #[test]
+    fn breaks_and_blocked_time_overlap_softly() {
+        let conflicts = detect(&[
+            focus("focus", at(9, 0), at(10, 0)),
+            block("break", at(9, 30), at(10, 30), BlockType::Break),
+            block("blocked", at(12, 0), at(13, 0), BlockType::Blocked),
+            block("meeting", at(12, 30), at(13, 30), BlockType::Meeting),
+        ]);
+        let severities: Vec<Severity> = conflicts.groups.iter().map(|g| g.severity).collect();
+        assert_eq!(severities, [Severity::Soft, Severity::Soft]);
+
+        let conflicts = detect(&[
+            focus("focus", at(9, 0), at(10, 0)),
+            block("break", at(9, 30), at(10, 30), BlockType::Break),
+            block("meeting", at(10, 15), at(11, 0), BlockType::Meeting),
+        ]);
+        assert_eq!(conflicts.groups[0].severity, Severity::Soft);
+        let conflicts = detect(&[
+            focus("focus", at(9, 0), at(10, 0)),
+            block("break", at(9, 30), at(10, 30), BlockType::Break),
+            block("meeting", at(9, 45), at(11, 0), BlockType::Meeting),
+        ]);
+        assert_eq!(conflicts.groups[0].severity, Severity::Hard);
+    }
+
+    /// `detect_time_block_conflicts()`: `written` gets a fresh group if it
+    /// overlaps any other stored block of its user and schedule.
+    fn trigger(stored: &mut Vec<TimeBlock>, mut written: TimeBlock) {
+        let conflicting = stored.iter().any(|other| {
+            other.user_id == written.user_id
+                && other.id != written.id
+                && written.daily_schedule_id.is_some()
+                && other.daily_schedule_id == written.daily_schedule_id
+                && overlaps(
+                    written.start_time,
+                    written.end_time,
+                    other.start_time,
+                    other.end_time,
+                )
+        });
+        written.conflict_group = if conflicting {
+            stored
+                .iter()
+                .filter(|other| other.user_id == written.user_id)
+                .map(|other| other.conflict_group + 1)
+                .max()
+                .unwrap_or(1)
+        } else {
+            0
+        };
+        stored.retain(|other| other.id != written.id);
+        stored.push(written);
+    }
+
+    fn flagged(blocks: &[TimeBlock]) -> Vec<(String, bool)> {
+        let mut flagged: Vec<(String, bool)> = blocks
+            .iter()
+            .map(|block| (block.id.clone(), block.conflict_group != 0))
+            .collect();
+        flagged.sort();
+        flagged
+    }
+
+    /// Days of random blocks across two users and schedules.
+    fn random_blocks(seed: u64) -> Vec<TimeBlock> {
+        let mut state = seed;
+        let mut next = |bound: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % bound
+        };
+        (0..30)
+            .map(|n| {
+                let start = at(8, 0) + Duration::minutes(15 * next(40) as i64);
+                let end = start + Duration::minutes(15 * next(8) as i64);
+                let mut block = focus(&format!("b{n:02}"), start, end);
+                block.user_id = format!("u{}", next(2));
+                block.daily_schedule_id = match next(5) {
+                    0 => None,
+                    day => Some(format!("day{}", day % 2)),
+                };
+                block
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detect_matches_what_the_trigger_converges_to() {
+        for seed in 0..20 {
+            let blocks = random_blocks(seed);
+            let mut stored = Vec::new();
+            for block in &blocks {
+                trigger(&mut stored, block.clone());
+            }
+            let mut detected = blocks.clone();
+            assign_groups(&mut detected);
+
+            // Every block the trigger flags on first write is flagged.
+            let after_inserts = flagged(&stored);
+            let expected = flagged(&detected);
+            for ((id, by_trigger), (_, by_detect)) in after_inserts.iter().zip(&expected) {
+                assert!(!by_trigger || *by_detect, "seed {seed}: {id}");
+            }
+
+            // Once every block has been rewritten, they agree exactly.
+            for block in &blocks {
+                let current = stored.iter().find(|b| b.id == block.id).unwrap().clone();
+                trigger(&mut stored, current);
+            }
+            assert_eq!(flagged(&stored), expected, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn the_trigger_depends_on_write_order() {
+        let (first, second) = (
+            focus("first", at(9, 0), at(10, 0)),
+            focus("second", at(9, 30), at(11, 0)),
+        );
+        let mut stored = Vec::new();
+        trigger(&mut stored, first.clone());
+        trigger(&mut stored, second.clone());
+        assert_eq!(
+            flagged(&stored),
+            [("first".into(), false), ("second".into(), true)]
+        );
+
+        let mut detected = vec![first, second];
+        assign_groups(&mut detected);
+        assert_eq!(
+            flagged(&detected),
+            [("first".into(), true), ("second".into(), true)]
+        );
+    }
+}
@@ -0,0 +1,363 @@
+//! Free-time finder for a day, honoring the user's work hours, work days and
+//! lunch from `user_preferences`. The offline counterpart of the
+//! `schedule_findGaps` AI tool.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::error::Result;
+use crate::store::{Store, TimeBlock, UserPreferences};
+
+/// Gaps shorter than this are never worth offering.
+pub const DEFAULT_MIN_MINUTES: i64 = 15;
+
+/// Bonus, in minutes, for a gap in the user's preferred deep-work period.
+const PREFERENCE_BONUS: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Morning,
+    Afternoon,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Gap {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub duration: i64,
+    pub period: Period,
+    /// Higher is better: the gap's length in minutes, plus a bonus when it
+    /// falls in the preferred deep-work period.
+    pub score: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreeTime {
+    pub date: NaiveDate,
+    pub work_day: bool,
+    /// Best first.
+    pub gaps: Vec<Gap>,
+    pub total_available_minutes: i64,
+}
+
+/// Restrict the search to part of the day, in wall-clock time.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct Between {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// Free slots of at least `min_minutes` on `date` within work hours, outside
+/// lunch and every block.
+pub fn find_gaps(
+    preferences: &UserPreferences,
+    blocks: &[TimeBlock],
+    date: NaiveDate,
+    min_minutes: i64,
+    between: Option<Between>,
+) -> FreeTime {
+    let work_day = preferences.works_on(date);
+    if !work_day {
+        return FreeTime {
+            date,
+            work_day,
+            gaps: Vec::new(),
+            total_available_minutes: 0,
+        };
+    }
+
+    let (start, end) = match between {
+        Some(between) => (
+            between.start.max(preferences.work_start_time),
+            between.end.min(preferences.work_end_time),
+        ),
+        None => (preferences.work_start_time, preferences.work_end_time),
+    };
+    let zone = preferences.zone();
+    let window_start = zone.instant(date, start);
+    let window_end = zone.instant(date, end);
+    let lunch_start = zone.instant(date, preferences.lunch_start_time);
+    let lunch_end = lunch_start + Duration::minutes(preferences.lunch_duration_minutes);
+
+    let busy = blocks
+        .iter()
+        .map(|block| (block.start_time, block.end_time))
+        .chain(std::iter::once((lunch_start, lunch_end)));
+
+    let mut gaps: Vec<Gap> = free_intervals(window_start, window_end, busy)
+        .into_iter()
+        .filter_map(|(start, end)| {
+            let duration = (end - start).num_minutes();
+            if duration < min_minutes {
+                return None;
+            }
+            let period = if start < lunch_start {
+                Period::Morning
+            } else {
+                Period::Afternoon
+            };
+            let preferred = matches!(
+                (preferences.deep_work_preference.as_str(), period),
+                ("morning", Period::Morning) | ("afternoon", Period::Afternoon)
+            );
+            Some(Gap {
+                start_time: start,
+                end_time: end,
+                duration,
+                period,
+                score: duration + if preferred { PREFERENCE_BONUS } else { 0 },
+            })
+        })
+        .collect();
+
+    let total_available_minutes = gaps.iter().map(|gap| gap.duration).sum();
+    gaps.sort_by(|a, b| b.score.cmp(&a.score).then(a.start_time.cmp(&b.start_time)));
+    FreeTime {
+        date,
+        work_day,
+        gaps,
+        total_available_minutes,
+    }
+}
+
+/// The parts of `[start, end)` not covered by any `busy` interval, in order.
+pub fn free_intervals(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    busy: impl IntoIterator<Item = (DateTime<Utc>, DateTime<Utc>)>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut busy: Vec<_> = busy
+        .into_iter()
+        .filter(|(s, e)| e > s && *e > start && *s < end)
+        .collect();
+    busy.sort();
+
+    let mut free = Vec::new();
+    let mut cursor = start;
+    for (s, e) in busy.into_iter().chain(std::iter::once((end, end))) {
+        let gap_end = s.min(end);
+        if gap_end > cursor {
+            free.push((cursor, gap_end));
+        }
+        cursor = cursor.max(e);
+    }
+    free
+}
+
+#[tauri::command]
+pub fn schedule_find_gaps(
+    date: NaiveDate,
+    min_minutes: Option<i64>,
+    between: Option<Between>,
+    store: State<'_, Store>,
+) -> Result<FreeTime> {
+    let preferences = store
+        .get_preferences()?
+        .unwrap_or_else(|| UserPreferences::defaults(""));
+    let (start, end) = preferences.zone().day_bounds(date);
+    let blocks = store.list_time_blocks(start, end)?;
+    Ok(find_gaps(
+        &preferences,
+        &blocks,
+        date,
+        min_minutes.unwrap_or(DEFAULT_MIN_MINUTES).max(1),
+        between,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::time::Zone;
+    use crate::testing::block;
+
+    fn preferences() -> UserPreferences {
+        let mut preferences = UserPreferences::defaults("u1");
+        preferences.timezone = "Europe/Berlin".into();
+        preferences
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, month, day).unwrap()
+    }
+
+    /// Monday 2 March 2026.
+    fn monday() -> NaiveDate {
+        date(3, 2)
+    }
+
+    fn berlin(date: NaiveDate, hour: u32, minute: u32) -> DateTime<Utc> {
+        Zone::named("Europe/Berlin")
+            .unwrap()
+            .instant(date, time(hour, minute))
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        berlin(monday(), hour, minute)
+    }
+
+    fn meeting(id: &str, from: (u32, u32), to: (u32, u32)) -> TimeBlock {
+        block(id, at(from.0, from.1), at(to.0, to.1))
+    }
+
+    /// The gaps as local (start, end) pairs, best first.
+    fn spans(free: &FreeTime) -> Vec<((u32, u32), (u32, u32))> {
+        use chrono::Timelike;
+        let zone = Zone::named("Europe/Berlin").unwrap();
+        let local = |at| {
+            let time = zone.local(at);
+            (time.hour(), time.minute())
+        };
+        free.gaps
+            .iter()
+            .map(|gap| (local(gap.start_time), local(gap.end_time)))
+            .collect()
+    }
+
+    #[test]
+    fn nothing_is_free_on_a_day_off() {
+        let saturday = date(3, 7);
+        let free = find_gaps(&preferences(), &[], saturday, 15, None);
+        assert!(!free.work_day);
+        assert!(free.gaps.is_empty());
+        assert_eq!(free.total_available_minutes, 0);
+    }
+
+    #[test]
+    fn lunch_splits_the_work_day() {
+        let free = find_gaps(&preferences(), &[], monday(), 15, None);
+        assert!(free.work_day);
+        assert_eq!(spans(&free), [((13, 0), (18, 0)), ((8, 0), (12, 0))]);
+        assert_eq!(free.gaps[0].period, Period::Afternoon);
+        assert_eq!(free.gaps[1].period, Period::Morning);
+        assert_eq!(free.total_available_minutes, 540);
+    }
+
+    #[test]
+    fn between_is_narrowed_to_work_hours() {
+        let window = |start, end| Some(Between { start, end });
+        let free = find_gaps(
+            &preferences(),
+            &[],
+            monday(),
+            15,
+            window(time(6, 0), time(10, 0)),
+        );
+        assert_eq!(spans(&free), [((8, 0), (10, 0))]);
+        let free = find_gaps(
+            &preferences(),
+            &[],
+            monday(),
+            15,
+            window(time(17, 0), time(22, 0)),
+        );
+        assert_eq!(spans(&free), [((17, 0), (18, 0))]);
+        let free = find_gaps(
+            &preferences(),
+            &[],
+            monday(),
+            15,
+            window(time(12, 15), time(12, 45)),
+        );
+        assert!(free.gaps.is_empty());
+    }
+
+    #[test]
+    fn short_gaps_are_dropped() {
+        let blocks = [
+            meeting("a", (8, 0), (9, 0)),
+            meeting("b", (9, 10), (12, 0)),
+            meeting("c", (13, 0), (18, 0)),
+        ];
+        let free = find_gaps(&preferences(), &blocks, monday(), 15, None);
+        assert!(free.gaps.is_empty());
+        assert_eq!(free.total_available_minutes, 0);
+
+        let free = find_gaps(&preferences(), &blocks, monday(), 10, None);
+        assert_eq!(spans(&free), [((9, 0), (9, 10))]);
+        assert_eq!(free.total_available_minutes, 10);
+    }
+
+    #[test]
+    fn preferred_period_wins_and_ties_go_to_the_earlier_gap() {
+        let blocks = [
+            meeting("late-morning", (10, 0), (12, 0)),
+            meeting("afternoon", (15, 0), (18, 0)),
+        ];
+        let mut preferences = preferences();
+        let free = find_gaps(&preferences, &blocks, monday(), 15, None);
+        assert_eq!(spans(&free), [((8, 0), (10, 0)), ((13, 0), (15, 0))]);
+        assert_eq!((free.gaps[0].score, free.gaps[1].score), (120, 120));
+
+        preferences.deep_work_preference = "afternoon".into();
+        let free = find_gaps(&preferences, &blocks, monday(), 15, None);
+        assert_eq!(spans(&free), [((13, 0), (15, 0)), ((8, 0), (10, 0))]);
+        assert_eq!(free.gaps[0].score, 120 + PREFERENCE_BONUS);
+        assert_eq!(free.gaps[0].duration, 120);
+
+        preferences.deep_work_preference = "morning".into();
+        let free = find_gaps(&preferences, &blocks, monday(), 15, None);
+        assert_eq!(free.gaps[0].period, Period::Morning);
+        assert_eq!(free.gaps[0].score, 120 + PREFERENCE_BONUS);
+    }
+
+    #[test]
+    fn work_hours_across_a_clock_change_are_elapsed_time() {
+        let mut preferences = preferences();
+        preferences.work_days.push("sunday".into());
+        preferences.work_start_time = time(1, 0);
+        preferences.work_end_time = time(6, 0);
+
+        // Clocks go forward at 02:00 on 29 March and back at 03:00 on
+        // 25 October.
+        let spring = find_gaps(&preferences, &[], date(3, 29), 15, None);
+        assert_eq!(spring.total_available_minutes, 4 * 60);
+        let autumn = find_gaps(&preferences, &[], date(10, 25), 15, None);
+        assert_eq!(autumn.total_available_minutes, 6 * 60);
+        assert_eq!(autumn.gaps[0].start_time, berlin(date(10, 25), 1, 0));
+        assert_eq!(autumn.gaps[0].end_time, berlin(date(10, 25), 6, 0));
+    }
+
+    #[test]
+    fn free_intervals_merge_overlapping_and_touching_busy_time() {
+        let free = free_intervals(
+            at(8, 0),
+            at(18, 0),
+            [
+                (at(9, 30), at(11, 0)),
+                (at(9, 0), at(10, 0)),
+                // Touching: no gap between 11:00 and 11:00.
+                (at(11, 0), at(12, 0)),
+                (at(14, 0), at(15, 0)),
+                // Clipped to the window on both sides.
+                (at(7, 0), at(8, 30)),
+                (at(17, 30), at(19, 0)),
+                // Empty, reversed and outside the window: ignored.
+                (at(13, 0), at(13, 0)),
+                (at(16, 0), at(15, 30)),
+                (at(20, 0), at(21, 0)),
+            ],
+        );
+        assert_eq!(
+            free,
+            [
+                (at(8, 30), at(9, 0)),
+                (at(12, 0), at(14, 0)),
+                (at(15, 0), at(17, 30)),
+            ]
+        );
+        assert_eq!(
+            free_intervals(at(8, 0), at(9, 0), []),
+            [(at(8, 0), at(9, 0))]
+        );
+        assert!(free_intervals(at(8, 0), at(9, 0), [(at(7, 0), at(10, 0))]).is_empty());
+    }
+}
@@ -0,0 +1,7 @@
+//! Native schedule computations over the local store, so the desktop app
+//! can answer questions about a day without a server round trip.
+
+pub mod conflicts;
+pub mod gaps;
+pub mod planner;
+pub mod time;
@@ -0,0 +1,487 @@
+//! Deterministic day planner: lays out email triage, lunch, deep-work and
+//! break blocks around the day's meetings and fills the focus blocks from
+//! the backlog. The same inputs always give the same plan, so it works as
+//! an offline fallback for the AI planner and as a baseline to diff the AI's
+//! plan against.
+
+use std::cmp::Reverse;
+
+use chrono::{DateTime, Duration, NaiveDate, Timelike, Utc};
+use serde::Serialize;
+use tauri::State;
+
+use super::gaps::free_intervals;
+use super::time::Zone;
+use crate::error::{Error, Result};
+use crate::store::{BlockType, EnergyLevel, Store, Task, TimeBlock, UserPreferences};
+
+/// Tasks without an estimate are planned as this long.
+const DEFAULT_TASK_MINUTES: i64 = 30;
+const BREAK_MINUTES: i64 = 15;
+/// Don't bother with a deep-work block shorter than this.
+const MIN_FOCUS_MINUTES: i64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposedBlock {
+    #[serde(rename = "type")]
+    pub block_type: BlockType,
+    pub title: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub energy_level: EnergyLevel,
+    pub assigned_tasks: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayPlan {
+    pub date: NaiveDate,
+    /// False on days outside `work_days`, which get no blocks.
+    pub work_day: bool,
+    /// In start order. Existing blocks aren't repeated here.
+    pub blocks: Vec<ProposedBlock>,
+    /// Backlog tasks that didn't fit into any focus block.
+    pub unscheduled_tasks: Vec<String>,
+}
+
+/// Expected energy at `at` on the wall clock in `zone`, using the same
+/// cut-offs migration 010 used to backfill `time_blocks.energy_level`.
+pub fn energy_at(at: DateTime<Utc>, zone: Zone) -> EnergyLevel {
+    let hour = zone.local(at).hour();
+    if hour < 12 {
+        EnergyLevel::High
+    } else if hour < 15 {
+        EnergyLevel::Medium
+    } else {
+        EnergyLevel::Low
+    }
+}
+
+fn task_energy(task: &Task) -> EnergyLevel {
+    match task.priority.as_deref() {
+        Some("high") => EnergyLevel::High,
+        Some("low") => EnergyLevel::Low,
+        _ => EnergyLevel::Medium,
+    }
+}
+
+/// Mirrors `calculateEnergyMatch` in the web app's schedule helpers.
+fn energy_match(block: EnergyLevel, task: EnergyLevel) -> i64 {
+    match (block, task) {
+        (a, b) if a == b => 100,
+        (EnergyLevel::High, EnergyLevel::Low) => 50,
+        (EnergyLevel::Low, EnergyLevel::High) => 25,
+        _ => 75,
+    }
+}
+
+fn task_minutes(task: &Task) -> i64 {
+    task.estimated_minutes
+        .filter(|minutes| *minutes > 0)
+        .unwrap_or(DEFAULT_TASK_MINUTES)
+}
+
+struct Planner<'a> {
+    preferences: &'a UserPreferences,
+    zone: Zone,
+    date: NaiveDate,
+    busy: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+    blocks: Vec<ProposedBlock>,
+}
+
+impl Planner<'_> {
+    fn day(&self) -> (DateTime<Utc>, DateTime<Utc>) {
+        (
+            self.zone
+                .instant(self.date, self.preferences.work_start_time),
+            self.zone.instant(self.date, self.preferences.work_end_time),
+        )
+    }
+
+    fn free(&self) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        let (start, end) = self.day();
+        free_intervals(start, end, self.busy.iter().copied())
+    }
+
+    fn place(&mut self, block_type: BlockType, title: &str, start: DateTime<Utc>, minutes: i64) {
+        let end = start + Duration::minutes(minutes);
+        self.busy.push((start, end));
+        self.blocks.push(ProposedBlock {
+            block_type,
+            title: title.to_owned(),
+            start_time: start,
+            end_time: end,
+            energy_level: energy_at(start, self.zone),
+            assigned_tasks: Vec::new(),
+        });
+    }
+
+    /// Place a block at the first free spot of `minutes` at or after `at`.
+    fn place_from(&mut self, block_type: BlockType, title: &str, at: DateTime<Utc>, minutes: i64) {
+        let slot = self.free().into_iter().find_map(|(start, end)| {
+            let start = start.max(at);
+            (end - start >= Duration::minutes(minutes)).then_some(start)
+        });
+        if let Some(start) = slot {
+            self.place(block_type, title, start, minutes);
+        }
+    }
+
+    fn place_deep_work(&mut self) {
+        let target = self.preferences.target_deep_work_blocks.max(0);
+        let minutes = self.preferences.deep_work_duration_hours * 60;
+        let lunch = self
+            .zone
+            .instant(self.date, self.preferences.lunch_start_time);
+
+        for _ in 0..target {
+            let mut candidates: Vec<_> = self
+                .free()
+                .into_iter()
+                .filter(|(start, end)| (*end - *start).num_minutes() >= MIN_FOCUS_MINUTES)
+                .collect();
+            match self.preferences.deep_work_preference.as_str() {
+                "morning" => candidates.sort_by_key(|(start, _)| *start),
+                "afternoon" => candidates.sort_by_key(|(start, _)| (*start < lunch, *start)),
+                _ => candidates.sort_by_key(|(start, end)| {
+                    (
+                        Reverse((*end - *start).min(Duration::minutes(minutes))),
+                        *start,
+                    )
+                }),
+            }
+            let Some((start, end)) = candidates.first().copied() else {
+                return;
+            };
+
+            let length = (end - start).num_minutes().min(minutes);
+            self.place(BlockType::Focus, "Deep work", start, length);
+            let break_end = start + Duration::minutes(length + BREAK_MINUTES);
+            if break_end <= end {
+                self.place(
+                    BlockType::Break,
+                    "Break",
+                    start + Duration::minutes(length),
+                    BREAK_MINUTES,
+                );
+            }
+        }
+    }
+
+    /// Fill focus blocks, earliest first, with the best-matching tasks that
+    /// fit. Returns the ids of tasks left over.
+    fn assign_tasks(&mut self, tasks: &[Task]) -> Vec<String> {
+        let mut remaining: Vec<&Task> = tasks.iter().collect();
+        remaining.sort_by_key(|task| {
+            (
+                Reverse(task_energy(task) == EnergyLevel::High),
+                Reverse(task.score.unwrap_or(0)),
+                Reverse(task.urgency.unwrap_or(0)),
+                task.created_at,
+                task.id.clone(),
+            )
+        });
+
+        self.blocks.sort_by_key(|block| block.start_time);
+        for block in self.blocks.iter_mut() {
+            if block.block_type != BlockType::Focus {
+                continue;
+            }
+            let mut capacity = (block.end_time - block.start_time).num_minutes();
+            loop {
+                // Stable max: the earliest of the equally good candidates.
+                let best = remaining
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, task)| task_minutes(task) <= capacity)
+                    .max_by_key(|(i, task)| {
+                        (
+                            energy_match(block.energy_level, task_energy(task)),
+                            Reverse(*i),
+                        )
+                    })
+                    .map(|(i, _)| i);
+                let Some(i) = best else { break };
+                let task = remaining.remove(i);
+                capacity -= task_minutes(task);
+                block.assigned_tasks.push(task.id.clone());
+            }
+            if let Some(first) = block.assigned_tasks.first() {
+                if let Some(task) = tasks.iter().find(|task| &task.id == first) {
+                    block.title = format!("Focus: {}", task.title);
+                }
+            }
+        }
+
+        remaining.into_iter().map(|task| task.id.clone()).collect()
+    }
+}
+
+/// Fails on a lunch, triage or deep-work length that isn't positive.
+fn check_durations(preferences: &UserPreferences) -> Result<()> {
+    let p = preferences;
+    let durations = [
+        ("lunch", p.lunch_duration_minutes),
+        ("morning triage", p.morning_triage_duration_minutes),
+        ("evening triage", p.evening_triage_duration_minutes),
+        ("deep work", p.deep_work_duration_hours),
+    ];
+    match durations.iter().find(|(_, length)| *length <= 0) {
+        Some((name, length)) => Err(Error::Schedule(format!(
+            "{name} length must be positive, not {length}"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Plan `date` around `existing` blocks (meetings and anything already
+/// scheduled) and fill it from `backlog`. Days off get an empty plan.
+pub fn plan_day(
+    preferences: &UserPreferences,
+    date: NaiveDate,
+    existing: &[TimeBlock],
+    backlog: &[Task],
+) -> Result<DayPlan> {
+    check_durations(preferences)?;
+    if !preferences.works_on(date) {
+        return Ok(DayPlan {
+            date,
+            work_day: false,
+            blocks: Vec::new(),
+            unscheduled_tasks: backlog.iter().map(|task| task.id.clone()).collect(),
+        });
+    }
+
+    let mut planner = Planner {
+        preferences,
+        zone: preferences.zone(),
+        date,
+        busy: existing
+            .iter()
+            .map(|block| (block.start_time, block.end_time))
+            .collect(),
+        blocks: Vec::new(),
+    };
+
+    let p = preferences;
+    planner.place_from(
+        BlockType::Break,
+        "Lunch",
+        planner.zone.instant(date, p.lunch_start_time),
+        p.lunch_duration_minutes,
+    );
+    planner.place_from(
+        BlockType::Email,
+        "Morning email triage",
+        planner.zone.instant(date, p.morning_triage_time),
+        p.morning_triage_duration_minutes,
+    );
+    planner.place_from(
+        BlockType::Email,
+        "Evening email triage",
+        planner.zone.instant(date, p.evening_triage_time),
+        p.evening_triage_duration_minutes,
+    );
+    planner.place_deep_work();
+    let unscheduled_tasks = planner.assign_tasks(backlog);
+
+    Ok(DayPlan {
+        date,
+        work_day: true,
+        blocks: planner.blocks,
+        unscheduled_tasks,
+    })
+}
+
+/// Plan `date` from what's in the store.
+pub fn plan_for(store: &Store, date: NaiveDate) -> Result<DayPlan> {
+    let preferences = store
+        .get_preferences()?
+        .unwrap_or_else(|| UserPreferences::defaults(""));
+    let (start, end) = preferences.zone().day_bounds(date);
+    let existing = store.list_time_blocks(start, end)?;
+    let backlog: Vec<Task> = store
+        .list_tasks(None)?
+        .into_iter()
+        .filter(|task| matches!(task.status.as_deref(), Some("backlog") | Some("active")))
+        .filter(|task| !task.completed)
+        .collect();
+    plan_day(&preferences, date, &existing, &backlog)
+}
+
+#[tauri::command]
+pub fn schedule_plan_day(date: NaiveDate, store: State<'_, Store>) -> Result<DayPlan> {
+    plan_for(&store, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+
+    use super::*;
+    use crate::testing;
+
+    fn preferences() -> UserPreferences {
+        let mut preferences = UserPreferences::defaults("u1");
+        preferences.timezone = "Europe/Berlin".into();
+        preferences
+    }
+
+    /// Monday 2 March 2026.
+    fn monday() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, 2).unwrap()
+    }
+
+    fn berlin(date: NaiveDate, hour: u32, minute: u32) -> DateTime<Utc> {
+        Zone::named("Europe/Berlin")
+            .unwrap()
+            .instant(date, NaiveTime::from_hms_opt(hour, minute, 0).unwrap())
+    }
+
+    fn meeting(id: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> TimeBlock {
+        TimeBlock {
+            block_type: BlockType::Meeting,
+            source: Some("calendar".into()),
+            energy_level: EnergyLevel::Medium,
+            ..testing::block(id, start, end)
+        }
+    }
+
+    fn task(id: &str, priority: Option<&str>, minutes: Option<i64>, score: i64) -> Task {
+        Task {
+            priority: priority.map(str::to_owned),
+            estimated_minutes: minutes,
+            score: Some(score),
+            ..testing::task(id, id)
+        }
+    }
+
+    fn backlog() -> Vec<Task> {
+        vec![
+            task("write-report", Some("high"), Some(90), 80),
+            task("review-pr", None, Some(30), 60),
+            task("expenses", Some("low"), None, 10),
+            task("plan-offsite", Some("high"), Some(60), 70),
+            task("inbox-zero", None, Some(0), 40),
+            task("huge", None, Some(600), 90),
+        ]
+    }
+
+    fn meetings() -> Vec<TimeBlock> {
+        let day = monday();
+        vec![
+            meeting("standup", berlin(day, 9, 0), berlin(day, 9, 30)),
+            meeting("1:1", berlin(day, 14, 0), berlin(day, 15, 0)),
+        ]
+    }
+
+    #[test]
+    fn the_same_inputs_give_the_same_plan() {
+        let plan = plan_day(&preferences(), monday(), &meetings(), &backlog()).unwrap();
+
+        let mut shuffled_backlog = backlog();
+        shuffled_backlog.reverse();
+        shuffled_backlog.swap(0, 3);
+        let mut shuffled_meetings = meetings();
+        shuffled_meetings.reverse();
+        for _ in 0..3 {
+            let again = plan_day(
+                &preferences(),
+                monday(),
+                &shuffled_meetings,
+                &shuffled_backlog,
+            );
+            assert_eq!(again.unwrap(), plan);
+        }
+    }
+
+    #[test]
+    fn plans_around_meetings_without_overlaps() {
+        let plan = plan_day(&preferences(), monday(), &meetings(), &backlog()).unwrap();
+        assert!(plan.work_day);
+
+        let (start, end) = (berlin(monday(), 8, 0), berlin(monday(), 18, 0));
+        let mut taken: Vec<(DateTime<Utc>, DateTime<Utc>)> = meetings()
+            .iter()
+            .map(|block| (block.start_time, block.end_time))
+            .collect();
+        for block in &plan.blocks {
+            assert!(block.start_time < block.end_time, "{block:?}");
+            assert!(
+                block.start_time >= start && block.end_time <= end,
+                "{block:?}"
+            );
+            for (s, e) in &taken {
+                assert!(block.end_time <= *s || block.start_time >= *e, "{block:?}");
+            }
+            taken.push((block.start_time, block.end_time));
+        }
+        assert!(plan
+            .blocks
+            .windows(2)
+            .all(|w| w[0].start_time <= w[1].start_time));
+
+        let lunch = plan.blocks.iter().find(|b| b.title == "Lunch").unwrap();
+        assert_eq!(lunch.start_time, berlin(monday(), 12, 0));
+        let focus: Vec<&ProposedBlock> = plan
+            .blocks
+            .iter()
+            .filter(|b| b.block_type == BlockType::Focus)
+            .collect();
+        assert_eq!(focus.len(), 2);
+
+        // Every task is either placed once or left over; the ten-hour one
+        // can't fit anywhere.
+        let mut seen: Vec<&String> = focus.iter().flat_map(|b| &b.assigned_tasks).collect();
+        seen.extend(&plan.unscheduled_tasks);
+        seen.sort();
+        let mut ids: Vec<String> = backlog().into_iter().map(|task| task.id).collect();
+        ids.sort();
+        assert_eq!(seen, ids.iter().collect::<Vec<_>>());
+        assert!(plan.unscheduled_tasks.contains(&"huge".to_owned()));
+    }
+
+    #[test]
+    fn days_off_get_no_blocks() {
+        let saturday = NaiveDate::from_ymd_opt(2026, 3, 7).unwrap();
+        let plan = plan_day(&preferences(), saturday, &[], &backlog()).unwrap();
+        assert!(!plan.work_day);
+        assert!(plan.blocks.is_empty());
+        assert_eq!(plan.unscheduled_tasks.len(), backlog().len());
+
+        let mut weekends = preferences();
+        weekends.work_days = vec!["Saturday".into(), "sunday".into()];
+        let plan = plan_day(&weekends, saturday, &[], &backlog()).unwrap();
+        assert!(plan.work_day);
+        assert!(!plan.blocks.is_empty());
+        assert!(!plan_day(&weekends, monday(), &[], &[]).unwrap().work_day);
+    }
+
+    #[test]
+    fn rejects_lengths_that_are_not_positive() {
+        let cases: [fn(&mut UserPreferences); 4] = [
+            |p| p.lunch_duration_minutes = 0,
+            |p| p.morning_triage_duration_minutes = -30,
+            |p| p.evening_triage_duration_minutes = 0,
+            |p| p.deep_work_duration_hours = -1,
+        ];
+        for break_it in cases {
+            let mut preferences = preferences();
+            break_it(&mut preferences);
+            let err = plan_day(&preferences, monday(), &[], &backlog()).unwrap_err();
+            assert!(matches!(err, Error::Schedule(_)), "{err}");
+        }
+    }
+
+    #[test]
+    fn energy_follows_the_clock_in_the_zone() {
+        let zone = Zone::named("Europe/Berlin").unwrap();
+        assert_eq!(energy_at(berlin(monday(), 11, 59), zone), EnergyLevel::High);
+        assert_eq!(
+            energy_at(berlin(monday(), 12, 0), zone),
+            EnergyLevel::Medium
+        );
+        assert_eq!(energy_at(berlin(monday(), 15, 0), zone), EnergyLevel::Low);
+    }
+}
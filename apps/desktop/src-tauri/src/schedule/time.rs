@@ -0,0 +1,567 @@
+//! Wall-clock times on the timeline. `user_preferences` keeps times like
+//! `work_start_time` as zone-less `TIME`s; here they're read in the user's
+//! IANA zone (`user_preferences.timezone`) to get the instants they mean on
+//! a given day, including days when DST skips or repeats them.
+//!
+//! Travel mode keeps that zone in step with the system's: when the system
+//! timezone changes, the preferences follow it (the home zone is remembered
+//! for when travel mode is turned off) and today is planned again, so "9am"
+//! means 9am wherever the user is.
+
+use std::time::Duration as StdDuration;
+
+use chrono::{
+    DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, Offset, TimeZone, Utc,
+};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+use super::planner::{plan_for, DayPlan};
+use crate::error::{Error, Result};
+use crate::queue::{Action, Connectivity, NewOperation, Table};
+use crate::store::{Store, UserPreferences};
+
+pub const ZONE_CHANGED_EVENT: &str = "time://zone-changed";
+
+const TRAVEL_SETTING: &str = "time.travel";
+/// The system zone as last seen, so a change while the app was closed is
+/// noticed on the next start.
+const SYSTEM_ZONE_SETTING: &str = "time.system_zone";
+const WATCH_INTERVAL: StdDuration = StdDuration::from_secs(60);
+
+/// Where a wall-clock time falls on a particular day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Placement {
+    Exact {
+        at: DateTime<Utc>,
+    },
+    /// The clocks jumped over it. `at` reads it with the offset from before
+    /// the jump, so 2:30 on a spring-forward day lands where 3:30 is.
+    Skipped {
+        at: DateTime<Utc>,
+    },
+    /// The clocks went back over it, so it happened twice.
+    Repeated {
+        earlier: DateTime<Utc>,
+        later: DateTime<Utc>,
+    },
+}
+
+impl Placement {
+    /// The single instant to use: the earlier one of a repeated time.
+    pub fn instant(self) -> DateTime<Utc> {
+        match self {
+            Placement::Exact { at } | Placement::Skipped { at } => at,
+            Placement::Repeated { earlier, .. } => earlier,
+        }
+    }
+}
+
+/// Place `naive` in `zone`.
+pub(crate) fn resolve_in<Z: TimeZone>(zone: &Z, naive: NaiveDateTime) -> Placement {
+    match zone.from_local_datetime(&naive) {
+        chrono::LocalResult::Single(at) => Placement::Exact {
+            at: at.with_timezone(&Utc),
+        },
+        chrono::LocalResult::Ambiguous(a, b) => {
+            let (a, b) = (a.with_timezone(&Utc), b.with_timezone(&Utc));
+            Placement::Repeated {
+                earlier: a.min(b),
+                later: a.max(b),
+            }
+        }
+        chrono::LocalResult::None => {
+            // A day earlier is safely before the jump; no zone changes its
+            // offset twice in a day.
+            let before = zone
+                .offset_from_utc_datetime(&(naive - Duration::days(1)))
+                .fix();
+            Placement::Skipped {
+                at: (naive - before).and_utc(),
+            }
+        }
+    }
+}
+
+/// The instant `naive` means in `zone`, read as [`Placement::instant`].
+pub(crate) fn zoned_instant<Z: TimeZone>(zone: &Z, naive: NaiveDateTime) -> DateTime<Utc> {
+    resolve_in(zone, naive).instant()
+}
+
+/// The system timezone's IANA name, if it can be told.
+pub fn system_zone_name() -> Option<String> {
+    iana_time_zone::get_timezone().ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Zone {
+    Named(Tz),
+    /// The system timezone, for when no IANA name is known.
+    System,
+}
+
+impl Zone {
+    pub fn named(name: &str) -> Option<Self> {
+        name.parse::<Tz>().ok().map(Zone::Named)
+    }
+
+    /// The system's current timezone.
+    pub fn system() -> Self {
+        system_zone_name()
+            .and_then(|name| Self::named(&name))
+            .unwrap_or(Zone::System)
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Zone::Named(tz) => Some(tz.name()),
+            Zone::System => None,
+        }
+    }
+
+    pub fn resolve(&self, date: NaiveDate, time: NaiveTime) -> Placement {
+        let naive = date.and_time(time);
+        match self {
+            Zone::Named(tz) => resolve_in(tz, naive),
+            Zone::System => resolve_in(&Local, naive),
+        }
+    }
+
+    pub fn instant(&self, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+        self.resolve(date, time).instant()
+    }
+
+    /// `[start, end)` of `date`. Days with a DST change are 23 or 25 hours
+    /// long, and a day whose midnight is skipped starts at the jump.
+    pub fn day_bounds(&self, date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+        let next = date.succ_opt().unwrap_or(date);
+        (
+            self.instant(date, NaiveTime::MIN),
+            self.instant(next, NaiveTime::MIN),
+        )
+    }
+
+    /// The wall-clock time `at` shows in this zone.
+    pub fn local(&self, at: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Named(tz) => at.with_timezone(tz).naive_local(),
+            Zone::System => at.with_timezone(&Local).naive_local(),
+        }
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.local(Utc::now()).date()
+    }
+}
+
+impl UserPreferences {
+    /// The zone the preferences' wall-clock times are in; the system zone if
+    /// the stored name isn't a known IANA zone.
+    pub fn zone(&self) -> Zone {
+        Zone::named(&self.timezone).unwrap_or(Zone::System)
+    }
+
+    /// Whether `date` falls on one of the work days.
+    pub fn works_on(&self, date: NaiveDate) -> bool {
+        let weekday = date.format("%A").to_string();
+        self.work_days
+            .iter()
+            .any(|day| day.eq_ignore_ascii_case(&weekday))
+    }
+}
+
+/// The instants the user's preferences mean on one day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayTimes {
+    pub date: NaiveDate,
+    pub timezone: Option<&'static str>,
+    pub day_start: DateTime<Utc>,
+    pub day_end: DateTime<Utc>,
+    pub work_start: Placement,
+    pub work_end: Placement,
+    pub lunch_start: Placement,
+    pub lunch_end: Placement,
+    pub morning_triage: Placement,
+    pub evening_triage: Placement,
+}
+
+pub fn day_times(preferences: &UserPreferences, date: NaiveDate) -> DayTimes {
+    let zone = preferences.zone();
+    let resolve = |time| zone.resolve(date, time);
+    // Lunch lasts its duration in wall-clock time, even across a DST change.
+    let lunch_end = date.and_time(preferences.lunch_start_time)
+        + Duration::minutes(preferences.lunch_duration_minutes);
+    let (day_start, day_end) = zone.day_bounds(date);
+    DayTimes {
+        date,
+        timezone: zone.name(),
+        day_start,
+        day_end,
+        work_start: resolve(preferences.work_start_time),
+        work_end: resolve(preferences.work_end_time),
+        lunch_start: resolve(preferences.lunch_start_time),
+        lunch_end: zone.resolve(lunch_end.date(), lunch_end.time()),
+        morning_triage: resolve(preferences.morning_triage_time),
+        evening_triage: resolve(preferences.evening_triage_time),
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TravelSettings {
+    enabled: bool,
+    /// The preferences' zone from before travel mode took over.
+    home_zone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoneStatus {
+    pub travel_mode: bool,
+    pub home_zone: Option<String>,
+    pub system_zone: Option<String>,
+    /// The zone preferences are read in right now.
+    pub timezone: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZoneChange {
+    pub from: Option<String>,
+    pub to: String,
+    /// Today planned again in the new zone, when travel mode moved the
+    /// preferences there.
+    pub plan: Option<DayPlan>,
+}
+
+fn status(store: &Store) -> Result<ZoneStatus> {
+    let travel: TravelSettings = store.setting(TRAVEL_SETTING)?.unwrap_or_default();
+    Ok(ZoneStatus {
+        travel_mode: travel.enabled,
+        home_zone: travel.home_zone,
+        system_zone: system_zone_name(),
+        timezone: store
+            .get_preferences()?
+            .map(|preferences| preferences.timezone),
+    })
+}
+
+/// Move the preferences to `timezone` and queue the change for the server.
+/// `false` if there's nothing to change.
+fn set_timezone<R: Runtime>(app: &AppHandle<R>, timezone: &str) -> Result<bool> {
+    let store = app.state::<Store>();
+    let Some(mut preferences) = store.get_preferences()? else {
+        return Ok(false);
+    };
+    if preferences.timezone == timezone {
+        return Ok(false);
+    }
+    preferences.timezone = timezone.to_owned();
+    preferences.updated_at = Utc::now();
+    store.save_preferences(&preferences)?;
+    store.enqueue(NewOperation {
+        idempotency_key: None,
+        table: Table::UserPreferences,
+        action: Action::Update,
+        row_id: Some(preferences.id.clone()),
+        payload: json!({ "timezone": preferences.timezone }),
+    })?;
+    app.state::<Connectivity>().wake();
+    Ok(true)
+}
+
+/// Bring everything that depends on the user's zone up to date.
+fn replan<R: Runtime>(app: &AppHandle<R>) -> Result<Option<DayPlan>> {
+    if let Err(err) = crate::recurrence::refresh(app) {
+        log::error!("failed to materialize recurrences: {err}");
+    }
+    crate::reminders::reschedule(app);
+    #[cfg(desktop)]
+    crate::tray::refresh(app);
+    let store = app.state::<Store>();
+    let Some(preferences) = store.get_preferences()? else {
+        return Ok(None);
+    };
+    Ok(Some(plan_for(&store, preferences.zone().today())?))
+}
+
+fn on_zone_change<R: Runtime>(app: &AppHandle<R>, from: Option<String>, to: String) -> Result<()> {
+    let store = app.state::<Store>();
+    let travel: TravelSettings = store.setting(TRAVEL_SETTING)?.unwrap_or_default();
+    let plan = if travel.enabled && Zone::named(&to).is_some() && set_timezone(app, &to)? {
+        replan(app)?
+    } else {
+        None
+    };
+    if let Err(err) = app.emit(ZONE_CHANGED_EVENT, ZoneChange { from, to, plan }) {
+        log::warn!("failed to emit zone change: {err}");
+    }
+    Ok(())
+}
+
+fn check<R: Runtime>(app: &AppHandle<R>) -> Result<()> {
+    let Some(current) = system_zone_name() else {
+        return Ok(());
+    };
+    let store = app.state::<Store>();
+    let last: Option<String> = store.setting(SYSTEM_ZONE_SETTING)?;
+    if last.as_deref() == Some(current.as_str()) {
+        return Ok(());
+    }
+    store.set_setting(SYSTEM_ZONE_SETTING, &current)?;
+    match last {
+        // First start: nothing to compare against yet.
+        None => Ok(()),
+        Some(last) => on_zone_change(app, Some(last), current),
+    }
+}
+
+/// Watch the system timezone.
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(err) = check(&handle) {
+                log::error!("failed to check the system timezone: {err}");
+            }
+            tokio::time::sleep(WATCH_INTERVAL).await;
+        }
+    });
+    Ok(())
+}
+
+/// What the user's preferences mean on `date`, today by default.
+#[tauri::command]
+pub fn schedule_day_times(date: Option<NaiveDate>, store: State<'_, Store>) -> Result<DayTimes> {
+    let preferences = store
+        .get_preferences()?
+        .unwrap_or_else(|| UserPreferences::defaults(""));
+    let date = date.unwrap_or_else(|| preferences.zone().today());
+    Ok(day_times(&preferences, date))
+}
+
+#[tauri::command]
+pub fn schedule_zone_status(store: State<'_, Store>) -> Result<ZoneStatus> {
+    status(&store)
+}
+
+/// Turn travel mode on, moving the preferences to the system zone, or off,
+/// moving them back home.
+#[tauri::command]
+pub fn schedule_set_travel_mode<R: Runtime>(
+    app: AppHandle<R>,
+    enabled: bool,
+    store: State<'_, Store>,
+) -> Result<ZoneStatus> {
+    let mut travel: TravelSettings = store.setting(TRAVEL_SETTING)?.unwrap_or_default();
+    let target = if enabled {
+        if !travel.enabled {
+            travel.home_zone = store
+                .get_preferences()?
+                .map(|preferences| preferences.timezone);
+        }
+        Some(
+            system_zone_name()
+                .filter(|name| Zone::named(name).is_some())
+                .ok_or_else(|| Error::Timezone("the system timezone can't be told".into()))?,
+        )
+    } else {
+        travel.home_zone.take()
+    };
+    travel.enabled = enabled;
+    store.set_setting(TRAVEL_SETTING, &travel)?;
+    if let Some(target) = target {
+        if set_timezone(&app, &target)? {
+            replan(&app)?;
+        }
+    }
+    status(&store)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Timelike;
+    use chrono_tz::America::{New_York, Santiago};
+
+    use super::*;
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    fn utc(m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, m, d, h, min, 0).unwrap()
+    }
+
+    fn date(m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, m, d).unwrap()
+    }
+
+    fn time(h: u32, min: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(h, min, 0).unwrap()
+    }
+
+    #[test]
+    fn spring_forward_skips_an_hour() {
+        // New York goes from 02:00 EST to 03:00 EDT on 8 March 2026.
+        assert_eq!(
+            resolve_in(&New_York, local(2026, 3, 8, 1, 30)),
+            Placement::Exact {
+                at: utc(3, 8, 6, 30)
+            }
+        );
+        let skipped = resolve_in(&New_York, local(2026, 3, 8, 2, 30));
+        assert_eq!(
+            skipped,
+            Placement::Skipped {
+                at: utc(3, 8, 7, 30)
+            }
+        );
+        // Read with the offset from before the jump, it lands on 03:30.
+        assert_eq!(skipped.instant().with_timezone(&New_York).hour(), 3);
+        assert_eq!(
+            resolve_in(&New_York, local(2026, 3, 8, 3, 30)),
+            Placement::Exact {
+                at: utc(3, 8, 7, 30)
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(skipped).unwrap(),
+            json!({ "kind": "skipped", "at": "2026-03-08T07:30:00Z" })
+        );
+
+        let zone = Zone::Named(New_York);
+        assert_eq!(
+            zone.day_bounds(date(3, 8)),
+            (utc(3, 8, 5, 0), utc(3, 9, 4, 0))
+        );
+    }
+
+    #[test]
+    fn fall_back_repeats_an_hour() {
+        // New York goes from 02:00 EDT back to 01:00 EST on 1 November.
+        let repeated = resolve_in(&New_York, local(2026, 11, 1, 1, 30));
+        assert_eq!(
+            repeated,
+            Placement::Repeated {
+                earlier: utc(11, 1, 5, 30),
+                later: utc(11, 1, 6, 30),
+            }
+        );
+        assert_eq!(repeated.instant(), utc(11, 1, 5, 30));
+        assert_eq!(
+            zoned_instant(&New_York, local(2026, 11, 1, 2, 30)),
+            utc(11, 1, 7, 30)
+        );
+
+        let zone = Zone::Named(New_York);
+        let (start, end) = zone.day_bounds(date(11, 1));
+        assert_eq!((start, end), (utc(11, 1, 4, 0), utc(11, 2, 5, 0)));
+        assert_eq!(end - start, Duration::hours(25));
+        assert_eq!(zone.local(utc(11, 1, 6, 30)), local(2026, 11, 1, 1, 30));
+    }
+
+    #[test]
+    fn a_skipped_midnight_starts_the_day_at_the_jump() {
+        // Chile moves its clocks from 00:00 to 01:00 on 6 September 2026
+        // and back from 00:00 to 23:00 on 5 April.
+        assert_eq!(
+            resolve_in(&Santiago, local(2026, 9, 6, 0, 0)),
+            Placement::Skipped {
+                at: utc(9, 6, 4, 0)
+            }
+        );
+        let zone = Zone::Named(Santiago);
+        let (start, end) = zone.day_bounds(date(9, 6));
+        assert_eq!((start, end), (utc(9, 6, 4, 0), utc(9, 7, 3, 0)));
+        assert_eq!(end - start, Duration::hours(23));
+        assert_eq!(zone.local(start), local(2026, 9, 6, 1, 0));
+
+        let (start, end) = zone.day_bounds(date(4, 4));
+        assert_eq!((start, end), (utc(4, 4, 3, 0), utc(4, 5, 4, 0)));
+        assert_eq!(end - start, Duration::hours(25));
+    }
+
+    #[test]
+    fn day_times_across_dst_changes() {
+        let mut preferences = UserPreferences::defaults("u1");
+        preferences.work_start_time = time(2, 30);
+        preferences.lunch_start_time = time(1, 30);
+        preferences.lunch_duration_minutes = 60;
+        preferences.morning_triage_time = time(1, 30);
+
+        let spring = day_times(&preferences, date(3, 8));
+        assert_eq!(spring.timezone, Some("America/New_York"));
+        assert_eq!(
+            (spring.day_start, spring.day_end),
+            (utc(3, 8, 5, 0), utc(3, 9, 4, 0))
+        );
+        assert_eq!(
+            spring.work_start,
+            Placement::Skipped {
+                at: utc(3, 8, 7, 30)
+            }
+        );
+        // Lunch ends an hour of wall-clock time after it starts, which on
+        // this day is a skipped time.
+        assert_eq!(
+            spring.lunch_start,
+            Placement::Exact {
+                at: utc(3, 8, 6, 30)
+            }
+        );
+        assert_eq!(
+            spring.lunch_end,
+            Placement::Skipped {
+                at: utc(3, 8, 7, 30)
+            }
+        );
+        assert_eq!(
+            spring.work_end,
+            Placement::Exact {
+                at: utc(3, 8, 22, 0)
+            }
+        );
+
+        let fall = day_times(&preferences, date(11, 1));
+        assert_eq!(
+            fall.morning_triage,
+            Placement::Repeated {
+                earlier: utc(11, 1, 5, 30),
+                later: utc(11, 1, 6, 30),
+            }
+        );
+        assert_eq!(
+            fall.lunch_end,
+            Placement::Exact {
+                at: utc(11, 1, 7, 30)
+            }
+        );
+
+        preferences.timezone = "America/Santiago".into();
+        preferences.morning_triage_time = time(0, 30);
+        let chile = day_times(&preferences, date(9, 6));
+        assert_eq!(
+            (chile.day_start, chile.day_end),
+            (utc(9, 6, 4, 0), utc(9, 7, 3, 0))
+        );
+        assert_eq!(
+            chile.morning_triage,
+            Placement::Skipped {
+                at: utc(9, 6, 4, 30)
+            }
+        );
+
+        // An unknown zone name falls back to the system's.
+        preferences.timezone = "Mars/Olympus_Mons".into();
+        assert_eq!(preferences.zone(), Zone::System);
+        assert_eq!(day_times(&preferences, date(3, 8)).timezone, None);
+    }
+}
@@ -0,0 +1,136 @@
+//! The desktop's do-not-disturb switch. KDE Plasma takes a notification
+//! inhibition over D-Bus that lasts as long as the connection, so it lifts
+//! by itself if Dayli dies. GNOME only has the `show-banners` setting, which
+//! persists; its previous value is recorded so a crash can be undone on the
+//! next start.
+
+use crate::error::Result;
+
+pub enum Dnd {
+    #[cfg(target_os = "linux")]
+    Gnome { show_banners: bool },
+    #[cfg(target_os = "linux")]
+    Kde {
+        conn: zbus::blocking::Connection,
+        cookie: u32,
+    },
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::collections::HashMap;
+    use std::process::Command;
+
+    use zbus::zvariant::Value;
+
+    use super::Dnd;
+    use crate::error::{Error, Result};
+
+    const SCHEMA: &str = "org.gnome.desktop.notifications";
+    const KEY: &str = "show-banners";
+
+    fn desktop_is(name: &str) -> bool {
+        std::env::var("XDG_CURRENT_DESKTOP")
+            .is_ok_and(|desktops| desktops.split(':').any(|d| d.eq_ignore_ascii_case(name)))
+    }
+
+    fn gsettings(args: &[&str]) -> Result<String> {
+        let output = Command::new("gsettings").args(args).output()?;
+        if !output.status.success() {
+            return Err(Error::Shield(
+                String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+    }
+
+    pub fn set_gnome_banners(show: bool) -> Result<()> {
+        gsettings(&["set", SCHEMA, KEY, if show { "true" } else { "false" }])?;
+        Ok(())
+    }
+
+    fn kde_call<B>(
+        conn: &zbus::blocking::Connection,
+        method: &str,
+        body: &B,
+    ) -> Result<zbus::Message>
+    where
+        B: serde::Serialize + zbus::zvariant::DynamicType,
+    {
+        conn.call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            method,
+            body,
+        )
+        .map_err(|err| Error::Shield(err.to_string()))
+    }
+
+    pub fn enable(record: impl FnOnce(bool) -> Result<()>) -> Result<Option<Dnd>> {
+        if desktop_is("KDE") {
+            let conn = zbus::blocking::Connection::session()
+                .map_err(|err| Error::Shield(err.to_string()))?;
+            let hints: HashMap<&str, Value> = HashMap::new();
+            let reply = kde_call(&conn, "Inhibit", &("dayli", "Focus block", hints))?;
+            let cookie: u32 = reply
+                .body()
+                .deserialize()
+                .map_err(|err| Error::Shield(err.to_string()))?;
+            return Ok(Some(Dnd::Kde { conn, cookie }));
+        }
+        if desktop_is("GNOME") {
+            let show_banners = gsettings(&["get", SCHEMA, KEY])? != "false";
+            record(show_banners)?;
+            set_gnome_banners(false)?;
+            return Ok(Some(Dnd::Gnome { show_banners }));
+        }
+        Ok(None)
+    }
+
+    pub fn disable(dnd: Dnd) -> Result<()> {
+        match dnd {
+            Dnd::Gnome { show_banners } => set_gnome_banners(show_banners),
+            Dnd::Kde { conn, cookie } => kde_call(&conn, "UnInhibit", &(cookie,)).map(|_| ()),
+        }
+    }
+}
+
+/// Turn on do-not-disturb, or `None` if this desktop has no switch Dayli
+/// knows about. On GNOME, `record` gets the `show-banners` value to restore
+/// before anything is changed; if it fails, nothing is.
+pub fn enable(record: impl FnOnce(bool) -> Result<()>) -> Result<Option<Dnd>> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::enable(record)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = record;
+        Ok(None)
+    }
+}
+
+pub fn disable(dnd: Dnd) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::disable(dnd)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        match dnd {}
+    }
+}
+
+/// Undo a GNOME change left behind by a crash.
+pub fn restore_gnome(show_banners: bool) -> Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::set_gnome_banners(show_banners)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = show_banners;
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+//! Domain blocking through a hosts file. Dayli only ever touches its own
+//! marked section, so clearing it is safe to repeat and leaves the rest of
+//! the file as it was.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Result;
+
+pub const DEFAULT_PATH: &str = "/etc/hosts";
+
+const BEGIN: &str = "# BEGIN dayli focus shield";
+const END: &str = "# END dayli focus shield";
+
+/// A bare lowercase host name from whatever the user typed, e.g.
+/// `https://www.reddit.com/r/rust` becomes `www.reddit.com`.
+pub fn normalize(domain: &str) -> Option<String> {
+    let domain = domain.trim();
+    let domain = domain.split_once("://").map_or(domain, |(_, rest)| rest);
+    let host = domain.split(['/', ':', '?', '#']).next()?.trim_matches('.');
+    let valid = !host.is_empty()
+        && host.contains('.')
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    valid.then(|| host.to_ascii_lowercase())
+}
+
+/// Each host in `domains` once, in the order first given; what doesn't
+/// normalize is dropped.
+pub fn normalize_all(domains: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    domains
+        .iter()
+        .filter_map(|domain| normalize(domain))
+        .filter(|host| seen.insert(host.clone()))
+        .collect()
+}
+
+fn strip(contents: &str) -> String {
+    let mut out = String::with_capacity(contents.len());
+    let mut inside = false;
+    for line in contents.lines() {
+        match line.trim() {
+            BEGIN => inside = true,
+            END => inside = false,
+            _ if !inside => {
+                out.push_str(line);
+                out.push('\n');
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+fn with_section(contents: &str, domains: &[String]) -> String {
+    let mut out = strip(contents);
+    out.push_str(BEGIN);
+    out.push('\n');
+    for domain in domains {
+        let bare = domain.strip_prefix("www.").unwrap_or(domain);
+        for host in [bare.to_owned(), format!("www.{bare}")] {
+            out.push_str(&format!("0.0.0.0 {host}\n:: {host}\n"));
+        }
+    }
+    out.push_str(END);
+    out.push('\n');
+    out
+}
+
+/// Point `domains` at nowhere. The file is rewritten in place; its directory
+/// usually isn't writable even when the file has been made so.
+pub fn block(path: &Path, domains: &[String]) -> Result<()> {
+    let contents = fs::read_to_string(path)?;
+    fs::write(path, with_section(&contents, domains))?;
+    Ok(())
+}
+
+/// Remove Dayli's section, if there is one.
+pub fn clear(path: &Path) -> Result<()> {
+    let contents = fs::read_to_string(path)?;
+    let stripped = strip(&contents);
+    if stripped != contents {
+        fs::write(path, stripped)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_HOSTS: &str =
+        "127.0.0.1 localhost\n::1 localhost\n\n# my printer\n192.168.1.9 printer.lan\n";
+
+    fn domains(domains: &[&str]) -> Vec<String> {
+        domains.iter().map(|d| d.to_string()).collect()
+    }
+
+    #[test]
+    fn normalize_keeps_only_the_host() {
+        let cases = [
+            ("reddit.com", Some("reddit.com")),
+            ("  News.YCombinator.com ", Some("news.ycombinator.com")),
+            ("https://www.reddit.com/r/rust", Some("www.reddit.com")),
+            ("http://example.com:8080/?q=1", Some("example.com")),
+            ("example.com#top", Some("example.com")),
+            (".example.com.", Some("example.com")),
+            ("xn--bcher-kva.example", Some("xn--bcher-kva.example")),
+        ];
+        for (input, expected) in cases {
+            assert_eq!(normalize(input).as_deref(), expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn normalize_rejects_what_isnt_a_host_name() {
+        for input in [
+            "",
+            "   ",
+            "localhost",
+            "https://",
+            "exa mple.com",
+            "ex_ample.com",
+            "bücher.example",
+            "evil.com\n0.0.0.0 bank.com",
+        ] {
+            assert_eq!(normalize(input), None, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn normalize_all_drops_duplicates_anywhere_in_the_list() {
+        let list = domains(&[
+            "reddit.com",
+            "x.com",
+            "https://Reddit.com/r/rust",
+            "not a host",
+            "x.com",
+            "news.ycombinator.com",
+        ]);
+        assert_eq!(
+            normalize_all(&list),
+            ["reddit.com", "x.com", "news.ycombinator.com"]
+        );
+    }
+
+    #[test]
+    fn the_section_blocks_both_the_bare_and_www_names() {
+        let out = with_section(USER_HOSTS, &domains(&["www.reddit.com", "x.com"]));
+        let section: Vec<_> = out.lines().skip_while(|line| *line != BEGIN).collect();
+        assert_eq!(
+            section,
+            [
+                BEGIN,
+                "0.0.0.0 reddit.com",
+                ":: reddit.com",
+                "0.0.0.0 www.reddit.com",
+                ":: www.reddit.com",
+                "0.0.0.0 x.com",
+                ":: x.com",
+                "0.0.0.0 www.x.com",
+                ":: www.x.com",
+                END,
+            ]
+        );
+    }
+
+    #[test]
+    fn user_lines_are_left_alone() {
+        let out = with_section(USER_HOSTS, &domains(&["reddit.com"]));
+        assert!(out.starts_with(USER_HOSTS));
+        assert_eq!(strip(&out), USER_HOSTS);
+
+        // Lines the user added after the section survive too.
+        let edited = format!("{out}10.0.0.2 nas.lan\n");
+        assert_eq!(strip(&edited), format!("{USER_HOSTS}10.0.0.2 nas.lan\n"));
+    }
+
+    #[test]
+    fn rewriting_is_idempotent() {
+        let list = domains(&["reddit.com", "x.com"]);
+        let once = with_section(USER_HOSTS, &list);
+        assert_eq!(with_section(&once, &list), once);
+        assert_eq!(strip(&strip(&once)), strip(&once));
+        assert_eq!(strip(USER_HOSTS), USER_HOSTS);
+
+        // A new list replaces the old section rather than adding another.
+        let replaced = with_section(&once, &domains(&["news.ycombinator.com"]));
+        assert_eq!(replaced.matches(BEGIN).count(), 1);
+        assert!(!replaced.contains("reddit.com"));
+        assert!(replaced.contains("0.0.0.0 news.ycombinator.com"));
+    }
+
+    #[test]
+    fn block_then_clear_restores_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hosts");
+        fs::write(&path, USER_HOSTS).unwrap();
+
+        block(&path, &domains(&["reddit.com"])).unwrap();
+        block(&path, &domains(&["reddit.com"])).unwrap();
+        let blocked = fs::read_to_string(&path).unwrap();
+        assert_eq!(blocked.matches("0.0.0.0 reddit.com").count(), 1);
+
+        clear(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), USER_HOSTS);
+        clear(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), USER_HOSTS);
+    }
+}
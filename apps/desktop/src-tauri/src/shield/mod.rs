@@ -0,0 +1,276 @@
+//! The focus shield: while a focus block is running, or a focus session is
+//! open, turn on the desktop's do-not-disturb and optionally send a list of
+//! distracting domains nowhere through the hosts file. Everything is put
+//! back when focus ends and on exit. What was changed is recorded in the
+//! store before the change, so after a crash the next start undoes it.
+
+mod dnd;
+mod hosts;
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration as StdDuration;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Listener, Manager, Runtime, State};
+
+use crate::config::AppConfig;
+use crate::error::Result;
+use crate::store::{BlockType, Store};
+
+use dnd::Dnd;
+
+pub use hosts::DEFAULT_PATH as HOSTS_PATH;
+
+pub const STATUS_EVENT: &str = "shield://status";
+
+const SETTINGS_KEY: &str = "shield.settings";
+const APPLIED_KEY: &str = "shield.applied";
+const CHECK_INTERVAL: StdDuration = StdDuration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ShieldSettings {
+    pub enabled: bool,
+    pub dnd: bool,
+    pub block_domains: bool,
+    pub domains: Vec<String>,
+}
+
+impl Default for ShieldSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dnd: true,
+            block_domains: false,
+            domains: Vec::new(),
+        }
+    }
+}
+
+/// What the shield changed that outlives the process.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Applied {
+    gnome_show_banners: Option<bool>,
+    /// Whether the configured hosts file may have Dayli's section in it.
+    #[serde(default)]
+    hosts: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShieldStatus {
+    pub active: bool,
+    pub dnd: bool,
+    pub blocked_domains: Vec<String>,
+    /// Parts of the shield that couldn't be raised.
+    pub errors: Vec<String>,
+}
+
+struct Raised {
+    dnd: Option<Dnd>,
+    hosts_path: Option<PathBuf>,
+    status: ShieldStatus,
+}
+
+#[derive(Default)]
+pub struct Shield {
+    raised: Mutex<Option<Raised>>,
+}
+
+fn settings(store: &Store) -> ShieldSettings {
+    store
+        .setting(SETTINGS_KEY)
+        .ok()
+        .flatten()
+        .unwrap_or_default()
+}
+
+/// Whether focus is on: a focus session is open or a focus block is running.
+fn focus_on<R: Runtime>(app: &AppHandle<R>) -> bool {
+    if crate::focus::status(app).is_some() {
+        return true;
+    }
+    app.state::<Store>()
+        .blocks_at(Utc::now())
+        .is_ok_and(|blocks| {
+            blocks
+                .iter()
+                .any(|block| block.block_type == BlockType::Focus)
+        })
+}
+
+fn raise(store: &Store, settings: &ShieldSettings, hosts_path: &Path) -> Raised {
+    let mut status = ShieldStatus {
+        active: true,
+        ..ShieldStatus::default()
+    };
+    let mut applied = Applied::default();
+
+    let dnd = if settings.dnd {
+        // GNOME's switch outlives the process, so what to put back is on
+        // record before it's flipped.
+        dnd::enable(|show_banners| {
+            applied.gnome_show_banners = Some(show_banners);
+            store.set_setting(APPLIED_KEY, &applied)
+        })
+        .unwrap_or_else(|err| {
+            status.errors.push(format!("do not disturb: {err}"));
+            None
+        })
+    } else {
+        None
+    };
+    status.dnd = dnd.is_some();
+
+    let domains = hosts::normalize_all(&settings.domains);
+    let mut raised_hosts = None;
+    if settings.block_domains && !domains.is_empty() {
+        applied.hosts = true;
+        if let Err(err) = store.set_setting(APPLIED_KEY, &applied) {
+            log::error!("failed to record focus shield: {err}");
+        }
+        match hosts::block(hosts_path, &domains) {
+            Ok(()) => {
+                raised_hosts = Some(hosts_path.to_owned());
+                status.blocked_domains = domains;
+            }
+            Err(err) => {
+                applied.hosts = false;
+                status
+                    .errors
+                    .push(format!("{}: {err}", hosts_path.display()));
+            }
+        }
+    }
+    if let Err(err) = store.set_setting(APPLIED_KEY, &applied) {
+        log::error!("failed to record focus shield: {err}");
+    }
+
+    Raised {
+        dnd,
+        hosts_path: raised_hosts,
+        status,
+    }
+}
+
+fn lower_raised(store: &Store, raised: Raised) {
+    if let Some(dnd) = raised.dnd {
+        if let Err(err) = dnd::disable(dnd) {
+            log::error!("failed to turn off do not disturb: {err}");
+        }
+    }
+    if let Some(path) = raised.hosts_path {
+        if let Err(err) = hosts::clear(&path) {
+            log::error!("failed to unblock domains: {err}");
+        }
+    }
+    if let Err(err) = store.set_setting(APPLIED_KEY, &None::<Applied>) {
+        log::error!("failed to clear focus shield record: {err}");
+    }
+}
+
+/// Undo whatever a previous run left raised.
+fn recover(store: &Store, hosts_path: &Path) -> Result<()> {
+    let Some(applied) = store.setting::<Option<Applied>>(APPLIED_KEY)?.flatten() else {
+        return Ok(());
+    };
+    if let Some(show_banners) = applied.gnome_show_banners {
+        dnd::restore_gnome(show_banners)?;
+    }
+    if applied.hosts {
+        hosts::clear(hosts_path)?;
+    }
+    store.set_setting(APPLIED_KEY, &None::<Applied>)
+}
+
+fn status_of(raised: &Option<Raised>) -> ShieldStatus {
+    raised
+        .as_ref()
+        .map(|raised| raised.status.clone())
+        .unwrap_or_default()
+}
+
+/// Raise or lower the shield to match whether focus is on.
+pub fn refresh<R: Runtime>(app: &AppHandle<R>) {
+    let store = app.state::<Store>();
+    let settings = settings(&store);
+    let wanted = settings.enabled && focus_on(app);
+
+    let shield = app.state::<Shield>();
+    let mut raised = shield.raised.lock().unwrap();
+    match (wanted, raised.is_some()) {
+        (true, false) => {
+            let hosts_path = &app.state::<AppConfig>().hosts_path;
+            *raised = Some(raise(&store, &settings, hosts_path));
+        }
+        (false, true) => lower_raised(&store, raised.take().unwrap()),
+        _ => return,
+    }
+    if let Err(err) = app.emit(STATUS_EVENT, status_of(&raised)) {
+        log::warn!("failed to emit shield status: {err}");
+    }
+}
+
+/// Lower the shield on the way out.
+pub fn lower<R: Runtime>(app: &AppHandle<R>) {
+    let Some(shield) = app.try_state::<Shield>() else {
+        return;
+    };
+    let raised = shield.raised.lock().unwrap().take();
+    if let Some(raised) = raised {
+        lower_raised(&app.state::<Store>(), raised);
+    }
+}
+
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    if let Err(err) = recover(&app.state::<Store>(), &app.state::<AppConfig>().hosts_path) {
+        log::error!("failed to undo the last focus shield: {err}");
+    }
+    app.manage(Shield::default());
+
+    // Follow focus sessions as they start and end, not just on the timer.
+    for event in [crate::focus::STATE_EVENT, crate::focus::COMPLETED_EVENT] {
+        let handle = app.handle().clone();
+        app.listen_any(event, move |_| {
+            let handle = handle.clone();
+            tauri::async_runtime::spawn_blocking(move || refresh(&handle));
+        });
+    }
+
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let app = handle.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || refresh(&app)).await;
+            tokio::time::sleep(CHECK_INTERVAL).await;
+        }
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn shield_status(shield: State<'_, Shield>) -> ShieldStatus {
+    status_of(&shield.raised.lock().unwrap())
+}
+
+#[tauri::command]
+pub fn shield_get_settings(store: State<'_, Store>) -> ShieldSettings {
+    settings(&store)
+}
+
+/// Save the settings and re-raise the shield with them if it's up.
+#[tauri::command]
+pub fn shield_set_settings<R: Runtime>(
+    app: AppHandle<R>,
+    mut settings: ShieldSettings,
+    store: State<'_, Store>,
+) -> Result<ShieldSettings> {
+    settings.domains = hosts::normalize_all(&settings.domains);
+    store.set_setting(SETTINGS_KEY, &settings)?;
+    lower(&app);
+    refresh(&app);
+    Ok(settings)
+}
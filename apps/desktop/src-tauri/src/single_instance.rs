@@ -0,0 +1,231 @@
+//! Keep one Dayli process per user session. A second launch hands its argv
+//! to the running instance and exits; `dayli://` URLs in it reach the
+//! deep-link handlers of the running instance, and its main window is
+//! brought to the front.
+//!
+//! On Unix the handoff goes over a socket in the app's local data dir that
+//! only the user can open. Whoever holds an `flock` on the lock file next to
+//! it is the running instance, so a crashed instance never leaves a stale
+//! claim behind. Windows uses the single-instance plugin.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Runtime};
+
+pub const ARGS_EVENT: &str = "single-instance://args";
+
+/// What a second launch passes on.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handoff {
+    pub argv: Vec<String>,
+    pub cwd: String,
+}
+
+impl Handoff {
+    /// This process's arguments and working directory.
+    pub fn current() -> Self {
+        Self {
+            argv: std::env::args().collect(),
+            cwd: std::env::current_dir()
+                .map(|dir| dir.display().to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn on_second_instance<R: Runtime>(app: &AppHandle<R>, handoff: Handoff) {
+    // A login-time launch of an already running app shouldn't pop it open.
+    if !handoff
+        .argv
+        .iter()
+        .any(|arg| arg == crate::autostart::MINIMIZED_FLAG)
+    {
+        crate::tray::show_main_window(app);
+    }
+    #[cfg(unix)]
+    for url in handoff
+        .argv
+        .iter()
+        .filter_map(|arg| url::Url::parse(arg).ok())
+    {
+        if url.scheme() == "dayli" {
+            crate::deep_link::dispatch(app, &url);
+        }
+    }
+    if let Err(err) = app.emit(ARGS_EVENT, handoff) {
+        log::warn!("failed to emit second instance args: {err}");
+    }
+}
+
+#[cfg(unix)]
+pub use unix::{claim, Claim, Primary};
+
+#[cfg(unix)]
+mod unix {
+    use std::fs::{self, File, OpenOptions};
+    use std::io::{self, BufRead, BufReader, Write};
+    use std::os::fd::AsRawFd;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::Handoff;
+
+    const SOCKET_FILE: &str = "instance.sock";
+    const LOCK_FILE: &str = "instance.lock";
+    /// How long a second launch waits for the running instance to start
+    /// listening.
+    const CONNECT_ATTEMPTS: u32 = 40;
+    const CONNECT_RETRY: Duration = Duration::from_millis(50);
+    const REPLY: &str = "ok";
+
+    pub enum Claim {
+        /// No other instance is running; this one is now.
+        Primary(Primary),
+        /// The running instance took the handoff.
+        Forwarded,
+    }
+
+    /// The running instance's socket. Holding it holds the lock.
+    pub struct Primary {
+        listener: UnixListener,
+        path: PathBuf,
+        _lock: File,
+    }
+
+    impl Primary {
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        /// Accept handoffs on a background thread for as long as the
+        /// process runs.
+        pub fn serve(self, on_handoff: impl Fn(Handoff) + Send + 'static) {
+            thread::spawn(move || {
+                let _lock = self._lock;
+                for stream in self.listener.incoming() {
+                    let handoff = stream.and_then(|stream| {
+                        let mut reader = BufReader::new(stream.try_clone()?);
+                        let mut line = String::new();
+                        reader.read_line(&mut line)?;
+                        let handoff: Handoff = serde_json::from_str(&line)?;
+                        writeln!(&stream, "{REPLY}")?;
+                        Ok(handoff)
+                    });
+                    match handoff {
+                        Ok(handoff) => on_handoff(handoff),
+                        Err(err) => log::warn!("bad single instance handoff: {err}"),
+                    }
+                }
+            });
+        }
+    }
+
+    fn try_lock(file: &File) -> io::Result<bool> {
+        // SAFETY: flock only reads the descriptor, which `file` keeps open.
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+            return Ok(true);
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::EWOULDBLOCK) => Ok(false),
+            _ => Err(err),
+        }
+    }
+
+    fn forward(path: &Path, handoff: &Handoff) -> io::Result<()> {
+        let mut attempts = 0;
+        let stream = loop {
+            match UnixStream::connect(path) {
+                Ok(stream) => break stream,
+                // The running instance holds the lock but may not have
+                // bound the socket yet.
+                Err(_) if attempts < CONNECT_ATTEMPTS => {
+                    attempts += 1;
+                    thread::sleep(CONNECT_RETRY);
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        stream.set_read_timeout(Some(CONNECT_RETRY * CONNECT_ATTEMPTS))?;
+        writeln!(&stream, "{}", serde_json::to_string(handoff)?)?;
+        let mut reply = String::new();
+        BufReader::new(&stream).read_line(&mut reply)?;
+        if reply.trim() != REPLY {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected reply {reply:?}"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Become the running instance for `dir`, or hand `handoff` to the one
+    /// that already is.
+    pub fn claim(dir: &Path, handoff: &Handoff) -> io::Result<Claim> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(SOCKET_FILE);
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(dir.join(LOCK_FILE))?;
+        if !try_lock(&lock)? {
+            forward(&path, handoff)?;
+            return Ok(Claim::Forwarded);
+        }
+
+        // Whatever is at the path was left by an instance that's gone.
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600))?;
+        Ok(Claim::Primary(Primary {
+            listener,
+            path,
+            _lock: lock,
+        }))
+    }
+}
+
+#[cfg(unix)]
+fn setup<R: Runtime>(app: &AppHandle<R>) -> crate::Result<()> {
+    use tauri::Manager;
+
+    match claim(&app.path().app_local_data_dir()?, &Handoff::current())? {
+        Claim::Forwarded => {
+            app.cleanup_before_exit();
+            std::process::exit(0);
+        }
+        Claim::Primary(primary) => {
+            let app = app.clone();
+            primary.serve(move |handoff| on_second_instance(&app, handoff));
+        }
+    }
+    Ok(())
+}
+
+/// The single-instance plugin. It must be the first plugin registered so a
+/// second instance exits before anything else starts.
+#[cfg(unix)]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    tauri::plugin::Builder::new("dayli-single-instance")
+        .setup(|app, _| {
+            // Better two instances than none.
+            if let Err(err) = setup(app) {
+                log::error!("single instance check failed: {err}");
+            }
+            Ok(())
+        })
+        .build()
+}
+
+/// The single-instance plugin. It must be the first plugin registered so a
+/// second instance exits before anything else starts.
+#[cfg(not(unix))]
+pub fn plugin<R: Runtime>() -> tauri::plugin::TauriPlugin<R> {
+    tauri_plugin_single_instance::init(|app, argv, cwd| {
+        on_second_instance(app, Handoff { argv, cwd })
+    })
+}
@@ -0,0 +1,137 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use super::{DailySchedule, Email, Store, Task, TimeBlock, UserPreferences};
+use crate::error::{Error, Result};
+
+/// A day's schedule row and its blocks.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DaySchedule {
+    pub date: NaiveDate,
+    pub schedule: Option<DailySchedule>,
+    pub time_blocks: Vec<TimeBlock>,
+}
+
+impl Store {
+    pub fn day_schedule(&self, date: NaiveDate) -> Result<DaySchedule> {
+        Ok(DaySchedule {
+            date,
+            schedule: self.get_schedule(date)?,
+            time_blocks: self.blocks_for_date(date)?,
+        })
+    }
+
+    /// Mark a block done at `at` and complete the tasks assigned to it.
+    pub fn complete_time_block(&self, id: &str, at: DateTime<Utc>) -> Result<Option<TimeBlock>> {
+        let Some(mut block) = self.get_time_block(id)? else {
+            return Ok(None);
+        };
+        if !block.metadata.is_object() {
+            block.metadata = json!({});
+        }
+        block.metadata["completedAt"] = json!(at);
+        block.updated_at = at;
+        self.save_time_block(&block)?;
+
+        for assignment in &block.assigned_tasks {
+            let Some(mut task) = self.get_task(&assignment.id)? else {
+                continue;
+            };
+            if !task.completed {
+                task.completed = true;
+                task.status = Some("completed".into());
+                task.updated_at = at;
+                self.save_task(&task)?;
+            }
+        }
+        Ok(Some(block))
+    }
+}
+
+/// Complete a block, ending its focus session first if one is open.
+pub fn complete_block<R: Runtime>(app: &AppHandle<R>, id: &str) -> Result<TimeBlock> {
+    if crate::focus::status(app).is_some_and(|session| session.block_id == id) {
+        crate::focus::complete(app)?;
+    }
+    let block = app
+        .state::<Store>()
+        .complete_time_block(id, Utc::now())?
+        .ok_or_else(|| Error::NotFound(format!("block {id}")))?;
+    crate::reminders::reschedule(app);
+    Ok(block)
+}
+
+#[tauri::command]
+pub fn store_today(store: State<'_, Store>) -> Result<DaySchedule> {
+    store.day_schedule(store.today()?)
+}
+
+#[tauri::command]
+pub fn store_day(date: NaiveDate, store: State<'_, Store>) -> Result<DaySchedule> {
+    store.day_schedule(date)
+}
+
+#[tauri::command]
+pub fn store_list_tasks(status: Option<String>, store: State<'_, Store>) -> Result<Vec<Task>> {
+    store.list_tasks(status.as_deref())
+}
+
+#[tauri::command]
+pub fn store_save_task(task: Task, store: State<'_, Store>) -> Result<()> {
+    store.save_task(&task)
+}
+
+#[tauri::command]
+pub fn store_list_emails(status: Option<String>, store: State<'_, Store>) -> Result<Vec<Email>> {
+    store.list_emails(status.as_deref())
+}
+
+#[tauri::command]
+pub fn store_save_email(email: Email, store: State<'_, Store>) -> Result<()> {
+    store.save_email(&email)
+}
+
+#[tauri::command]
+pub fn store_save_schedule(schedule: DailySchedule, store: State<'_, Store>) -> Result<()> {
+    store.save_schedule(&schedule)
+}
+
+#[tauri::command]
+pub fn store_save_time_block<R: Runtime>(
+    app: AppHandle<R>,
+    block: TimeBlock,
+    store: State<'_, Store>,
+) -> Result<()> {
+    store.save_time_block(&block)?;
+    crate::reminders::reschedule(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn store_delete_time_block<R: Runtime>(
+    app: AppHandle<R>,
+    id: String,
+    store: State<'_, Store>,
+) -> Result<()> {
+    store.delete_time_block(&id)?;
+    crate::reminders::reschedule(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn store_complete_time_block<R: Runtime>(app: AppHandle<R>, id: String) -> Result<TimeBlock> {
+    complete_block(&app, &id)
+}
+
+#[tauri::command]
+pub fn store_get_preferences(store: State<'_, Store>) -> Result<Option<UserPreferences>> {
+    store.get_preferences()
+}
+
+#[tauri::command]
+pub fn store_save_preferences(preferences: UserPreferences, store: State<'_, Store>) -> Result<()> {
+    store.save_preferences(&preferences)
+}
@@ -0,0 +1,107 @@
+-- Local mirror of the Supabase tables the desktop app reads offline.
+-- Columns follow migrations/003 and 010; timestamps are RFC 3339 text,
+-- JSONB columns are JSON text.
+
+CREATE TABLE tasks (
+  id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  title TEXT NOT NULL,
+  description TEXT,
+  completed INTEGER NOT NULL DEFAULT 0,
+  source TEXT CHECK (source IN ('email', 'calendar', 'ai', 'manual')) DEFAULT 'manual',
+  source_id TEXT,
+  email_id TEXT,
+  status TEXT CHECK (status IN ('active', 'backlog', 'scheduled', 'completed', 'cancelled')) DEFAULT 'backlog',
+  priority TEXT,
+  estimated_minutes INTEGER,
+  score INTEGER DEFAULT 0,
+  urgency INTEGER DEFAULT 50,
+  days_in_backlog INTEGER DEFAULT 0,
+  tags TEXT NOT NULL DEFAULT '[]',
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL
+);
+
+CREATE TABLE emails (
+  id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  gmail_id TEXT UNIQUE,
+  from_email TEXT NOT NULL,
+  from_name TEXT,
+  subject TEXT NOT NULL,
+  body_preview TEXT,
+  full_body TEXT,
+  is_read INTEGER NOT NULL DEFAULT 0,
+  decision TEXT CHECK (decision IN ('now', 'tomorrow', 'never')),
+  action_type TEXT CHECK (action_type IN ('quick_reply', 'thoughtful_response', 'archive', 'no_action')),
+  status TEXT CHECK (status IN ('unread', 'read', 'archived', 'backlog', 'processed')) DEFAULT 'unread',
+  urgency TEXT CHECK (urgency IN ('urgent', 'important', 'normal', 'low')) DEFAULT 'normal',
+  importance TEXT CHECK (importance IN ('high', 'normal', 'low')) DEFAULT 'normal',
+  days_in_backlog INTEGER DEFAULT 0,
+  received_at TEXT NOT NULL,
+  processed_at TEXT,
+  metadata TEXT NOT NULL DEFAULT '{}',
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL
+);
+
+CREATE TABLE daily_schedules (
+  id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  schedule_date TEXT NOT NULL,
+  stats TEXT NOT NULL DEFAULT '{"emailsProcessed": 0, "tasksCompleted": 0, "focusMinutes": 0}',
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL,
+  UNIQUE (user_id, schedule_date)
+);
+
+CREATE TABLE time_blocks (
+  id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  daily_schedule_id TEXT REFERENCES daily_schedules (id) ON DELETE CASCADE,
+  start_time TEXT NOT NULL,
+  end_time TEXT NOT NULL,
+  type TEXT CHECK (type IN ('focus', 'meeting', 'email', 'quick-decisions', 'break', 'blocked')) NOT NULL,
+  title TEXT NOT NULL,
+  description TEXT,
+  source TEXT CHECK (source IN ('calendar', 'ai', 'manual')) DEFAULT 'manual',
+  calendar_event_id TEXT,
+  metadata TEXT NOT NULL DEFAULT '{}',
+  conflict_group INTEGER NOT NULL DEFAULT 0,
+  energy_level TEXT CHECK (energy_level IN ('high', 'medium', 'low')) DEFAULT 'medium',
+  assigned_tasks TEXT NOT NULL DEFAULT '[]',
+  assigned_emails TEXT NOT NULL DEFAULT '[]',
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL
+);
+
+CREATE TABLE user_preferences (
+  id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL UNIQUE,
+  work_start_time TEXT NOT NULL DEFAULT '08:00',
+  work_end_time TEXT NOT NULL DEFAULT '18:00',
+  work_days TEXT NOT NULL DEFAULT '["monday","tuesday","wednesday","thursday","friday"]',
+  lunch_start_time TEXT NOT NULL DEFAULT '12:00',
+  lunch_duration_minutes INTEGER NOT NULL DEFAULT 60,
+  target_deep_work_blocks INTEGER NOT NULL DEFAULT 2,
+  deep_work_duration_hours INTEGER NOT NULL DEFAULT 2,
+  deep_work_preference TEXT NOT NULL DEFAULT 'no_preference',
+  morning_triage_time TEXT NOT NULL DEFAULT '08:00',
+  morning_triage_duration_minutes INTEGER NOT NULL DEFAULT 30,
+  evening_triage_time TEXT NOT NULL DEFAULT '16:30',
+  evening_triage_duration_minutes INTEGER NOT NULL DEFAULT 30,
+  meeting_windows TEXT NOT NULL DEFAULT '[{"start": "10:00", "end": "12:00"}, {"start": "14:00", "end": "16:00"}]',
+  focus_blocks TEXT NOT NULL DEFAULT '[{"day": "monday", "start": "09:00", "end": "11:00"}, {"day": "friday", "start": "14:00", "end": "17:00"}]',
+  protect_deep_work INTEGER NOT NULL DEFAULT 1,
+  show_busy_during_triage INTEGER NOT NULL DEFAULT 1,
+  add_meeting_buffer INTEGER NOT NULL DEFAULT 1,
+  meeting_buffer_minutes INTEGER NOT NULL DEFAULT 15,
+  timezone TEXT NOT NULL DEFAULT 'America/New_York',
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_tasks_status_user ON tasks (status, user_id);
+CREATE INDEX idx_emails_status_user ON emails (status, user_id);
+CREATE INDEX idx_time_blocks_start_time ON time_blocks (user_id, start_time);
+CREATE INDEX idx_time_blocks_schedule_id ON time_blocks (daily_schedule_id);
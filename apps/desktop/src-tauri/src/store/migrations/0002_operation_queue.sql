@@ -0,0 +1,20 @@
+-- Journal of writes waiting to be pushed to Supabase. Rows are removed only
+-- once the server has accepted them; permanent failures stay as 'failed'
+-- until the user retries or discards them.
+
+CREATE TABLE operation_queue (
+  seq INTEGER PRIMARY KEY AUTOINCREMENT,
+  id TEXT NOT NULL UNIQUE,
+  idempotency_key TEXT NOT NULL UNIQUE,
+  table_name TEXT NOT NULL,
+  action TEXT CHECK (action IN ('insert', 'upsert', 'update', 'delete')) NOT NULL,
+  row_id TEXT,
+  payload TEXT NOT NULL DEFAULT 'null',
+  status TEXT CHECK (status IN ('pending', 'in_flight', 'failed')) NOT NULL DEFAULT 'pending',
+  attempts INTEGER NOT NULL DEFAULT 0,
+  next_attempt_at TEXT NOT NULL,
+  last_error TEXT,
+  created_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_operation_queue_status ON operation_queue (status, seq);
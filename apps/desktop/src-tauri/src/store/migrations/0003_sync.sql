@@ -0,0 +1,27 @@
+-- Bookkeeping for the sync engine: the pull cursor per table, the last
+-- server version of every synced row (the merge base), and conflicts
+-- waiting for the user.
+
+CREATE TABLE sync_cursors (
+  table_name TEXT PRIMARY KEY,
+  cursor TEXT NOT NULL
+);
+
+CREATE TABLE sync_shadows (
+  table_name TEXT NOT NULL,
+  row_id TEXT NOT NULL,
+  data TEXT NOT NULL,
+  PRIMARY KEY (table_name, row_id)
+);
+
+CREATE TABLE sync_conflicts (
+  id TEXT PRIMARY KEY,
+  table_name TEXT NOT NULL,
+  row_id TEXT NOT NULL,
+  fields TEXT NOT NULL,
+  local TEXT NOT NULL,
+  remote TEXT NOT NULL,
+  detected_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_sync_conflicts_row ON sync_conflicts (table_name, row_id);
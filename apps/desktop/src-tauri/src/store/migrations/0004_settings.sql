@@ -0,0 +1,6 @@
+-- Desktop-only settings, as JSON values by key.
+
+CREATE TABLE settings (
+  key TEXT PRIMARY KEY,
+  value TEXT NOT NULL
+);
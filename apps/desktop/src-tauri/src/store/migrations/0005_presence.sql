@@ -0,0 +1,11 @@
+-- Stretches of time the user was away from the machine, as seen by the idle
+-- monitor. block_id is the block that was running when they left, if any.
+
+CREATE TABLE away_intervals (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  started_at TEXT NOT NULL,
+  ended_at TEXT NOT NULL,
+  block_id TEXT
+);
+
+CREATE INDEX idx_away_intervals_started_at ON away_intervals (started_at);
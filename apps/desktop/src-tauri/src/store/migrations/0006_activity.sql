@@ -0,0 +1,11 @@
+-- Foreground application samples from the opt-in activity sampler. Local
+-- only: never synced, and window titles are never recorded.
+
+CREATE TABLE activity_samples (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  sampled_at TEXT NOT NULL,
+  app TEXT NOT NULL,
+  window_class TEXT NOT NULL
+);
+
+CREATE INDEX idx_activity_samples_sampled_at ON activity_samples (sampled_at);
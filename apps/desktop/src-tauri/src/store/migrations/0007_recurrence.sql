@@ -0,0 +1,19 @@
+-- Recurring blocks and tasks. Each row is an RFC 5545 RRULE over wall-clock
+-- times in an IANA zone; occurrences are materialized into time_blocks and
+-- tasks with deterministic ids. exdates and overrides are JSON arrays keyed
+-- by an occurrence's original wall-clock start.
+
+CREATE TABLE recurrences (
+  id TEXT PRIMARY KEY,
+  user_id TEXT NOT NULL,
+  kind TEXT CHECK (kind IN ('time_block', 'task')) NOT NULL,
+  rule TEXT NOT NULL,
+  start TEXT NOT NULL,
+  timezone TEXT NOT NULL,
+  duration_minutes INTEGER,
+  template TEXT NOT NULL DEFAULT '{}',
+  exdates TEXT NOT NULL DEFAULT '[]',
+  overrides TEXT NOT NULL DEFAULT '[]',
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL
+);
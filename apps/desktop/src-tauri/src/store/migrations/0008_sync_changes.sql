@@ -0,0 +1,77 @@
+-- Change tracking for the sync engine. Local writes mark their row dirty so
+-- a push only visits edited rows, and deletes leave a tombstone until the
+-- server has forgotten the row too. `version` lets the engine clear a mark
+-- without losing an edit made while the push was in flight. The pull cursor
+-- gains the id of the last row so paging is strict on (updated_at, id).
+
+ALTER TABLE sync_cursors ADD COLUMN cursor_id TEXT;
+
+CREATE TABLE sync_dirty (
+  table_name TEXT NOT NULL,
+  row_id TEXT NOT NULL,
+  version INTEGER NOT NULL DEFAULT 1,
+  PRIMARY KEY (table_name, row_id)
+);
+
+CREATE TABLE sync_tombstones (
+  table_name TEXT NOT NULL,
+  row_id TEXT NOT NULL,
+  deleted_at TEXT NOT NULL,
+  PRIMARY KEY (table_name, row_id)
+);
+
+CREATE TRIGGER tasks_sync_insert AFTER INSERT ON tasks BEGIN
+  INSERT INTO sync_dirty (table_name, row_id) VALUES ('tasks', NEW.id)
+    ON CONFLICT (table_name, row_id) DO UPDATE SET version = version + 1;
+  DELETE FROM sync_tombstones WHERE table_name = 'tasks' AND row_id = NEW.id;
+END;
+
+CREATE TRIGGER tasks_sync_update AFTER UPDATE ON tasks BEGIN
+  INSERT INTO sync_dirty (table_name, row_id) VALUES ('tasks', NEW.id)
+    ON CONFLICT (table_name, row_id) DO UPDATE SET version = version + 1;
+END;
+
+CREATE TRIGGER tasks_sync_delete AFTER DELETE ON tasks BEGIN
+  DELETE FROM sync_dirty WHERE table_name = 'tasks' AND row_id = OLD.id;
+  INSERT OR REPLACE INTO sync_tombstones (table_name, row_id, deleted_at)
+    VALUES ('tasks', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+END;
+
+CREATE TRIGGER time_blocks_sync_insert AFTER INSERT ON time_blocks BEGIN
+  INSERT INTO sync_dirty (table_name, row_id) VALUES ('time_blocks', NEW.id)
+    ON CONFLICT (table_name, row_id) DO UPDATE SET version = version + 1;
+  DELETE FROM sync_tombstones WHERE table_name = 'time_blocks' AND row_id = NEW.id;
+END;
+
+CREATE TRIGGER time_blocks_sync_update AFTER UPDATE ON time_blocks BEGIN
+  INSERT INTO sync_dirty (table_name, row_id) VALUES ('time_blocks', NEW.id)
+    ON CONFLICT (table_name, row_id) DO UPDATE SET version = version + 1;
+END;
+
+CREATE TRIGGER time_blocks_sync_delete AFTER DELETE ON time_blocks BEGIN
+  DELETE FROM sync_dirty WHERE table_name = 'time_blocks' AND row_id = OLD.id;
+  INSERT OR REPLACE INTO sync_tombstones (table_name, row_id, deleted_at)
+    VALUES ('time_blocks', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+END;
+
+CREATE TRIGGER emails_sync_insert AFTER INSERT ON emails BEGIN
+  INSERT INTO sync_dirty (table_name, row_id) VALUES ('emails', NEW.id)
+    ON CONFLICT (table_name, row_id) DO UPDATE SET version = version + 1;
+  DELETE FROM sync_tombstones WHERE table_name = 'emails' AND row_id = NEW.id;
+END;
+
+CREATE TRIGGER emails_sync_update AFTER UPDATE ON emails BEGIN
+  INSERT INTO sync_dirty (table_name, row_id) VALUES ('emails', NEW.id)
+    ON CONFLICT (table_name, row_id) DO UPDATE SET version = version + 1;
+END;
+
+CREATE TRIGGER emails_sync_delete AFTER DELETE ON emails BEGIN
+  DELETE FROM sync_dirty WHERE table_name = 'emails' AND row_id = OLD.id;
+  INSERT OR REPLACE INTO sync_tombstones (table_name, row_id, deleted_at)
+    VALUES ('emails', OLD.id, strftime('%Y-%m-%dT%H:%M:%fZ', 'now'));
+END;
+
+-- Rows written before tracking existed haven't necessarily been pushed.
+INSERT INTO sync_dirty (table_name, row_id) SELECT 'tasks', id FROM tasks;
+INSERT INTO sync_dirty (table_name, row_id) SELECT 'time_blocks', id FROM time_blocks;
+INSERT INTO sync_dirty (table_name, row_id) SELECT 'emails', id FROM emails;
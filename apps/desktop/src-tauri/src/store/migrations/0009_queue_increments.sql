@@ -0,0 +1,30 @@
+-- Allow 'increment' operations, which add to counters on the server instead
+-- of overwriting them. SQLite can't alter a CHECK constraint, so the queue is
+-- rebuilt with its rows and sequence intact.
+
+CREATE TABLE operation_queue_next (
+  seq INTEGER PRIMARY KEY AUTOINCREMENT,
+  id TEXT NOT NULL UNIQUE,
+  idempotency_key TEXT NOT NULL UNIQUE,
+  table_name TEXT NOT NULL,
+  action TEXT CHECK (action IN ('insert', 'upsert', 'update', 'delete', 'increment')) NOT NULL,
+  row_id TEXT,
+  payload TEXT NOT NULL DEFAULT 'null',
+  status TEXT CHECK (status IN ('pending', 'in_flight', 'failed')) NOT NULL DEFAULT 'pending',
+  attempts INTEGER NOT NULL DEFAULT 0,
+  next_attempt_at TEXT NOT NULL,
+  last_error TEXT,
+  created_at TEXT NOT NULL
+);
+
+INSERT INTO operation_queue_next
+  (seq, id, idempotency_key, table_name, action, row_id, payload, status, attempts,
+   next_attempt_at, last_error, created_at)
+SELECT seq, id, idempotency_key, table_name, action, row_id, payload, status, attempts,
+       next_attempt_at, last_error, created_at
+FROM operation_queue;
+
+DROP TABLE operation_queue;
+ALTER TABLE operation_queue_next RENAME TO operation_queue;
+
+CREATE INDEX idx_operation_queue_status ON operation_queue (status, seq);
@@ -0,0 +1,519 @@
+//! Local SQLite mirror of the Supabase tables the desktop app needs offline:
+//! `tasks`, `emails`, `daily_schedules`, `time_blocks` and `user_preferences`.
+
+pub mod commands;
+mod models;
+
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use tauri::{Manager, Runtime};
+
+use crate::error::Result;
+use crate::schedule::time::Zone;
+
+pub use commands::DaySchedule;
+pub(crate) use models::{json_column, to_json};
+pub use models::{
+    Assignment, BlockType, DailySchedule, DayWindow, Email, EnergyLevel, Task, TimeBlock,
+    TimeWindow, UserPreferences,
+};
+
+/// Embedded schema migrations, applied in order. `PRAGMA user_version`
+/// records how many have run.
+const MIGRATIONS: &[&str] = &[
+    include_str!("migrations/0001_initial.sql"),
+    include_str!("migrations/0002_operation_queue.sql"),
+    include_str!("migrations/0003_sync.sql"),
+    include_str!("migrations/0004_settings.sql"),
+    include_str!("migrations/0005_presence.sql"),
+    include_str!("migrations/0006_activity.sql"),
+    include_str!("migrations/0007_recurrence.sql"),
+    include_str!("migrations/0008_sync_changes.sql"),
+    include_str!("migrations/0009_queue_increments.sql"),
+];
+
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::with_connection(conn)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Self> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut conn)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub(crate) fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap()
+    }
+
+    pub fn list_tasks(&self, status: Option<&str>) -> Result<Vec<Task>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM tasks WHERE ?1 IS NULL OR status = ?1 ORDER BY score DESC, created_at",
+            Task::COLUMNS
+        ))?;
+        let tasks = stmt
+            .query_map([status], Task::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(tasks)
+    }
+
+    pub fn get_task(&self, id: &str) -> Result<Option<Task>> {
+        let conn = self.conn();
+        let task = conn
+            .query_row(
+                &format!("SELECT {} FROM tasks WHERE id = ?1", Task::COLUMNS),
+                [id],
+                Task::from_row,
+            )
+            .optional()?;
+        Ok(task)
+    }
+
+    pub fn save_task(&self, task: &Task) -> Result<()> {
+        self.conn().execute(
+            &format!(
+                "INSERT INTO tasks ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+                 ON CONFLICT (id) DO UPDATE SET
+                   title = excluded.title, description = excluded.description,
+                   completed = excluded.completed, source = excluded.source,
+                   source_id = excluded.source_id, email_id = excluded.email_id,
+                   status = excluded.status, priority = excluded.priority,
+                   estimated_minutes = excluded.estimated_minutes, score = excluded.score,
+                   urgency = excluded.urgency, days_in_backlog = excluded.days_in_backlog,
+                   tags = excluded.tags, updated_at = excluded.updated_at",
+                Task::COLUMNS
+            ),
+            params![
+                task.id,
+                task.user_id,
+                task.title,
+                task.description,
+                task.completed,
+                task.source,
+                task.source_id,
+                task.email_id,
+                task.status,
+                task.priority,
+                task.estimated_minutes,
+                task.score,
+                task.urgency,
+                task.days_in_backlog,
+                to_json(&task.tags)?,
+                task.created_at,
+                task.updated_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_task(&self, id: &str) -> Result<()> {
+        self.conn()
+            .execute("DELETE FROM tasks WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    pub fn list_emails(&self, status: Option<&str>) -> Result<Vec<Email>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM emails WHERE ?1 IS NULL OR status = ?1 ORDER BY received_at DESC",
+            Email::COLUMNS
+        ))?;
+        let emails = stmt
+            .query_map([status], Email::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(emails)
+    }
+
+    pub fn get_email(&self, id: &str) -> Result<Option<Email>> {
+        let conn = self.conn();
+        let email = conn
+            .query_row(
+                &format!("SELECT {} FROM emails WHERE id = ?1", Email::COLUMNS),
+                [id],
+                Email::from_row,
+            )
+            .optional()?;
+        Ok(email)
+    }
+
+    pub fn save_email(&self, email: &Email) -> Result<()> {
+        self.conn().execute(
+            &format!(
+                "INSERT INTO emails ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20)
+                 ON CONFLICT (id) DO UPDATE SET
+                   gmail_id = excluded.gmail_id, from_email = excluded.from_email,
+                   from_name = excluded.from_name, subject = excluded.subject,
+                   body_preview = excluded.body_preview, full_body = excluded.full_body,
+                   is_read = excluded.is_read, decision = excluded.decision,
+                   action_type = excluded.action_type, status = excluded.status,
+                   urgency = excluded.urgency, importance = excluded.importance,
+                   days_in_backlog = excluded.days_in_backlog, received_at = excluded.received_at,
+                   processed_at = excluded.processed_at, metadata = excluded.metadata,
+                   updated_at = excluded.updated_at",
+                Email::COLUMNS
+            ),
+            params![
+                email.id,
+                email.user_id,
+                email.gmail_id,
+                email.from_email,
+                email.from_name,
+                email.subject,
+                email.body_preview,
+                email.full_body,
+                email.is_read,
+                email.decision,
+                email.action_type,
+                email.status,
+                email.urgency,
+                email.importance,
+                email.days_in_backlog,
+                email.received_at,
+                email.processed_at,
+                email.metadata,
+                email.created_at,
+                email.updated_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_schedule(&self, date: NaiveDate) -> Result<Option<DailySchedule>> {
+        let conn = self.conn();
+        let schedule = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM daily_schedules WHERE schedule_date = ?1",
+                    DailySchedule::COLUMNS
+                ),
+                [date],
+                DailySchedule::from_row,
+            )
+            .optional()?;
+        Ok(schedule)
+    }
+
+    pub fn save_schedule(&self, schedule: &DailySchedule) -> Result<()> {
+        self.conn().execute(
+            &format!(
+                "INSERT INTO daily_schedules ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                 ON CONFLICT (id) DO UPDATE SET
+                   schedule_date = excluded.schedule_date, stats = excluded.stats,
+                   updated_at = excluded.updated_at",
+                DailySchedule::COLUMNS
+            ),
+            params![
+                schedule.id,
+                schedule.user_id,
+                schedule.schedule_date,
+                schedule.stats,
+                schedule.created_at,
+                schedule.updated_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Blocks starting in `[start, end)`, in start order.
+    pub fn list_time_blocks(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<TimeBlock>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM time_blocks WHERE start_time >= ?1 AND start_time < ?2 ORDER BY start_time, end_time",
+            TimeBlock::COLUMNS
+        ))?;
+        let blocks = stmt
+            .query_map(params![start, end], TimeBlock::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(blocks)
+    }
+
+    /// Blocks running at `at`, in start order.
+    pub fn blocks_at(&self, at: DateTime<Utc>) -> Result<Vec<TimeBlock>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM time_blocks WHERE start_time <= ?1 AND end_time > ?1 ORDER BY start_time, end_time",
+            TimeBlock::COLUMNS
+        ))?;
+        let blocks = stmt
+            .query_map([at], TimeBlock::from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(blocks)
+    }
+
+    /// Blocks starting on the calendar day `date` in the user's zone.
+    pub fn blocks_for_date(&self, date: NaiveDate) -> Result<Vec<TimeBlock>> {
+        let (start, end) = self.day_bounds(date)?;
+        self.list_time_blocks(start, end)
+    }
+
+    pub fn get_time_block(&self, id: &str) -> Result<Option<TimeBlock>> {
+        let conn = self.conn();
+        let block = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM time_blocks WHERE id = ?1",
+                    TimeBlock::COLUMNS
+                ),
+                [id],
+                TimeBlock::from_row,
+            )
+            .optional()?;
+        Ok(block)
+    }
+
+    pub fn save_time_block(&self, block: &TimeBlock) -> Result<()> {
+        self.conn().execute(
+            &format!(
+                "INSERT INTO time_blocks ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)
+                 ON CONFLICT (id) DO UPDATE SET
+                   daily_schedule_id = excluded.daily_schedule_id,
+                   start_time = excluded.start_time, end_time = excluded.end_time,
+                   type = excluded.type, title = excluded.title,
+                   description = excluded.description, source = excluded.source,
+                   calendar_event_id = excluded.calendar_event_id, metadata = excluded.metadata,
+                   conflict_group = excluded.conflict_group, energy_level = excluded.energy_level,
+                   assigned_tasks = excluded.assigned_tasks,
+                   assigned_emails = excluded.assigned_emails, updated_at = excluded.updated_at",
+                TimeBlock::COLUMNS
+            ),
+            params![
+                block.id,
+                block.user_id,
+                block.daily_schedule_id,
+                block.start_time,
+                block.end_time,
+                block.block_type,
+                block.title,
+                block.description,
+                block.source,
+                block.calendar_event_id,
+                block.metadata,
+                block.conflict_group,
+                block.energy_level,
+                to_json(&block.assigned_tasks)?,
+                to_json(&block.assigned_emails)?,
+                block.created_at,
+                block.updated_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_time_block(&self, id: &str) -> Result<()> {
+        self.conn()
+            .execute("DELETE FROM time_blocks WHERE id = ?1", [id])?;
+        Ok(())
+    }
+
+    pub fn get_preferences(&self) -> Result<Option<UserPreferences>> {
+        let conn = self.conn();
+        let preferences = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM user_preferences LIMIT 1",
+                    UserPreferences::COLUMNS
+                ),
+                [],
+                UserPreferences::from_row,
+            )
+            .optional()?;
+        Ok(preferences)
+    }
+
+    /// The zone the user's days are in: the one in their preferences, or
+    /// the system's before any are synced.
+    pub fn zone(&self) -> Result<Zone> {
+        Ok(self
+            .get_preferences()?
+            .map_or_else(Zone::system, |preferences| preferences.zone()))
+    }
+
+    /// Today's date in the user's zone.
+    pub fn today(&self) -> Result<NaiveDate> {
+        Ok(self.zone()?.today())
+    }
+
+    /// The UTC instants bounding the calendar day `date` in the user's zone.
+    pub fn day_bounds(&self, date: NaiveDate) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+        Ok(self.zone()?.day_bounds(date))
+    }
+
+    pub fn save_preferences(&self, preferences: &UserPreferences) -> Result<()> {
+        let p = preferences;
+        self.conn().execute(
+            &format!(
+                "INSERT INTO user_preferences ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)
+                 ON CONFLICT (user_id) DO UPDATE SET
+                   work_start_time = excluded.work_start_time,
+                   work_end_time = excluded.work_end_time, work_days = excluded.work_days,
+                   lunch_start_time = excluded.lunch_start_time,
+                   lunch_duration_minutes = excluded.lunch_duration_minutes,
+                   target_deep_work_blocks = excluded.target_deep_work_blocks,
+                   deep_work_duration_hours = excluded.deep_work_duration_hours,
+                   deep_work_preference = excluded.deep_work_preference,
+                   morning_triage_time = excluded.morning_triage_time,
+                   morning_triage_duration_minutes = excluded.morning_triage_duration_minutes,
+                   evening_triage_time = excluded.evening_triage_time,
+                   evening_triage_duration_minutes = excluded.evening_triage_duration_minutes,
+                   meeting_windows = excluded.meeting_windows,
+                   focus_blocks = excluded.focus_blocks,
+                   protect_deep_work = excluded.protect_deep_work,
+                   show_busy_during_triage = excluded.show_busy_during_triage,
+                   add_meeting_buffer = excluded.add_meeting_buffer,
+                   meeting_buffer_minutes = excluded.meeting_buffer_minutes,
+                   timezone = excluded.timezone, updated_at = excluded.updated_at",
+                UserPreferences::COLUMNS
+            ),
+            params![
+                p.id,
+                p.user_id,
+                p.work_start_time,
+                p.work_end_time,
+                to_json(&p.work_days)?,
+                p.lunch_start_time,
+                p.lunch_duration_minutes,
+                p.target_deep_work_blocks,
+                p.deep_work_duration_hours,
+                p.deep_work_preference,
+                p.morning_triage_time,
+                p.morning_triage_duration_minutes,
+                p.evening_triage_time,
+                p.evening_triage_duration_minutes,
+                to_json(&p.meeting_windows)?,
+                to_json(&p.focus_blocks)?,
+                p.protect_deep_work,
+                p.show_busy_during_triage,
+                p.add_meeting_buffer,
+                p.meeting_buffer_minutes,
+                p.timezone,
+                p.created_at,
+                p.updated_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// A desktop setting, or `None` if it was never set.
+    pub fn setting<T: serde::de::DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let value: Option<serde_json::Value> = self
+            .conn()
+            .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        Ok(value.map(serde_json::from_value).transpose()?)
+    }
+
+    pub fn set_setting<T: serde::Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+            // Bound as JSON text: a `null` value would otherwise become SQL
+            // NULL, which the column refuses.
+            params![key, serde_json::to_string(value)?],
+        )?;
+        Ok(())
+    }
+}
+
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let applied: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (version, sql) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", version + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Open the store in the app data dir and manage it.
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    let store = Store::open(&app.path().app_local_data_dir()?.join("dayli.db"))?;
+    app.manage(store);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip_including_none() {
+        let store = Store::open_in_memory().unwrap();
+        assert_eq!(store.setting::<String>("k").unwrap(), None);
+
+        store.set_setting("k", &Some("v")).unwrap();
+        assert_eq!(
+            store.setting::<Option<String>>("k").unwrap(),
+            Some(Some("v".into()))
+        );
+        store.set_setting("k", &None::<String>).unwrap();
+        assert_eq!(store.setting::<Option<String>>("k").unwrap(), Some(None));
+        store.set_setting("n", &42).unwrap();
+        assert_eq!(store.setting::<i64>("n").unwrap(), Some(42));
+    }
+
+    #[test]
+    fn days_are_in_the_users_zone() {
+        let store = Store::open_in_memory().unwrap();
+        assert_eq!(store.zone().unwrap(), Zone::system());
+
+        let mut preferences = UserPreferences::defaults("u1");
+        preferences.timezone = "Pacific/Auckland".into();
+        store.save_preferences(&preferences).unwrap();
+        assert_eq!(
+            store.zone().unwrap(),
+            Zone::Named(chrono_tz::Pacific::Auckland)
+        );
+        let auckland = Utc::now().with_timezone(&chrono_tz::Pacific::Auckland);
+        assert_eq!(store.today().unwrap(), auckland.date_naive());
+
+        // 20:00 UTC on 1 March is 09:00 on 2 March in Auckland.
+        let block: TimeBlock = serde_json::from_value(serde_json::json!({
+            "id": "b1",
+            "user_id": "u1",
+            "start_time": "2026-03-01T20:00:00Z",
+            "end_time": "2026-03-01T21:00:00Z",
+            "type": "focus",
+            "title": "Deep work",
+            "description": null,
+            "source": null,
+            "calendar_event_id": null,
+            "daily_schedule_id": null,
+            "metadata": {},
+            "created_at": "2026-03-01T00:00:00Z",
+            "updated_at": "2026-03-01T00:00:00Z",
+        }))
+        .unwrap();
+        store.save_time_block(&block).unwrap();
+        let day = |d| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+        assert!(store.blocks_for_date(day(1)).unwrap().is_empty());
+        assert_eq!(store.blocks_for_date(day(2)).unwrap()[0].id, "b1");
+        assert_eq!(store.day_schedule(day(2)).unwrap().time_blocks.len(), 1);
+    }
+}
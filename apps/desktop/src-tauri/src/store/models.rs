@@ -0,0 +1,439 @@
+//! Rows of the local store. Field names match the Supabase tables so rows
+//! serialize to the same JSON shape the web app already uses.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use rusqlite::Row;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Implements `as_str`, `Display`, `FromStr` and SQLite conversions for a
+/// fieldless enum stored as its `CHECK`-constrained text value.
+macro_rules! text_enum {
+    ($name:ident { $($variant:ident => $text:literal),+ $(,)? }) => {
+        impl $name {
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $($name::$variant => $text),+
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s {
+                    $($text => Ok($name::$variant),)+
+                    other => Err(format!("unknown {} {other:?}", stringify!($name))),
+                }
+            }
+        }
+
+        impl ToSql for $name {
+            fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+                Ok(self.as_str().into())
+            }
+        }
+
+        impl FromSql for $name {
+            fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+                value
+                    .as_str()?
+                    .parse()
+                    .map_err(|err: String| FromSqlError::Other(err.into()))
+            }
+        }
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlockType {
+    Focus,
+    Meeting,
+    Email,
+    QuickDecisions,
+    Break,
+    Blocked,
+}
+
+text_enum!(BlockType {
+    Focus => "focus",
+    Meeting => "meeting",
+    Email => "email",
+    QuickDecisions => "quick-decisions",
+    Break => "break",
+    Blocked => "blocked",
+});
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnergyLevel {
+    High,
+    #[default]
+    Medium,
+    Low,
+}
+
+text_enum!(EnergyLevel {
+    High => "high",
+    Medium => "medium",
+    Low => "low",
+});
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    pub user_id: String,
+    pub title: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub completed: bool,
+    pub source: Option<String>,
+    pub source_id: Option<String>,
+    pub email_id: Option<String>,
+    pub status: Option<String>,
+    pub priority: Option<String>,
+    pub estimated_minutes: Option<i64>,
+    pub score: Option<i64>,
+    pub urgency: Option<i64>,
+    pub days_in_backlog: Option<i64>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Task {
+    pub(crate) const COLUMNS: &'static str = "id, user_id, title, description, completed, \
+        source, source_id, email_id, status, priority, estimated_minutes, score, urgency, \
+        days_in_backlog, tags, created_at, updated_at";
+
+    pub(crate) fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            title: row.get("title")?,
+            description: row.get("description")?,
+            completed: row.get("completed")?,
+            source: row.get("source")?,
+            source_id: row.get("source_id")?,
+            email_id: row.get("email_id")?,
+            status: row.get("status")?,
+            priority: row.get("priority")?,
+            estimated_minutes: row.get("estimated_minutes")?,
+            score: row.get("score")?,
+            urgency: row.get("urgency")?,
+            days_in_backlog: row.get("days_in_backlog")?,
+            tags: json_column(row, "tags")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Email {
+    pub id: String,
+    pub user_id: String,
+    pub gmail_id: Option<String>,
+    pub from_email: String,
+    pub from_name: Option<String>,
+    pub subject: String,
+    pub body_preview: Option<String>,
+    pub full_body: Option<String>,
+    #[serde(default)]
+    pub is_read: bool,
+    pub decision: Option<String>,
+    pub action_type: Option<String>,
+    pub status: Option<String>,
+    pub urgency: Option<String>,
+    pub importance: Option<String>,
+    pub days_in_backlog: Option<i64>,
+    pub received_at: DateTime<Utc>,
+    pub processed_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub metadata: Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Email {
+    pub(crate) const COLUMNS: &'static str = "id, user_id, gmail_id, from_email, from_name, \
+        subject, body_preview, full_body, is_read, decision, action_type, status, urgency, \
+        importance, days_in_backlog, received_at, processed_at, metadata, created_at, updated_at";
+
+    pub(crate) fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            gmail_id: row.get("gmail_id")?,
+            from_email: row.get("from_email")?,
+            from_name: row.get("from_name")?,
+            subject: row.get("subject")?,
+            body_preview: row.get("body_preview")?,
+            full_body: row.get("full_body")?,
+            is_read: row.get("is_read")?,
+            decision: row.get("decision")?,
+            action_type: row.get("action_type")?,
+            status: row.get("status")?,
+            urgency: row.get("urgency")?,
+            importance: row.get("importance")?,
+            days_in_backlog: row.get("days_in_backlog")?,
+            received_at: row.get("received_at")?,
+            processed_at: row.get("processed_at")?,
+            metadata: row.get("metadata")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailySchedule {
+    pub id: String,
+    pub user_id: String,
+    pub schedule_date: NaiveDate,
+    #[serde(default)]
+    pub stats: Value,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl DailySchedule {
+    pub(crate) const COLUMNS: &'static str =
+        "id, user_id, schedule_date, stats, created_at, updated_at";
+
+    pub(crate) fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            schedule_date: row.get("schedule_date")?,
+            stats: row.get("stats")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+/// An entry of `time_blocks.assigned_tasks` or `assigned_emails`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assignment {
+    pub id: String,
+    #[serde(default)]
+    pub position: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeBlock {
+    pub id: String,
+    pub user_id: String,
+    pub daily_schedule_id: Option<String>,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    #[serde(rename = "type")]
+    pub block_type: BlockType,
+    pub title: String,
+    pub description: Option<String>,
+    pub source: Option<String>,
+    pub calendar_event_id: Option<String>,
+    #[serde(default)]
+    pub metadata: Value,
+    #[serde(default)]
+    pub conflict_group: i64,
+    #[serde(default)]
+    pub energy_level: EnergyLevel,
+    #[serde(default)]
+    pub assigned_tasks: Vec<Assignment>,
+    #[serde(default)]
+    pub assigned_emails: Vec<Assignment>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TimeBlock {
+    pub(crate) const COLUMNS: &'static str = "id, user_id, daily_schedule_id, start_time, \
+        end_time, type, title, description, source, calendar_event_id, metadata, \
+        conflict_group, energy_level, assigned_tasks, assigned_emails, created_at, updated_at";
+
+    pub(crate) fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            daily_schedule_id: row.get("daily_schedule_id")?,
+            start_time: row.get("start_time")?,
+            end_time: row.get("end_time")?,
+            block_type: row.get("type")?,
+            title: row.get("title")?,
+            description: row.get("description")?,
+            source: row.get("source")?,
+            calendar_event_id: row.get("calendar_event_id")?,
+            metadata: row.get("metadata")?,
+            conflict_group: row.get("conflict_group")?,
+            energy_level: row.get("energy_level")?,
+            assigned_tasks: json_column(row, "assigned_tasks")?,
+            assigned_emails: json_column(row, "assigned_emails")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+/// `meeting_windows` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: String,
+    pub end: String,
+}
+
+/// `focus_blocks` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DayWindow {
+    pub day: String,
+    pub start: String,
+    pub end: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserPreferences {
+    pub id: String,
+    pub user_id: String,
+    pub work_start_time: NaiveTime,
+    pub work_end_time: NaiveTime,
+    pub work_days: Vec<String>,
+    pub lunch_start_time: NaiveTime,
+    pub lunch_duration_minutes: i64,
+    pub target_deep_work_blocks: i64,
+    pub deep_work_duration_hours: i64,
+    pub deep_work_preference: String,
+    pub morning_triage_time: NaiveTime,
+    pub morning_triage_duration_minutes: i64,
+    pub evening_triage_time: NaiveTime,
+    pub evening_triage_duration_minutes: i64,
+    pub meeting_windows: Vec<TimeWindow>,
+    pub focus_blocks: Vec<DayWindow>,
+    pub protect_deep_work: bool,
+    pub show_busy_during_triage: bool,
+    pub add_meeting_buffer: bool,
+    pub meeting_buffer_minutes: i64,
+    pub timezone: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl UserPreferences {
+    pub(crate) const COLUMNS: &'static str = "id, user_id, work_start_time, work_end_time, \
+        work_days, lunch_start_time, lunch_duration_minutes, target_deep_work_blocks, \
+        deep_work_duration_hours, deep_work_preference, morning_triage_time, \
+        morning_triage_duration_minutes, evening_triage_time, evening_triage_duration_minutes, \
+        meeting_windows, focus_blocks, protect_deep_work, show_busy_during_triage, \
+        add_meeting_buffer, meeting_buffer_minutes, timezone, created_at, updated_at";
+
+    pub(crate) fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            work_start_time: row.get("work_start_time")?,
+            work_end_time: row.get("work_end_time")?,
+            work_days: json_column(row, "work_days")?,
+            lunch_start_time: row.get("lunch_start_time")?,
+            lunch_duration_minutes: row.get("lunch_duration_minutes")?,
+            target_deep_work_blocks: row.get("target_deep_work_blocks")?,
+            deep_work_duration_hours: row.get("deep_work_duration_hours")?,
+            deep_work_preference: row.get("deep_work_preference")?,
+            morning_triage_time: row.get("morning_triage_time")?,
+            morning_triage_duration_minutes: row.get("morning_triage_duration_minutes")?,
+            evening_triage_time: row.get("evening_triage_time")?,
+            evening_triage_duration_minutes: row.get("evening_triage_duration_minutes")?,
+            meeting_windows: json_column(row, "meeting_windows")?,
+            focus_blocks: json_column(row, "focus_blocks")?,
+            protect_deep_work: row.get("protect_deep_work")?,
+            show_busy_during_triage: row.get("show_busy_during_triage")?,
+            add_meeting_buffer: row.get("add_meeting_buffer")?,
+            meeting_buffer_minutes: row.get("meeting_buffer_minutes")?,
+            timezone: row.get("timezone")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+
+    /// The defaults from `migrations/002_user_preferences.sql`.
+    pub fn defaults(user_id: &str) -> Self {
+        let time = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_owned(),
+            work_start_time: time(8, 0),
+            work_end_time: time(18, 0),
+            work_days: ["monday", "tuesday", "wednesday", "thursday", "friday"]
+                .map(String::from)
+                .to_vec(),
+            lunch_start_time: time(12, 0),
+            lunch_duration_minutes: 60,
+            target_deep_work_blocks: 2,
+            deep_work_duration_hours: 2,
+            deep_work_preference: "no_preference".into(),
+            morning_triage_time: time(8, 0),
+            morning_triage_duration_minutes: 30,
+            evening_triage_time: time(16, 30),
+            evening_triage_duration_minutes: 30,
+            meeting_windows: vec![
+                TimeWindow {
+                    start: "10:00".into(),
+                    end: "12:00".into(),
+                },
+                TimeWindow {
+                    start: "14:00".into(),
+                    end: "16:00".into(),
+                },
+            ],
+            focus_blocks: vec![
+                DayWindow {
+                    day: "monday".into(),
+                    start: "09:00".into(),
+                    end: "11:00".into(),
+                },
+                DayWindow {
+                    day: "friday".into(),
+                    start: "14:00".into(),
+                    end: "17:00".into(),
+                },
+            ],
+            protect_deep_work: true,
+            show_busy_during_triage: true,
+            add_meeting_buffer: true,
+            meeting_buffer_minutes: 15,
+            timezone: "America/New_York".into(),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// Read a JSON text column into a typed value.
+pub(crate) fn json_column<T: serde::de::DeserializeOwned>(
+    row: &Row<'_>,
+    column: &str,
+) -> rusqlite::Result<T> {
+    let value: Value = row.get(column)?;
+    serde_json::from_value(value).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(err))
+    })
+}
+
+/// Serialize a value for a JSON text column.
+pub(crate) fn to_json<T: Serialize>(value: &T) -> rusqlite::Result<String> {
+    serde_json::to_string(value)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))
+}
@@ -0,0 +1,48 @@
+//! Minimal PostgREST access to the Supabase project, authenticated with the
+//! session stored in the vault.
+
+use reqwest::{Method, RequestBuilder};
+
+use crate::config::SupabaseConfig;
+use crate::error::{Error, Result};
+use crate::vault::{Provider, TokenVault};
+
+pub struct Rest<'a> {
+    client: &'a reqwest::Client,
+    config: &'a SupabaseConfig,
+    access_token: String,
+}
+
+impl<'a> Rest<'a> {
+    pub fn new(
+        client: &'a reqwest::Client,
+        config: &'a SupabaseConfig,
+        access_token: String,
+    ) -> Self {
+        Self {
+            client,
+            config,
+            access_token,
+        }
+    }
+
+    /// Authenticate as the signed-in user, or fail if nobody is signed in.
+    pub fn from_vault(
+        client: &'a reqwest::Client,
+        config: &'a SupabaseConfig,
+        vault: &TokenVault,
+    ) -> Result<Self> {
+        let credential = vault
+            .get(Provider::Supabase)?
+            .ok_or_else(|| Error::Auth("not signed in".into()))?;
+        Ok(Self::new(client, config, credential.access_token))
+    }
+
+    /// A request against `/rest/v1/{path}`.
+    pub fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.client
+            .request(method, format!("{}/rest/v1/{path}", self.config.url))
+            .header("apikey", &self.config.anon_key)
+            .bearer_auth(&self.access_token)
+    }
+}
@@ -0,0 +1,203 @@
+use serde_json::{Map, Value};
+
+/// Fields that never conflict; the server's value always wins.
+const SERVER_OWNED: &[&str] = &["id", "user_id", "created_at", "updated_at"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merged {
+    pub row: Map<String, Value>,
+    /// Fields both sides changed to different values. The local value is
+    /// kept in `row` until the conflict is resolved.
+    pub conflicts: Vec<String>,
+}
+
+/// Three-way merge of a row. `base` is the last server version both sides
+/// agreed on. Fields in the same group (e.g. a block's start and end) merge
+/// as a unit, so a move on one side and a resize on the other conflict
+/// instead of producing a block neither side asked for.
+pub fn merge(
+    base: &Map<String, Value>,
+    local: &Map<String, Value>,
+    remote: &Map<String, Value>,
+    groups: &[&[&str]],
+) -> Merged {
+    let mut row = remote.clone();
+    let mut conflicts = Vec::new();
+
+    let mut fields: Vec<&str> = local.keys().map(String::as_str).collect();
+    fields.retain(|field| !SERVER_OWNED.contains(field));
+
+    let mut seen: Vec<&str> = Vec::new();
+    for field in fields {
+        if seen.contains(&field) {
+            continue;
+        }
+        let group: Vec<&str> = groups
+            .iter()
+            .find(|group| group.contains(&field))
+            .map(|group| group.to_vec())
+            .unwrap_or_else(|| vec![field]);
+        seen.extend(&group);
+
+        let get = |row: &Map<String, Value>| -> Vec<Value> {
+            group
+                .iter()
+                .map(|f| row.get(*f).cloned().unwrap_or(Value::Null))
+                .collect()
+        };
+        let (b, l, r) = (get(base), get(local), get(remote));
+
+        let keep_local = if l == r || l == b {
+            false
+        } else if r == b {
+            true
+        } else {
+            conflicts.extend(group.iter().map(|f| f.to_string()));
+            true
+        };
+        if keep_local {
+            for (f, value) in group.iter().zip(l) {
+                row.insert(f.to_string(), value);
+            }
+        }
+    }
+
+    Merged { row, conflicts }
+}
+
+/// The fields of `row` that differ from `base`, for a partial update.
+pub fn changed_fields(base: &Map<String, Value>, row: &Map<String, Value>) -> Map<String, Value> {
+    row.iter()
+        .filter(|(field, value)| {
+            !SERVER_OWNED.contains(&field.as_str()) && base.get(*field) != Some(*value)
+        })
+        .map(|(field, value)| (field.clone(), value.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn row(value: Value) -> Map<String, Value> {
+        match value {
+            Value::Object(row) => row,
+            _ => unreachable!(),
+        }
+    }
+
+    fn base() -> Map<String, Value> {
+        row(json!({
+            "id": "t1",
+            "updated_at": "2026-03-01T09:00:00Z",
+            "title": "Write",
+            "priority": "low",
+            "notes": "draft",
+        }))
+    }
+
+    fn with(changes: Value) -> Map<String, Value> {
+        let mut row = base();
+        row.extend(self::row(changes));
+        row
+    }
+
+    #[test]
+    fn one_sided_changes_are_taken() {
+        let local = with(json!({ "title": "Write report" }));
+        let remote = with(json!({ "priority": "high", "updated_at": "2026-03-02T09:00:00Z" }));
+        let merged = merge(&base(), &local, &remote, &[]);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(
+            merged.row,
+            with(json!({
+                "title": "Write report",
+                "priority": "high",
+                "updated_at": "2026-03-02T09:00:00Z",
+            }))
+        );
+    }
+
+    #[test]
+    fn differing_changes_conflict_and_keep_local() {
+        let local = with(json!({ "title": "Local", "priority": "high" }));
+        let remote = with(json!({ "title": "Remote", "priority": "high" }));
+        let merged = merge(&base(), &local, &remote, &[]);
+        // The same change on both sides isn't a conflict.
+        assert_eq!(merged.conflicts, ["title"]);
+        assert_eq!(merged.row["title"], "Local");
+        assert_eq!(merged.row["priority"], "high");
+    }
+
+    #[test]
+    fn server_owned_fields_never_conflict() {
+        let local = with(json!({ "id": "other", "updated_at": "local" }));
+        let remote = with(json!({ "updated_at": "remote" }));
+        let merged = merge(&base(), &local, &remote, &[]);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.row["id"], "t1");
+        assert_eq!(merged.row["updated_at"], "remote");
+    }
+
+    #[test]
+    fn deleted_fields() {
+        let mut removed = base();
+        removed.remove("notes");
+
+        // Removed on the server and untouched here: it stays removed.
+        let merged = merge(&base(), &base(), &removed, &[]);
+        assert!(merged.conflicts.is_empty());
+        assert!(!merged.row.contains_key("notes"));
+
+        // Removed on the server but edited here: that's a conflict.
+        let local = with(json!({ "notes": "final" }));
+        let merged = merge(&base(), &local, &removed, &[]);
+        assert_eq!(merged.conflicts, ["notes"]);
+        assert_eq!(merged.row["notes"], "final");
+
+        // Missing here, e.g. a column this version doesn't know: the
+        // server's value is kept.
+        let remote = with(json!({ "notes": "edited" }));
+        let merged = merge(&base(), &removed, &remote, &[]);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.row["notes"], "edited");
+
+        // Cleared to null locally is an edit like any other.
+        let local = with(json!({ "notes": null }));
+        let merged = merge(&base(), &local, &base(), &[]);
+        assert_eq!(merged.row["notes"], Value::Null);
+    }
+
+    #[test]
+    fn grouped_fields_merge_as_a_unit() {
+        let groups: &[&[&str]] = &[&["start_time", "end_time"]];
+        let base = row(json!({ "start_time": 9, "end_time": 10 }));
+        // A move here and a resize there would otherwise make 10-11.
+        let local = row(json!({ "start_time": 10, "end_time": 11 }));
+        let remote = row(json!({ "start_time": 9, "end_time": 11 }));
+        let merged = merge(&base, &local, &remote, groups);
+        assert_eq!(merged.conflicts, ["start_time", "end_time"]);
+        assert_eq!(merged.row, local);
+
+        // Only one side moved: the whole group comes from it.
+        let merged = merge(&base, &base, &remote, groups);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(merged.row, remote);
+    }
+
+    #[test]
+    fn changed_fields_skip_unchanged_and_server_owned() {
+        let edited = with(json!({
+            "title": "Write report",
+            "updated_at": "later",
+            "tags": ["work"],
+        }));
+        assert_eq!(
+            Value::Object(changed_fields(&base(), &edited)),
+            json!({ "title": "Write report", "tags": ["work"] })
+        );
+        assert!(changed_fields(&base(), &base()).is_empty());
+    }
+}
@@ -0,0 +1,1153 @@
+//! Two-way sync between the local store and Supabase.
+//!
+//! Pulls rows changed since a per-table `(updated_at, id)` cursor, merges
+//! them field by field against the last server version (the shadow), and
+//! pushes the rows local writes marked dirty as partial updates. Conflicting
+//! edits keep the local value and are reported on [`CONFLICT_EVENT`] until
+//! the user picks a side; rows with open conflicts aren't pushed. These
+//! tables are pushed here only, never through the operation queue.
+//!
+//! Local deletes leave a tombstone that the push turns into a `DELETE`.
+//! Rows deleted on the server are found by listing its ids: a missing row
+//! that has a shadow is deleted locally, or reported as a conflict on
+//! [`DELETED`] if it has unpushed edits.
+
+mod merge;
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use reqwest::Method;
+use rusqlite::{params, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::config::AppConfig;
+use crate::error::{Error, Result};
+use crate::queue::{Connectivity, Table};
+use crate::store::{Email, Store, Task, TimeBlock};
+use crate::supabase::Rest;
+use crate::vault::TokenVault;
+
+pub use merge::{changed_fields, merge, Merged};
+
+pub const STATUS_EVENT: &str = "sync://status";
+pub const CONFLICT_EVENT: &str = "sync://conflict";
+
+const SYNC_INTERVAL: Duration = Duration::from_secs(60);
+const PAGE_SIZE: usize = 500;
+
+/// The field a conflict names when one side deleted the row and the other
+/// edited it. Keeping the remote side deletes the row; keeping the local
+/// side puts it back on the server.
+pub const DELETED: &str = "_deleted";
+
+/// Held for the length of a sync so the periodic loop, the sync command and
+/// the login-time fetch never run against the shadows at the same time.
+static RUNNING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// A local table the engine keeps in step with its Supabase counterpart.
+pub trait Synced: Serialize + DeserializeOwned {
+    const TABLE: Table;
+    /// Fields that only make sense changed together.
+    const GROUPS: &'static [&'static [&'static str]] = &[];
+
+    fn id(&self) -> &str;
+    fn load(store: &Store, id: &str) -> Result<Option<Self>>;
+    fn save(&self, store: &Store) -> Result<()>;
+}
+
+impl Synced for Task {
+    const TABLE: Table = Table::Tasks;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn load(store: &Store, id: &str) -> Result<Option<Self>> {
+        store.get_task(id)
+    }
+
+    fn save(&self, store: &Store) -> Result<()> {
+        store.save_task(self)
+    }
+}
+
+impl Synced for TimeBlock {
+    const TABLE: Table = Table::TimeBlocks;
+    const GROUPS: &'static [&'static [&'static str]] = &[&["start_time", "end_time"]];
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn load(store: &Store, id: &str) -> Result<Option<Self>> {
+        store.get_time_block(id)
+    }
+
+    fn save(&self, store: &Store) -> Result<()> {
+        store.save_time_block(self)
+    }
+}
+
+impl Synced for Email {
+    const TABLE: Table = Table::Emails;
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn load(store: &Store, id: &str) -> Result<Option<Self>> {
+        store.get_email(id)
+    }
+
+    fn save(&self, store: &Store) -> Result<()> {
+        store.save_email(self)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+/// Edits to the same fields of a row on both sides.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    pub id: String,
+    pub table: Table,
+    pub row_id: String,
+    pub fields: Vec<String>,
+    pub local: Map<String, Value>,
+    pub remote: Map<String, Value>,
+    pub detected_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub pulled: usize,
+    pub pushed: usize,
+    pub conflicts: usize,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "phase")]
+enum StatusEvent {
+    Started,
+    Finished(SyncReport),
+    Failed { error: String },
+}
+
+/// Where the last pull stopped: the newest row seen, in the order the
+/// server returns them.
+#[derive(Debug, Clone, PartialEq)]
+struct Cursor {
+    updated_at: DateTime<Utc>,
+    id: String,
+}
+
+impl Cursor {
+    /// A PostgREST `or` filter for the rows strictly after the cursor.
+    fn filter(&self) -> String {
+        let at = self.updated_at.to_rfc3339_opts(SecondsFormat::AutoSi, true);
+        format!(
+            "(updated_at.gt.\"{at}\",and(updated_at.eq.\"{at}\",id.gt.\"{}\"))",
+            self.id
+        )
+    }
+}
+
+/// Sync bookkeeping on the local store.
+impl Store {
+    fn sync_cursor(&self, table: Table) -> Result<Option<Cursor>> {
+        let cursor = self
+            .conn()
+            .query_row(
+                "SELECT cursor, cursor_id FROM sync_cursors WHERE table_name = ?1",
+                [table.as_str()],
+                |row| {
+                    Ok(Cursor {
+                        updated_at: row.get(0)?,
+                        id: row.get::<_, Option<String>>(1)?.unwrap_or_default(),
+                    })
+                },
+            )
+            .optional()?;
+        Ok(cursor)
+    }
+
+    fn set_sync_cursor(&self, table: Table, cursor: &Cursor) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO sync_cursors (table_name, cursor, cursor_id) VALUES (?1, ?2, ?3)
+             ON CONFLICT (table_name) DO UPDATE
+             SET cursor = excluded.cursor, cursor_id = excluded.cursor_id",
+            params![table.as_str(), cursor.updated_at, cursor.id],
+        )?;
+        Ok(())
+    }
+
+    /// Rows written locally since they were last pushed, with the version
+    /// of their mark.
+    fn dirty_rows(&self, table: Table) -> Result<Vec<(String, i64)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT row_id, version FROM sync_dirty WHERE table_name = ?1 ORDER BY row_id",
+        )?;
+        let rows = stmt
+            .query_map([table.as_str()], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    fn dirty_version(&self, table: Table, row_id: &str) -> Result<Option<i64>> {
+        let version = self
+            .conn()
+            .query_row(
+                "SELECT version FROM sync_dirty WHERE table_name = ?1 AND row_id = ?2",
+                params![table.as_str(), row_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(version)
+    }
+
+    fn mark_dirty(&self, table: Table, row_id: &str) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO sync_dirty (table_name, row_id) VALUES (?1, ?2)
+             ON CONFLICT (table_name, row_id) DO UPDATE SET version = version + 1",
+            params![table.as_str(), row_id],
+        )?;
+        Ok(())
+    }
+
+    /// Clear the row's mark unless it was written again after `version`.
+    fn clear_dirty(&self, table: Table, row_id: &str, version: i64) -> Result<()> {
+        self.conn().execute(
+            "DELETE FROM sync_dirty WHERE table_name = ?1 AND row_id = ?2 AND version = ?3",
+            params![table.as_str(), row_id, version],
+        )?;
+        Ok(())
+    }
+
+    fn tombstones(&self, table: Table) -> Result<Vec<String>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT row_id FROM sync_tombstones WHERE table_name = ?1 ORDER BY deleted_at",
+        )?;
+        let rows = stmt
+            .query_map([table.as_str()], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    fn is_tombstoned(&self, table: Table, row_id: &str) -> Result<bool> {
+        let count: i64 = self.conn().query_row(
+            "SELECT COUNT(*) FROM sync_tombstones WHERE table_name = ?1 AND row_id = ?2",
+            params![table.as_str(), row_id],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Ids of the rows the server has sent us.
+    fn shadow_ids(&self, table: Table) -> Result<Vec<String>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT row_id FROM sync_shadows WHERE table_name = ?1 ORDER BY row_id")?;
+        let rows = stmt
+            .query_map([table.as_str()], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(rows)
+    }
+
+    fn drop_shadow(&self, table: Table, row_id: &str) -> Result<()> {
+        self.conn().execute(
+            "DELETE FROM sync_shadows WHERE table_name = ?1 AND row_id = ?2",
+            params![table.as_str(), row_id],
+        )?;
+        Ok(())
+    }
+
+    /// Delete a row both sides agree is gone, leaving no sync state behind.
+    fn delete_synced(&self, table: Table, row_id: &str) -> Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            &format!("DELETE FROM {} WHERE id = ?1", table.as_str()),
+            [row_id],
+        )?;
+        for bookkeeping in [
+            "sync_dirty",
+            "sync_tombstones",
+            "sync_shadows",
+            "sync_conflicts",
+        ] {
+            tx.execute(
+                &format!("DELETE FROM {bookkeeping} WHERE table_name = ?1 AND row_id = ?2"),
+                params![table.as_str(), row_id],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn shadow(&self, table: Table, row_id: &str) -> Result<Option<Map<String, Value>>> {
+        let data: Option<Value> = self
+            .conn()
+            .query_row(
+                "SELECT data FROM sync_shadows WHERE table_name = ?1 AND row_id = ?2",
+                params![table.as_str(), row_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(data.and_then(|value| match value {
+            Value::Object(map) => Some(map),
+            _ => None,
+        }))
+    }
+
+    fn set_shadow(&self, table: Table, row_id: &str, data: &Map<String, Value>) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO sync_shadows (table_name, row_id, data) VALUES (?1, ?2, ?3)
+             ON CONFLICT (table_name, row_id) DO UPDATE SET data = excluded.data",
+            params![table.as_str(), row_id, Value::Object(data.clone())],
+        )?;
+        Ok(())
+    }
+
+    fn has_conflict(&self, table: Table, row_id: &str) -> Result<bool> {
+        let count: i64 = self.conn().query_row(
+            "SELECT COUNT(*) FROM sync_conflicts WHERE table_name = ?1 AND row_id = ?2",
+            params![table.as_str(), row_id],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    /// Replace any open conflict on the row with `conflict`.
+    fn record_conflict(&self, conflict: &Conflict) -> Result<()> {
+        let conn = self.conn();
+        conn.execute(
+            "DELETE FROM sync_conflicts WHERE table_name = ?1 AND row_id = ?2",
+            params![conflict.table.as_str(), conflict.row_id],
+        )?;
+        conn.execute(
+            "INSERT INTO sync_conflicts (id, table_name, row_id, fields, local, remote, detected_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                conflict.id,
+                conflict.table.as_str(),
+                conflict.row_id,
+                serde_json::to_value(&conflict.fields)?,
+                Value::Object(conflict.local.clone()),
+                Value::Object(conflict.remote.clone()),
+                conflict.detected_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn clear_conflicts(&self, table: Table, row_id: &str) -> Result<()> {
+        self.conn().execute(
+            "DELETE FROM sync_conflicts WHERE table_name = ?1 AND row_id = ?2",
+            params![table.as_str(), row_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn conflicts(&self) -> Result<Vec<Conflict>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, table_name, row_id, fields, local, remote, detected_at
+             FROM sync_conflicts ORDER BY detected_at",
+        )?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Value>(3)?,
+                    row.get::<_, Value>(4)?,
+                    row.get::<_, Value>(5)?,
+                    row.get::<_, DateTime<Utc>>(6)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        rows.into_iter()
+            .map(|(id, table, row_id, fields, local, remote, detected_at)| {
+                Ok(Conflict {
+                    id,
+                    table: table.parse()?,
+                    row_id,
+                    fields: serde_json::from_value(fields)?,
+                    local: serde_json::from_value(local)?,
+                    remote: serde_json::from_value(remote)?,
+                    detected_at,
+                })
+            })
+            .collect()
+    }
+}
+
+pub struct SyncEngine<'a> {
+    store: &'a Store,
+    rest: Rest<'a>,
+    report: SyncReport,
+    conflicts: Vec<Conflict>,
+}
+
+impl<'a> SyncEngine<'a> {
+    pub fn new(store: &'a Store, rest: Rest<'a>) -> Self {
+        Self {
+            store,
+            rest,
+            report: SyncReport::default(),
+            conflicts: Vec::new(),
+        }
+    }
+
+    /// Pull then push every synced table. Returns what happened and the
+    /// conflicts found along the way.
+    pub async fn run(mut self) -> Result<(SyncReport, Vec<Conflict>)> {
+        self.pull::<Task>().await?;
+        self.pull::<TimeBlock>().await?;
+        self.pull::<Email>().await?;
+        self.push::<Task>().await?;
+        self.push::<TimeBlock>().await?;
+        self.push::<Email>().await?;
+        self.report.conflicts = self.conflicts.len();
+        Ok((self.report, self.conflicts))
+    }
+
+    async fn pull<T: Synced>(&mut self) -> Result<()> {
+        let table = T::TABLE;
+        loop {
+            let mut request = self.rest.request(Method::GET, table.as_str()).query(&[
+                ("select", "*"),
+                ("order", "updated_at.asc,id.asc"),
+                ("limit", &PAGE_SIZE.to_string()),
+            ]);
+            if let Some(cursor) = self.store.sync_cursor(table)? {
+                request = request.query(&[("or", cursor.filter())]);
+            }
+
+            let response = request.send().await?.error_for_status()?;
+            let rows: Vec<Value> = response.json().await?;
+            let page_len = rows.len();
+
+            let mut last = None;
+            for row in rows {
+                let remote: T = serde_json::from_value(row)?;
+                let updated_at = object(&remote)?
+                    .get("updated_at")
+                    .and_then(Value::as_str)
+                    .and_then(|at| DateTime::parse_from_rfc3339(at).ok())
+                    .map(|at| at.with_timezone(&Utc))
+                    .ok_or_else(|| {
+                        Error::Sync(format!("{} row without updated_at", table.as_str()))
+                    })?;
+                last = Some(Cursor {
+                    updated_at,
+                    id: remote.id().to_owned(),
+                });
+                self.merge_remote(remote)?;
+            }
+
+            if let Some(last) = &last {
+                self.store.set_sync_cursor(table, last)?;
+            }
+            if page_len < PAGE_SIZE {
+                return self.pull_deletes::<T>().await;
+            }
+        }
+    }
+
+    fn merge_remote<T: Synced>(&mut self, remote: T) -> Result<()> {
+        let table = T::TABLE;
+        let id = remote.id().to_owned();
+        let remote = object(&remote)?;
+        // The local delete wins; the push sends it.
+        if self.store.is_tombstoned(table, &id)? {
+            return Ok(());
+        }
+
+        let Some(local) = T::load(self.store, &id)? else {
+            self.save_clean::<T>(&id, &remote)?;
+            self.report.pulled += 1;
+            return self.store.set_shadow(table, &id, &remote);
+        };
+        let local = object(&local)?;
+        // Rows from before sync existed count as unedited.
+        let base = self
+            .store
+            .shadow(table, &id)?
+            .unwrap_or_else(|| local.clone());
+
+        let merged = merge(&base, &local, &remote, T::GROUPS);
+        if merged.row != local {
+            if merged.row == remote {
+                self.save_clean::<T>(&id, &merged.row)?;
+            } else {
+                save_object::<T>(self.store, &merged.row)?;
+            }
+            self.report.pulled += 1;
+        }
+        self.store.set_shadow(table, &id, &remote)?;
+
+        if merged.conflicts.is_empty() {
+            self.store.clear_conflicts(table, &id)?;
+        } else {
+            let pick = |row: &Map<String, Value>| {
+                merged
+                    .conflicts
+                    .iter()
+                    .map(|f| (f.clone(), row.get(f).cloned().unwrap_or(Value::Null)))
+                    .collect()
+            };
+            self.conflict(
+                table,
+                id,
+                merged.conflicts.clone(),
+                pick(&local),
+                pick(&remote),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Save a row that now matches the server without marking it dirty.
+    fn save_clean<T: Synced>(&self, id: &str, row: &Map<String, Value>) -> Result<()> {
+        let version = self.store.dirty_version(T::TABLE, id)?.unwrap_or(0) + 1;
+        save_object::<T>(self.store, row)?;
+        self.store.clear_dirty(T::TABLE, id, version)
+    }
+
+    fn conflict(
+        &mut self,
+        table: Table,
+        row_id: String,
+        fields: Vec<String>,
+        local: Map<String, Value>,
+        remote: Map<String, Value>,
+    ) -> Result<()> {
+        let conflict = Conflict {
+            id: uuid::Uuid::new_v4().to_string(),
+            table,
+            row_id,
+            fields,
+            local,
+            remote,
+            detected_at: Utc::now(),
+        };
+        self.store.record_conflict(&conflict)?;
+        self.conflicts.push(conflict);
+        Ok(())
+    }
+
+    /// Find rows the server no longer has and delete them here too.
+    async fn pull_deletes<T: Synced>(&mut self) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Id {
+            id: String,
+        }
+
+        let table = T::TABLE;
+        let known = self.store.shadow_ids(table)?;
+        if known.is_empty() {
+            return Ok(());
+        }
+
+        let mut remote = HashSet::new();
+        let mut after: Option<String> = None;
+        loop {
+            let mut request = self.rest.request(Method::GET, table.as_str()).query(&[
+                ("select", "id"),
+                ("order", "id.asc"),
+                ("limit", &PAGE_SIZE.to_string()),
+            ]);
+            if let Some(after) = &after {
+                request = request.query(&[("id", format!("gt.{after}"))]);
+            }
+            let page: Vec<Id> = request.send().await?.error_for_status()?.json().await?;
+            let page_len = page.len();
+            after = page.last().map(|row| row.id.clone());
+            remote.extend(page.into_iter().map(|row| row.id));
+            if page_len < PAGE_SIZE {
+                break;
+            }
+        }
+
+        for id in known {
+            if remote.contains(&id) {
+                continue;
+            }
+            let edited = self.store.dirty_version(table, &id)?.is_some()
+                && T::load(self.store, &id)?.is_some();
+            if !edited {
+                self.store.delete_synced(table, &id)?;
+                self.report.pulled += 1;
+                continue;
+            }
+            let reported = self.store.conflicts()?.iter().any(|conflict| {
+                conflict.table == table && conflict.row_id == id && conflict.fields == [DELETED]
+            });
+            if !reported {
+                let flag = |deleted: bool| Map::from_iter([(DELETED.into(), Value::Bool(deleted))]);
+                self.conflict(table, id, vec![DELETED.into()], flag(false), flag(true))?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn push<T: Synced>(&mut self) -> Result<()> {
+        let table = T::TABLE;
+        for id in self.store.tombstones(table)? {
+            // Rows the server never had only need forgetting.
+            if self.store.shadow(table, &id)?.is_some() {
+                self.rest
+                    .request(Method::DELETE, table.as_str())
+                    .query(&[("id", format!("eq.{id}"))])
+                    .header("Prefer", "return=minimal")
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                self.report.pushed += 1;
+            }
+            self.store.delete_synced(table, &id)?;
+        }
+
+        for (id, version) in self.store.dirty_rows(table)? {
+            if self.store.has_conflict(table, &id)? {
+                continue;
+            }
+            let Some(row) = T::load(self.store, &id)? else {
+                self.store.clear_dirty(table, &id, version)?;
+                continue;
+            };
+            let local = object(&row)?;
+
+            let request = match self.store.shadow(table, &id)? {
+                Some(shadow) => {
+                    let changes = changed_fields(&shadow, &local);
+                    if changes.is_empty() {
+                        self.store.clear_dirty(table, &id, version)?;
+                        continue;
+                    }
+                    self.rest
+                        .request(Method::PATCH, table.as_str())
+                        .query(&[("id", format!("eq.{id}"))])
+                        .json(&changes)
+                }
+                None => self
+                    .rest
+                    .request(Method::POST, table.as_str())
+                    .header("Prefer", "resolution=merge-duplicates")
+                    .json(&local),
+            };
+            request
+                .header("Prefer", "return=minimal")
+                .send()
+                .await?
+                .error_for_status()?;
+
+            // The server's copy now matches ours; the next pull brings back
+            // its new `updated_at`.
+            self.store.set_shadow(table, &id, &local)?;
+            self.store.clear_dirty(table, &id, version)?;
+            self.report.pushed += 1;
+        }
+        Ok(())
+    }
+}
+
+fn object<T: Serialize>(row: &T) -> Result<Map<String, Value>> {
+    match serde_json::to_value(row)? {
+        Value::Object(map) => Ok(map),
+        _ => Err(Error::Sync("row did not serialize to an object".into())),
+    }
+}
+
+fn save_object<T: Synced>(store: &Store, row: &Map<String, Value>) -> Result<()> {
+    serde_json::from_value::<T>(Value::Object(row.clone()))?.save(store)
+}
+
+/// Keep the side of a conflict the user picked.
+fn resolve(store: &Store, conflict: &Conflict, keep: Side) -> Result<()> {
+    let (table, row_id) = (conflict.table, conflict.row_id.as_str());
+    if conflict.fields == [DELETED] {
+        if keep == Side::Remote {
+            return store.delete_synced(table, row_id);
+        }
+        // Without a shadow the next push posts the row back.
+        store.drop_shadow(table, row_id)?;
+        store.mark_dirty(table, row_id)?;
+        return store.clear_conflicts(table, row_id);
+    }
+    if keep == Side::Remote {
+        fn apply<T: Synced>(store: &Store, conflict: &Conflict) -> Result<()> {
+            let Some(row) = T::load(store, &conflict.row_id)? else {
+                return Ok(());
+            };
+            let mut row = object(&row)?;
+            row.extend(conflict.remote.clone());
+            save_object::<T>(store, &row)
+        }
+        match conflict.table {
+            Table::Tasks => apply::<Task>(store, conflict)?,
+            Table::TimeBlocks => apply::<TimeBlock>(store, conflict)?,
+            Table::Emails => apply::<Email>(store, conflict)?,
+            other => return Err(Error::Sync(format!("{} isn't synced", other.as_str()))),
+        }
+    }
+    // Keeping the local side leaves the row differing from its shadow, so
+    // the next push sends it.
+    store.clear_conflicts(conflict.table, &conflict.row_id)
+}
+
+/// Run one sync and report it to the webview.
+pub async fn sync_now<R: Runtime>(
+    app: &AppHandle<R>,
+    client: &reqwest::Client,
+) -> Result<SyncReport> {
+    let _running = RUNNING.lock().await;
+    let store = app.state::<Store>();
+    let config = app.state::<AppConfig>();
+    let vault = app.state::<TokenVault>();
+
+    emit(app, STATUS_EVENT, StatusEvent::Started);
+    let result = match Rest::from_vault(client, &config.supabase, &vault) {
+        Ok(rest) => SyncEngine::new(&store, rest).run().await,
+        Err(err) => Err(err),
+    };
+    match result {
+        Ok((report, conflicts)) => {
+            if report.pulled > 0 {
+                crate::reminders::reschedule(app);
+            }
+            for conflict in conflicts {
+                emit(app, CONFLICT_EVENT, conflict);
+            }
+            emit(app, STATUS_EVENT, StatusEvent::Finished(report.clone()));
+            Ok(report)
+        }
+        Err(err) => {
+            emit(
+                app,
+                STATUS_EVENT,
+                StatusEvent::Failed {
+                    error: err.to_string(),
+                },
+            );
+            Err(err)
+        }
+    }
+}
+
+fn emit<R: Runtime, S: Serialize + Clone>(app: &AppHandle<R>, event: &str, payload: S) {
+    if let Err(err) = app.emit(event, payload) {
+        log::warn!("failed to emit {event}: {err}");
+    }
+}
+
+/// Sync periodically while online.
+pub fn spawn<R: Runtime>(app: AppHandle<R>) {
+    tauri::async_runtime::spawn(async move {
+        let client = reqwest::Client::new();
+        loop {
+            if app.state::<Connectivity>().is_online() {
+                let _ = sync_now(&app, &client).await;
+            }
+            tokio::time::sleep(SYNC_INTERVAL).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn sync_run<R: Runtime>(app: AppHandle<R>) -> Result<SyncReport> {
+    sync_now(&app, &reqwest::Client::new()).await
+}
+
+#[tauri::command]
+pub fn sync_conflicts(store: State<'_, Store>) -> Result<Vec<Conflict>> {
+    store.conflicts()
+}
+
+#[tauri::command]
+pub fn sync_resolve(id: String, keep: Side, store: State<'_, Store>) -> Result<()> {
+    let conflict = store
+        .conflicts()?
+        .into_iter()
+        .find(|conflict| conflict.id == id)
+        .ok_or_else(|| Error::Sync(format!("no conflict {id}")))?;
+    resolve(&store, &conflict, keep)
+}
+
+#[cfg(all(test, desktop))]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::{Arc, Mutex};
+
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::SupabaseConfig;
+    use crate::testing::task;
+
+    #[derive(Default)]
+    struct Tables {
+        rows: HashMap<String, BTreeMap<String, Map<String, Value>>>,
+        clock: i64,
+        requests: Vec<String>,
+    }
+
+    impl Tables {
+        fn tick(&mut self) -> Value {
+            self.clock += 1;
+            let at = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap()
+                + chrono::Duration::seconds(self.clock);
+            at.format("%Y-%m-%dT%H:%M:%S%.6f+00:00").to_string().into()
+        }
+    }
+
+    /// Just enough PostgREST for the engine: keyset-paged reads, id
+    /// listings, and single-row writes that stamp `updated_at`.
+    struct PostgRest {
+        server: Arc<tiny_http::Server>,
+        tables: Arc<Mutex<Tables>>,
+    }
+
+    impl PostgRest {
+        fn start() -> Self {
+            let server = Arc::new(tiny_http::Server::http("127.0.0.1:0").unwrap());
+            let tables = Arc::new(Mutex::new(Tables::default()));
+            let (incoming, state) = (server.clone(), tables.clone());
+            std::thread::spawn(move || {
+                for mut request in incoming.incoming_requests() {
+                    let mut body = String::new();
+                    request.as_reader().read_to_string(&mut body).unwrap();
+                    let (status, reply) = state.lock().unwrap().handle(
+                        request.method().as_str(),
+                        request.url(),
+                        &body,
+                    );
+                    let response = tiny_http::Response::from_string(reply).with_status_code(status);
+                    let _ = request.respond(response);
+                }
+            });
+            Self { server, tables }
+        }
+
+        fn config(&self) -> SupabaseConfig {
+            SupabaseConfig {
+                url: format!("http://{}", self.server.server_addr()),
+                anon_key: "anon".into(),
+            }
+        }
+
+        fn put(&self, table: &str, row: Map<String, Value>, updated_at: Option<&str>) {
+            let mut tables = self.tables.lock().unwrap();
+            let mut row = row;
+            let at = match updated_at {
+                Some(at) => at.into(),
+                None => tables.tick(),
+            };
+            row.insert("updated_at".into(), at);
+            let id = row["id"].as_str().unwrap().to_owned();
+            tables.rows.entry(table.into()).or_default().insert(id, row);
+        }
+
+        fn get(&self, table: &str, id: &str) -> Option<Map<String, Value>> {
+            let tables = self.tables.lock().unwrap();
+            tables.rows.get(table)?.get(id).cloned()
+        }
+
+        fn remove(&self, table: &str, id: &str) {
+            let mut tables = self.tables.lock().unwrap();
+            tables.rows.entry(table.into()).or_default().remove(id);
+        }
+
+        /// Writes received since the last call; reads aren't recorded.
+        fn writes(&self) -> Vec<String> {
+            std::mem::take(&mut self.tables.lock().unwrap().requests)
+        }
+    }
+
+    impl Drop for PostgRest {
+        fn drop(&mut self) {
+            self.server.unblock();
+        }
+    }
+
+    impl Tables {
+        fn handle(&mut self, method: &str, url: &str, body: &str) -> (u16, String) {
+            let url = reqwest::Url::parse(&format!("http://stand-in{url}")).unwrap();
+            let table = url.path().trim_start_matches("/rest/v1/").to_owned();
+            let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+            let eq_id = query
+                .get("id")
+                .and_then(|id| id.strip_prefix("eq."))
+                .map(str::to_owned);
+            let body: Option<Map<String, Value>> = serde_json::from_str(body).ok();
+
+            match method {
+                "GET" => (200, self.select(&table, &query).to_string()),
+                "PATCH" => {
+                    let id = eq_id.unwrap();
+                    let body = body.unwrap();
+                    let mut fields: Vec<&String> = body.keys().collect();
+                    fields.sort();
+                    self.requests.push(format!("PATCH {table} {id} {fields:?}"));
+                    let at = self.tick();
+                    if let Some(row) = self.rows.entry(table).or_default().get_mut(&id) {
+                        row.extend(body);
+                        row.insert("updated_at".into(), at);
+                    }
+                    (204, String::new())
+                }
+                "POST" => {
+                    let mut row = body.unwrap();
+                    let id = row["id"].as_str().unwrap().to_owned();
+                    self.requests.push(format!("POST {table} {id}"));
+                    row.insert("updated_at".into(), self.tick());
+                    self.rows.entry(table).or_default().insert(id, row);
+                    (201, String::new())
+                }
+                "DELETE" => {
+                    let id = eq_id.unwrap();
+                    self.requests.push(format!("DELETE {table} {id}"));
+                    self.rows.entry(table).or_default().remove(&id);
+                    (204, String::new())
+                }
+                other => panic!("unexpected {other}"),
+            }
+        }
+
+        fn select(&self, table: &str, query: &HashMap<String, String>) -> Value {
+            let limit: usize = query["limit"].parse().unwrap();
+            let rows = self.rows.get(table).cloned().unwrap_or_default();
+
+            if query["select"] == "id" {
+                let after = query.get("id").map(|id| id.strip_prefix("gt.").unwrap());
+                let ids: Vec<Value> = rows
+                    .keys()
+                    .filter(|id| after.map_or(true, |after| id.as_str() > after))
+                    .take(limit)
+                    .map(|id| json!({ "id": id }))
+                    .collect();
+                return ids.into();
+            }
+
+            assert_eq!(query["order"], "updated_at.asc,id.asc");
+            let key = |row: &Map<String, Value>| {
+                let at = DateTime::parse_from_rfc3339(row["updated_at"].as_str().unwrap()).unwrap();
+                (
+                    at.with_timezone(&Utc),
+                    row["id"].as_str().unwrap().to_owned(),
+                )
+            };
+            // (updated_at.gt."AT",and(updated_at.eq."AT",id.gt."ID"))
+            let after = query.get("or").map(|filter| {
+                let parts: Vec<&str> = filter.split('"').collect();
+                let at = DateTime::parse_from_rfc3339(parts[1]).unwrap();
+                assert_eq!(parts[1], parts[3]);
+                (at.with_timezone(&Utc), parts[5].to_owned())
+            });
+            let mut rows: Vec<Map<String, Value>> = rows.into_values().collect();
+            rows.sort_by_key(key);
+            rows.into_iter()
+                .filter(|row| after.as_ref().map_or(true, |after| key(row) > *after))
+                .take(limit)
+                .map(Value::Object)
+                .collect::<Vec<_>>()
+                .into()
+        }
+    }
+
+    fn sync(store: &Store, server: &PostgRest) -> (SyncReport, Vec<Conflict>) {
+        let client = reqwest::Client::new();
+        let config = server.config();
+        let rest = Rest::new(&client, &config, "token".into());
+        tauri::async_runtime::block_on(SyncEngine::new(store, rest).run()).unwrap()
+    }
+
+    /// A store and server that agree on `ids` after one sync.
+    fn synced(ids: &[&str]) -> (Store, PostgRest) {
+        let (store, server) = (Store::open_in_memory().unwrap(), PostgRest::start());
+        for id in ids {
+            server.put("tasks", object(&task(id, id)).unwrap(), None);
+        }
+        let (report, _) = sync(&store, &server);
+        assert_eq!(report.pulled, ids.len());
+        assert_eq!(report.pushed, 0);
+        assert!(server.writes().is_empty());
+        (store, server)
+    }
+
+    #[test]
+    fn the_cursor_filter_is_strict_on_updated_at_then_id() {
+        let cursor = Cursor {
+            updated_at: Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap(),
+            id: "t1".into(),
+        };
+        assert_eq!(
+            cursor.filter(),
+            r#"(updated_at.gt."2026-03-01T12:00:00Z",and(updated_at.eq."2026-03-01T12:00:00Z",id.gt."t1"))"#
+        );
+    }
+
+    #[test]
+    fn pages_sharing_a_timestamp_are_pulled_once() {
+        let (store, server) = (Store::open_in_memory().unwrap(), PostgRest::start());
+        for n in 0..=PAGE_SIZE {
+            let row = object(&task(&format!("t{n:04}"), "same second")).unwrap();
+            server.put("tasks", row, Some("2026-03-01T08:00:00.000000+00:00"));
+        }
+
+        let (report, _) = sync(&store, &server);
+        assert_eq!(report.pulled, PAGE_SIZE + 1);
+        assert_eq!(report.pushed, 0);
+        assert_eq!(store.list_tasks(None).unwrap().len(), PAGE_SIZE + 1);
+
+        // Nothing changed, so nothing comes back and nothing is pushed.
+        let (report, _) = sync(&store, &server);
+        assert_eq!((report.pulled, report.pushed), (0, 0));
+        assert!(server.writes().is_empty());
+    }
+
+    #[test]
+    fn only_rows_written_locally_are_pushed() {
+        let (store, server) = synced(&["t1", "t2"]);
+
+        let mut edited = store.get_task("t1").unwrap().unwrap();
+        edited.title = "renamed".into();
+        edited.updated_at = Utc::now();
+        store.save_task(&edited).unwrap();
+        store.save_task(&task("t3", "new")).unwrap();
+
+        let (report, _) = sync(&store, &server);
+        assert_eq!(report.pushed, 2);
+        assert_eq!(
+            server.writes(),
+            ["PATCH tasks t1 [\"title\"]", "POST tasks t3"]
+        );
+        assert_eq!(server.get("tasks", "t1").unwrap()["title"], "renamed");
+
+        // The server's new updated_at comes back once; then all is quiet.
+        let (report, _) = sync(&store, &server);
+        assert_eq!(report.pushed, 0);
+        let (report, _) = sync(&store, &server);
+        assert_eq!((report.pulled, report.pushed), (0, 0));
+        assert!(server.writes().is_empty());
+    }
+
+    #[test]
+    fn local_deletes_reach_the_server() {
+        let (store, server) = synced(&["t1", "t2"]);
+        store.delete_task("t1").unwrap();
+        // Never pushed, so there's nothing to delete remotely.
+        store.save_task(&task("t9", "draft")).unwrap();
+        store.delete_task("t9").unwrap();
+
+        let (report, _) = sync(&store, &server);
+        assert_eq!(report.pushed, 1);
+        assert_eq!(server.writes(), ["DELETE tasks t1"]);
+        assert!(server.get("tasks", "t1").is_none());
+        assert!(store.tombstones(Table::Tasks).unwrap().is_empty());
+
+        sync(&store, &server);
+        assert!(server.writes().is_empty());
+    }
+
+    #[test]
+    fn a_local_delete_wins_over_a_remote_edit() {
+        let (store, server) = synced(&["t1"]);
+        store.delete_task("t1").unwrap();
+        server.put(
+            "tasks",
+            object(&task("t1", "edited remotely")).unwrap(),
+            None,
+        );
+
+        sync(&store, &server);
+        assert!(store.get_task("t1").unwrap().is_none());
+        assert_eq!(server.writes(), ["DELETE tasks t1"]);
+        assert!(server.get("tasks", "t1").is_none());
+    }
+
+    #[test]
+    fn remote_deletes_remove_the_local_row() {
+        let (store, server) = synced(&["t1", "t2"]);
+        server.remove("tasks", "t2");
+
+        let (report, conflicts) = sync(&store, &server);
+        assert_eq!(report.pulled, 1);
+        assert!(conflicts.is_empty());
+        assert!(store.get_task("t2").unwrap().is_none());
+        assert!(store.get_task("t1").unwrap().is_some());
+        // The engine's own delete isn't sent back.
+        assert!(server.writes().is_empty());
+        assert!(store.tombstones(Table::Tasks).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_remote_delete_of_an_edited_row_conflicts() {
+        for keep in [Side::Local, Side::Remote] {
+            let (store, server) = synced(&["t1"]);
+            let mut edited = store.get_task("t1").unwrap().unwrap();
+            edited.title = "kept".into();
+            store.save_task(&edited).unwrap();
+            server.remove("tasks", "t1");
+
+            let (_, conflicts) = sync(&store, &server);
+            assert_eq!(conflicts.len(), 1);
+            assert_eq!(conflicts[0].fields, [DELETED]);
+            assert!(server.writes().is_empty(), "the edit waits for the user");
+            // Still open, so not reported twice.
+            let (_, again) = sync(&store, &server);
+            assert!(again.is_empty());
+
+            resolve(&store, &store.conflicts().unwrap()[0], keep).unwrap();
+            sync(&store, &server);
+            match keep {
+                Side::Local => {
+                    assert_eq!(server.writes(), ["POST tasks t1"]);
+                    assert_eq!(server.get("tasks", "t1").unwrap()["title"], "kept");
+                }
+                Side::Remote => {
+                    assert!(server.writes().is_empty());
+                    assert!(store.get_task("t1").unwrap().is_none());
+                }
+            }
+            assert!(store.conflicts().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn field_conflicts_still_wait_for_the_user() {
+        let (store, server) = synced(&["t1"]);
+        let mut edited = store.get_task("t1").unwrap().unwrap();
+        edited.title = "local".into();
+        store.save_task(&edited).unwrap();
+        server.put("tasks", object(&task("t1", "remote")).unwrap(), None);
+
+        let (_, conflicts) = sync(&store, &server);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].fields, ["title"]);
+        assert!(server.writes().is_empty());
+
+        resolve(&store, &conflicts[0], Side::Local).unwrap();
+        sync(&store, &server);
+        assert_eq!(server.writes(), ["PATCH tasks t1 [\"title\"]"]);
+        assert_eq!(server.get("tasks", "t1").unwrap()["title"], "local");
+    }
+}
@@ -0,0 +1,90 @@
+//! Shared fixtures for unit tests: a mock app and the rows they work on.
+
+use chrono::{DateTime, TimeZone, Utc};
+use serde_json::json;
+use tauri::test::MockRuntime;
+use tauri::{App, AppHandle, Manager};
+use tempfile::TempDir;
+
+use crate::store::{BlockType, EnergyLevel, Store, Task, TimeBlock, UserPreferences};
+use crate::vault::{FileStore, TokenVault};
+
+/// A mock app with an in-memory store and the state the commands reach for.
+/// The vault lives in a temp dir that goes away with it.
+pub struct TestApp {
+    pub app: App<MockRuntime>,
+    _dir: TempDir,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        let dir = tempfile::tempdir().unwrap();
+        let app = tauri::test::mock_app();
+        let store = Store::open_in_memory().unwrap();
+        store
+            .save_preferences(&UserPreferences::defaults("u1"))
+            .unwrap();
+        app.manage(store);
+        app.manage(TokenVault::new(Box::new(
+            FileStore::open(dir.path()).unwrap(),
+        )));
+        app.manage(crate::queue::Connectivity::default());
+        app.manage(crate::focus::Focus::default());
+        app.manage(crate::reminders::Reminders::default());
+        Self { app, _dir: dir }
+    }
+
+    pub fn handle(&self) -> &AppHandle<MockRuntime> {
+        self.app.handle()
+    }
+
+    pub fn store(&self) -> tauri::State<'_, Store> {
+        self.app.state::<Store>()
+    }
+}
+
+pub fn block(id: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> TimeBlock {
+    TimeBlock {
+        id: id.into(),
+        user_id: "u1".into(),
+        daily_schedule_id: None,
+        start_time: start,
+        end_time: end,
+        block_type: BlockType::Focus,
+        title: id.into(),
+        description: None,
+        source: Some("manual".into()),
+        calendar_event_id: None,
+        metadata: json!({}),
+        conflict_group: 0,
+        energy_level: EnergyLevel::default(),
+        assigned_tasks: Vec::new(),
+        assigned_emails: Vec::new(),
+        created_at: start,
+        updated_at: start,
+    }
+}
+
+/// A half-hour backlog task created on 1 February 2026.
+pub fn task(id: &str, title: &str) -> Task {
+    let created = Utc.with_ymd_and_hms(2026, 2, 1, 9, 0, 0).unwrap();
+    Task {
+        id: id.into(),
+        user_id: "u1".into(),
+        title: title.into(),
+        description: None,
+        completed: false,
+        source: Some("manual".into()),
+        source_id: None,
+        email_id: None,
+        status: Some("backlog".into()),
+        priority: None,
+        estimated_minutes: Some(30),
+        score: Some(0),
+        urgency: Some(50),
+        days_in_backlog: Some(0),
+        tags: Vec::new(),
+        created_at: created,
+        updated_at: created,
+    }
+}
@@ -0,0 +1,283 @@
+//! Menu-bar / system tray mode. The tray title shows the current block and
+//! how long is left; the menu lists the rest of today. Closing the main
+//! window hides it to the tray instead of quitting.
+
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Utc};
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{TrayIcon, TrayIconBuilder};
+use tauri::{AppHandle, Emitter, Manager, Runtime, Window, WindowEvent};
+
+use crate::config::AppConfig;
+use crate::error::Result;
+use crate::focus::{self, FocusStatus};
+use crate::schedule::time::Zone;
+use crate::store::{Store, TimeBlock};
+
+pub const PLAN_DAY_EVENT: &str = "tray://plan-day";
+
+const TRAY_ID: &str = "main";
+const REFRESH_INTERVAL: StdDuration = StdDuration::from_secs(30);
+/// Most upcoming blocks to list in the menu.
+const MENU_BLOCKS: usize = 8;
+
+/// The block happening at `now` and the ones still to come.
+fn split_day(blocks: &[TimeBlock], now: DateTime<Utc>) -> (Option<&TimeBlock>, Vec<&TimeBlock>) {
+    let current = blocks
+        .iter()
+        .find(|block| block.start_time <= now && now < block.end_time);
+    let upcoming = blocks
+        .iter()
+        .filter(|block| block.start_time > now)
+        .collect();
+    (current, upcoming)
+}
+
+fn countdown(until: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (until - now).num_minutes().max(0);
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+fn title(current: Option<&TimeBlock>, next: Option<&&TimeBlock>, now: DateTime<Utc>) -> String {
+    match (current, next) {
+        (Some(block), _) => format!("{} · {} left", block.title, countdown(block.end_time, now)),
+        (None, Some(block)) => format!(
+            "Next: {} in {}",
+            block.title,
+            countdown(block.start_time, now)
+        ),
+        (None, None) => "Dayli".to_owned(),
+    }
+}
+
+/// The focus item: start a session on the block running now, or pause or
+/// resume the one in progress.
+fn focus_label(status: Option<&FocusStatus>) -> &'static str {
+    match status {
+        None => "Start focus",
+        Some(status) if status.paused.is_some() => "Resume focus",
+        Some(_) => "Pause focus",
+    }
+}
+
+fn local_time(at: DateTime<Utc>, zone: Zone) -> String {
+    zone.local(at).format("%H:%M").to_string()
+}
+
+fn build_menu<R: Runtime>(
+    app: &AppHandle<R>,
+    blocks: &[TimeBlock],
+    zone: Zone,
+) -> tauri::Result<Menu<R>> {
+    let now = Utc::now();
+    let (current, upcoming) = split_day(blocks, now);
+    let menu = Menu::new(app)?;
+
+    if let Some(block) = current {
+        let label = format!(
+            "Now: {} (until {})",
+            block.title,
+            local_time(block.end_time, zone)
+        );
+        menu.append(&MenuItem::with_id(
+            app,
+            "current",
+            label,
+            false,
+            None::<&str>,
+        )?)?;
+    }
+    for block in upcoming.iter().take(MENU_BLOCKS) {
+        let label = format!("{}  {}", local_time(block.start_time, zone), block.title);
+        menu.append(&MenuItem::new(app, label, false, None::<&str>)?)?;
+    }
+    if current.is_none() && upcoming.is_empty() {
+        menu.append(&MenuItem::new(
+            app,
+            "Nothing else today",
+            false,
+            None::<&str>,
+        )?)?;
+    }
+
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        "plan",
+        "Plan my day",
+        true,
+        None::<&str>,
+    )?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        "focus",
+        focus_label(focus::status(app).as_ref()),
+        true,
+        None::<&str>,
+    )?)?;
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    menu.append(&MenuItem::with_id(
+        app,
+        "open",
+        "Open Dayli",
+        true,
+        None::<&str>,
+    )?)?;
+    menu.append(&MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?)?;
+    Ok(menu)
+}
+
+/// Bring the main window back from the tray.
+pub fn show_main_window<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+fn on_menu_event<R: Runtime>(app: &AppHandle<R>, event: MenuEvent) {
+    let emitted = match event.id().as_ref() {
+        "plan" => {
+            show_main_window(app);
+            app.emit(PLAN_DAY_EVENT, ())
+        }
+        "focus" => {
+            // The focus module reports the change and redraws the tray.
+            let toggled = match focus::status(app) {
+                None => focus::start(app, None, None),
+                Some(status) if status.paused.is_some() => focus::resume(app),
+                Some(_) => focus::pause(app),
+            };
+            if let Err(err) = toggled {
+                log::warn!("failed to toggle focus from the tray: {err}");
+            }
+            Ok(())
+        }
+        "open" => {
+            show_main_window(app);
+            Ok(())
+        }
+        "quit" => {
+            app.exit(0);
+            Ok(())
+        }
+        _ => Ok(()),
+    };
+    if let Err(err) = emitted {
+        log::warn!("failed to emit tray event: {err}");
+    }
+}
+
+/// Redraw the tray title and menu from today's blocks.
+pub fn refresh<R: Runtime>(app: &AppHandle<R>) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    if let Err(err) = update(app, &tray) {
+        log::error!("failed to update tray: {err}");
+    }
+}
+
+fn update<R: Runtime>(app: &AppHandle<R>, tray: &TrayIcon<R>) -> Result<()> {
+    let store = app.state::<Store>();
+    let zone = store.zone()?;
+    let blocks = store.blocks_for_date(zone.today())?;
+    let now = Utc::now();
+    let (current, upcoming) = split_day(&blocks, now);
+    let title = title(current, upcoming.first(), now);
+
+    tray.set_title(Some(&title))?;
+    tray.set_tooltip(Some(&title))?;
+    tray.set_menu(Some(build_menu(app, &blocks, zone)?))?;
+    Ok(())
+}
+
+/// Create the tray icon and keep it up to date, unless the tray is turned
+/// off in [`AppConfig`].
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    if !app.state::<AppConfig>().tray {
+        return Ok(());
+    }
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .menu(&build_menu(app.handle(), &[], Zone::system())?)
+        .on_menu_event(on_menu_event);
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    builder.build(app)?;
+
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            refresh(&handle);
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+        }
+    });
+    Ok(())
+}
+
+/// Hide the main window instead of closing it, so the app lives on in the
+/// tray until "Quit".
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if let WindowEvent::CloseRequested { api, .. } = event {
+        if window.label() == "main" && window.state::<AppConfig>().tray {
+            api.prevent_close();
+            let _ = window.hide();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::focus::{PauseReason, Phase};
+
+    fn status(paused: Option<PauseReason>) -> FocusStatus {
+        FocusStatus {
+            block_id: "b1".into(),
+            started_at: Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap(),
+            paused,
+            phase: Phase::Work,
+            pomodoro: None,
+            planned_minutes: 60,
+            worked_seconds: 600,
+            remaining_seconds: 3000,
+            phase_remaining_seconds: None,
+            interruptions: 0,
+        }
+    }
+
+    #[test]
+    fn the_focus_item_follows_the_session() {
+        assert_eq!(focus_label(None), "Start focus");
+        assert_eq!(focus_label(Some(&status(None))), "Pause focus");
+        assert_eq!(
+            focus_label(Some(&status(Some(PauseReason::User)))),
+            "Resume focus"
+        );
+        assert_eq!(
+            focus_label(Some(&status(Some(PauseReason::Idle)))),
+            "Resume focus"
+        );
+    }
+
+    #[test]
+    fn countdowns_switch_to_hours_past_an_hour() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap();
+        assert_eq!(countdown(now + chrono::Duration::minutes(59), now), "59m");
+        assert_eq!(
+            countdown(now + chrono::Duration::minutes(65), now),
+            "1h 05m"
+        );
+        assert_eq!(countdown(now - chrono::Duration::minutes(5), now), "0m");
+    }
+}
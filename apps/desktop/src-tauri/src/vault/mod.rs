@@ -0,0 +1,361 @@
+//! Native storage for the Supabase session and Google OAuth tokens.
+//!
+//! Refresh tokens never leave the Rust side: the webview can read access
+//! tokens through [`vault_get`], and the vault refreshes them before they
+//! expire.
+
+mod refresh;
+mod store;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::config::AppConfig;
+use crate::error::Result;
+
+pub use refresh::RefreshError;
+pub use store::{FileStore, KeyringStore, SecretStore};
+
+pub const REFRESHED_EVENT: &str = "vault://refreshed";
+pub const REFRESH_FAILED_EVENT: &str = "vault://refresh-failed";
+
+const SERVICE: &str = "com.mitchforest.dayli";
+/// Refresh access tokens this long before they expire.
+const REFRESH_MARGIN_SECS: i64 = 5 * 60;
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+/// The longest wait between attempts after failed refreshes.
+const MAX_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    Supabase,
+    Google,
+}
+
+impl Provider {
+    pub const ALL: [Provider; 2] = [Provider::Supabase, Provider::Google];
+
+    fn account(self) -> &'static str {
+        match self {
+            Provider::Supabase => "supabase",
+            Provider::Google => "google",
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.account())
+    }
+}
+
+/// A stored token set. `expires_at` is in unix seconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credential {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub expires_at: Option<i64>,
+}
+
+/// The part of a [`Credential`] the webview is allowed to see.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccessToken {
+    pub provider: Provider,
+    pub access_token: String,
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RefreshFailed {
+    provider: Provider,
+    message: String,
+    /// Whether the vault will try again; if not, the user has to sign in
+    /// again (or the app needs configuring).
+    retrying: bool,
+}
+
+pub struct TokenVault {
+    store: Box<dyn SecretStore>,
+    client: reqwest::Client,
+}
+
+impl TokenVault {
+    pub fn new(store: Box<dyn SecretStore>) -> Self {
+        Self {
+            store,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Use the OS credential store, or a [`FileStore`] in `fallback_dir`
+    /// when there isn't one.
+    pub fn open(fallback_dir: &std::path::Path) -> Result<Self> {
+        let store: Box<dyn SecretStore> = match KeyringStore::open(SERVICE) {
+            Some(store) => Box::new(store),
+            None => {
+                log::warn!(
+                    "no OS credential store; keeping tokens in {} with the key beside them, \
+                     protected only by file permissions",
+                    fallback_dir.display()
+                );
+                Box::new(FileStore::open(fallback_dir)?)
+            }
+        };
+        Ok(Self::new(store))
+    }
+
+    pub fn get(&self, provider: Provider) -> Result<Option<Credential>> {
+        match self.store.get(provider.account())? {
+            Some(secret) => Ok(Some(serde_json::from_str(&secret)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store `credential`, keeping the existing refresh token if the new
+    /// credential doesn't carry one.
+    pub fn set(&self, provider: Provider, mut credential: Credential) -> Result<()> {
+        if credential.refresh_token.is_none() {
+            credential.refresh_token = self.get(provider)?.and_then(|c| c.refresh_token);
+        }
+        self.store
+            .set(provider.account(), &serde_json::to_string(&credential)?)
+    }
+
+    pub fn delete(&self, provider: Provider) -> Result<()> {
+        self.store.delete(provider.account())
+    }
+
+    /// The signed-in Supabase user's id, from the `sub` claim of the stored
+    /// access token. The token isn't verified; this only says who the local
+    /// data belongs to.
+    pub fn user_id(&self) -> Result<Option<String>> {
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use base64::Engine;
+
+        let Some(credential) = self.get(Provider::Supabase)? else {
+            return Ok(None);
+        };
+        let claims = credential
+            .access_token
+            .split('.')
+            .nth(1)
+            .and_then(|payload| URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok())
+            .and_then(|json| serde_json::from_slice::<serde_json::Value>(&json).ok());
+        Ok(claims.and_then(|claims| claims["sub"].as_str().map(str::to_owned)))
+    }
+
+    /// Refresh `provider`'s token if it expires within the refresh margin.
+    /// Returns the new access token if one was fetched.
+    pub async fn refresh_if_needed(
+        &self,
+        config: &AppConfig,
+        provider: Provider,
+    ) -> std::result::Result<Option<AccessToken>, RefreshError> {
+        let Some(credential) = self.get(provider)? else {
+            return Ok(None);
+        };
+        let due = credential
+            .expires_at
+            .is_some_and(|at| at - unix_now() <= REFRESH_MARGIN_SECS);
+        if !due || credential.refresh_token.is_none() {
+            return Ok(None);
+        }
+
+        let fresh = refresh::refresh(&self.client, config, provider, &credential).await?;
+        self.set(provider, fresh.clone())?;
+        Ok(Some(AccessToken {
+            provider,
+            access_token: fresh.access_token,
+            expires_at: fresh.expires_at,
+        }))
+    }
+}
+
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Open the vault, manage it, and start the background refresh loop.
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    let vault = TokenVault::open(&app.path().app_local_data_dir()?)?;
+    app.manage(vault);
+
+    let handle = app.handle().clone();
+    tauri::async_runtime::spawn(async move {
+        let mut backoff = HashMap::new();
+        loop {
+            refresh_all(&handle, &mut backoff).await;
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+        }
+    });
+    Ok(())
+}
+
+/// How the refresh loop treats one provider after failures.
+#[derive(Debug, Default)]
+struct Backoff {
+    failures: u32,
+    /// No attempts before this.
+    until: Option<Instant>,
+    /// The refresh token a non-retryable failure was for. Nothing is tried
+    /// again until a different one is stored, e.g. by signing in again.
+    given_up_on: Option<Option<String>>,
+}
+
+impl Backoff {
+    fn ready(&self, refresh_token: &Option<String>, now: Instant) -> bool {
+        self.given_up_on.as_ref() != Some(refresh_token)
+            && self.until.map_or(true, |until| now >= until)
+    }
+
+    fn succeeded(&mut self) {
+        *self = Self::default();
+    }
+
+    fn failed(&mut self, err: &RefreshError, refresh_token: Option<String>, now: Instant) {
+        if err.retryable {
+            self.failures += 1;
+            self.until = Some(now + backoff_delay(self.failures));
+        } else {
+            self.given_up_on = Some(refresh_token);
+        }
+    }
+}
+
+/// The wait before the next attempt after `failures` failures in a row:
+/// doubling from the refresh interval up to [`MAX_BACKOFF`].
+fn backoff_delay(failures: u32) -> Duration {
+    REFRESH_INTERVAL
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(MAX_BACKOFF)
+}
+
+async fn refresh_all<R: Runtime>(app: &AppHandle<R>, backoff: &mut HashMap<Provider, Backoff>) {
+    let vault = app.state::<TokenVault>();
+    let config = app.state::<AppConfig>();
+    for provider in Provider::ALL {
+        let refresh_token = match vault.get(provider) {
+            Ok(credential) => credential.and_then(|credential| credential.refresh_token),
+            Err(err) => {
+                log::error!("failed to read the {provider} credential: {err}");
+                continue;
+            }
+        };
+        let state = backoff.entry(provider).or_default();
+        if !state.ready(&refresh_token, Instant::now()) {
+            continue;
+        }
+        let result = match vault.refresh_if_needed(&config, provider).await {
+            Ok(Some(token)) => {
+                state.succeeded();
+                app.emit(REFRESHED_EVENT, token)
+            }
+            Ok(None) => {
+                state.succeeded();
+                continue;
+            }
+            Err(err) => {
+                state.failed(&err, refresh_token, Instant::now());
+                app.emit(
+                    REFRESH_FAILED_EVENT,
+                    RefreshFailed {
+                        provider,
+                        message: err.error.to_string(),
+                        retrying: err.retryable,
+                    },
+                )
+            }
+        };
+        if let Err(err) = result {
+            log::warn!("failed to emit vault event: {err}");
+        }
+    }
+}
+
+#[tauri::command]
+pub fn vault_get(provider: Provider, vault: State<'_, TokenVault>) -> Result<Option<AccessToken>> {
+    Ok(vault.get(provider)?.map(|credential| AccessToken {
+        provider,
+        access_token: credential.access_token,
+        expires_at: credential.expires_at,
+    }))
+}
+
+#[tauri::command]
+pub fn vault_set(
+    provider: Provider,
+    credential: Credential,
+    vault: State<'_, TokenVault>,
+) -> Result<()> {
+    vault.set(provider, credential)
+}
+
+#[tauri::command]
+pub fn vault_delete(provider: Provider, vault: State<'_, TokenVault>) -> Result<()> {
+    vault.delete(provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failure(retryable: bool) -> RefreshError {
+        RefreshError {
+            error: crate::error::Error::Vault("refresh failed".into()),
+            retryable,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        assert_eq!(backoff_delay(1), REFRESH_INTERVAL);
+        assert_eq!(backoff_delay(2), REFRESH_INTERVAL * 2);
+        assert_eq!(backoff_delay(3), REFRESH_INTERVAL * 4);
+        assert_eq!(backoff_delay(7), MAX_BACKOFF);
+        assert_eq!(backoff_delay(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn retryable_failures_wait_longer_each_time() {
+        let token = Some("rt".to_owned());
+        let now = Instant::now();
+        let mut backoff = Backoff::default();
+        assert!(backoff.ready(&token, now));
+
+        backoff.failed(&failure(true), token.clone(), now);
+        assert!(!backoff.ready(&token, now + REFRESH_INTERVAL / 2));
+        assert!(backoff.ready(&token, now + REFRESH_INTERVAL));
+
+        let later = now + REFRESH_INTERVAL;
+        backoff.failed(&failure(true), token.clone(), later);
+        assert!(!backoff.ready(&token, later + REFRESH_INTERVAL));
+        assert!(backoff.ready(&token, later + REFRESH_INTERVAL * 2));
+
+        backoff.succeeded();
+        assert!(backoff.ready(&token, later));
+        assert_eq!(backoff.failures, 0);
+    }
+
+    #[test]
+    fn a_permanent_failure_stops_until_the_token_changes() {
+        let token = Some("revoked".to_owned());
+        let now = Instant::now();
+        let mut backoff = Backoff::default();
+        backoff.failed(&failure(false), token.clone(), now);
+
+        assert!(!backoff.ready(&token, now + MAX_BACKOFF * 10));
+        assert!(backoff.ready(&Some("after-sign-in".to_owned()), now));
+    }
+}
@@ -0,0 +1,196 @@
+use serde::Deserialize;
+
+use super::{unix_now, Credential, Provider};
+use crate::config::AppConfig;
+use crate::error::Error;
+
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+/// A failed refresh, and whether trying again later could help. Network
+/// errors and server trouble pass; a missing client id or a refresh token
+/// the provider rejected won't fix themselves.
+#[derive(Debug)]
+pub struct RefreshError {
+    pub error: Error,
+    pub retryable: bool,
+}
+
+impl RefreshError {
+    fn permanent(error: Error) -> Self {
+        Self {
+            error,
+            retryable: false,
+        }
+    }
+}
+
+impl<E: Into<Error>> From<E> for RefreshError {
+    fn from(error: E) -> Self {
+        Self {
+            error: error.into(),
+            retryable: true,
+        }
+    }
+}
+
+/// Whether a token endpoint's `status` may go away on its own: rate limits
+/// and server errors do, a rejected grant doesn't.
+fn retryable_status(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status.is_server_error()
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    expires_in: i64,
+}
+
+/// Trade `credential`'s refresh token for a new access token. Providers may
+/// rotate the refresh token; if they don't, the old one is kept.
+pub async fn refresh(
+    client: &reqwest::Client,
+    config: &AppConfig,
+    provider: Provider,
+    credential: &Credential,
+) -> std::result::Result<Credential, RefreshError> {
+    let refresh_token = credential.refresh_token.as_deref().ok_or_else(|| {
+        RefreshError::permanent(Error::Vault(format!("no {provider} refresh token")))
+    })?;
+
+    let request = match provider {
+        Provider::Supabase => client
+            .post(format!("{}/auth/v1/token", config.supabase.url))
+            .query(&[("grant_type", "refresh_token")])
+            .header("apikey", &config.supabase.anon_key)
+            .json(&serde_json::json!({ "refresh_token": refresh_token })),
+        Provider::Google => {
+            let client_id = config.google.client_id.as_deref().ok_or_else(|| {
+                RefreshError::permanent(Error::Vault("GOOGLE_CLIENT_ID is not configured".into()))
+            })?;
+            let mut form = vec![
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+                ("client_id", client_id),
+            ];
+            if let Some(secret) = config.google.client_secret.as_deref() {
+                form.push(("client_secret", secret));
+            }
+            client.post(GOOGLE_TOKEN_URL).form(&form)
+        }
+    };
+
+    let response = request.send().await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        return Err(RefreshError {
+            error: Error::Vault(format!("{provider} refresh failed ({status}): {body}")),
+            retryable: retryable_status(status),
+        });
+    }
+
+    let token: TokenResponse = response.json().await?;
+    Ok(Credential {
+        access_token: token.access_token,
+        refresh_token: token
+            .refresh_token
+            .or_else(|| credential.refresh_token.clone()),
+        expires_at: Some(unix_now() + token.expires_in),
+    })
+}
+
+#[cfg(all(test, desktop))]
+mod tests {
+    use super::*;
+    use crate::config::{GoogleConfig, SupabaseConfig};
+
+    fn config(url: String) -> AppConfig {
+        AppConfig {
+            open_devtools: false,
+            start_minimized: false,
+            tray: false,
+            supabase: SupabaseConfig {
+                url,
+                anon_key: "anon".into(),
+            },
+            google: GoogleConfig::default(),
+            hosts_path: Default::default(),
+        }
+    }
+
+    fn credential() -> Credential {
+        Credential {
+            access_token: "old".into(),
+            refresh_token: Some("rt-1".into()),
+            expires_at: Some(0),
+        }
+    }
+
+    /// Refresh against a token endpoint answering with `status` and `body`.
+    fn refresh_against(
+        status: u16,
+        body: &'static str,
+    ) -> std::result::Result<Credential, RefreshError> {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let config = config(format!("http://{}", server.server_addr()));
+        let handle = std::thread::spawn(move || {
+            let request = server.recv().unwrap();
+            let response = tiny_http::Response::from_string(body).with_status_code(status);
+            request.respond(response).unwrap();
+        });
+        let result = tauri::async_runtime::block_on(refresh(
+            &reqwest::Client::new(),
+            &config,
+            Provider::Supabase,
+            &credential(),
+        ));
+        handle.join().unwrap();
+        result
+    }
+
+    #[test]
+    fn keeps_the_refresh_token_unless_rotated() {
+        let fresh = refresh_against(200, r#"{"access_token":"new","expires_in":3600}"#).unwrap();
+        assert_eq!(fresh.access_token, "new");
+        assert_eq!(fresh.refresh_token.as_deref(), Some("rt-1"));
+        assert!(fresh.expires_at.unwrap() > unix_now());
+
+        let rotated = refresh_against(
+            200,
+            r#"{"access_token":"new","refresh_token":"rt-2","expires_in":3600}"#,
+        )
+        .unwrap();
+        assert_eq!(rotated.refresh_token.as_deref(), Some("rt-2"));
+    }
+
+    #[test]
+    fn a_rejected_grant_is_not_retried() {
+        let err = refresh_against(400, r#"{"error":"invalid_grant"}"#).unwrap_err();
+        assert!(!err.retryable);
+        let err = refresh_against(401, "").unwrap_err();
+        assert!(!err.retryable);
+    }
+
+    #[test]
+    fn server_trouble_is_retried() {
+        assert!(refresh_against(503, "").unwrap_err().retryable);
+        assert!(refresh_against(429, "").unwrap_err().retryable);
+    }
+
+    #[test]
+    fn missing_configuration_is_not_retried() {
+        let err = tauri::async_runtime::block_on(refresh(
+            &reqwest::Client::new(),
+            &config(String::new()),
+            Provider::Google,
+            &credential(),
+        ))
+        .unwrap_err();
+        assert!(!err.retryable);
+        assert!(err.error.to_string().contains("GOOGLE_CLIENT_ID"));
+    }
+}
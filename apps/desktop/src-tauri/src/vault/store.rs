@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+use crate::error::{Error, Result};
+
+/// Somewhere secrets can be kept at rest.
+pub trait SecretStore: Send + Sync {
+    fn get(&self, account: &str) -> Result<Option<String>>;
+    fn set(&self, account: &str, secret: &str) -> Result<()>;
+    fn delete(&self, account: &str) -> Result<()>;
+}
+
+/// The OS credential store: Secret Service on Linux, Keychain on macOS and
+/// Credential Manager on Windows.
+pub struct KeyringStore {
+    service: String,
+}
+
+impl KeyringStore {
+    /// Returns `None` if the platform store can't be reached, e.g. no Secret
+    /// Service daemon is running.
+    pub fn open(service: &str) -> Option<Self> {
+        let probe = keyring::Entry::new(service, "probe").ok()?;
+        match probe.get_password() {
+            Ok(_) | Err(keyring::Error::NoEntry) => Some(Self {
+                service: service.to_owned(),
+            }),
+            Err(_) => None,
+        }
+    }
+
+    fn entry(&self, account: &str) -> Result<keyring::Entry> {
+        Ok(keyring::Entry::new(&self.service, account)?)
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        match self.entry(account)?.get_password() {
+            Ok(secret) => Ok(Some(secret)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<()> {
+        Ok(self.entry(account)?.set_password(secret)?)
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        match self.entry(account)?.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+const NONCE_LEN: usize = 24;
+
+/// Fallback for systems without a credential store: all secrets in one
+/// XChaCha20-Poly1305 encrypted file, keyed by a random key kept in a
+/// separate owner-only file.
+///
+/// The key sits in plaintext next to the file it opens, so this is
+/// obfuscation, not encryption at rest: it keeps tokens out of casual
+/// greps and backups of `vault.bin` alone, but anyone who can read the
+/// user's files can read the secrets. Protection comes from the owner-only
+/// permissions.
+pub struct FileStore {
+    path: PathBuf,
+    cipher: XChaCha20Poly1305,
+    lock: Mutex<()>,
+}
+
+impl FileStore {
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let key_path = dir.join("vault.key");
+        let key = match fs::read(&key_path) {
+            Ok(key) if key.len() == 32 => key,
+            Ok(_) => return Err(Error::Vault("vault key is corrupt".into())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+                let key = XChaCha20Poly1305::generate_key(&mut OsRng).to_vec();
+                write_private(&key_path, &key)?;
+                key
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            path: dir.join("vault.bin"),
+            cipher: XChaCha20Poly1305::new_from_slice(&key)
+                .map_err(|_| Error::Vault("invalid vault key".into()))?,
+            lock: Mutex::new(()),
+        })
+    }
+
+    fn load(&self) -> Result<HashMap<String, String>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(err) => return Err(err.into()),
+        };
+        if data.len() < NONCE_LEN {
+            return Err(Error::Vault("vault file is corrupt".into()));
+        }
+
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| Error::Vault("vault file could not be decrypted".into()))?;
+        Ok(serde_json::from_slice(&plaintext)?)
+    }
+
+    fn save(&self, secrets: &HashMap<String, String>) -> Result<()> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, serde_json::to_vec(secrets)?.as_slice())
+            .map_err(|_| Error::Vault("failed to encrypt vault".into()))?;
+
+        let mut data = nonce.to_vec();
+        data.extend(ciphertext);
+        write_private(&self.path, &data)
+    }
+}
+
+impl SecretStore for FileStore {
+    fn get(&self, account: &str) -> Result<Option<String>> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.load()?.remove(account))
+    }
+
+    fn set(&self, account: &str, secret: &str) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut secrets = self.load()?;
+        secrets.insert(account.to_owned(), secret.to_owned());
+        self.save(&secrets)
+    }
+
+    fn delete(&self, account: &str) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut secrets = self.load()?;
+        if secrets.remove(account).is_some() {
+            self.save(&secrets)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write `data` to `path` atomically, readable only by the current user.
+fn write_private(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&tmp)?;
+    file.write_all(data)?;
+    file.sync_all()?;
+    fs::rename(tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_store_round_trips_secrets() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::open(dir.path()).unwrap();
+        assert_eq!(store.get("supabase").unwrap(), None);
+
+        store.set("supabase", "secret-1").unwrap();
+        store.set("google", "secret-2").unwrap();
+        store.set("supabase", "secret-3").unwrap();
+        store.delete("google").unwrap();
+        store.delete("missing").unwrap();
+
+        // A second handle reads the same file with the same key.
+        let reopened = FileStore::open(dir.path()).unwrap();
+        assert_eq!(
+            reopened.get("supabase").unwrap().as_deref(),
+            Some("secret-3")
+        );
+        assert_eq!(reopened.get("google").unwrap(), None);
+
+        let on_disk = fs::read(dir.path().join("vault.bin")).unwrap();
+        assert!(!on_disk.windows(8).any(|window| window == b"secret-3"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            for file in ["vault.key", "vault.bin"] {
+                let mode = fs::metadata(dir.path().join(file))
+                    .unwrap()
+                    .permissions()
+                    .mode();
+                assert_eq!(mode & 0o777, 0o600, "{file}");
+            }
+        }
+    }
+
+    #[test]
+    fn file_store_refuses_the_wrong_key() {
+        let dir = tempfile::tempdir().unwrap();
+        FileStore::open(dir.path())
+            .unwrap()
+            .set("supabase", "secret")
+            .unwrap();
+
+        let other = tempfile::tempdir().unwrap();
+        FileStore::open(other.path()).unwrap();
+        fs::copy(other.path().join("vault.key"), dir.path().join("vault.key")).unwrap();
+
+        let store = FileStore::open(dir.path()).unwrap();
+        assert!(matches!(store.get("supabase"), Err(Error::Vault(_))));
+        // Nothing is overwritten by a failed read.
+        assert!(store.set("google", "other").is_err());
+    }
+
+    #[test]
+    fn file_store_rejects_a_corrupt_key_or_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("vault.key"), b"short").unwrap();
+        assert!(matches!(FileStore::open(dir.path()), Err(Error::Vault(_))));
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileStore::open(dir.path()).unwrap();
+        store.set("supabase", "secret").unwrap();
+        let path = dir.path().join("vault.bin");
+        let mut data = fs::read(&path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        fs::write(&path, data).unwrap();
+        assert!(matches!(store.get("supabase"), Err(Error::Vault(_))));
+
+        fs::write(&path, b"tiny").unwrap();
+        assert!(matches!(store.get("supabase"), Err(Error::Vault(_))));
+    }
+}
@@ -0,0 +1,285 @@
+//! Remember where each window was: position, size, maximized/fullscreen and
+//! which monitor it was on, saved to `window-state.json` in the app config
+//! dir and restored on the next launch. A window whose monitor is gone is
+//! moved onto the primary monitor and clamped to fit.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    Manager, Monitor, PhysicalPosition, PhysicalSize, Runtime, WebviewWindow, Window, WindowEvent,
+};
+
+use crate::config::AppConfig;
+use crate::error::Result;
+
+const FILE_NAME: &str = "window-state.json";
+/// Windows that place themselves every time they're shown.
+const UNTRACKED: &[&str] = &["capture"];
+
+/// Geometry is in physical pixels; position is the outer position and size
+/// the inner size, matching what `set_position` and `set_size` take.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowState {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+    pub fullscreen: bool,
+    pub monitor: Option<String>,
+}
+
+pub struct WindowStates {
+    path: PathBuf,
+    states: Mutex<HashMap<String, WindowState>>,
+}
+
+impl WindowStates {
+    fn load(path: PathBuf) -> Self {
+        let states = fs::read(&path)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            states: Mutex::new(states),
+        }
+    }
+
+    fn save(&self) -> Result<()> {
+        let states = self.states.lock().unwrap();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&*states)?)?;
+        fs::rename(tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Record `window`'s current state. Position and size are only taken
+    /// from a normal window, so un-maximizing restores the old geometry.
+    fn capture<R: Runtime>(&self, window: &Window<R>) -> tauri::Result<()> {
+        let maximized = window.is_maximized()?;
+        let fullscreen = window.is_fullscreen()?;
+        let minimized = window.is_minimized()?;
+
+        let mut states = self.states.lock().unwrap();
+        let state = states.entry(window.label().to_owned()).or_default();
+        state.maximized = maximized;
+        state.fullscreen = fullscreen;
+        if !maximized && !fullscreen && !minimized {
+            let position = window.outer_position()?;
+            let size = window.inner_size()?;
+            state.x = position.x;
+            state.y = position.y;
+            state.width = size.width;
+            state.height = size.height;
+            state.monitor = window
+                .current_monitor()?
+                .and_then(|monitor| monitor.name().cloned());
+        }
+        Ok(())
+    }
+}
+
+/// Fit `state` onto the monitor it was saved on, or the primary monitor if
+/// that one isn't connected any more.
+fn clamp(mut state: WindowState, monitors: &[Monitor], primary: Option<&Monitor>) -> WindowState {
+    let saved = monitors
+        .iter()
+        .find(|monitor| state.monitor.is_some() && monitor.name() == state.monitor.as_ref());
+    let Some(monitor) = saved.or(primary).or(monitors.first()) else {
+        return state;
+    };
+
+    let origin = monitor.position();
+    let size = monitor.size();
+    state.width = state.width.clamp(1, size.width);
+    state.height = state.height.clamp(1, size.height);
+    let max_x = origin.x + (size.width - state.width) as i32;
+    let max_y = origin.y + (size.height - state.height) as i32;
+    state.x = state.x.clamp(origin.x, max_x);
+    state.y = state.y.clamp(origin.y, max_y);
+    state.monitor = monitor.name().cloned();
+    state
+}
+
+fn restore<R: Runtime>(window: &WebviewWindow<R>, state: WindowState) -> tauri::Result<()> {
+    let monitors = window.available_monitors()?;
+    let primary = window.primary_monitor()?;
+    let state = clamp(state, &monitors, primary.as_ref());
+
+    window.set_position(PhysicalPosition::new(state.x, state.y))?;
+    window.set_size(PhysicalSize::new(state.width, state.height))?;
+    if state.maximized {
+        window.maximize()?;
+    }
+    if state.fullscreen {
+        window.set_fullscreen(true)?;
+    }
+    Ok(())
+}
+
+/// Load saved states and apply them to the app's windows. The configured
+/// windows start hidden so they don't flash at their default position first;
+/// they're shown once restored, unless the app was started minimized.
+pub fn init<R: Runtime>(app: &tauri::App<R>) -> Result<()> {
+    let states = WindowStates::load(app.path().app_config_dir()?.join(FILE_NAME));
+    let saved = states.states.lock().unwrap().clone();
+    app.manage(states);
+    let visible = !app.state::<AppConfig>().start_minimized;
+
+    for (label, window) in app.webview_windows() {
+        if let Some(state) = saved.get(&label) {
+            if let Err(err) = restore(&window, state.clone()) {
+                log::error!("failed to restore window {label}: {err}");
+            }
+        }
+        if visible {
+            window.show()?;
+        }
+    }
+    Ok(())
+}
+
+/// Track geometry changes and persist them when a window closes.
+pub fn on_window_event<R: Runtime>(window: &Window<R>, event: &WindowEvent) {
+    if UNTRACKED.contains(&window.label()) {
+        return;
+    }
+    let Some(states) = window.try_state::<WindowStates>() else {
+        return;
+    };
+    let result = match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+            states.capture(window).map_err(Into::into)
+        }
+        WindowEvent::CloseRequested { .. } => states
+            .capture(window)
+            .map_err(Into::into)
+            .and_then(|_| states.save()),
+        WindowEvent::Destroyed => states.save(),
+        _ => Ok(()),
+    };
+    if let Err(err) = result {
+        log::error!("failed to save window state: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tauri::PhysicalRect;
+
+    use super::*;
+
+    fn monitor(name: &str, x: i32, y: i32, width: u32, height: u32) -> Monitor {
+        let (position, size) = (
+            PhysicalPosition::new(x, y),
+            PhysicalSize::new(width, height),
+        );
+        tauri_runtime::monitor::Monitor {
+            name: Some(name.into()),
+            size,
+            position,
+            work_area: PhysicalRect { position, size },
+            scale_factor: 1.0,
+        }
+        .into()
+    }
+
+    fn state(x: i32, y: i32, width: u32, height: u32, monitor: &str) -> WindowState {
+        WindowState {
+            x,
+            y,
+            width,
+            height,
+            maximized: false,
+            fullscreen: false,
+            monitor: Some(monitor.into()),
+        }
+    }
+
+    /// A laptop panel with an external display to its right.
+    fn desk() -> Vec<Monitor> {
+        vec![
+            monitor("eDP-1", 0, 0, 1920, 1080),
+            monitor("HDMI-1", 1920, 0, 2560, 1440),
+        ]
+    }
+
+    #[test]
+    fn stays_put_on_its_own_monitor() {
+        let monitors = desk();
+        let saved = state(2200, 100, 1200, 800, "HDMI-1");
+        assert_eq!(clamp(saved.clone(), &monitors, Some(&monitors[0])), saved);
+    }
+
+    #[test]
+    fn moves_to_the_primary_monitor_when_its_own_is_gone() {
+        let monitors = vec![monitor("eDP-1", 0, 0, 1920, 1080)];
+        let restored = clamp(
+            state(2200, 100, 1200, 800, "HDMI-1"),
+            &monitors,
+            Some(&monitors[0]),
+        );
+        assert_eq!(restored, state(720, 100, 1200, 800, "eDP-1"));
+
+        // No primary reported: the first monitor will do.
+        let restored = clamp(state(2200, 100, 1200, 800, "HDMI-1"), &monitors, None);
+        assert_eq!(restored.monitor.as_deref(), Some("eDP-1"));
+
+        // Nor does a window saved without a monitor name match one.
+        let mut unnamed = state(10, 10, 800, 600, "");
+        unnamed.monitor = None;
+        let desk = desk();
+        let restored = clamp(unnamed, &desk, Some(&desk[1]));
+        assert_eq!(restored, state(1920, 10, 800, 600, "HDMI-1"));
+    }
+
+    #[test]
+    fn shrinks_a_window_larger_than_the_monitor() {
+        let monitors = desk();
+        let restored = clamp(
+            state(-50, -50, 4000, 3000, "eDP-1"),
+            &monitors,
+            Some(&monitors[0]),
+        );
+        assert_eq!(restored, state(0, 0, 1920, 1080, "eDP-1"));
+
+        let empty = clamp(
+            state(100, 100, 0, 0, "eDP-1"),
+            &monitors,
+            Some(&monitors[0]),
+        );
+        assert_eq!((empty.width, empty.height), (1, 1));
+    }
+
+    #[test]
+    fn pulls_an_off_screen_window_back() {
+        let monitors = desk();
+        let restored = clamp(
+            state(5000, 2000, 1200, 800, "HDMI-1"),
+            &monitors,
+            Some(&monitors[0]),
+        );
+        assert_eq!(restored, state(3280, 640, 1200, 800, "HDMI-1"));
+        let restored = clamp(
+            state(-900, -300, 1200, 800, "HDMI-1"),
+            &monitors,
+            Some(&monitors[0]),
+        );
+        assert_eq!(restored, state(1920, 0, 1200, 800, "HDMI-1"));
+    }
+
+    #[test]
+    fn leaves_the_state_alone_without_monitors() {
+        let saved = state(5000, -300, 4000, 3000, "HDMI-1");
+        assert_eq!(clamp(saved.clone(), &[], None), saved);
+    }
+}
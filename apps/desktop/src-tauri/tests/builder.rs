@@ -0,0 +1,77 @@
+//! Builds the full app on the mock runtime and calls a command over IPC.
+
+use dayli_lib::{AppConfig, GoogleConfig, SupabaseConfig};
+use serde_json::{json, Value};
+use tauri::ipc::{CallbackFn, InvokeBody};
+use tauri::test::{get_ipc_response, mock_builder, mock_context, noop_assets, INVOKE_KEY};
+use tauri::webview::InvokeRequest;
+use tauri::{Manager, RunEvent, WebviewWindowBuilder};
+
+fn request(cmd: &str, body: Value) -> InvokeRequest {
+    InvokeRequest {
+        cmd: cmd.into(),
+        callback: CallbackFn(0),
+        error: CallbackFn(1),
+        url: "tauri://localhost".parse().unwrap(),
+        body: InvokeBody::Json(body),
+        headers: Default::default(),
+        invoke_key: INVOKE_KEY.to_string(),
+    }
+}
+
+#[test]
+fn builds_on_the_mock_runtime_and_answers_commands() {
+    // Keep the store, vault file and settings out of the real home.
+    let home = tempfile::tempdir().unwrap();
+    std::env::set_var("HOME", home.path());
+    std::env::set_var("XDG_DATA_HOME", home.path().join("data"));
+    std::env::set_var("XDG_CONFIG_HOME", home.path().join("config"));
+
+    let config = AppConfig {
+        open_devtools: false,
+        start_minimized: true,
+        tray: false,
+        supabase: SupabaseConfig {
+            url: String::new(),
+            anon_key: String::new(),
+        },
+        google: GoogleConfig::default(),
+        hosts_path: home.path().join("hosts"),
+    };
+    let mut context = mock_context(noop_assets());
+    context.config_mut().identifier = "com.dayli.test".into();
+    let app = dayli_lib::configure(mock_builder(), config)
+        .build(context)
+        .expect("the app builds on the mock runtime");
+    let webview = WebviewWindowBuilder::new(&app, "main", Default::default())
+        .build()
+        .unwrap();
+
+    // Setup runs once the event loop is ready; the commands are called from
+    // another thread so the loop keeps serving them.
+    let (done, results) = std::sync::mpsc::channel();
+    app.run_return(move |handle, event| {
+        if let RunEvent::Ready = event {
+            let (webview, done, handle) = (webview.clone(), done.clone(), handle.clone());
+            std::thread::spawn(move || {
+                let times = get_ipc_response(
+                    &webview,
+                    request("schedule_day_times", json!({ "date": "2026-03-08" })),
+                )
+                .map(|body| body.deserialize::<Value>().unwrap());
+                let missing = get_ipc_response(&webview, request("no_such_command", json!({})));
+                done.send((times, missing.is_err())).unwrap();
+                // The mock runtime stops once its last window is gone.
+                for window in handle.webview_windows().into_values() {
+                    window.destroy().unwrap();
+                }
+            });
+        }
+    });
+
+    let (times, missing_failed) = results.recv().unwrap();
+    let times = times.expect("schedule_day_times succeeds");
+    assert_eq!(times["date"], "2026-03-08");
+    assert!(times["workStart"]["at"].is_string());
+    assert!(missing_failed, "unknown commands are rejected");
+}
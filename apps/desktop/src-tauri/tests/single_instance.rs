@@ -0,0 +1,109 @@
+//! Two real processes racing for the single-instance socket.
+#![cfg(unix)]
+
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, ChildStderr, Command, Stdio};
+
+use dayli_lib::single_instance::{claim, Claim, Handoff};
+
+const DIR_VAR: &str = "DAYLI_INSTANCE_TEST_DIR";
+const ARGS_VAR: &str = "DAYLI_INSTANCE_TEST_ARGS";
+
+/// Run in the child processes: claim the socket, then either report the
+/// first handoff received or that ours was forwarded. Reports go to stderr,
+/// which the harness doesn't buffer.
+#[test]
+fn instance_process() {
+    let Ok(dir) = std::env::var(DIR_VAR) else {
+        return;
+    };
+    let argv: Vec<String> = serde_json::from_str(&std::env::var(ARGS_VAR).unwrap()).unwrap();
+    let handoff = Handoff {
+        argv,
+        cwd: "/work".into(),
+    };
+    match claim(Path::new(&dir), &handoff).unwrap() {
+        Claim::Forwarded => eprintln!("forwarded"),
+        Claim::Primary(primary) => {
+            let (tx, rx) = std::sync::mpsc::channel();
+            primary.serve(move |handoff| tx.send(handoff).unwrap());
+            eprintln!("primary");
+            let received = rx.recv().unwrap();
+            eprintln!("received {}", serde_json::to_string(&received).unwrap());
+        }
+    }
+}
+
+fn spawn(dir: &Path, argv: &[&str]) -> (Child, BufReader<ChildStderr>) {
+    let mut child = Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "instance_process",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env(DIR_VAR, dir)
+        .env(ARGS_VAR, serde_json::to_string(argv).unwrap())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let stderr = BufReader::new(child.stderr.take().unwrap());
+    (child, stderr)
+}
+
+/// The next line the child reported, skipping anything else on stderr.
+fn next_line(stderr: &mut BufReader<ChildStderr>) -> String {
+    loop {
+        let mut line = String::new();
+        assert_ne!(
+            stderr.read_line(&mut line).unwrap(),
+            0,
+            "child exited early"
+        );
+        let line = line.trim();
+        if line.starts_with("primary")
+            || line.starts_with("forwarded")
+            || line.starts_with("received")
+        {
+            return line.to_owned();
+        }
+    }
+}
+
+#[test]
+fn a_second_process_forwards_its_arguments() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let (mut first, mut first_out) = spawn(dir.path(), &["dayli"]);
+    assert_eq!(next_line(&mut first_out), "primary");
+
+    let link = "dayli://auth/callback?code=abc&state=xyz";
+    let (mut second, mut second_out) = spawn(dir.path(), &["dayli", link]);
+    assert_eq!(next_line(&mut second_out), "forwarded");
+    assert!(second.wait().unwrap().success());
+
+    let received = next_line(&mut first_out);
+    let handoff: Handoff =
+        serde_json::from_str(received.strip_prefix("received ").unwrap()).unwrap();
+    assert_eq!(handoff.argv, ["dayli", link]);
+    assert_eq!(handoff.cwd, "/work");
+    assert!(first.wait().unwrap().success());
+}
+
+#[test]
+fn a_dead_instance_leaves_no_claim() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let (mut first, mut first_out) = spawn(dir.path(), &["dayli"]);
+    assert_eq!(next_line(&mut first_out), "primary");
+    // Killed without cleaning up: the socket file stays, the lock doesn't.
+    first.kill().unwrap();
+    first.wait().unwrap();
+    assert!(dir.path().join("instance.sock").exists());
+
+    let (mut next, mut next_out) = spawn(dir.path(), &["dayli"]);
+    assert_eq!(next_line(&mut next_out), "primary");
+    next.kill().unwrap();
+    next.wait().unwrap();
+}
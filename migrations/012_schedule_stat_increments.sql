@@ -0,0 +1,66 @@
+-- Migration 012: Schedule stat increments
+-- Lets clients add to the counters in daily_schedules.stats instead of
+-- overwriting the whole object, so minutes logged on two devices add up.
+-- Every increment carries the client's idempotency key and a replayed key
+-- is a no-op, so a retry after a lost response doesn't count twice.
+
+BEGIN;
+
+-- 1. Keys of increments already applied
+CREATE TABLE IF NOT EXISTS public.applied_increments (
+  user_id UUID REFERENCES auth.users ON DELETE CASCADE NOT NULL DEFAULT auth.uid(),
+  idempotency_key TEXT NOT NULL,
+  applied_at TIMESTAMPTZ DEFAULT NOW(),
+  PRIMARY KEY (user_id, idempotency_key)
+);
+
+ALTER TABLE public.applied_increments ENABLE ROW LEVEL SECURITY;
+
+CREATE POLICY "Users can view their own applied increments"
+ON public.applied_increments FOR SELECT
+USING (auth.uid() = user_id);
+
+CREATE POLICY "Users can create their own applied increments"
+ON public.applied_increments FOR INSERT
+WITH CHECK (auth.uid() = user_id);
+
+-- 2. Add each delta to the matching stats counter of the user's schedule for
+-- the date, creating the schedule if there isn't one yet
+CREATE OR REPLACE FUNCTION public.increment_daily_schedules(
+  schedule_date DATE,
+  deltas JSONB,
+  idempotency_key TEXT
+) RETURNS VOID
+LANGUAGE plpgsql
+SECURITY INVOKER
+AS $$
+DECLARE
+  delta RECORD;
+BEGIN
+  INSERT INTO public.applied_increments (user_id, idempotency_key)
+  VALUES (auth.uid(), increment_daily_schedules.idempotency_key)
+  ON CONFLICT DO NOTHING;
+  IF NOT FOUND THEN
+    RETURN;
+  END IF;
+
+  INSERT INTO public.daily_schedules (user_id, schedule_date)
+  VALUES (auth.uid(), increment_daily_schedules.schedule_date)
+  ON CONFLICT (user_id, schedule_date) DO NOTHING;
+
+  FOR delta IN SELECT key, value FROM jsonb_each(deltas) LOOP
+    UPDATE public.daily_schedules s
+    SET stats = jsonb_set(
+      COALESCE(s.stats, '{}'::jsonb),
+      ARRAY[delta.key],
+      to_jsonb(COALESCE((s.stats ->> delta.key)::numeric, 0) + (delta.value #>> '{}')::numeric)
+    )
+    WHERE s.user_id = auth.uid()
+      AND s.schedule_date = increment_daily_schedules.schedule_date;
+  END LOOP;
+END;
+$$;
+
+GRANT EXECUTE ON FUNCTION public.increment_daily_schedules(DATE, JSONB, TEXT) TO authenticated;
+
+COMMIT;